@@ -23,11 +23,15 @@ impl BenchCtx {
         }
     }
     pub fn get_eval_ctx<'a>(&'a mut self) -> EvalCtx<'a> {
-        EvalCtx {
-            tmp1: &mut self.tmp1[..],
-            tmp2: &mut self.tmp2[..],
-            out:  &mut self.out[..]
-        }
+        EvalCtx::new(&mut self.tmp1[..],
+                     &mut self.tmp2[..],
+                     &mut self.out[..])
+    }
+}
+
+impl Default for BenchCtx {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -0,0 +1,166 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Conversions between newel operands and Apache Arrow arrays, enabled by the
+// `arrow` feature.
+//
+// An Arrow primitive array keeps its values in a contiguous, aligned buffer of
+// native scalars, which is exactly what a `Slice` borrows, so those convert to
+// newel without copying. The other way, an Arrow array shares ownership of its
+// buffers, so a borrowed `Slice` has to be copied into one; an `OwnedSlice`'s
+// `Arc` column is shared with the array instead, without copying. Arrow
+// booleans and validity bitmaps are bit-packed though, whereas newel uses a
+// byte per boolean, so those get unpacked into a caller-supplied buffer (eg.
+// one of the `EvalCtx` buffers of an earlier step) and re-packed on the way
+// out.
+//
+// Newel itself knows nothing about nulls: the values in null slots are
+// arbitrary and get computed-with like any others. Callers carry validity
+// alongside as a bool `Slice`, combine it as they see fit, and hand it back to
+// `Slice::to_arrow` when building the result array.
+
+use std::panic::RefUnwindSafe;
+use std::ptr::NonNull;
+use std::sync::Arc;
+
+use arrow_array::types::*;
+use arrow_array::{Array, ArrayRef, BooleanArray, PrimitiveArray};
+use arrow_buffer::{BooleanBuffer, Buffer, NullBuffer, ScalarBuffer};
+
+use crate::eval::*;
+use crate::operands::*;
+use crate::owned::OwnedSlice;
+
+impl_arrow_from!([UInt8Type] SliceU8
+                 [UInt16Type] SliceU16
+                 [UInt32Type] SliceU32
+                 [UInt64Type] SliceU64
+                 [Int8Type] SliceI8
+                 [Int16Type] SliceI16
+                 [Int32Type] SliceI32
+                 [Int64Type] SliceI64
+                 [Float32Type] SliceF32
+                 [Float64Type] SliceF64
+);
+
+// Packs `validity`, which must have an entry per element, into an Arrow
+// validity bitmap.
+fn null_buffer(len: usize, validity: Option<&[bool]>) -> Result<Option<NullBuffer>, EvalError> {
+    match validity {
+        Some(v) if v.len() != len => Err(EvalError::BadBuffer),
+        Some(v) => Ok(Some(NullBuffer::new(BooleanBuffer::from(v)))),
+        None => Ok(None)
+    }
+}
+
+// Returns an Arrow buffer over the elements of `s` that holds a reference to
+// it, rather than a copy.
+fn shared_buffer<T: Send + Sync + RefUnwindSafe + 'static>(s: &Arc<[T]>) -> Buffer {
+    let ptr = NonNull::new(s.as_ptr() as *mut u8).expect("Arc pointers aren't null");
+    let len = std::mem::size_of_val(&**s);
+    // Safety: the `Arc` clone the buffer owns keeps the elements allocated,
+    // and they can't be mutated while it's shared.
+    unsafe { Buffer::from_custom_allocation(ptr, len, Arc::new(s.clone())) }
+}
+
+// Unpacks `bits` into the front of `buf`, one byte per boolean.
+fn unpack_bits<'a>(bits: &BooleanBuffer, buf: &'a mut [u8]) -> Result<&'a [bool], EvalError> {
+    let dst: &mut [bool] = transmute_buf_mut(buf)?;
+    if dst.len() < bits.len() {
+        return Err(EvalError::BadBuffer);
+    }
+    let dst = &mut dst[0..bits.len()];
+    for (d, b) in dst.iter_mut().zip(bits.iter()) {
+        *d = b;
+    }
+    Ok(dst)
+}
+
+impl<'a> Slice<'a> {
+
+    /// Returns a `Slice` of the values of an Arrow array. Primitive arrays are
+    /// borrowed without copying; boolean arrays are unpacked into `buf`, which
+    /// must hold at least one byte per element. Arrow types with no newel
+    /// counterpart give `EvalError::UnsupportedOp`.
+    pub fn from_arrow(array: &'a dyn Array, buf: &'a mut [u8]) -> Result<Slice<'a>, EvalError> {
+        if let Some(s) = primitive_slice_from_arrow(array) {
+            return Ok(s);
+        }
+        match array.as_any().downcast_ref::<BooleanArray>() {
+            Some(b) => Ok(Slice::SliceBool(unpack_bits(b.values(), buf)?)),
+            None => Err(EvalError::UnsupportedOp)
+        }
+    }
+
+    /// Returns the validity bitmap of an Arrow array unpacked into `buf` as a
+    /// bool `Slice` (`true` meaning valid), or `None` if the array has no
+    /// nulls.
+    pub fn from_arrow_validity(array: &dyn Array, buf: &'a mut [u8])
+                               -> Result<Option<Slice<'a>>, EvalError> {
+        match array.nulls() {
+            Some(nulls) => Ok(Some(Slice::SliceBool(unpack_bits(nulls.inner(), buf)?))),
+            None => Ok(None)
+        }
+    }
+
+    /// Builds an Arrow array holding a copy of this `Slice`, with nulls
+    /// wherever `validity` (if given) is `false`. The copy is needed because
+    /// the array shares ownership of its values, which a borrowed `Slice`
+    /// can't give it; `OwnedSlice::to_arrow` doesn't copy. 128-bit integers
+    /// have no Arrow counterpart and give `EvalError::UnsupportedOp`.
+    pub fn to_arrow(&self, validity: Option<&[bool]>) -> Result<ArrayRef, EvalError> {
+        use Slice::*;
+        let nulls = null_buffer(self.len(), validity)?;
+        fn prim<T: ArrowPrimitiveType>(s: &[T::Native], nulls: Option<NullBuffer>) -> ArrayRef {
+            Arc::new(PrimitiveArray::<T>::new(ScalarBuffer::from(s.to_vec()), nulls))
+        }
+        let array: ArrayRef = match *self {
+            SliceBool(s) => Arc::new(BooleanArray::new(BooleanBuffer::from(s), nulls)),
+            SliceU8(s) => prim::<UInt8Type>(s, nulls),
+            SliceU16(s) => prim::<UInt16Type>(s, nulls),
+            SliceU32(s) => prim::<UInt32Type>(s, nulls),
+            SliceU64(s) => prim::<UInt64Type>(s, nulls),
+            SliceI8(s) => prim::<Int8Type>(s, nulls),
+            SliceI16(s) => prim::<Int16Type>(s, nulls),
+            SliceI32(s) => prim::<Int32Type>(s, nulls),
+            SliceI64(s) => prim::<Int64Type>(s, nulls),
+            SliceF32(s) => prim::<Float32Type>(s, nulls),
+            SliceF64(s) => prim::<Float64Type>(s, nulls),
+            SliceU128(_) | SliceI128(_) => return Err(EvalError::UnsupportedOp),
+        };
+        Ok(array)
+    }
+}
+
+impl OwnedSlice {
+    /// Builds an Arrow array sharing this column, with nulls wherever
+    /// `validity` (if given) is `false`. Primitive values aren't copied: the
+    /// array holds a reference to the `Arc` they're in. Booleans are packed
+    /// into a new bitmap. 128-bit integers have no Arrow counterpart and give
+    /// `EvalError::UnsupportedOp`.
+    pub fn to_arrow(&self, validity: Option<&[bool]>) -> Result<ArrayRef, EvalError> {
+        use OwnedSlice::*;
+        let nulls = null_buffer(self.as_slice().len(), validity)?;
+        fn prim<T: ArrowPrimitiveType>(s: &Arc<[T::Native]>, nulls: Option<NullBuffer>) -> ArrayRef
+        where T::Native: RefUnwindSafe
+        {
+            let values = ScalarBuffer::new(shared_buffer(s), 0, s.len());
+            Arc::new(PrimitiveArray::<T>::new(values, nulls))
+        }
+        let array: ArrayRef = match self {
+            SliceBool(s) => Arc::new(BooleanArray::new(BooleanBuffer::from(&s[..]), nulls)),
+            SliceU8(s) => prim::<UInt8Type>(s, nulls),
+            SliceU16(s) => prim::<UInt16Type>(s, nulls),
+            SliceU32(s) => prim::<UInt32Type>(s, nulls),
+            SliceU64(s) => prim::<UInt64Type>(s, nulls),
+            SliceI8(s) => prim::<Int8Type>(s, nulls),
+            SliceI16(s) => prim::<Int16Type>(s, nulls),
+            SliceI32(s) => prim::<Int32Type>(s, nulls),
+            SliceI64(s) => prim::<Int64Type>(s, nulls),
+            SliceF32(s) => prim::<Float32Type>(s, nulls),
+            SliceF64(s) => prim::<Float64Type>(s, nulls),
+            SliceU128(_) | SliceI128(_) => return Err(EvalError::UnsupportedOp),
+        };
+        Ok(array)
+    }
+}
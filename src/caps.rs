@@ -0,0 +1,79 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// The capability table: which opcodes work on which types. It's read off the
+// `SUPPORTED` consts of the kernels the `impl_*` macro invocations in ops.rs
+// produce (see plan.rs), so it can't disagree with what evaluation does.
+
+use crate::ops::*;
+use crate::scalarty::ScalarTy;
+use crate::plan::*;
+
+/// An opcode of any kind.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OpCode {
+    ValBin(ValBinOpCode),
+    ValUn(ValUnOpCode),
+    ValTern(ValTernOpCode),
+    BoolBin(BoolBinOpCode),
+    BoolUn(BoolUnOpCode),
+}
+
+/// Whether `op` works on operands all of type `ty`, and if so the type of its
+/// result. Operands of different types are first promoted to a common type,
+/// which is the one to look up; `plan_*` do this for a given policy.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Capability {
+    pub op: OpCode,
+    pub ty: ScalarTy,
+    pub supported: bool,
+    pub result_ty: ScalarTy,
+}
+
+impl OpCode {
+    /// Returns whether this opcode works on operands of type `ty`.
+    pub fn supports(&self, ty: ScalarTy) -> bool {
+        match self {
+            OpCode::ValBin(op) => val_binop_supported(op.clone(), ty),
+            OpCode::ValUn(op) => val_unop_supported(op.clone(), ty),
+            OpCode::ValTern(op) => val_ternop_supported(op.clone(), ty),
+            OpCode::BoolBin(op) => bool_binop_supported(op.clone(), ty),
+            OpCode::BoolUn(op) => bool_unop_supported(op.clone(), ty),
+        }
+    }
+
+    /// Returns the type of this opcode's result on operands of type `ty`.
+    pub fn result_ty(&self, ty: ScalarTy) -> ScalarTy {
+        match self {
+            OpCode::ValBin(_) | OpCode::ValUn(_) | OpCode::ValTern(_) => ty,
+            OpCode::BoolBin(_) | OpCode::BoolUn(_) => ScalarTy::TBool,
+        }
+    }
+
+    /// Returns the `Capability` of this opcode on operands of type `ty`.
+    pub fn capability(&self, ty: ScalarTy) -> Capability {
+        Capability {
+            op: self.clone(),
+            ty,
+            supported: self.supports(ty),
+            result_ty: self.result_ty(ty),
+        }
+    }
+
+    /// Every opcode, of every kind.
+    pub fn all() -> impl Iterator<Item = OpCode> {
+        ValBinOpCode::ALL.iter().cloned().map(OpCode::ValBin)
+            .chain(ValUnOpCode::ALL.iter().cloned().map(OpCode::ValUn))
+            .chain(ValTernOpCode::ALL.iter().cloned().map(OpCode::ValTern))
+            .chain(BoolBinOpCode::ALL.iter().cloned().map(OpCode::BoolBin))
+            .chain(BoolUnOpCode::ALL.iter().cloned().map(OpCode::BoolUn))
+    }
+}
+
+/// Returns the capability table: the `Capability` of every opcode on every
+/// `ScalarTy`, supported or not, with the features this build has.
+pub fn capabilities() -> impl Iterator<Item = Capability> {
+    OpCode::all().flat_map(|op| ScalarTy::ALL.iter().cloned().map(move |ty| op.capability(ty)))
+}
@@ -0,0 +1,436 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// A compact binary encoding for operands and opcodes, so that (partial) plans
+// can be shipped between processes.
+//
+// Everything is encoded as a sequence of records, each of which starts with a
+// 16-byte header:
+//
+//     bytes 0..3    magic, b"NWL"
+//     byte  3       format version, `CODEC_VERSION`
+//     byte  4       record kind (const, slice, or one of the opcode types)
+//     byte  5       tag: the `ScalarTy` of an operand, or the opcode itself
+//     bytes 6..8    reserved, zero
+//     bytes 8..16   u64 element count of a slice; zero otherwise
+//
+// A const record is followed by 16 bytes holding its value, zero-extended; a
+// slice record by its elements, zero-padded out to a multiple of 16 bytes;
+// opcode records have no payload. All multi-byte values are little-endian.
+// Readers reject records whose reserved bytes, unused count or padding aren't
+// zero, so those can be given meanings by later versions.
+// Since every record is a multiple of 16 bytes long, every slice payload in a
+// 16-byte-aligned message is itself aligned for its element type.
+//
+// Slices are read directly into a caller-supplied buffer (eg. one of the
+// `EvalCtx` buffers, or a buffer later passed in as one) with the same size and
+// alignment requirements the evaluator has, and byte-swapped in place if the
+// host is big-endian.
+
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+use std::mem::{size_of, size_of_val};
+use std::sync::Arc;
+
+use crate::eval::*;
+use crate::operands::*;
+use crate::ops::*;
+use crate::owned::*;
+use crate::scalarty::*;
+use crate::traits::*;
+
+/// The version of the binary encoding written by this crate. Records with any
+/// other version are rejected with `CodecError::BadVersion`.
+pub const CODEC_VERSION: u8 = 1;
+
+const MAGIC: [u8; 3] = *b"NWL";
+const HEADER_BYTES: usize = 16;
+const RECORD_ALIGN: usize = 16;
+
+const KIND_CONST: u8 = 0;
+const KIND_SLICE: u8 = 1;
+const KIND_VAL_BINOP: u8 = 2;
+const KIND_VAL_UNOP: u8 = 3;
+const KIND_BOOL_BINOP: u8 = 4;
+const KIND_BOOL_UNOP: u8 = 5;
+const KIND_VAL_TERNOP: u8 = 6;
+
+#[derive(Debug)]
+pub enum CodecError {
+    /// The underlying reader or writer failed.
+    Io(std::io::Error),
+    /// A record didn't start with the expected magic bytes.
+    BadMagic,
+    /// A record was written by an incompatible version of the encoding.
+    BadVersion(u8),
+    /// A record wasn't of the expected kind, or had an unknown tag (type or
+    /// opcode), or held an invalid value (eg. a bool other than 0 or 1), or
+    /// had non-zero reserved or padding bytes.
+    BadRecord,
+    /// The buffer passed to read a slice into was too small or misaligned.
+    BadBuffer,
+}
+
+impl From<std::io::Error> for CodecError {
+    fn from(e: std::io::Error) -> Self {
+        CodecError::Io(e)
+    }
+}
+
+impl From<EvalError> for CodecError {
+    fn from(_: EvalError) -> Self {
+        CodecError::BadBuffer
+    }
+}
+
+// Scalars that can be written and read as little-endian bytes.
+trait Wire: ScalarT + Copy + Default {
+    // Writes `s` to `w` as little-endian bytes.
+    fn write_le<W: Write>(s: &[Self], w: &mut W) -> std::io::Result<()>;
+    // Checks that little-endian `bytes` hold valid elements.
+    fn check_le(_bytes: &[u8]) -> Result<(), CodecError> {
+        Ok(())
+    }
+    // Converts elements just read as little-endian bytes to native order.
+    fn fix_le(s: &mut [Self]);
+    // Reads one element from the front of little-endian `bytes`.
+    fn from_le(bytes: &[u8]) -> Result<Self, CodecError>;
+    // Returns the element as little-endian bytes, zero-extended to 16.
+    fn to_le16(self) -> [u8; 16];
+}
+
+impl_wire!(u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
+
+impl Wire for bool {
+    fn write_le<W: Write>(s: &[bool], w: &mut W) -> std::io::Result<()> {
+        // bools are a single byte holding 0 or 1 on every target.
+        let bytes = unsafe {
+            std::slice::from_raw_parts(s.as_ptr() as *const u8, s.len())
+        };
+        w.write_all(bytes)
+    }
+    fn check_le(bytes: &[u8]) -> Result<(), CodecError> {
+        if bytes.iter().any(|b| *b > 1) {
+            return Err(CodecError::BadRecord);
+        }
+        Ok(())
+    }
+    fn fix_le(_s: &mut [bool]) {}
+    fn from_le(bytes: &[u8]) -> Result<bool, CodecError> {
+        Self::check_le(&bytes[0..1])?;
+        Ok(bytes[0] == 1)
+    }
+    fn to_le16(self) -> [u8; 16] {
+        let mut b = [0; 16];
+        b[0] = self as u8;
+        b
+    }
+}
+
+impl_codec_tags!(ScalarTy {
+    TBool = 0, TU8 = 1, TU16 = 2, TU32 = 3, TU64 = 4, TU128 = 5,
+    TI8 = 6, TI16 = 7, TI32 = 8, TI64 = 9, TI128 = 10,
+    TF32 = 11, TF64 = 12,
+});
+
+impl_codec_tags!(ValBinOpCode {
+    Add = 0, Sub = 1, Mul = 2, Div = 3, Rem = 4, Min = 5, Max = 6, Pow = 7,
+    BitAnd = 8, BitOr = 9, BitXor = 10, Shl = 11, LShr = 12, AShr = 13, RotL = 14,
+    RotR = 15, Atan2 = 16, Hypot = 17,
+});
+
+impl_codec_tags!(ValUnOpCode {
+    Neg = 0, BitNot = 1, Abs = 2, Ln = 3, Exp = 4, Sqrt = 5, Sin = 6, Cos = 7, Signum = 8,
+    PopCount = 9, LeadingZeros = 10, TrailingZeros = 11, BitReverse = 12, ByteSwap = 13,
+    Tan = 14, Asin = 15, Acos = 16, Atan = 17, Sinh = 18, Cosh = 19, Tanh = 20, Log2 = 21,
+    Log10 = 22, Log1p = 23, Expm1 = 24, Cbrt = 25, Floor = 26, Ceil = 27, Round = 28,
+    Trunc = 29,
+});
+
+impl_codec_tags!(ValTernOpCode {
+    MulAdd = 0,
+});
+
+impl_codec_tags!(BoolBinOpCode {
+    Lt = 0, Le = 1, Eq = 2, Ne = 3, Ge = 4, Gt = 5,
+});
+
+impl_codec_tags!(BoolUnOpCode {
+    IsNaN = 0, IsInf = 1, IsFin = 2,
+});
+
+impl_opcode_codec!(ValBinOpCode KIND_VAL_BINOP
+                   ValUnOpCode KIND_VAL_UNOP
+                   BoolBinOpCode KIND_BOOL_BINOP
+                   BoolUnOpCode KIND_BOOL_UNOP
+                   ValTernOpCode KIND_VAL_TERNOP);
+
+// Returns the number of zero bytes that pad `n` out to a record boundary.
+fn padding(n: usize) -> usize {
+    (RECORD_ALIGN - n % RECORD_ALIGN) % RECORD_ALIGN
+}
+
+fn write_header<W: Write>(w: &mut W, kind: u8, tag: u8, len: u64) -> Result<(), CodecError> {
+    let mut h = [0u8; HEADER_BYTES];
+    h[0..3].copy_from_slice(&MAGIC);
+    h[3] = CODEC_VERSION;
+    h[4] = kind;
+    h[5] = tag;
+    h[8..16].copy_from_slice(&len.to_le_bytes());
+    w.write_all(&h)?;
+    Ok(())
+}
+
+// Reads a header, returning its kind, tag and length.
+fn read_header<R: Read>(r: &mut R) -> Result<(u8, u8, u64), CodecError> {
+    let mut h = [0u8; HEADER_BYTES];
+    r.read_exact(&mut h)?;
+    if h[0..3] != MAGIC {
+        return Err(CodecError::BadMagic);
+    }
+    if h[3] != CODEC_VERSION {
+        return Err(CodecError::BadVersion(h[3]));
+    }
+    check_zero(&h[6..8])?;
+    let mut len = [0u8; 8];
+    len.copy_from_slice(&h[8..16]);
+    Ok((h[4], h[5], u64::from_le_bytes(len)))
+}
+
+fn read_ty(tag: u8) -> Result<ScalarTy, CodecError> {
+    ScalarTy::from_codec_tag(tag).ok_or(CodecError::BadRecord)
+}
+
+// Checks that reserved or padding bytes are all zero.
+fn check_zero(bytes: &[u8]) -> Result<(), CodecError> {
+    if bytes.iter().any(|b| *b != 0) {
+        return Err(CodecError::BadRecord);
+    }
+    Ok(())
+}
+
+// Reads the padding after an `n`-byte payload.
+fn skip_padding<R: Read>(r: &mut R, n: usize) -> Result<(), CodecError> {
+    let mut pad = [0u8; RECORD_ALIGN];
+    r.read_exact(&mut pad[0..padding(n)])?;
+    check_zero(&pad)
+}
+
+// Returns the payload size in bytes of `len` elements of `T`, if sensible.
+fn payload_bytes<T>(len: u64) -> Result<usize, CodecError> {
+    let len = usize::try_from(len).map_err(|_| CodecError::BadRecord)?;
+    len.checked_mul(size_of::<T>()).ok_or(CodecError::BadRecord)
+}
+
+fn write_payload<W: Write, T: Wire>(w: &mut W, s: &[T]) -> Result<(), CodecError> {
+    T::write_le(s, w)?;
+    let pad = [0u8; RECORD_ALIGN];
+    w.write_all(&pad[0..padding(size_of_val(s))])?;
+    Ok(())
+}
+
+// Reads `len` elements of `T` directly into the front of `buf`, which must be
+// aligned for `T` and large enough to hold them.
+fn read_payload<'a, R: Read, T: Wire>(r: &mut R, len: u64, buf: &'a mut [u8])
+                                      -> Result<&'a [T], CodecError> {
+    let n = payload_bytes::<T>(len)?;
+    if buf.len() < n {
+        return Err(CodecError::BadBuffer);
+    }
+    let buf = &mut buf[0..n];
+    // Check alignment before consuming anything from the reader.
+    transmute_buf_mut::<T>(buf)?;
+    r.read_exact(buf)?;
+    skip_padding(r, n)?;
+    T::check_le(buf)?;
+    let s: &mut [T] = transmute_buf_mut(buf)?;
+    T::fix_le(s);
+    Ok(s)
+}
+
+// Reads `len` elements of `T` into a freshly-allocated owned column.
+fn read_owned_payload<R: Read, T: Wire>(r: &mut R, len: u64) -> Result<Arc<[T]>, CodecError> {
+    let n = payload_bytes::<T>(len)?;
+    // Reading via `take` grows the buffer as bytes actually arrive, rather
+    // than trusting a possibly-bogus length up front.
+    let mut bytes = Vec::new();
+    r.by_ref().take(n as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != n {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    skip_padding(r, n)?;
+    T::check_le(&bytes)?;
+    let v = bytes.chunks_exact(size_of::<T>())
+        .map(T::from_le)
+        .collect::<Result<Vec<T>, CodecError>>()?;
+    Ok(v.into())
+}
+
+fn read_const_payload<R: Read>(r: &mut R, ty: ScalarTy) -> Result<Const, CodecError> {
+    use Const::*;
+    use ScalarTy::*;
+    let mut b = [0u8; 16];
+    r.read_exact(&mut b)?;
+    check_zero(&b[ty.size_of()..])?;
+    let c = match ty {
+        TBool => ConstBool(Wire::from_le(&b)?),
+        TU8 => ConstU8(Wire::from_le(&b)?),
+        TU16 => ConstU16(Wire::from_le(&b)?),
+        TU32 => ConstU32(Wire::from_le(&b)?),
+        TU64 => ConstU64(Wire::from_le(&b)?),
+        TU128 => ConstU128(Wire::from_le(&b)?),
+        TI8 => ConstI8(Wire::from_le(&b)?),
+        TI16 => ConstI16(Wire::from_le(&b)?),
+        TI32 => ConstI32(Wire::from_le(&b)?),
+        TI64 => ConstI64(Wire::from_le(&b)?),
+        TI128 => ConstI128(Wire::from_le(&b)?),
+        TF32 => ConstF32(Wire::from_le(&b)?),
+        TF64 => ConstF64(Wire::from_le(&b)?),
+    };
+    Ok(c)
+}
+
+fn read_slice_payload<'a, R: Read>(r: &mut R, ty: ScalarTy, len: u64, buf: &'a mut [u8])
+                                   -> Result<Slice<'a>, CodecError> {
+    use Slice::*;
+    use ScalarTy::*;
+    let s = match ty {
+        TBool => SliceBool(read_payload(r, len, buf)?),
+        TU8 => SliceU8(read_payload(r, len, buf)?),
+        TU16 => SliceU16(read_payload(r, len, buf)?),
+        TU32 => SliceU32(read_payload(r, len, buf)?),
+        TU64 => SliceU64(read_payload(r, len, buf)?),
+        TU128 => SliceU128(read_payload(r, len, buf)?),
+        TI8 => SliceI8(read_payload(r, len, buf)?),
+        TI16 => SliceI16(read_payload(r, len, buf)?),
+        TI32 => SliceI32(read_payload(r, len, buf)?),
+        TI64 => SliceI64(read_payload(r, len, buf)?),
+        TI128 => SliceI128(read_payload(r, len, buf)?),
+        TF32 => SliceF32(read_payload(r, len, buf)?),
+        TF64 => SliceF64(read_payload(r, len, buf)?),
+    };
+    Ok(s)
+}
+
+fn read_owned_slice_payload<R: Read>(r: &mut R, ty: ScalarTy, len: u64)
+                                     -> Result<OwnedSlice, CodecError> {
+    use OwnedSlice::*;
+    use ScalarTy::*;
+    let s = match ty {
+        TBool => SliceBool(read_owned_payload(r, len)?),
+        TU8 => SliceU8(read_owned_payload(r, len)?),
+        TU16 => SliceU16(read_owned_payload(r, len)?),
+        TU32 => SliceU32(read_owned_payload(r, len)?),
+        TU64 => SliceU64(read_owned_payload(r, len)?),
+        TU128 => SliceU128(read_owned_payload(r, len)?),
+        TI8 => SliceI8(read_owned_payload(r, len)?),
+        TI16 => SliceI16(read_owned_payload(r, len)?),
+        TI32 => SliceI32(read_owned_payload(r, len)?),
+        TI64 => SliceI64(read_owned_payload(r, len)?),
+        TI128 => SliceI128(read_owned_payload(r, len)?),
+        TF32 => SliceF32(read_owned_payload(r, len)?),
+        TF64 => SliceF64(read_owned_payload(r, len)?),
+    };
+    Ok(s)
+}
+
+impl Const {
+    /// Writes the constant as a single record.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), CodecError> {
+        use Const::*;
+        write_header(w, KIND_CONST, self.get_scalar_ty().codec_tag(), 0)?;
+        let b = match *self {
+            ConstBool(v) => v.to_le16(),
+            ConstU8(v) => v.to_le16(),
+            ConstU16(v) => v.to_le16(),
+            ConstU32(v) => v.to_le16(),
+            ConstU64(v) => v.to_le16(),
+            ConstU128(v) => v.to_le16(),
+            ConstI8(v) => v.to_le16(),
+            ConstI16(v) => v.to_le16(),
+            ConstI32(v) => v.to_le16(),
+            ConstI64(v) => v.to_le16(),
+            ConstI128(v) => v.to_le16(),
+            ConstF32(v) => v.to_le16(),
+            ConstF64(v) => v.to_le16(),
+        };
+        w.write_all(&b)?;
+        Ok(())
+    }
+
+    /// Reads a constant record.
+    pub fn read_from<R: Read>(r: &mut R) -> Result<Const, CodecError> {
+        match read_header(r)? {
+            (KIND_CONST, tag, 0) => read_const_payload(r, read_ty(tag)?),
+            _ => Err(CodecError::BadRecord)
+        }
+    }
+}
+
+impl<'a> Slice<'a> {
+    /// Writes the slice as a single record.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), CodecError> {
+        use Slice::*;
+        write_header(w, KIND_SLICE, self.get_scalar_ty().codec_tag(), self.len() as u64)?;
+        match *self {
+            SliceBool(s) => write_payload(w, s),
+            SliceU8(s) => write_payload(w, s),
+            SliceU16(s) => write_payload(w, s),
+            SliceU32(s) => write_payload(w, s),
+            SliceU64(s) => write_payload(w, s),
+            SliceU128(s) => write_payload(w, s),
+            SliceI8(s) => write_payload(w, s),
+            SliceI16(s) => write_payload(w, s),
+            SliceI32(s) => write_payload(w, s),
+            SliceI64(s) => write_payload(w, s),
+            SliceI128(s) => write_payload(w, s),
+            SliceF32(s) => write_payload(w, s),
+            SliceF64(s) => write_payload(w, s),
+        }
+    }
+
+    /// Reads a slice record directly into the front of `buf`, which must be
+    /// aligned for, and large enough to hold, the slice's elements.
+    pub fn read_from<R: Read>(r: &mut R, buf: &'a mut [u8]) -> Result<Slice<'a>, CodecError> {
+        match read_header(r)? {
+            (KIND_SLICE, tag, len) => read_slice_payload(r, read_ty(tag)?, len, buf),
+            _ => Err(CodecError::BadRecord)
+        }
+    }
+}
+
+impl<'a> Operand<'a> {
+    /// Writes the operand as a single const or slice record.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), CodecError> {
+        match self {
+            Operand::OperandConst(c) => c.write_to(w),
+            Operand::OperandSlice(s) => s.write_to(w),
+        }
+    }
+
+    /// Reads a const or slice record; slices are read into `buf` as with
+    /// `Slice::read_from`.
+    pub fn read_from<R: Read>(r: &mut R, buf: &'a mut [u8]) -> Result<Operand<'a>, CodecError> {
+        match read_header(r)? {
+            (KIND_CONST, tag, 0) => Ok(Operand::OperandConst(read_const_payload(r, read_ty(tag)?)?)),
+            (KIND_SLICE, tag, len) => Ok(Operand::OperandSlice(read_slice_payload(r, read_ty(tag)?, len, buf)?)),
+            _ => Err(CodecError::BadRecord)
+        }
+    }
+}
+
+impl OwnedOperand {
+    /// Writes the operand as a single const or slice record.
+    pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), CodecError> {
+        self.as_operand().write_to(w)
+    }
+
+    /// Reads a const or slice record, allocating a new column for slices.
+    pub fn read_from<R: Read>(r: &mut R) -> Result<OwnedOperand, CodecError> {
+        match read_header(r)? {
+            (KIND_CONST, tag, 0) => Ok(OwnedOperand::OperandConst(read_const_payload(r, read_ty(tag)?)?)),
+            (KIND_SLICE, tag, len) => Ok(OwnedOperand::OperandSlice(read_owned_slice_payload(r, read_ty(tag)?, len)?)),
+            _ => Err(CodecError::BadRecord)
+        }
+    }
+}
@@ -39,6 +39,10 @@ pub const VECBYTES: usize = 0x40;
 // quite enough time to amortize its overhead. Seems the sweet spot is around
 // 1024 iterations, or 64k chunks. This does not appear to antagonize the L1
 // cache any worse than we did by falling out into Rayon too early. Oh well!
+//
+// All of which was measured on one machine, so this is only the default: the
+// chunk size actually used is `EvalCtx::chunk_bytes`, which can be set per
+// context, eg. to the result of `calibrate_chunk_bytes` on the machine at hand.
 pub const CHUNKBYTES: usize = 0x10000;
 
 // Hacky workaround for "no control flow ops in const at present": calculates
@@ -66,11 +70,38 @@ where
 // reason: we _could_ pass any chunk size we wanted to Rayon, but for the sake
 // of a (hopefully) uniform cache-performance / work-granularity profile we aim
 // to have all the different operand combinations use (as close as possible to)
-// the same number of bytes in their Rayon chunks.
-pub const fn chunksz_min<T, U>() -> usize
+// the same number of bytes in their Rayon chunks. Chunks are kept a multiple of
+// the step size, so that only the very last one has a partial vector.
+pub const fn chunksz_min<T, U>(chunk_bytes: usize) -> usize
 where
     T: std::marker::Sized,
     U: std::marker::Sized,
 {
-    CHUNKBYTES / cmax(size_of::<T>(), size_of::<U>())
+    let n = chunk_bytes / cmax(size_of::<T>(), size_of::<U>());
+    let step = stepsz_min::<T, U>();
+    cmax(n - n % step, step)
+}
+
+// Transposing copies work in square tiles of this many elements a side, so
+// that the lines of the rows read and of the columns written both stay in L1
+// across a tile. A side of at least a cache line of elements uses every line
+// it touches fully; at least 16 keeps the loop overhead down for wide types.
+pub const fn tile_len<T>() -> usize
+where
+    T: std::marker::Sized,
+{
+    cmax(VECBYTES / size_of::<T>(), 16)
+}
+
+// Matrix products work on blocks of the right-hand matrix this many rows
+// deep and `matmul_cols` wide: each row segment of the result, updated once
+// per row of the block, stays in L1, while the block (a few hundred KB)
+// stays in L2 across all the rows of the left-hand matrix.
+pub const MATMUL_DEPTH: usize = 64;
+
+pub const fn matmul_cols<T>() -> usize
+where
+    T: std::marker::Sized,
+{
+    0x1000 / size_of::<T>()
 }
@@ -0,0 +1,61 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Runtime CPU feature detection.
+//
+// The kernels are compiled for the target's baseline features (SSE2 on plain
+// x86-64), plus, on x86 and x86-64, again with AVX2 and with AVX-512 enabled,
+// each along with FMA. The best version the running CPU supports is picked
+// once per operation, by the kernel, from the `SimdLevel` in its `Sched`.
+// Detection itself is done once per process and cached.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The instruction set extensions kernels can be compiled for, in increasing
+/// order. `Baseline` is whatever the crate was compiled for, and is the only
+/// level on targets other than x86 and x86-64.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SimdLevel {
+    Baseline,
+    Avx2,
+    Avx512,
+}
+
+// Zero means not yet detected.
+static DETECTED: AtomicU8 = AtomicU8::new(0);
+
+impl SimdLevel {
+    /// Returns the highest level the running CPU supports.
+    pub fn detect() -> SimdLevel {
+        match DETECTED.load(Ordering::Relaxed) {
+            0 => {
+                let level = detect_uncached();
+                DETECTED.store(level as u8 + 1, Ordering::Relaxed);
+                level
+            }
+            1 => SimdLevel::Baseline,
+            2 => SimdLevel::Avx2,
+            _ => SimdLevel::Avx512,
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn detect_uncached() -> SimdLevel {
+    // Every CPU with AVX2 or AVX-512 that's been made also has FMA, but it's
+    // a separate feature, which the kernels enable with either.
+    if !is_x86_feature_detected!("fma") {
+        SimdLevel::Baseline
+    } else if is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("avx512bw") {
+        SimdLevel::Avx512
+    } else if is_x86_feature_detected!("avx2") {
+        SimdLevel::Avx2
+    } else {
+        SimdLevel::Baseline
+    }
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn detect_uncached() -> SimdLevel {
+    SimdLevel::Baseline
+}
@@ -1,10 +1,18 @@
 // Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
 // Licensed under the MIT and Apache-2.0 licenses.
 
+use std::sync::Mutex;
+
 use crate::ops::*;
 use crate::traits::*;
 use crate::scalarty::*;
 use crate::operands::*;
+use crate::consts::*;
+use crate::sched::*;
+use crate::cpu::SimdLevel;
+use crate::promotion::*;
+use crate::fallback::swap_ordering_mask;
+use crate::plan::{compares_exactly, val_binop_ty};
 
 #[derive(Debug)]
 pub enum EvalError {
@@ -15,29 +23,62 @@ pub enum EvalError {
     /// accommodate the input and output operands. If any buffer sizes are
     /// wrong, or one of the buffer transmutes fails for some other reason (bad
     /// alignment or inadequate size) an `EvalError::BadBuffer` is returned.
-    BadBuffer
+    BadBuffer,
+    /// A `CastMode::Checked` conversion found a value out of range of the
+    /// destination type, at the given index (0 for a const). If there are
+    /// several, this is the first.
+    CastOutOfRange(usize),
+    /// A `CastMode::Checked` conversion to an integer type found a NaN, at
+    /// the given index (0 for a const). If there are several, this is the
+    /// first.
+    CastNaN(usize),
+    /// The `PromotionPolicy` refused to promote operands of the given types
+    /// to a common type.
+    LossyPromotion(ScalarTy, ScalarTy),
+    /// An in-place operation on a slice of the first type was given an
+    /// operand of the second type, which would first need converting to a
+    /// separate buffer.
+    InPlaceConversion(ScalarTy, ScalarTy),
+    /// The shapes of the operands of an operation on `ShapedOperand`s don't
+    /// agree, and neither is a single element.
+    ShapeMismatch(Vec<usize>, Vec<usize>),
+    /// An axis given to a structural operation on a `ShapedOperand` is out
+    /// of range of its rank, or the axes given to `transpose_axes` aren't a
+    /// permutation of its axes.
+    BadAxis,
+}
+
+impl From<OpError> for EvalError {
+    fn from(e: OpError) -> Self {
+        match e {
+            OpError::Unsupported => EvalError::UnsupportedOp,
+            OpError::CastOutOfRange(i) => EvalError::CastOutOfRange(i),
+            OpError::CastNaN(i) => EvalError::CastNaN(i),
+        }
+    }
 }
 
 // Returns the count of `T` elements that fit in x, or error if
-// `x` is not an even multiple of the size of `T` or is not aligned
-// to the alignment of `T`.
+// `x` is not aligned to the alignment of `T`. Any trailing bytes
+// too few for another `T` are ignored.
 fn check_align_and_size<T>(x: &[u8]) -> Result<usize, EvalError>
 {
     use core::mem::{align_of, size_of};
     let a = x.as_ptr() as usize;
-    if (a % align_of::<T>()) != 0 {
+    if a & (align_of::<T>() - 1) != 0 {
         return Err(EvalError::BadBuffer)
     }
-    let n = x.len();
-    if (n % size_of::<T>()) != 0 {
-        return Err(EvalError::BadBuffer)
-    }
-    Ok(n / size_of::<T>())
+    Ok(x.len() / size_of::<T>())
 }
 
-fn transmute_buf_mut<T>(x: &mut [u8]) -> Result<&mut [T], EvalError>
+// An empty buffer transmutes to an empty slice whatever its alignment, so
+// buffers an operation doesn't need can be left empty.
+pub(crate) fn transmute_buf_mut<T>(x: &mut [u8]) -> Result<&mut [T], EvalError>
 where T:ScalarT
 {
+    if x.is_empty() {
+        return Ok(&mut []);
+    }
     let m = check_align_and_size::<T>(x)?;
     let p = x.as_mut_ptr() as *mut T;
     Ok(unsafe { core::slice::from_raw_parts_mut(p, m) })
@@ -54,27 +95,10 @@ fn check_equal_lengths<T>(x: &[T], y: &[T]) -> Result<(), EvalError>
     Ok(())
 }
 
-// Checks that `x` is an exact multiple of `consts::VECBYTES / sizeof(T)`
-// and `consts::CHUNKBYTES / sizeof(T)`, returns error if not.
-fn check_ok_length<T>(x: &[T]) -> Result<(), EvalError> {
-    let n = x.len();
-    let sz: usize = core::mem::size_of::<T>();
-    let ch: usize = crate::consts::CHUNKBYTES / sz;
-    let v: usize = crate::consts::VECBYTES / sz;
-    if (n & (ch - 1)) != 0 {
-        return Err(EvalError::BadBuffer);
-    }
-    if (n & (v - 1)) != 0 {
-        return Err(EvalError::BadBuffer);
-    }
-    Ok(())
-}
-
 // Returns `x` sliced-down to to the length of `bound`, or error if
 // `x` is less than `bound`.
 fn bound_output_length<'a, T, U>(x: &'a mut [T], bound: &[U]) -> Result<&'a mut [T], EvalError>
 {
-    check_ok_length(bound)?;
     let n = x.len();
     let k = bound.len();
     if n < k {
@@ -84,18 +108,127 @@ fn bound_output_length<'a, T, U>(x: &'a mut [T], bound: &[U]) -> Result<&'a mut
 }
 
 /// Every top-level evaluation step in newel happens against an EvalCtx that
-/// holds 3 mutable buffers of some multiple of CHUNKSZ bytes. These buffers
-/// (and the EvalCtx itself) get _used up_ during the operation. The first two
-/// buffers are for holding possible conversions of 1 or 2 inputs to the
-/// operation; the third buffer stores the output.
+/// holds 3 mutable buffers, each aligned for and at least as large as the
+/// operands they'll hold. These buffers (and the EvalCtx itself) get _used up_
+/// during the operation. The first two buffers are for holding possible
+/// conversions of 1 or 2 inputs to the operation; the third buffer stores the
+/// output. Operands may be any length: sizing them in multiples of the chunk
+/// size is a little faster, but not required. The `plan_*` functions
+/// give the exact sizes an operation needs; buffers it doesn't use can be
+/// empty.
+///
+/// Operations on slices are split into chunks of about `CHUNKBYTES` bytes, or
+/// as set by `with_chunk_bytes`, which are spread across threads as set by
+/// `with_parallelism`: by default on rayon's global thread pool, or on the one
+/// given to `with_thread_pool`. `calibrate_chunk_bytes` can find a better
+/// chunk size for the machine at hand. Without the `rayon` feature, every
+/// operation runs serially on the calling thread.
+///
+/// Kernels use the widest SIMD instruction set the CPU supports, up to any
+/// limit set by `with_simd_level`; lowering it is mostly useful for testing
+/// and benchmarking.
+///
+/// The operands of binary and ternary operators are promoted to a common type
+/// chosen by `DefaultPromotion`, or the policy given to `with_promotion`.
 pub struct EvalCtx<'eval> {
     pub tmp1: &'eval mut [u8],
     pub tmp2: &'eval mut [u8],
     pub out: &'eval mut [u8],
+    pub(crate) chunk_bytes: usize,
+    pub(crate) parallelism: Parallelism,
+    pub(crate) simd_level: SimdLevel,
+    pub(crate) promotion: &'eval dyn PromotionPolicy,
+    #[cfg(feature = "rayon")]
+    pub(crate) thread_pool: Option<&'eval rayon::ThreadPool>,
 }
 
 impl<'eval> EvalCtx<'eval> {
 
+    /// Returns an `EvalCtx` over the given buffers, with the default chunk
+    /// size `CHUNKBYTES`.
+    pub fn new(tmp1: &'eval mut [u8],
+               tmp2: &'eval mut [u8],
+               out: &'eval mut [u8]) -> EvalCtx<'eval> {
+        EvalCtx {
+            tmp1, tmp2, out,
+            chunk_bytes: CHUNKBYTES,
+            parallelism: Parallelism::default(),
+            simd_level: SimdLevel::detect(),
+            promotion: &DefaultPromotion,
+            #[cfg(feature = "rayon")]
+            thread_pool: None,
+        }
+    }
+
+    /// Sets the chunk size, in bytes, that operations are split into.
+    pub fn with_chunk_bytes(self, chunk_bytes: usize) -> EvalCtx<'eval> {
+        EvalCtx { chunk_bytes, ..self }
+    }
+
+    /// Sets how operations are spread across threads.
+    pub fn with_parallelism(self, parallelism: Parallelism) -> EvalCtx<'eval> {
+        EvalCtx { parallelism, ..self }
+    }
+
+    /// Limits kernels to SIMD instruction sets up to `simd_level`.
+    pub fn with_simd_level(self, simd_level: SimdLevel) -> EvalCtx<'eval> {
+        EvalCtx { simd_level, ..self }
+    }
+
+    /// Promotes operands to common types according to `promotion`.
+    pub fn with_promotion(self, promotion: &'eval dyn PromotionPolicy) -> EvalCtx<'eval> {
+        EvalCtx { promotion, ..self }
+    }
+
+    /// Runs parallel operations on `pool` rather than rayon's global pool.
+    #[cfg(feature = "rayon")]
+    pub fn with_thread_pool(self, pool: &'eval rayon::ThreadPool) -> EvalCtx<'eval> {
+        EvalCtx { thread_pool: Some(pool), ..self }
+    }
+
+    // Returns a copy of this `EvalCtx` that borrows its buffers, so that it
+    // can be used up by an operation and this one used again afterwards.
+    pub(crate) fn reborrow(&mut self) -> EvalCtx<'_> {
+        EvalCtx {
+            tmp1: &mut *self.tmp1,
+            tmp2: &mut *self.tmp2,
+            out: &mut *self.out,
+            chunk_bytes: self.chunk_bytes,
+            parallelism: self.parallelism,
+            simd_level: self.simd_level,
+            promotion: self.promotion,
+            #[cfg(feature = "rayon")]
+            thread_pool: self.thread_pool,
+        }
+    }
+
+    // Returns a copy of this `EvalCtx`'s settings, using the given buffers.
+    pub(crate) fn with_buffers<'b>(&self, tmp1: &'b mut [u8], tmp2: &'b mut [u8],
+                                   out: &'b mut [u8]) -> EvalCtx<'b>
+    where 'eval: 'b
+    {
+        EvalCtx {
+            tmp1,
+            tmp2,
+            out,
+            chunk_bytes: self.chunk_bytes,
+            parallelism: self.parallelism,
+            simd_level: self.simd_level,
+            promotion: self.promotion,
+            #[cfg(feature = "rayon")]
+            thread_pool: self.thread_pool,
+        }
+    }
+
+    pub(crate) fn sched(&self) -> Sched<'eval> {
+        let sched = Sched::new(self.chunk_bytes)
+            .with_parallelism(self.parallelism)
+            .with_simd_level(self.simd_level);
+        #[cfg(feature = "rayon")]
+        let sched = sched.with_pool(self.thread_pool);
+        sched
+    }
+
     /// Convert an `Operand` to a given `ScalarTy`.
     pub fn conv<'slice: 'eval>(self, s: &Operand<'slice>, ty: ScalarTy)
                            -> Result<Operand<'eval>, EvalError> {
@@ -136,25 +269,91 @@ impl<'eval> EvalCtx<'eval> {
         use transmute_buf_mut as tm;
         use Slice::*;
         use ScalarTy::*;
+        let sched = self.sched();
+        let ok = match ty {
+            TBool => SliceBool(conv_slice_dynamic(&sched, s, tm(self.out)?)?),
+            TU8 => SliceU8(conv_slice_dynamic(&sched, s, tm(self.out)?)?),
+            TU16 => SliceU16(conv_slice_dynamic(&sched, s, tm(self.out)?)?),
+            TU32 => SliceU32(conv_slice_dynamic(&sched, s, tm(self.out)?)?),
+            TU64 => SliceU64(conv_slice_dynamic(&sched, s, tm(self.out)?)?),
+            TU128 => SliceU128(conv_slice_dynamic(&sched, s, tm(self.out)?)?),
+            TI8 => SliceI8(conv_slice_dynamic(&sched, s, tm(self.out)?)?),
+            TI16 => SliceI16(conv_slice_dynamic(&sched, s, tm(self.out)?)?),
+            TI32 => SliceI32(conv_slice_dynamic(&sched, s, tm(self.out)?)?),
+            TI64 => SliceI64(conv_slice_dynamic(&sched, s, tm(self.out)?)?),
+            TI128 => SliceI128(conv_slice_dynamic(&sched, s, tm(self.out)?)?),
+            TF32 => SliceF32(conv_slice_dynamic(&sched, s, tm(self.out)?)?),
+            TF64 => SliceF64(conv_slice_dynamic(&sched, s, tm(self.out)?)?),
+        };
+        Ok(ok)
+    }
+
+    /// Convert an `Operand` to a given `ScalarTy`, handling values out of its
+    /// range according to `mode`. `CastMode::Wrapping` is the same as `conv`.
+    pub fn cast<'slice: 'eval>(self, s: &Operand<'slice>, ty: ScalarTy, mode: CastMode)
+                           -> Result<Operand<'eval>, EvalError> {
+        use Operand::*;
+        if mode == CastMode::Wrapping {
+            return self.conv(s, ty);
+        }
+        let ok = match s {
+            OperandSlice(s) => OperandSlice(self.cast_slice(s, ty, mode)?),
+            OperandConst(c) => OperandConst(self.cast_const(c, ty, mode)?),
+        };
+        Ok(ok)
+    }
+
+    fn cast_const(self, c: &Const, ty: ScalarTy, mode: CastMode) -> Result<Const, EvalError> {
+        use Const::*;
+        use ScalarTy::*;
+        let ok = match ty {
+            TBool => ConstBool(cast_const_dynamic(c, mode)?),
+            TU8 => ConstU8(cast_const_dynamic(c, mode)?),
+            TU16 => ConstU16(cast_const_dynamic(c, mode)?),
+            TU32 => ConstU32(cast_const_dynamic(c, mode)?),
+            TU64 => ConstU64(cast_const_dynamic(c, mode)?),
+            TU128 => ConstU128(cast_const_dynamic(c, mode)?),
+            TI8 => ConstI8(cast_const_dynamic(c, mode)?),
+            TI16 => ConstI16(cast_const_dynamic(c, mode)?),
+            TI32 => ConstI32(cast_const_dynamic(c, mode)?),
+            TI64 => ConstI64(cast_const_dynamic(c, mode)?),
+            TI128 => ConstI128(cast_const_dynamic(c, mode)?),
+            TF32 => ConstF32(cast_const_dynamic(c, mode)?),
+            TF64 => ConstF64(cast_const_dynamic(c, mode)?),
+        };
+        Ok(ok)
+    }
+
+    #[inline(never)]
+    fn cast_slice<'slice>(self, s: &Slice<'slice>, ty: ScalarTy, mode: CastMode)
+                          -> Result<Slice<'eval>, EvalError>
+    where 'slice: 'eval
+    {
+        use transmute_buf_mut as tm;
+        use Slice::*;
+        use ScalarTy::*;
+        let sched = self.sched();
         let ok = match ty {
-            TBool => SliceBool(conv_slice_dynamic(s, tm(self.out)?)?),
-            TU8 => SliceU8(conv_slice_dynamic(s, tm(self.out)?)?),
-            TU16 => SliceU16(conv_slice_dynamic(s, tm(self.out)?)?),
-            TU32 => SliceU32(conv_slice_dynamic(s, tm(self.out)?)?),
-            TU64 => SliceU64(conv_slice_dynamic(s, tm(self.out)?)?),
-            TU128 => SliceU128(conv_slice_dynamic(s, tm(self.out)?)?),
-            TI8 => SliceI8(conv_slice_dynamic(s, tm(self.out)?)?),
-            TI16 => SliceI16(conv_slice_dynamic(s, tm(self.out)?)?),
-            TI32 => SliceI32(conv_slice_dynamic(s, tm(self.out)?)?),
-            TI64 => SliceI64(conv_slice_dynamic(s, tm(self.out)?)?),
-            TI128 => SliceI128(conv_slice_dynamic(s, tm(self.out)?)?),
-            TF32 => SliceF32(conv_slice_dynamic(s, tm(self.out)?)?),
-            TF64 => SliceF64(conv_slice_dynamic(s, tm(self.out)?)?),
+            TBool => SliceBool(cast_slice_dynamic(&sched, s, tm(self.out)?, mode)?),
+            TU8 => SliceU8(cast_slice_dynamic(&sched, s, tm(self.out)?, mode)?),
+            TU16 => SliceU16(cast_slice_dynamic(&sched, s, tm(self.out)?, mode)?),
+            TU32 => SliceU32(cast_slice_dynamic(&sched, s, tm(self.out)?, mode)?),
+            TU64 => SliceU64(cast_slice_dynamic(&sched, s, tm(self.out)?, mode)?),
+            TU128 => SliceU128(cast_slice_dynamic(&sched, s, tm(self.out)?, mode)?),
+            TI8 => SliceI8(cast_slice_dynamic(&sched, s, tm(self.out)?, mode)?),
+            TI16 => SliceI16(cast_slice_dynamic(&sched, s, tm(self.out)?, mode)?),
+            TI32 => SliceI32(cast_slice_dynamic(&sched, s, tm(self.out)?, mode)?),
+            TI64 => SliceI64(cast_slice_dynamic(&sched, s, tm(self.out)?, mode)?),
+            TI128 => SliceI128(cast_slice_dynamic(&sched, s, tm(self.out)?, mode)?),
+            TF32 => SliceF32(cast_slice_dynamic(&sched, s, tm(self.out)?, mode)?),
+            TF64 => SliceF64(cast_slice_dynamic(&sched, s, tm(self.out)?, mode)?),
         };
         Ok(ok)
     }
 
-    /// Perform a given `ValBinOpCode` on a pair of `Operand`s.
+    /// Perform a given `ValBinOpCode` on a pair of `Operand`s, converted to
+    /// the type the `PromotionPolicy` joins theirs to; except for shifts and
+    /// rotates, which keep the type of `lhs` and only convert `rhs`.
     #[inline(never)]
     pub fn val_binop<'slice>(self, op: ValBinOpCode,
                              lhs: &Operand<'slice>,
@@ -169,8 +368,8 @@ impl<'eval> EvalCtx<'eval> {
     where 'slice: 'eval
     {
         use ScalarTy::*;
-        let common_ty = lhs.get_scalar_ty().join(rhs.get_scalar_ty());
-        match common_ty {
+        let ty = val_binop_ty(self.promotion, &op, lhs.get_scalar_ty(), rhs.get_scalar_ty())?;
+        match ty {
             TBool => self.val_binop_static::<bool>(op, lhs, rhs),
             TU8 => self.val_binop_static::<u8>(op, lhs, rhs),
             TU16 => self.val_binop_static::<u16>(op, lhs, rhs),
@@ -187,7 +386,11 @@ impl<'eval> EvalCtx<'eval> {
         }
     }
 
-    /// Perform a given `BoolBinOpCode` on a pair of `Operand`s.
+    /// Perform a given `BoolBinOpCode` on a pair of `Operand`s. If their
+    /// common type can't represent both operand types exactly (eg. `i64` and
+    /// `u64`, or `f32` and `i64`), or comparisons on it aren't compiled in,
+    /// they're compared exactly, unconverted, without consulting the
+    /// `PromotionPolicy`.
     #[inline(never)]
     pub fn bool_binop<'slice>(self, op: BoolBinOpCode,
                               lhs: &Operand<'slice>,
@@ -196,8 +399,13 @@ impl<'eval> EvalCtx<'eval> {
     where 'slice: 'eval
     {
         use ScalarTy::*;
-        let common_ty = lhs.get_scalar_ty().join(rhs.get_scalar_ty());
-        match common_ty {
+        let (lty, rty) = (lhs.get_scalar_ty(), rhs.get_scalar_ty());
+        // If converting to the common type would lose information, or isn't
+        // compiled in, compare the operands exactly, as they are.
+        if compares_exactly(op.clone(), lty, rty) {
+            return self.mixed_cmp(op.ordering_mask(), lhs, rhs);
+        }
+        match self.promotion.join(lty, rty)? {
             TBool => self.bool_binop_static::<bool>(op, lhs, rhs),
             TU8 => self.bool_binop_static::<u8>(op, lhs, rhs),
             TU16 => self.bool_binop_static::<u16>(op, lhs, rhs),
@@ -264,6 +472,101 @@ impl<'eval> EvalCtx<'eval> {
         }
     }
 
+    /// Perform a given `ValTernOpCode` on three `Operand`s. Any that are
+    /// slices must all be the same length; consts are broadcast across them.
+    #[inline(never)]
+    pub fn val_ternop<'slice>(self, op: ValTernOpCode,
+                              a: &Operand<'slice>,
+                              b: &Operand<'slice>,
+                              c: &Operand<'slice>)
+                              -> Result<Operand<'eval>, EvalError>
+    where 'slice: 'eval
+    {
+        use ScalarTy::*;
+        let ab_ty = self.promotion.join(a.get_scalar_ty(), b.get_scalar_ty())?;
+        let common_ty = self.promotion.join(ab_ty, c.get_scalar_ty())?;
+        match common_ty {
+            TBool => self.val_ternop_static::<bool>(op, a, b, c),
+            TU8 => self.val_ternop_static::<u8>(op, a, b, c),
+            TU16 => self.val_ternop_static::<u16>(op, a, b, c),
+            TU32 => self.val_ternop_static::<u32>(op, a, b, c),
+            TU64 => self.val_ternop_static::<u64>(op, a, b, c),
+            TU128 => self.val_ternop_static::<u128>(op, a, b, c),
+            TI8 => self.val_ternop_static::<i8>(op, a, b, c),
+            TI16 => self.val_ternop_static::<i16>(op, a, b, c),
+            TI32 => self.val_ternop_static::<i32>(op, a, b, c),
+            TI64 => self.val_ternop_static::<i64>(op, a, b, c),
+            TI128 => self.val_ternop_static::<i128>(op, a, b, c),
+            TF32 => self.val_ternop_static::<f32>(op, a, b, c),
+            TF64 => self.val_ternop_static::<f64>(op, a, b, c),
+        }
+    }
+
+    /// Perform a given `ValUnOpCode` on a `SliceMut`, overwriting it with the
+    /// result. This needs none of the `EvalCtx`'s buffers, which can be empty.
+    /// If the opcode is unsupported on the slice's type, it's left unchanged.
+    #[inline(never)]
+    pub fn val_unop_in_place(self, op: ValUnOpCode, acc: &mut SliceMut)
+                             -> Result<(), EvalError>
+    {
+        use SliceMut::*;
+        match acc {
+            SliceMutBool(s) => self.val_unop_in_place_static::<bool>(op, s),
+            SliceMutU8(s) => self.val_unop_in_place_static::<u8>(op, s),
+            SliceMutU16(s) => self.val_unop_in_place_static::<u16>(op, s),
+            SliceMutU32(s) => self.val_unop_in_place_static::<u32>(op, s),
+            SliceMutU64(s) => self.val_unop_in_place_static::<u64>(op, s),
+            SliceMutU128(s) => self.val_unop_in_place_static::<u128>(op, s),
+            SliceMutI8(s) => self.val_unop_in_place_static::<i8>(op, s),
+            SliceMutI16(s) => self.val_unop_in_place_static::<i16>(op, s),
+            SliceMutI32(s) => self.val_unop_in_place_static::<i32>(op, s),
+            SliceMutI64(s) => self.val_unop_in_place_static::<i64>(op, s),
+            SliceMutI128(s) => self.val_unop_in_place_static::<i128>(op, s),
+            SliceMutF32(s) => self.val_unop_in_place_static::<f32>(op, s),
+            SliceMutF64(s) => self.val_unop_in_place_static::<f64>(op, s),
+        }
+    }
+
+    /// Perform a given `ValBinOpCode` on a `SliceMut` and an `Operand`,
+    /// overwriting the slice with the result, as in `lhs op= rhs`. This needs
+    /// none of the `EvalCtx`'s buffers, which can be empty. Nothing is
+    /// converted: if `rhs` is a slice it must be of the same type and length
+    /// as `lhs`, and if it's a const its type must promote to that of `lhs`
+    /// (or, for shifts and rotates, be an integer), or an
+    /// `EvalError::InPlaceConversion` is returned. If the opcode is
+    /// unsupported on the slice's type, it's left unchanged.
+    #[inline(never)]
+    pub fn val_binop_in_place<'slice>(self, op: ValBinOpCode,
+                                      lhs: &mut SliceMut,
+                                      rhs: &Operand<'slice>)
+                                      -> Result<(), EvalError>
+    {
+        use SliceMut::*;
+        let (lty, rty) = (lhs.get_scalar_ty(), rhs.get_scalar_ty());
+        let converts = match rhs {
+            Operand::OperandSlice(_) => rty != lty,
+            Operand::OperandConst(_) => val_binop_ty(self.promotion, &op, lty, rty)? != lty,
+        };
+        if converts {
+            return Err(EvalError::InPlaceConversion(lty, rty));
+        }
+        match lhs {
+            SliceMutBool(s) => self.val_binop_in_place_static::<bool>(op, s, rhs),
+            SliceMutU8(s) => self.val_binop_in_place_static::<u8>(op, s, rhs),
+            SliceMutU16(s) => self.val_binop_in_place_static::<u16>(op, s, rhs),
+            SliceMutU32(s) => self.val_binop_in_place_static::<u32>(op, s, rhs),
+            SliceMutU64(s) => self.val_binop_in_place_static::<u64>(op, s, rhs),
+            SliceMutU128(s) => self.val_binop_in_place_static::<u128>(op, s, rhs),
+            SliceMutI8(s) => self.val_binop_in_place_static::<i8>(op, s, rhs),
+            SliceMutI16(s) => self.val_binop_in_place_static::<i16>(op, s, rhs),
+            SliceMutI32(s) => self.val_binop_in_place_static::<i32>(op, s, rhs),
+            SliceMutI64(s) => self.val_binop_in_place_static::<i64>(op, s, rhs),
+            SliceMutI128(s) => self.val_binop_in_place_static::<i128>(op, s, rhs),
+            SliceMutF32(s) => self.val_binop_in_place_static::<f32>(op, s, rhs),
+            SliceMutF64(s) => self.val_binop_in_place_static::<f64>(op, s, rhs),
+        }
+    }
+
     #[inline(never)]
     fn val_binop_static<'slice, T>(self, op: ValBinOpCode,
                                    lhs: &Operand<'slice>,
@@ -272,7 +575,7 @@ impl<'eval> EvalCtx<'eval> {
     where
         'slice: 'eval,
         T: 'eval,
-        T: ScalarT,
+        T: ScalarT + ScalarOf,
         Slice<'slice>: From<&'eval [T]>,
         Const: From<T>,
 
@@ -287,6 +590,13 @@ impl<'eval> EvalCtx<'eval> {
         BitAndOp<T, T>: BinOp<T, T>,
         BitOrOp<T, T>: BinOp<T, T>,
         BitXorOp<T, T>: BinOp<T, T>,
+        ShlOp<T, T>: BinOp<T, T>,
+        LShrOp<T, T>: BinOp<T, T>,
+        AShrOp<T, T>: BinOp<T, T>,
+        RotLOp<T, T>: BinOp<T, T>,
+        RotROp<T, T>: BinOp<T, T>,
+        Atan2Op<T, T>: BinOp<T, T>,
+        HypotOp<T, T>: BinOp<T, T>,
 
         ConvOp<bool, T>: UnOp<bool, T>,
         ConvOp<u8, T>: UnOp<u8, T>,
@@ -305,30 +615,35 @@ impl<'eval> EvalCtx<'eval> {
         use Operand::*;
         use ValBinOpCode::*;
         use transmute_buf_mut as tm;
+        let sched = self.sched();
         match (lhs, rhs) {
             (OperandSlice(lhs), OperandSlice(rhs)) => {
                 let tlhs: &mut [T] = tm(self.tmp1)?;
                 let trhs: &mut [T] = tm(self.tmp2)?;
                 let tdst: &mut [T] = tm(self.out)?;
-                let clhs = conv_slice_dynamic(lhs, tlhs)?;
-                let crhs = conv_slice_dynamic(rhs, trhs)?;
+                let clhs = conv_slice_dynamic(&sched, lhs, tlhs)?;
+                let crhs = conv_slice_dynamic(&sched, rhs, trhs)?;
                 let dst = bound_output_length(tdst, crhs)?;
                 check_equal_lengths(clhs, crhs)?;
-                check_ok_length(clhs)?;
-                check_ok_length(crhs)?;
-                check_ok_length(dst)?;
                 let res = match op {
-                    Add => <AddOp<T, T>>::apply_slice_slice(clhs, crhs, dst),
-                    Sub => <SubOp<T, T>>::apply_slice_slice(clhs, crhs, dst),
-                    Mul => <MulOp<T, T>>::apply_slice_slice(clhs, crhs, dst),
-                    Div => <DivOp<T, T>>::apply_slice_slice(clhs, crhs, dst),
-                    Rem => <RemOp<T, T>>::apply_slice_slice(clhs, crhs, dst),
-                    Min => <MinOp<T, T>>::apply_slice_slice(clhs, crhs, dst),
-                    Max => <MaxOp<T, T>>::apply_slice_slice(clhs, crhs, dst),
-                    Pow => <PowOp<T, T>>::apply_slice_slice(clhs, crhs, dst),
-                    BitAnd => <BitAndOp<T, T>>::apply_slice_slice(clhs, crhs, dst),
-                    BitOr => <BitOrOp<T, T>>::apply_slice_slice(clhs, crhs, dst),
-                    BitXor => <BitXorOp<T, T>>::apply_slice_slice(clhs, crhs, dst),
+                    Add => <AddOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Sub => <SubOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Mul => <MulOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Div => <DivOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Rem => <RemOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Min => <MinOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Max => <MaxOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Pow => <PowOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    BitAnd => <BitAndOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    BitOr => <BitOrOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    BitXor => <BitXorOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Shl => <ShlOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    LShr => <LShrOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    AShr => <AShrOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    RotL => <RotLOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    RotR => <RotROp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Atan2 => <Atan2Op<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Hypot => <HypotOp<T, T>>::apply_slice_slice(&sched, clhs, crhs, dst),
                 };
                 match res {
                     Ok(slice) => Ok(OperandSlice(slice.into())),
@@ -338,23 +653,28 @@ impl<'eval> EvalCtx<'eval> {
             (OperandSlice(lhs), OperandConst(rhs)) => {
                 let tlhs: &mut [T] = tm(self.tmp1)?;
                 let tdst: &mut [T] = tm(self.out)?;
-                let clhs = conv_slice_dynamic(lhs, tlhs)?;
+                let clhs = conv_slice_dynamic(&sched, lhs, tlhs)?;
                 let crhs = conv_const_dynamic(rhs)?;
                 let dst = bound_output_length(tdst, clhs)?;
-                check_ok_length(clhs)?;
-                check_ok_length(dst)?;
                 let res = match op {
-                    Add => <AddOp<T, T>>::apply_slice_const(clhs, crhs, dst),
-                    Sub => <SubOp<T, T>>::apply_slice_const(clhs, crhs, dst),
-                    Mul => <MulOp<T, T>>::apply_slice_const(clhs, crhs, dst),
-                    Div => <DivOp<T, T>>::apply_slice_const(clhs, crhs, dst),
-                    Rem => <RemOp<T, T>>::apply_slice_const(clhs, crhs, dst),
-                    Min => <MinOp<T, T>>::apply_slice_const(clhs, crhs, dst),
-                    Max => <MaxOp<T, T>>::apply_slice_const(clhs, crhs, dst),
-                    Pow => <PowOp<T, T>>::apply_slice_const(clhs, crhs, dst),
-                    BitAnd => <BitAndOp<T, T>>::apply_slice_const(clhs, crhs, dst),
-                    BitOr => <BitOrOp<T, T>>::apply_slice_const(clhs, crhs, dst),
-                    BitXor => <BitXorOp<T, T>>::apply_slice_const(clhs, crhs, dst),
+                    Add => <AddOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Sub => <SubOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Mul => <MulOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Div => <DivOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Rem => <RemOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Min => <MinOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Max => <MaxOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Pow => <PowOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    BitAnd => <BitAndOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    BitOr => <BitOrOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    BitXor => <BitXorOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Shl => <ShlOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    LShr => <LShrOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    AShr => <AShrOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    RotL => <RotLOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    RotR => <RotROp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Atan2 => <Atan2Op<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Hypot => <HypotOp<T, T>>::apply_slice_const(&sched, clhs, crhs, dst),
                 };
                 match res {
                     Ok(slice) => Ok(OperandSlice(slice.into())),
@@ -365,22 +685,27 @@ impl<'eval> EvalCtx<'eval> {
                 let trhs: &mut [T] = tm(self.tmp2)?;
                 let tdst: &mut [T] = tm(self.out)?;
                 let clhs = conv_const_dynamic(lhs)?;
-                let crhs = conv_slice_dynamic(rhs, trhs)?;
+                let crhs = conv_slice_dynamic(&sched, rhs, trhs)?;
                 let dst = bound_output_length(tdst, crhs)?;
-                check_ok_length(crhs)?;
-                check_ok_length(dst)?;
                 let res = match op {
-                    Add => <AddOp<T, T>>::apply_const_slice(clhs, crhs, dst),
-                    Sub => <SubOp<T, T>>::apply_const_slice(clhs, crhs, dst),
-                    Mul => <MulOp<T, T>>::apply_const_slice(clhs, crhs, dst),
-                    Div => <DivOp<T, T>>::apply_const_slice(clhs, crhs, dst),
-                    Rem => <RemOp<T, T>>::apply_const_slice(clhs, crhs, dst),
-                    Min => <MinOp<T, T>>::apply_const_slice(clhs, crhs, dst),
-                    Max => <MaxOp<T, T>>::apply_const_slice(clhs, crhs, dst),
-                    Pow => <PowOp<T, T>>::apply_const_slice(clhs, crhs, dst),
-                    BitAnd => <BitAndOp<T, T>>::apply_const_slice(clhs, crhs, dst),
-                    BitOr => <BitOrOp<T, T>>::apply_const_slice(clhs, crhs, dst),
-                    BitXor => <BitXorOp<T, T>>::apply_const_slice(clhs, crhs, dst),
+                    Add => <AddOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Sub => <SubOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Mul => <MulOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Div => <DivOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Rem => <RemOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Min => <MinOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Max => <MaxOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Pow => <PowOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    BitAnd => <BitAndOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    BitOr => <BitOrOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    BitXor => <BitXorOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Shl => <ShlOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    LShr => <LShrOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    AShr => <AShrOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    RotL => <RotLOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    RotR => <RotROp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Atan2 => <Atan2Op<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Hypot => <HypotOp<T, T>>::apply_const_slice(&sched, clhs, crhs, dst),
                 };
                 match res {
                     Ok(slice) => Ok(OperandSlice(slice.into())),
@@ -402,6 +727,13 @@ impl<'eval> EvalCtx<'eval> {
                     BitAnd => <BitAndOp<T, T>>::apply_const_const(clhs, crhs),
                     BitOr => <BitOrOp<T, T>>::apply_const_const(clhs, crhs),
                     BitXor => <BitXorOp<T, T>>::apply_const_const(clhs, crhs),
+                    Shl => <ShlOp<T, T>>::apply_const_const(clhs, crhs),
+                    LShr => <LShrOp<T, T>>::apply_const_const(clhs, crhs),
+                    AShr => <AShrOp<T, T>>::apply_const_const(clhs, crhs),
+                    RotL => <RotLOp<T, T>>::apply_const_const(clhs, crhs),
+                    RotR => <RotROp<T, T>>::apply_const_const(clhs, crhs),
+                    Atan2 => <Atan2Op<T, T>>::apply_const_const(clhs, crhs),
+                    Hypot => <HypotOp<T, T>>::apply_const_const(clhs, crhs),
                 };
                 match res {
                     Ok(c) => Ok(OperandConst(c.into())),
@@ -411,6 +743,206 @@ impl<'eval> EvalCtx<'eval> {
         }
     }
 
+    // The slice form of a ternop works in place on `out`, which first gets a
+    // copy of `c` (converted, if need be, via `tmp1`). `a` and `b` are then
+    // converted or broadcast into `tmp1` and `tmp2`, as for binops.
+    #[inline(never)]
+    fn val_ternop_static<'slice, T>(self, op: ValTernOpCode,
+                                    a: &Operand<'slice>,
+                                    b: &Operand<'slice>,
+                                    c: &Operand<'slice>)
+                                    -> Result<Operand<'eval>, EvalError>
+    where
+        'slice: 'eval,
+        T: 'eval,
+        T: ScalarT + ScalarOf + Copy,
+        Slice<'slice>: From<&'eval [T]>,
+        Const: From<T>,
+
+        MulAddOp<T, T>: TernOp<T, T>,
+
+        ConvOp<bool, T>: UnOp<bool, T>,
+        ConvOp<u8, T>: UnOp<u8, T>,
+        ConvOp<u16, T>: UnOp<u16, T>,
+        ConvOp<u32, T>: UnOp<u32, T>,
+        ConvOp<u64, T>: UnOp<u64, T>,
+        ConvOp<u128, T>: UnOp<u128, T>,
+        ConvOp<i8, T>: UnOp<i8, T>,
+        ConvOp<i16, T>: UnOp<i16, T>,
+        ConvOp<i32, T>: UnOp<i32, T>,
+        ConvOp<i64, T>: UnOp<i64, T>,
+        ConvOp<i128, T>: UnOp<i128, T>,
+        ConvOp<f32, T>: UnOp<f32, T>,
+        ConvOp<f64, T>: UnOp<f64, T>,
+    {
+        use Operand::*;
+        use ValTernOpCode::*;
+        use transmute_buf_mut as tm;
+        let sched = self.sched();
+        if let (OperandConst(a), OperandConst(b), OperandConst(c)) = (a, b, c) {
+            let ca = conv_const_dynamic(a)?;
+            let cb = conv_const_dynamic(b)?;
+            let cc = conv_const_dynamic(c)?;
+            let res = match op {
+                MulAdd => <MulAddOp<T, T>>::apply_const(ca, cb, cc),
+            };
+            return match res {
+                Ok(c) => Ok(OperandConst(c.into())),
+                Err(_) => Err(EvalError::UnsupportedOp)
+            };
+        }
+        let n = [a, b, c].iter()
+            .filter_map(|x| match x {
+                OperandSlice(s) => Some(s.len()),
+                OperandConst(_) => None,
+            })
+            .max()
+            .unwrap_or(0);
+        let tacc: &mut [T] = tm(self.out)?;
+        if tacc.len() < n {
+            return Err(EvalError::BadBuffer);
+        }
+        let acc = &mut tacc[0..n];
+        let cc = operand_slice_dynamic(&sched, c, n, tm(&mut self.tmp1[..])?)?;
+        acc.copy_from_slice(cc);
+        let ca = operand_slice_dynamic(&sched, a, n, tm(self.tmp1)?)?;
+        let cb = operand_slice_dynamic(&sched, b, n, tm(self.tmp2)?)?;
+        let res = match op {
+            MulAdd => <MulAddOp<T, T>>::apply_slices(&sched, ca, cb, acc),
+        };
+        match res {
+            Ok(slice) => Ok(OperandSlice(slice.into())),
+            Err(_) => Err(EvalError::UnsupportedOp)
+        }
+    }
+
+    #[inline(never)]
+    // Compares operands of two different types exactly, for the outcomes in
+    // `mask`. Kernels exist for one order of each pair of types: floats on the
+    // left of integers, and signed on the left of unsigned; other pairs are
+    // swapped into that order.
+    fn mixed_cmp<'slice>(self, mask: u8,
+                         lhs: &Operand<'slice>,
+                         rhs: &Operand<'slice>)
+                         -> Result<Operand<'eval>, EvalError>
+    where 'slice: 'eval
+    {
+        use ScalarTy::*;
+        fn rank(ty: ScalarTy) -> u8 {
+            match ty {
+                TF32 | TF64 => 2,
+                TI8 | TI16 | TI32 | TI64 | TI128 => 1,
+                _ => 0,
+            }
+        }
+        let (lty, rty) = (lhs.get_scalar_ty(), rhs.get_scalar_ty());
+        if rank(lty) < rank(rty) {
+            return self.mixed_cmp(swap_ordering_mask(mask), rhs, lhs);
+        }
+        match (lty, rty) {
+            (TI8, TU8) => self.mixed_cmp_static::<i8, u8>(mask, lhs, rhs),
+            (TI8, TU16) => self.mixed_cmp_static::<i8, u16>(mask, lhs, rhs),
+            (TI8, TU32) => self.mixed_cmp_static::<i8, u32>(mask, lhs, rhs),
+            (TI8, TU64) => self.mixed_cmp_static::<i8, u64>(mask, lhs, rhs),
+            (TI8, TU128) => self.mixed_cmp_static::<i8, u128>(mask, lhs, rhs),
+            (TI16, TU8) => self.mixed_cmp_static::<i16, u8>(mask, lhs, rhs),
+            (TI16, TU16) => self.mixed_cmp_static::<i16, u16>(mask, lhs, rhs),
+            (TI16, TU32) => self.mixed_cmp_static::<i16, u32>(mask, lhs, rhs),
+            (TI16, TU64) => self.mixed_cmp_static::<i16, u64>(mask, lhs, rhs),
+            (TI16, TU128) => self.mixed_cmp_static::<i16, u128>(mask, lhs, rhs),
+            (TI32, TU8) => self.mixed_cmp_static::<i32, u8>(mask, lhs, rhs),
+            (TI32, TU16) => self.mixed_cmp_static::<i32, u16>(mask, lhs, rhs),
+            (TI32, TU32) => self.mixed_cmp_static::<i32, u32>(mask, lhs, rhs),
+            (TI32, TU64) => self.mixed_cmp_static::<i32, u64>(mask, lhs, rhs),
+            (TI32, TU128) => self.mixed_cmp_static::<i32, u128>(mask, lhs, rhs),
+            (TI64, TU8) => self.mixed_cmp_static::<i64, u8>(mask, lhs, rhs),
+            (TI64, TU16) => self.mixed_cmp_static::<i64, u16>(mask, lhs, rhs),
+            (TI64, TU32) => self.mixed_cmp_static::<i64, u32>(mask, lhs, rhs),
+            (TI64, TU64) => self.mixed_cmp_static::<i64, u64>(mask, lhs, rhs),
+            (TI64, TU128) => self.mixed_cmp_static::<i64, u128>(mask, lhs, rhs),
+            (TI128, TU8) => self.mixed_cmp_static::<i128, u8>(mask, lhs, rhs),
+            (TI128, TU16) => self.mixed_cmp_static::<i128, u16>(mask, lhs, rhs),
+            (TI128, TU32) => self.mixed_cmp_static::<i128, u32>(mask, lhs, rhs),
+            (TI128, TU64) => self.mixed_cmp_static::<i128, u64>(mask, lhs, rhs),
+            (TI128, TU128) => self.mixed_cmp_static::<i128, u128>(mask, lhs, rhs),
+            (TF32, TU8) => self.mixed_cmp_static::<f32, u8>(mask, lhs, rhs),
+            (TF32, TU16) => self.mixed_cmp_static::<f32, u16>(mask, lhs, rhs),
+            (TF32, TU32) => self.mixed_cmp_static::<f32, u32>(mask, lhs, rhs),
+            (TF32, TU64) => self.mixed_cmp_static::<f32, u64>(mask, lhs, rhs),
+            (TF32, TU128) => self.mixed_cmp_static::<f32, u128>(mask, lhs, rhs),
+            (TF32, TI8) => self.mixed_cmp_static::<f32, i8>(mask, lhs, rhs),
+            (TF32, TI16) => self.mixed_cmp_static::<f32, i16>(mask, lhs, rhs),
+            (TF32, TI32) => self.mixed_cmp_static::<f32, i32>(mask, lhs, rhs),
+            (TF32, TI64) => self.mixed_cmp_static::<f32, i64>(mask, lhs, rhs),
+            (TF32, TI128) => self.mixed_cmp_static::<f32, i128>(mask, lhs, rhs),
+            (TF64, TU8) => self.mixed_cmp_static::<f64, u8>(mask, lhs, rhs),
+            (TF64, TU16) => self.mixed_cmp_static::<f64, u16>(mask, lhs, rhs),
+            (TF64, TU32) => self.mixed_cmp_static::<f64, u32>(mask, lhs, rhs),
+            (TF64, TU64) => self.mixed_cmp_static::<f64, u64>(mask, lhs, rhs),
+            (TF64, TU128) => self.mixed_cmp_static::<f64, u128>(mask, lhs, rhs),
+            (TF64, TI8) => self.mixed_cmp_static::<f64, i8>(mask, lhs, rhs),
+            (TF64, TI16) => self.mixed_cmp_static::<f64, i16>(mask, lhs, rhs),
+            (TF64, TI32) => self.mixed_cmp_static::<f64, i32>(mask, lhs, rhs),
+            (TF64, TI64) => self.mixed_cmp_static::<f64, i64>(mask, lhs, rhs),
+            (TF64, TI128) => self.mixed_cmp_static::<f64, i128>(mask, lhs, rhs),
+            _ => Err(EvalError::UnsupportedOp),
+        }
+    }
+
+    #[inline(never)]
+    fn mixed_cmp_static<'slice, L, R>(self, mask: u8,
+                                      lhs: &Operand<'slice>,
+                                      rhs: &Operand<'slice>)
+                                      -> Result<Operand<'eval>, EvalError>
+    where
+        'slice: 'eval,
+        L: 'eval,
+        R: 'eval,
+        L: ScalarT + ScalarOf,
+        R: ScalarT + ScalarOf,
+        MixedCmpOp<L, R>: MixedCmp<L, R>,
+    {
+        use Operand::*;
+        let sched = self.sched();
+        let tdst: &mut [bool] = transmute_buf_mut(self.out)?;
+        let (lslice, lconst) = match lhs {
+            OperandSlice(s) => (L::of_slice(s), None),
+            OperandConst(c) => (None, L::of_const(c)),
+        };
+        let (rslice, rconst) = match rhs {
+            OperandSlice(s) => (R::of_slice(s), None),
+            OperandConst(c) => (None, R::of_const(c)),
+        };
+        let res = match (lslice, lconst, rslice, rconst) {
+            (Some(l), _, Some(r), _) => {
+                if l.len() != r.len() {
+                    return Err(EvalError::BadBuffer);
+                }
+                let dst = bound_output_length(tdst, l)?;
+                <MixedCmpOp<L, R>>::apply_slice_slice(&sched, mask, l, r, dst)
+            }
+            (Some(l), _, _, Some(r)) => {
+                let dst = bound_output_length(tdst, l)?;
+                <MixedCmpOp<L, R>>::apply_slice_const(&sched, mask, l, r, dst)
+            }
+            (_, Some(l), Some(r), _) => {
+                let dst = bound_output_length(tdst, r)?;
+                <MixedCmpOp<L, R>>::apply_const_slice(&sched, mask, l, r, dst)
+            }
+            (_, Some(l), _, Some(r)) => {
+                return match <MixedCmpOp<L, R>>::apply_const_const(mask, l, r) {
+                    Ok(c) => Ok(OperandConst(c.into())),
+                    Err(_) => Err(EvalError::UnsupportedOp)
+                };
+            }
+            _ => return Err(EvalError::UnsupportedOp),
+        };
+        match res {
+            Ok(slice) => Ok(OperandSlice(slice.into())),
+            Err(_) => Err(EvalError::UnsupportedOp)
+        }
+    }
+
     #[inline(never)]
     fn bool_binop_static<'slice, T>(self, op: BoolBinOpCode,
                                     lhs: &Operand<'slice>,
@@ -419,7 +951,7 @@ impl<'eval> EvalCtx<'eval> {
     where
         'slice: 'eval,
         T: 'eval,
-        T: ScalarT,
+        T: ScalarT + ScalarOf,
         Slice<'slice>: From<&'eval [T]>,
         Const: From<T>,
 
@@ -447,24 +979,23 @@ impl<'eval> EvalCtx<'eval> {
         use Operand::*;
         use BoolBinOpCode::*;
         use transmute_buf_mut as tm;
+        let sched = self.sched();
         match (lhs, rhs) {
             (OperandSlice(lhs), OperandSlice(rhs)) => {
                 let tlhs: &mut [T] = tm(self.tmp1)?;
                 let trhs: &mut [T] = tm(self.tmp2)?;
                 let tdst: &mut [bool] = tm(self.out)?;
-                let clhs = conv_slice_dynamic(lhs, tlhs)?;
-                let crhs = conv_slice_dynamic(rhs, trhs)?;
+                let clhs = conv_slice_dynamic(&sched, lhs, tlhs)?;
+                let crhs = conv_slice_dynamic(&sched, rhs, trhs)?;
                 let dst = bound_output_length(tdst, crhs)?;
-                check_ok_length(clhs)?;
-                check_ok_length(crhs)?;
-                check_ok_length(dst)?;
+                check_equal_lengths(clhs, crhs)?;
                 let res = match op {
-                    Lt => <LtOp<T, bool>>::apply_slice_slice(clhs, crhs, dst),
-                    Le => <LeOp<T, bool>>::apply_slice_slice(clhs, crhs, dst),
-                    Eq => <EqOp<T, bool>>::apply_slice_slice(clhs, crhs, dst),
-                    Ne => <NeOp<T, bool>>::apply_slice_slice(clhs, crhs, dst),
-                    Ge => <GeOp<T, bool>>::apply_slice_slice(clhs, crhs, dst),
-                    Gt => <GtOp<T, bool>>::apply_slice_slice(clhs, crhs, dst),
+                    Lt => <LtOp<T, bool>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Le => <LeOp<T, bool>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Eq => <EqOp<T, bool>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Ne => <NeOp<T, bool>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Ge => <GeOp<T, bool>>::apply_slice_slice(&sched, clhs, crhs, dst),
+                    Gt => <GtOp<T, bool>>::apply_slice_slice(&sched, clhs, crhs, dst),
                 };
                 match res {
                     Ok(slice) => Ok(OperandSlice(slice.into())),
@@ -474,18 +1005,16 @@ impl<'eval> EvalCtx<'eval> {
             (OperandSlice(lhs), OperandConst(rhs)) => {
                 let tlhs: &mut [T] = tm(self.tmp1)?;
                 let tdst: &mut [bool] = tm(self.out)?;
-                let clhs = conv_slice_dynamic(lhs, tlhs)?;
+                let clhs = conv_slice_dynamic(&sched, lhs, tlhs)?;
                 let crhs = conv_const_dynamic(rhs)?;
                 let dst = bound_output_length(tdst, clhs)?;
-                check_ok_length(clhs)?;
-                check_ok_length(dst)?;
                 let res = match op {
-                    Lt => <LtOp<T, bool>>::apply_slice_const(clhs, crhs, dst),
-                    Le => <LeOp<T, bool>>::apply_slice_const(clhs, crhs, dst),
-                    Eq => <EqOp<T, bool>>::apply_slice_const(clhs, crhs, dst),
-                    Ne => <NeOp<T, bool>>::apply_slice_const(clhs, crhs, dst),
-                    Ge => <GeOp<T, bool>>::apply_slice_const(clhs, crhs, dst),
-                    Gt => <GtOp<T, bool>>::apply_slice_const(clhs, crhs, dst),
+                    Lt => <LtOp<T, bool>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Le => <LeOp<T, bool>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Eq => <EqOp<T, bool>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Ne => <NeOp<T, bool>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Ge => <GeOp<T, bool>>::apply_slice_const(&sched, clhs, crhs, dst),
+                    Gt => <GtOp<T, bool>>::apply_slice_const(&sched, clhs, crhs, dst),
                 };
                 match res {
                     Ok(slice) => Ok(OperandSlice(slice.into())),
@@ -496,17 +1025,15 @@ impl<'eval> EvalCtx<'eval> {
                 let trhs: &mut [T] = tm(self.tmp2)?;
                 let tdst: &mut [bool] = tm(self.out)?;
                 let clhs = conv_const_dynamic(lhs)?;
-                let crhs = conv_slice_dynamic(rhs, trhs)?;
+                let crhs = conv_slice_dynamic(&sched, rhs, trhs)?;
                 let dst = bound_output_length(tdst, crhs)?;
-                check_ok_length(crhs)?;
-                check_ok_length(dst)?;
                 let res = match op {
-                    Lt => <LtOp<T, bool>>::apply_const_slice(clhs, crhs, dst),
-                    Le => <LeOp<T, bool>>::apply_const_slice(clhs, crhs, dst),
-                    Eq => <EqOp<T, bool>>::apply_const_slice(clhs, crhs, dst),
-                    Ne => <NeOp<T, bool>>::apply_const_slice(clhs, crhs, dst),
-                    Ge => <GeOp<T, bool>>::apply_const_slice(clhs, crhs, dst),
-                    Gt => <GtOp<T, bool>>::apply_const_slice(clhs, crhs, dst),
+                    Lt => <LtOp<T, bool>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Le => <LeOp<T, bool>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Eq => <EqOp<T, bool>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Ne => <NeOp<T, bool>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Ge => <GeOp<T, bool>>::apply_const_slice(&sched, clhs, crhs, dst),
+                    Gt => <GtOp<T, bool>>::apply_const_slice(&sched, clhs, crhs, dst),
                 };
                 match res {
                     Ok(slice) => Ok(OperandSlice(slice.into())),
@@ -539,7 +1066,7 @@ impl<'eval> EvalCtx<'eval> {
     where
         'slice: 'eval,
         T: 'eval,
-        T: ScalarT,
+        T: ScalarT + ScalarOf,
         Slice<'slice>: From<&'eval [T]>,
         Const: From<T>,
 
@@ -551,6 +1078,28 @@ impl<'eval> EvalCtx<'eval> {
         SqrtOp<T, T>: UnOp<T, T>,
         SinOp<T, T>: UnOp<T, T>,
         CosOp<T, T>: UnOp<T, T>,
+        SignumOp<T, T>: UnOp<T, T>,
+        PopCountOp<T, T>: UnOp<T, T>,
+        LeadingZerosOp<T, T>: UnOp<T, T>,
+        TrailingZerosOp<T, T>: UnOp<T, T>,
+        BitReverseOp<T, T>: UnOp<T, T>,
+        ByteSwapOp<T, T>: UnOp<T, T>,
+        TanOp<T, T>: UnOp<T, T>,
+        AsinOp<T, T>: UnOp<T, T>,
+        AcosOp<T, T>: UnOp<T, T>,
+        AtanOp<T, T>: UnOp<T, T>,
+        SinhOp<T, T>: UnOp<T, T>,
+        CoshOp<T, T>: UnOp<T, T>,
+        TanhOp<T, T>: UnOp<T, T>,
+        Log2Op<T, T>: UnOp<T, T>,
+        Log10Op<T, T>: UnOp<T, T>,
+        Log1pOp<T, T>: UnOp<T, T>,
+        Expm1Op<T, T>: UnOp<T, T>,
+        CbrtOp<T, T>: UnOp<T, T>,
+        FloorOp<T, T>: UnOp<T, T>,
+        CeilOp<T, T>: UnOp<T, T>,
+        RoundOp<T, T>: UnOp<T, T>,
+        TruncOp<T, T>: UnOp<T, T>,
 
         ConvOp<bool, T>: UnOp<bool, T>,
         ConvOp<u8, T>: UnOp<u8, T>,
@@ -569,23 +1118,44 @@ impl<'eval> EvalCtx<'eval> {
         use Operand::*;
         use ValUnOpCode::*;
         use transmute_buf_mut as tm;
+        let sched = self.sched();
         match operand {
             OperandSlice(s) => {
                 let ts: &mut [T] = tm(self.tmp1)?;
                 let tdst: &mut [T] = tm(self.out)?;
-                let cs = conv_slice_dynamic(s, ts)?;
+                let cs = conv_slice_dynamic(&sched, s, ts)?;
                 let dst = bound_output_length(tdst, cs)?;
-                check_ok_length(cs)?;
-                check_ok_length(dst)?;
                 let res = match op {
-                    Neg => <NegOp<T, T>>::apply_slice(cs, dst),
-                    BitNot => <NotOp<T, T>>::apply_slice(cs, dst),
-                    Abs => <AbsOp<T, T>>::apply_slice(cs, dst),
-                    Ln => <LnOp<T, T>>::apply_slice(cs, dst),
-                    Exp => <ExpOp<T, T>>::apply_slice(cs, dst),
-                    Sqrt => <SqrtOp<T, T>>::apply_slice(cs, dst),
-                    Sin => <SinOp<T, T>>::apply_slice(cs, dst),
-                    Cos => <CosOp<T, T>>::apply_slice(cs, dst),
+                    Neg => <NegOp<T, T>>::apply_slice(&sched, cs, dst),
+                    BitNot => <NotOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Abs => <AbsOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Ln => <LnOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Exp => <ExpOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Sqrt => <SqrtOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Sin => <SinOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Cos => <CosOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Signum => <SignumOp<T, T>>::apply_slice(&sched, cs, dst),
+                    PopCount => <PopCountOp<T, T>>::apply_slice(&sched, cs, dst),
+                    LeadingZeros => <LeadingZerosOp<T, T>>::apply_slice(&sched, cs, dst),
+                    TrailingZeros => <TrailingZerosOp<T, T>>::apply_slice(&sched, cs, dst),
+                    BitReverse => <BitReverseOp<T, T>>::apply_slice(&sched, cs, dst),
+                    ByteSwap => <ByteSwapOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Tan => <TanOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Asin => <AsinOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Acos => <AcosOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Atan => <AtanOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Sinh => <SinhOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Cosh => <CoshOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Tanh => <TanhOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Log2 => <Log2Op<T, T>>::apply_slice(&sched, cs, dst),
+                    Log10 => <Log10Op<T, T>>::apply_slice(&sched, cs, dst),
+                    Log1p => <Log1pOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Expm1 => <Expm1Op<T, T>>::apply_slice(&sched, cs, dst),
+                    Cbrt => <CbrtOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Floor => <FloorOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Ceil => <CeilOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Round => <RoundOp<T, T>>::apply_slice(&sched, cs, dst),
+                    Trunc => <TruncOp<T, T>>::apply_slice(&sched, cs, dst),
                 };
                 match res {
                     Ok(slice) => Ok(OperandSlice(slice.into())),
@@ -603,6 +1173,28 @@ impl<'eval> EvalCtx<'eval> {
                     Sqrt => <SqrtOp<T, T>>::apply_const(cc),
                     Sin => <SinOp<T, T>>::apply_const(cc),
                     Cos => <CosOp<T, T>>::apply_const(cc),
+                    Signum => <SignumOp<T, T>>::apply_const(cc),
+                    PopCount => <PopCountOp<T, T>>::apply_const(cc),
+                    LeadingZeros => <LeadingZerosOp<T, T>>::apply_const(cc),
+                    TrailingZeros => <TrailingZerosOp<T, T>>::apply_const(cc),
+                    BitReverse => <BitReverseOp<T, T>>::apply_const(cc),
+                    ByteSwap => <ByteSwapOp<T, T>>::apply_const(cc),
+                    Tan => <TanOp<T, T>>::apply_const(cc),
+                    Asin => <AsinOp<T, T>>::apply_const(cc),
+                    Acos => <AcosOp<T, T>>::apply_const(cc),
+                    Atan => <AtanOp<T, T>>::apply_const(cc),
+                    Sinh => <SinhOp<T, T>>::apply_const(cc),
+                    Cosh => <CoshOp<T, T>>::apply_const(cc),
+                    Tanh => <TanhOp<T, T>>::apply_const(cc),
+                    Log2 => <Log2Op<T, T>>::apply_const(cc),
+                    Log10 => <Log10Op<T, T>>::apply_const(cc),
+                    Log1p => <Log1pOp<T, T>>::apply_const(cc),
+                    Expm1 => <Expm1Op<T, T>>::apply_const(cc),
+                    Cbrt => <CbrtOp<T, T>>::apply_const(cc),
+                    Floor => <FloorOp<T, T>>::apply_const(cc),
+                    Ceil => <CeilOp<T, T>>::apply_const(cc),
+                    Round => <RoundOp<T, T>>::apply_const(cc),
+                    Trunc => <TruncOp<T, T>>::apply_const(cc),
                 };
                 match res {
                     Ok(c) => Ok(OperandConst(c.into())),
@@ -619,7 +1211,7 @@ impl<'eval> EvalCtx<'eval> {
     where
         'slice: 'eval,
         T: 'eval,
-        T: ScalarT,
+        T: ScalarT + ScalarOf,
         Slice<'slice>: From<&'eval [T]>,
         Const: From<T>,
 
@@ -644,18 +1236,17 @@ impl<'eval> EvalCtx<'eval> {
         use Operand::*;
         use BoolUnOpCode::*;
         use transmute_buf_mut as tm;
+        let sched = self.sched();
         match operand {
             OperandSlice(s) => {
                 let ts: &mut [T] = tm(self.tmp1)?;
                 let tdst: &mut [bool] = tm(self.out)?;
-                let cs = conv_slice_dynamic(s, ts)?;
+                let cs = conv_slice_dynamic(&sched, s, ts)?;
                 let dst = bound_output_length(tdst, cs)?;
-                check_ok_length(cs)?;
-                check_ok_length(dst)?;
                 let res = match op {
-                    IsNaN => <IsNaNOp<T, bool>>::apply_slice(cs, dst),
-                    IsInf => <IsInfOp<T, bool>>::apply_slice(cs, dst),
-                    IsFin => <IsFinOp<T, bool>>::apply_slice(cs, dst),
+                    IsNaN => <IsNaNOp<T, bool>>::apply_slice(&sched, cs, dst),
+                    IsInf => <IsInfOp<T, bool>>::apply_slice(&sched, cs, dst),
+                    IsFin => <IsFinOp<T, bool>>::apply_slice(&sched, cs, dst),
                 };
                 match res {
                     Ok(slice) => Ok(OperandSlice(slice.into())),
@@ -676,6 +1267,220 @@ impl<'eval> EvalCtx<'eval> {
             }
         }
     }
+
+    #[inline(never)]
+    fn val_unop_in_place_static<T>(self, op: ValUnOpCode, acc: &mut [T])
+                                   -> Result<(), EvalError>
+    where
+        T: ScalarT + Copy + Send + Sync,
+
+        NegOp<T, T>: UnOp<T, T>,
+        NotOp<T, T>: UnOp<T, T>,
+        AbsOp<T, T>: UnOp<T, T>,
+        LnOp<T, T>: UnOp<T, T>,
+        ExpOp<T, T>: UnOp<T, T>,
+        SqrtOp<T, T>: UnOp<T, T>,
+        SinOp<T, T>: UnOp<T, T>,
+        CosOp<T, T>: UnOp<T, T>,
+        SignumOp<T, T>: UnOp<T, T>,
+        PopCountOp<T, T>: UnOp<T, T>,
+        LeadingZerosOp<T, T>: UnOp<T, T>,
+        TrailingZerosOp<T, T>: UnOp<T, T>,
+        BitReverseOp<T, T>: UnOp<T, T>,
+        ByteSwapOp<T, T>: UnOp<T, T>,
+        TanOp<T, T>: UnOp<T, T>,
+        AsinOp<T, T>: UnOp<T, T>,
+        AcosOp<T, T>: UnOp<T, T>,
+        AtanOp<T, T>: UnOp<T, T>,
+        SinhOp<T, T>: UnOp<T, T>,
+        CoshOp<T, T>: UnOp<T, T>,
+        TanhOp<T, T>: UnOp<T, T>,
+        Log2Op<T, T>: UnOp<T, T>,
+        Log10Op<T, T>: UnOp<T, T>,
+        Log1pOp<T, T>: UnOp<T, T>,
+        Expm1Op<T, T>: UnOp<T, T>,
+        CbrtOp<T, T>: UnOp<T, T>,
+        FloorOp<T, T>: UnOp<T, T>,
+        CeilOp<T, T>: UnOp<T, T>,
+        RoundOp<T, T>: UnOp<T, T>,
+        TruncOp<T, T>: UnOp<T, T>,
+    {
+        use ValUnOpCode::*;
+        let sched = self.sched();
+        // Each chunk is already the size the kernels chunk by, and is run on
+        // whichever thread it's been given to.
+        let serial = sched.with_parallelism(Parallelism::Serial);
+        let apply = |src: &[T], dst: &mut [T]| {
+            let res = match op {
+                Neg => <NegOp<T, T>>::apply_slice(&serial, src, dst),
+                BitNot => <NotOp<T, T>>::apply_slice(&serial, src, dst),
+                Abs => <AbsOp<T, T>>::apply_slice(&serial, src, dst),
+                Ln => <LnOp<T, T>>::apply_slice(&serial, src, dst),
+                Exp => <ExpOp<T, T>>::apply_slice(&serial, src, dst),
+                Sqrt => <SqrtOp<T, T>>::apply_slice(&serial, src, dst),
+                Sin => <SinOp<T, T>>::apply_slice(&serial, src, dst),
+                Cos => <CosOp<T, T>>::apply_slice(&serial, src, dst),
+                Signum => <SignumOp<T, T>>::apply_slice(&serial, src, dst),
+                PopCount => <PopCountOp<T, T>>::apply_slice(&serial, src, dst),
+                LeadingZeros => <LeadingZerosOp<T, T>>::apply_slice(&serial, src, dst),
+                TrailingZeros => <TrailingZerosOp<T, T>>::apply_slice(&serial, src, dst),
+                BitReverse => <BitReverseOp<T, T>>::apply_slice(&serial, src, dst),
+                ByteSwap => <ByteSwapOp<T, T>>::apply_slice(&serial, src, dst),
+                Tan => <TanOp<T, T>>::apply_slice(&serial, src, dst),
+                Asin => <AsinOp<T, T>>::apply_slice(&serial, src, dst),
+                Acos => <AcosOp<T, T>>::apply_slice(&serial, src, dst),
+                Atan => <AtanOp<T, T>>::apply_slice(&serial, src, dst),
+                Sinh => <SinhOp<T, T>>::apply_slice(&serial, src, dst),
+                Cosh => <CoshOp<T, T>>::apply_slice(&serial, src, dst),
+                Tanh => <TanhOp<T, T>>::apply_slice(&serial, src, dst),
+                Log2 => <Log2Op<T, T>>::apply_slice(&serial, src, dst),
+                Log10 => <Log10Op<T, T>>::apply_slice(&serial, src, dst),
+                Log1p => <Log1pOp<T, T>>::apply_slice(&serial, src, dst),
+                Expm1 => <Expm1Op<T, T>>::apply_slice(&serial, src, dst),
+                Cbrt => <CbrtOp<T, T>>::apply_slice(&serial, src, dst),
+                Floor => <FloorOp<T, T>>::apply_slice(&serial, src, dst),
+                Ceil => <CeilOp<T, T>>::apply_slice(&serial, src, dst),
+                Round => <RoundOp<T, T>>::apply_slice(&serial, src, dst),
+                Trunc => <TruncOp<T, T>>::apply_slice(&serial, src, dst),
+            };
+            res.map(|_| ())
+        };
+        // Unsupported kernels fail whatever they're given, so try an empty
+        // slice before touching `acc`.
+        apply(&[], &mut [])?;
+        let failed = Mutex::new(None);
+        sched.for_each_chunk_in_place(
+            sched.chunk_len::<T, T>(), acc,
+            &|src, dst| if let Err(e) = apply(src, dst) {
+                failed.lock().unwrap().get_or_insert(e);
+            });
+        match failed.into_inner().unwrap() {
+            Some(e) => Err(e.into()),
+            None => Ok(())
+        }
+    }
+
+    #[inline(never)]
+    fn val_binop_in_place_static<'slice, T>(self, op: ValBinOpCode,
+                                            acc: &mut [T],
+                                            rhs: &Operand<'slice>)
+                                            -> Result<(), EvalError>
+    where
+        T: ScalarT + ScalarOf + Copy + Send + Sync,
+
+        AddOp<T, T>: BinOp<T, T>,
+        SubOp<T, T>: BinOp<T, T>,
+        MulOp<T, T>: BinOp<T, T>,
+        DivOp<T, T>: BinOp<T, T>,
+        RemOp<T, T>: BinOp<T, T>,
+        MinOp<T, T>: BinOp<T, T>,
+        MaxOp<T, T>: BinOp<T, T>,
+        PowOp<T, T>: BinOp<T, T>,
+        BitAndOp<T, T>: BinOp<T, T>,
+        BitOrOp<T, T>: BinOp<T, T>,
+        BitXorOp<T, T>: BinOp<T, T>,
+        ShlOp<T, T>: BinOp<T, T>,
+        LShrOp<T, T>: BinOp<T, T>,
+        AShrOp<T, T>: BinOp<T, T>,
+        RotLOp<T, T>: BinOp<T, T>,
+        RotROp<T, T>: BinOp<T, T>,
+        Atan2Op<T, T>: BinOp<T, T>,
+        HypotOp<T, T>: BinOp<T, T>,
+
+        ConvOp<bool, T>: UnOp<bool, T>,
+        ConvOp<u8, T>: UnOp<u8, T>,
+        ConvOp<u16, T>: UnOp<u16, T>,
+        ConvOp<u32, T>: UnOp<u32, T>,
+        ConvOp<u64, T>: UnOp<u64, T>,
+        ConvOp<u128, T>: UnOp<u128, T>,
+        ConvOp<i8, T>: UnOp<i8, T>,
+        ConvOp<i16, T>: UnOp<i16, T>,
+        ConvOp<i32, T>: UnOp<i32, T>,
+        ConvOp<i64, T>: UnOp<i64, T>,
+        ConvOp<i128, T>: UnOp<i128, T>,
+        ConvOp<f32, T>: UnOp<f32, T>,
+        ConvOp<f64, T>: UnOp<f64, T>,
+    {
+        use Operand::*;
+        use ValBinOpCode::*;
+        let sched = self.sched();
+        let serial = sched.with_parallelism(Parallelism::Serial);
+        let chunksz = sched.chunk_len::<T, T>();
+        let failed = Mutex::new(None);
+        let record = |res: Result<(), OpError>| if let Err(e) = res {
+            failed.lock().unwrap().get_or_insert(e);
+        };
+        match rhs {
+            OperandSlice(rhs) => {
+                // The caller has checked `rhs` is already of type `T`.
+                let rhs = T::of_slice(rhs).ok_or(EvalError::UnsupportedOp)?;
+                check_equal_lengths(acc, rhs)?;
+                let apply = |lhs: &[T], rhs: &[T], dst: &mut [T]| {
+                    let res = match op {
+                        Add => <AddOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        Sub => <SubOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        Mul => <MulOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        Div => <DivOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        Rem => <RemOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        Min => <MinOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        Max => <MaxOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        Pow => <PowOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        BitAnd => <BitAndOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        BitOr => <BitOrOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        BitXor => <BitXorOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        Shl => <ShlOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        LShr => <LShrOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        AShr => <AShrOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        RotL => <RotLOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        RotR => <RotROp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        Atan2 => <Atan2Op<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                        Hypot => <HypotOp<T, T>>::apply_slice_slice(&serial, lhs, rhs, dst),
+                    };
+                    res.map(|_| ())
+                };
+                // Unsupported kernels fail whatever they're given, so try
+                // empty slices before touching `acc`.
+                apply(&[], &[], &mut [])?;
+                sched.for_each_chunk2_in_place(
+                    chunksz, acc, rhs,
+                    &|lhs, rhs, dst| record(apply(lhs, rhs, dst)));
+            }
+            OperandConst(rhs) => {
+                let rhs = conv_const_dynamic::<T>(rhs)?;
+                let apply = |lhs: &[T], dst: &mut [T]| {
+                    let res = match op {
+                        Add => <AddOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        Sub => <SubOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        Mul => <MulOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        Div => <DivOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        Rem => <RemOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        Min => <MinOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        Max => <MaxOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        Pow => <PowOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        BitAnd => <BitAndOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        BitOr => <BitOrOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        BitXor => <BitXorOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        Shl => <ShlOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        LShr => <LShrOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        AShr => <AShrOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        RotL => <RotLOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        RotR => <RotROp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        Atan2 => <Atan2Op<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                        Hypot => <HypotOp<T, T>>::apply_slice_const(&serial, lhs, rhs, dst),
+                    };
+                    res.map(|_| ())
+                };
+                apply(&[], &mut [])?;
+                sched.for_each_chunk_in_place(
+                    chunksz, acc,
+                    &|lhs, dst| record(apply(lhs, dst)));
+            }
+        }
+        match failed.into_inner().unwrap() {
+            Some(e) => Err(e.into()),
+            None => Ok(())
+        }
+    }
 }
 
 fn conv_const_dynamic<DstT:ScalarT>(c: &Const) -> Result<DstT, EvalError>
@@ -713,13 +1518,14 @@ where
     res.map_err(|_| EvalError::UnsupportedOp)
 }
 
-fn conv_slice_dynamic<'src, 'dst, DstT>(s: &Slice<'src>,
+fn conv_slice_dynamic<'src, 'dst, DstT>(sched: &Sched,
+                                        s: &Slice<'src>,
                                         tmp: &'dst mut [DstT])
                                         -> Result<&'dst [DstT], EvalError>
 where
     'src: 'dst,
     DstT: 'dst,
-    DstT: ScalarT,
+    DstT: ScalarT + ScalarOf,
     ConvOp<bool, DstT>: UnOp<bool, DstT>,
     ConvOp<u8, DstT>: UnOp<u8, DstT>,
     ConvOp<u16, DstT>: UnOp<u16, DstT>,
@@ -736,20 +1542,241 @@ where
 {
     use Slice::*;
     use bound_output_length as bl;
+    // A slice that's already of type `DstT` is used as-is, without `tmp`.
+    if let Some(x) = DstT::of_slice(s) {
+        return Ok(x);
+    }
     let res = match *s {
-        SliceBool(b) => <ConvOp<_, _>>::apply_slice(b, bl::<DstT, _>(tmp, b)?),
-        SliceU8(u) => <ConvOp<_, _>>::apply_slice(u, bl::<DstT, _>(tmp, u)?),
-        SliceU16(u) => <ConvOp<_, _>>::apply_slice(u, bl::<DstT, _>(tmp, u)?),
-        SliceU32(u) => <ConvOp<_, _>>::apply_slice(u, bl::<DstT, _>(tmp, u)?),
-        SliceU64(u) => <ConvOp<_, _>>::apply_slice(u, bl::<DstT, _>(tmp, u)?),
-        SliceU128(u) => <ConvOp<_, _>>::apply_slice(u, bl::<DstT, _>(tmp, u)?),
-        SliceI8(i) => <ConvOp<_, _>>::apply_slice(i, bl::<DstT, _>(tmp, i)?),
-        SliceI16(i) => <ConvOp<_, _>>::apply_slice(i, bl::<DstT, _>(tmp, i)?),
-        SliceI32(i) => <ConvOp<_, _>>::apply_slice(i, bl::<DstT, _>(tmp, i)?),
-        SliceI64(i) => <ConvOp<_, _>>::apply_slice(i, bl::<DstT, _>(tmp, i)?),
-        SliceI128(i) => <ConvOp<_, _>>::apply_slice(i, bl::<DstT, _>(tmp, i)?),
-        SliceF32(v) => <ConvOp<_, _>>::apply_slice(v, bl::<DstT, _>(tmp, v)?),
-        SliceF64(v) => <ConvOp<_, _>>::apply_slice(v, bl::<DstT, _>(tmp, v)?),
+        SliceBool(b) => <ConvOp<_, _>>::apply_slice(sched, b, bl::<DstT, _>(tmp, b)?),
+        SliceU8(u) => <ConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+        SliceU16(u) => <ConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+        SliceU32(u) => <ConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+        SliceU64(u) => <ConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+        SliceU128(u) => <ConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+        SliceI8(i) => <ConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+        SliceI16(i) => <ConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+        SliceI32(i) => <ConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+        SliceI64(i) => <ConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+        SliceI128(i) => <ConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+        SliceF32(v) => <ConvOp<_, _>>::apply_slice(sched, v, bl::<DstT, _>(tmp, v)?),
+        SliceF64(v) => <ConvOp<_, _>>::apply_slice(sched, v, bl::<DstT, _>(tmp, v)?),
     };
     res.map_err(|_| EvalError::UnsupportedOp)
 }
+
+// Returns `x` as an `n`-element slice: if it's a slice, which must be `n` long,
+// converted into `tmp` (or as-is, if it's already of type `DstT`), and if it's
+// a const, converted and broadcast across `tmp`.
+fn operand_slice_dynamic<'src, 'dst, DstT>(sched: &Sched,
+                                           x: &Operand<'src>,
+                                           n: usize,
+                                           tmp: &'dst mut [DstT])
+                                           -> Result<&'dst [DstT], EvalError>
+where
+    'src: 'dst,
+    DstT: 'dst,
+    DstT: ScalarT + ScalarOf + Copy,
+    ConvOp<bool, DstT>: UnOp<bool, DstT>,
+    ConvOp<u8, DstT>: UnOp<u8, DstT>,
+    ConvOp<u16, DstT>: UnOp<u16, DstT>,
+    ConvOp<u32, DstT>: UnOp<u32, DstT>,
+    ConvOp<u64, DstT>: UnOp<u64, DstT>,
+    ConvOp<u128, DstT>: UnOp<u128, DstT>,
+    ConvOp<i8, DstT>: UnOp<i8, DstT>,
+    ConvOp<i16, DstT>: UnOp<i16, DstT>,
+    ConvOp<i32, DstT>: UnOp<i32, DstT>,
+    ConvOp<i64, DstT>: UnOp<i64, DstT>,
+    ConvOp<i128, DstT>: UnOp<i128, DstT>,
+    ConvOp<f32, DstT>: UnOp<f32, DstT>,
+    ConvOp<f64, DstT>: UnOp<f64, DstT>,
+{
+    match x {
+        Operand::OperandSlice(s) => {
+            let cs = conv_slice_dynamic(sched, s, tmp)?;
+            if cs.len() != n {
+                return Err(EvalError::BadBuffer);
+            }
+            Ok(cs)
+        }
+        Operand::OperandConst(c) => {
+            let v = conv_const_dynamic(c)?;
+            if tmp.len() < n {
+                return Err(EvalError::BadBuffer);
+            }
+            let t = &mut tmp[0..n];
+            t.fill(v);
+            Ok(t)
+        }
+    }
+}
+
+// Like `conv_const_dynamic`, for the saturating and checked modes.
+fn cast_const_dynamic<DstT:ScalarT>(c: &Const, mode: CastMode) -> Result<DstT, EvalError>
+where
+    ConvOp<bool, DstT>: UnOp<bool, DstT>,
+    ConvOp<u8, DstT>: UnOp<u8, DstT>,
+    ConvOp<u16, DstT>: UnOp<u16, DstT>,
+    ConvOp<u32, DstT>: UnOp<u32, DstT>,
+    ConvOp<u64, DstT>: UnOp<u64, DstT>,
+    ConvOp<u128, DstT>: UnOp<u128, DstT>,
+    ConvOp<i8, DstT>: UnOp<i8, DstT>,
+    ConvOp<i16, DstT>: UnOp<i16, DstT>,
+    ConvOp<i32, DstT>: UnOp<i32, DstT>,
+    ConvOp<i64, DstT>: UnOp<i64, DstT>,
+    ConvOp<i128, DstT>: UnOp<i128, DstT>,
+    ConvOp<f32, DstT>: UnOp<f32, DstT>,
+    ConvOp<f64, DstT>: UnOp<f64, DstT>,
+    SatConvOp<bool, DstT>: UnOp<bool, DstT>,
+    SatConvOp<u8, DstT>: UnOp<u8, DstT>,
+    SatConvOp<u16, DstT>: UnOp<u16, DstT>,
+    SatConvOp<u32, DstT>: UnOp<u32, DstT>,
+    SatConvOp<u64, DstT>: UnOp<u64, DstT>,
+    SatConvOp<u128, DstT>: UnOp<u128, DstT>,
+    SatConvOp<i8, DstT>: UnOp<i8, DstT>,
+    SatConvOp<i16, DstT>: UnOp<i16, DstT>,
+    SatConvOp<i32, DstT>: UnOp<i32, DstT>,
+    SatConvOp<i64, DstT>: UnOp<i64, DstT>,
+    SatConvOp<i128, DstT>: UnOp<i128, DstT>,
+    SatConvOp<f32, DstT>: UnOp<f32, DstT>,
+    SatConvOp<f64, DstT>: UnOp<f64, DstT>,
+    CheckedConvOp<bool, DstT>: UnOp<bool, DstT>,
+    CheckedConvOp<u8, DstT>: UnOp<u8, DstT>,
+    CheckedConvOp<u16, DstT>: UnOp<u16, DstT>,
+    CheckedConvOp<u32, DstT>: UnOp<u32, DstT>,
+    CheckedConvOp<u64, DstT>: UnOp<u64, DstT>,
+    CheckedConvOp<u128, DstT>: UnOp<u128, DstT>,
+    CheckedConvOp<i8, DstT>: UnOp<i8, DstT>,
+    CheckedConvOp<i16, DstT>: UnOp<i16, DstT>,
+    CheckedConvOp<i32, DstT>: UnOp<i32, DstT>,
+    CheckedConvOp<i64, DstT>: UnOp<i64, DstT>,
+    CheckedConvOp<i128, DstT>: UnOp<i128, DstT>,
+    CheckedConvOp<f32, DstT>: UnOp<f32, DstT>,
+    CheckedConvOp<f64, DstT>: UnOp<f64, DstT>,
+{
+    use Const::*;
+    let res = match mode {
+        CastMode::Wrapping => return conv_const_dynamic(c),
+        CastMode::Saturating => match *c {
+            ConstBool(b) => <SatConvOp<_, _>>::apply_const(b),
+            ConstU8(u) => <SatConvOp<_, _>>::apply_const(u),
+            ConstU16(u) => <SatConvOp<_, _>>::apply_const(u),
+            ConstU32(u) => <SatConvOp<_, _>>::apply_const(u),
+            ConstU64(u) => <SatConvOp<_, _>>::apply_const(u),
+            ConstU128(u) => <SatConvOp<_, _>>::apply_const(u),
+            ConstI8(i) => <SatConvOp<_, _>>::apply_const(i),
+            ConstI16(i) => <SatConvOp<_, _>>::apply_const(i),
+            ConstI32(i) => <SatConvOp<_, _>>::apply_const(i),
+            ConstI64(i) => <SatConvOp<_, _>>::apply_const(i),
+            ConstI128(i) => <SatConvOp<_, _>>::apply_const(i),
+            ConstF32(v) => <SatConvOp<_, _>>::apply_const(v),
+            ConstF64(v) => <SatConvOp<_, _>>::apply_const(v),
+        },
+        CastMode::Checked => match *c {
+            ConstBool(b) => <CheckedConvOp<_, _>>::apply_const(b),
+            ConstU8(u) => <CheckedConvOp<_, _>>::apply_const(u),
+            ConstU16(u) => <CheckedConvOp<_, _>>::apply_const(u),
+            ConstU32(u) => <CheckedConvOp<_, _>>::apply_const(u),
+            ConstU64(u) => <CheckedConvOp<_, _>>::apply_const(u),
+            ConstU128(u) => <CheckedConvOp<_, _>>::apply_const(u),
+            ConstI8(i) => <CheckedConvOp<_, _>>::apply_const(i),
+            ConstI16(i) => <CheckedConvOp<_, _>>::apply_const(i),
+            ConstI32(i) => <CheckedConvOp<_, _>>::apply_const(i),
+            ConstI64(i) => <CheckedConvOp<_, _>>::apply_const(i),
+            ConstI128(i) => <CheckedConvOp<_, _>>::apply_const(i),
+            ConstF32(v) => <CheckedConvOp<_, _>>::apply_const(v),
+            ConstF64(v) => <CheckedConvOp<_, _>>::apply_const(v),
+        },
+    };
+    res.map_err(EvalError::from)
+}
+
+// Like `conv_slice_dynamic`, for the saturating and checked modes.
+fn cast_slice_dynamic<'src, 'dst, DstT>(sched: &Sched,
+                                        s: &Slice<'src>,
+                                        tmp: &'dst mut [DstT],
+                                        mode: CastMode)
+                                        -> Result<&'dst [DstT], EvalError>
+where
+    'src: 'dst,
+    DstT: 'dst,
+    DstT: ScalarT + ScalarOf,
+    ConvOp<bool, DstT>: UnOp<bool, DstT>,
+    ConvOp<u8, DstT>: UnOp<u8, DstT>,
+    ConvOp<u16, DstT>: UnOp<u16, DstT>,
+    ConvOp<u32, DstT>: UnOp<u32, DstT>,
+    ConvOp<u64, DstT>: UnOp<u64, DstT>,
+    ConvOp<u128, DstT>: UnOp<u128, DstT>,
+    ConvOp<i8, DstT>: UnOp<i8, DstT>,
+    ConvOp<i16, DstT>: UnOp<i16, DstT>,
+    ConvOp<i32, DstT>: UnOp<i32, DstT>,
+    ConvOp<i64, DstT>: UnOp<i64, DstT>,
+    ConvOp<i128, DstT>: UnOp<i128, DstT>,
+    ConvOp<f32, DstT>: UnOp<f32, DstT>,
+    ConvOp<f64, DstT>: UnOp<f64, DstT>,
+    SatConvOp<bool, DstT>: UnOp<bool, DstT>,
+    SatConvOp<u8, DstT>: UnOp<u8, DstT>,
+    SatConvOp<u16, DstT>: UnOp<u16, DstT>,
+    SatConvOp<u32, DstT>: UnOp<u32, DstT>,
+    SatConvOp<u64, DstT>: UnOp<u64, DstT>,
+    SatConvOp<u128, DstT>: UnOp<u128, DstT>,
+    SatConvOp<i8, DstT>: UnOp<i8, DstT>,
+    SatConvOp<i16, DstT>: UnOp<i16, DstT>,
+    SatConvOp<i32, DstT>: UnOp<i32, DstT>,
+    SatConvOp<i64, DstT>: UnOp<i64, DstT>,
+    SatConvOp<i128, DstT>: UnOp<i128, DstT>,
+    SatConvOp<f32, DstT>: UnOp<f32, DstT>,
+    SatConvOp<f64, DstT>: UnOp<f64, DstT>,
+    CheckedConvOp<bool, DstT>: UnOp<bool, DstT>,
+    CheckedConvOp<u8, DstT>: UnOp<u8, DstT>,
+    CheckedConvOp<u16, DstT>: UnOp<u16, DstT>,
+    CheckedConvOp<u32, DstT>: UnOp<u32, DstT>,
+    CheckedConvOp<u64, DstT>: UnOp<u64, DstT>,
+    CheckedConvOp<u128, DstT>: UnOp<u128, DstT>,
+    CheckedConvOp<i8, DstT>: UnOp<i8, DstT>,
+    CheckedConvOp<i16, DstT>: UnOp<i16, DstT>,
+    CheckedConvOp<i32, DstT>: UnOp<i32, DstT>,
+    CheckedConvOp<i64, DstT>: UnOp<i64, DstT>,
+    CheckedConvOp<i128, DstT>: UnOp<i128, DstT>,
+    CheckedConvOp<f32, DstT>: UnOp<f32, DstT>,
+    CheckedConvOp<f64, DstT>: UnOp<f64, DstT>,
+{
+    use Slice::*;
+    use bound_output_length as bl;
+    if let Some(x) = DstT::of_slice(s) {
+        return Ok(x);
+    }
+    let res = match mode {
+        CastMode::Wrapping => return conv_slice_dynamic(sched, s, tmp),
+        CastMode::Saturating => match *s {
+            SliceBool(b) => <SatConvOp<_, _>>::apply_slice(sched, b, bl::<DstT, _>(tmp, b)?),
+            SliceU8(u) => <SatConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+            SliceU16(u) => <SatConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+            SliceU32(u) => <SatConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+            SliceU64(u) => <SatConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+            SliceU128(u) => <SatConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+            SliceI8(i) => <SatConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+            SliceI16(i) => <SatConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+            SliceI32(i) => <SatConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+            SliceI64(i) => <SatConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+            SliceI128(i) => <SatConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+            SliceF32(v) => <SatConvOp<_, _>>::apply_slice(sched, v, bl::<DstT, _>(tmp, v)?),
+            SliceF64(v) => <SatConvOp<_, _>>::apply_slice(sched, v, bl::<DstT, _>(tmp, v)?),
+        },
+        CastMode::Checked => match *s {
+            SliceBool(b) => <CheckedConvOp<_, _>>::apply_slice(sched, b, bl::<DstT, _>(tmp, b)?),
+            SliceU8(u) => <CheckedConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+            SliceU16(u) => <CheckedConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+            SliceU32(u) => <CheckedConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+            SliceU64(u) => <CheckedConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+            SliceU128(u) => <CheckedConvOp<_, _>>::apply_slice(sched, u, bl::<DstT, _>(tmp, u)?),
+            SliceI8(i) => <CheckedConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+            SliceI16(i) => <CheckedConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+            SliceI32(i) => <CheckedConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+            SliceI64(i) => <CheckedConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+            SliceI128(i) => <CheckedConvOp<_, _>>::apply_slice(sched, i, bl::<DstT, _>(tmp, i)?),
+            SliceF32(v) => <CheckedConvOp<_, _>>::apply_slice(sched, v, bl::<DstT, _>(tmp, v)?),
+            SliceF64(v) => <CheckedConvOp<_, _>>::apply_slice(sched, v, bl::<DstT, _>(tmp, v)?),
+        },
+    };
+    res.map_err(EvalError::from)
+}
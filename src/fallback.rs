@@ -0,0 +1,409 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Scalar implementations of operations that have no vector form, used by the
+// scalar-loop kernels (see `impl_unop_scalar` and `impl_binop_scalar` in
+// macros.rs), and of the saturating and checked conversions. Like the vector
+// integer kernels, the arithmetic ones wrap on overflow.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+use crate::traits::OpError;
+use crate::zeroone::ConstZero;
+
+pub trait IntPow {
+    // Raises `self` to the power `exp`, wrapping on overflow. The exponent is
+    // the same type as the base, so can exceed `u32` or be negative. Negative
+    // exponents give the truncated reciprocal, which is 0 unless the base is 1
+    // or -1; this includes a base of 0, rather than failing.
+    fn int_pow(self, exp: Self) -> Self;
+}
+
+pub trait Signum {
+    // Returns -1, 0 or 1 according to the sign of `self`. Floating-point zeroes
+    // and NaNs are returned as-is.
+    fn signum_of(self) -> Self;
+}
+
+// Why a checked conversion failed; see `Cast`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CastFault {
+    OutOfRange,
+    NaN,
+}
+
+impl CastFault {
+    // The `OpError` for this fault at element `i`.
+    pub fn at(self, i: usize) -> OpError {
+        match self {
+            CastFault::OutOfRange => OpError::CastOutOfRange(i),
+            CastFault::NaN => OpError::CastNaN(i),
+        }
+    }
+}
+
+// Conversions to `D` for the saturating and checked cast modes, between
+// distinct numeric types. Conversions from floating point to integer truncate
+// towards zero, and those to floating point round to nearest, in every mode;
+// only the handling of values outside the range of `D` differs.
+pub trait Cast<D> {
+    // Converts `self`, clamping it to the range of `D`. A NaN converts to 0 if
+    // `D` is an integer type, and stays a NaN otherwise.
+    fn saturating_cast(self) -> D;
+
+    // Returns why `self` has no conversion to `D`, if it hasn't: it's out of
+    // range, or it's a NaN and `D` is an integer type. When this is `None`,
+    // `saturating_cast` returns the exact conversion.
+    fn cast_fault(self) -> Option<CastFault>;
+}
+
+// Exact comparison of `self` with an `R` of a different signedness, or with
+// floating point against an integer: neither is converted to the other's type,
+// so large values don't lose precision. `None` means unordered (a NaN).
+pub trait CmpExact<R> {
+    fn cmp_exact(self, rhs: R) -> Option<Ordering>;
+}
+
+// The outcomes of a comparison, as bits of a mask selecting those for which a
+// comparison operator is true (see `BoolBinOpCode::ordering_mask`).
+pub const ORD_LESS: u8 = 1;
+pub const ORD_EQUAL: u8 = 2;
+pub const ORD_GREATER: u8 = 4;
+pub const ORD_UNORDERED: u8 = 8;
+
+#[inline(always)]
+pub fn ordering_bit(o: Option<Ordering>) -> u8 {
+    match o {
+        Some(Ordering::Less) => ORD_LESS,
+        Some(Ordering::Equal) => ORD_EQUAL,
+        Some(Ordering::Greater) => ORD_GREATER,
+        None => ORD_UNORDERED,
+    }
+}
+
+// The mask for the same comparison with its operands swapped.
+pub fn swap_ordering_mask(mask: u8) -> u8 {
+    let lg = if mask & ORD_LESS != 0 { ORD_GREATER } else { 0 } |
+        if mask & ORD_GREATER != 0 { ORD_LESS } else { 0 };
+    (mask & (ORD_EQUAL | ORD_UNORDERED)) | lg
+}
+
+// Only used by the `bitwise` kernels.
+#[cfg(feature = "bitwise")]
+pub trait Shift {
+    // Shifts and rotates by `n` modulo the bit width. A negative `n` is taken
+    // as its two's complement, so eg. -1 shifts by the width minus one.
+    fn shl_masked(self, n: Self) -> Self;
+    fn lshr_masked(self, n: Self) -> Self;
+    fn ashr_masked(self, n: Self) -> Self;
+    fn rotl_masked(self, n: Self) -> Self;
+    fn rotr_masked(self, n: Self) -> Self;
+}
+
+// Only used by the `bitwise` kernels.
+#[cfg(feature = "bitwise")]
+pub trait BitCount {
+    // The inherent methods' counts, as `Self` rather than `u32`.
+    fn pop_count(self) -> Self;
+    fn leading_zeros_of(self) -> Self;
+    fn trailing_zeros_of(self) -> Self;
+}
+
+// Exponentiation by squaring, over every bit of a non-negative `exp`.
+macro_rules! int_pow_loop {
+    ($base:expr, $exp:expr) => {{
+        let (mut base, mut exp, mut acc) = ($base, $exp, 1);
+        while exp != 0 {
+            if exp & 1 == 1 {
+                acc = base.wrapping_mul(acc);
+            }
+            base = base.wrapping_mul(base);
+            exp >>= 1;
+        }
+        acc
+    }}
+}
+
+macro_rules! impl_unsigned {
+    ($($T:ty)*) => {
+        $(
+            impl IntPow for $T {
+                #[inline(always)]
+                fn int_pow(self, exp: $T) -> $T {
+                    int_pow_loop!(self, exp)
+                }
+            }
+
+            impl Signum for $T {
+                #[inline(always)]
+                fn signum_of(self) -> $T {
+                    (self != 0) as $T
+                }
+            }
+        )*
+    }
+}
+
+macro_rules! impl_signed {
+    ($($T:ty)*) => {
+        $(
+            impl IntPow for $T {
+                #[inline(always)]
+                fn int_pow(self, exp: $T) -> $T {
+                    if exp >= 0 {
+                        int_pow_loop!(self, exp)
+                    } else {
+                        match self {
+                            1 => 1,
+                            -1 => if exp & 1 == 0 { 1 } else { -1 },
+                            _ => 0,
+                        }
+                    }
+                }
+            }
+
+            impl Signum for $T {
+                #[inline(always)]
+                fn signum_of(self) -> $T {
+                    self.signum()
+                }
+            }
+        )*
+    }
+}
+
+macro_rules! impl_float {
+    ($($T:ty)*) => {
+        $(
+            impl Signum for $T {
+                #[inline(always)]
+                fn signum_of(self) -> $T {
+                    if self > 0.0 {
+                        1.0
+                    } else if self < 0.0 {
+                        -1.0
+                    } else {
+                        self
+                    }
+                }
+            }
+        )*
+    }
+}
+
+// `BitCount` and `Shift` for `$T`, given its signed and unsigned counterparts
+// for the logical and arithmetic shifts. Truncating `n` to `u32` preserves it
+// modulo the width, which is a power of two no greater than 128.
+#[cfg(feature = "bitwise")]
+macro_rules! impl_bitwise {
+    ($(($T:ty, $S:ty, $U:ty))*) => {
+        $(
+            impl BitCount for $T {
+                #[inline(always)]
+                fn pop_count(self) -> $T {
+                    self.count_ones() as $T
+                }
+                #[inline(always)]
+                fn leading_zeros_of(self) -> $T {
+                    self.leading_zeros() as $T
+                }
+                #[inline(always)]
+                fn trailing_zeros_of(self) -> $T {
+                    self.trailing_zeros() as $T
+                }
+            }
+
+            impl Shift for $T {
+                #[inline(always)]
+                fn shl_masked(self, n: $T) -> $T {
+                    self.wrapping_shl(n as u32)
+                }
+                #[inline(always)]
+                fn lshr_masked(self, n: $T) -> $T {
+                    (self as $U).wrapping_shr(n as u32) as $T
+                }
+                #[inline(always)]
+                fn ashr_masked(self, n: $T) -> $T {
+                    (self as $S).wrapping_shr(n as u32) as $T
+                }
+                #[inline(always)]
+                fn rotl_masked(self, n: $T) -> $T {
+                    self.rotate_left(n as u32)
+                }
+                #[inline(always)]
+                fn rotr_masked(self, n: $T) -> $T {
+                    self.rotate_right(n as u32)
+                }
+            }
+        )*
+    }
+}
+
+// `Cast` from the integer type `$S` to each of the integer types `$D`. A
+// failed `try_from` means `self` was out of range, on the side of its sign.
+macro_rules! impl_int_cast {
+    ($($S:ty => $($D:ty)*;)*) => {
+        $($(
+            impl Cast<$D> for $S {
+                #[inline(always)]
+                fn saturating_cast(self) -> $D {
+                    match <$D>::try_from(self) {
+                        Ok(d) => d,
+                        Err(_) if self > <$S>::ZERO => <$D>::MAX,
+                        Err(_) => <$D>::MIN,
+                    }
+                }
+                #[inline(always)]
+                fn cast_fault(self) -> Option<CastFault> {
+                    <$D>::try_from(self).err().map(|_| CastFault::OutOfRange)
+                }
+            }
+        )*)*
+    }
+}
+
+// `Cast` between each integer type `$I` and each floating-point type `$F`.
+// `as` already saturates from floating point, and NaNs go to 0. The range check
+// is against the truncated value: the lower bound, being 0 or a power of two,
+// is exactly representable, and the upper bound `MAX + 1` either is too or is
+// where `MAX` rounds to anyway (and `MAX + 1` rounds back down to it). Going
+// the other way, only `u128` can exceed `f32`, and rounds to infinity if it
+// does.
+macro_rules! impl_float_cast {
+    ($($F:ty => $($I:ty)*;)*) => {
+        $($(
+            impl Cast<$I> for $F {
+                #[inline(always)]
+                fn saturating_cast(self) -> $I {
+                    self as $I
+                }
+                #[inline(always)]
+                fn cast_fault(self) -> Option<CastFault> {
+                    let t = self.trunc();
+                    if self.is_nan() {
+                        Some(CastFault::NaN)
+                    } else if t >= <$I>::MIN as $F && t < <$I>::MAX as $F + 1.0 {
+                        None
+                    } else {
+                        Some(CastFault::OutOfRange)
+                    }
+                }
+            }
+
+            impl Cast<$F> for $I {
+                #[inline(always)]
+                fn saturating_cast(self) -> $F {
+                    let f = self as $F;
+                    if f.is_infinite() { <$F>::MAX } else { f }
+                }
+                #[inline(always)]
+                fn cast_fault(self) -> Option<CastFault> {
+                    (self as $F).is_infinite().then_some(CastFault::OutOfRange)
+                }
+            }
+        )*)*
+    }
+}
+
+impl Cast<f64> for f32 {
+    #[inline(always)]
+    fn saturating_cast(self) -> f64 {
+        self as f64
+    }
+    #[inline(always)]
+    fn cast_fault(self) -> Option<CastFault> {
+        None
+    }
+}
+
+// Finite values beyond the range of `f32` would round to infinity.
+impl Cast<f32> for f64 {
+    #[inline(always)]
+    fn saturating_cast(self) -> f32 {
+        let f = self as f32;
+        if f.is_infinite() && self.is_finite() { f32::MAX.copysign(f) } else { f }
+    }
+    #[inline(always)]
+    fn cast_fault(self) -> Option<CastFault> {
+        let f = self as f32;
+        (f.is_infinite() && self.is_finite()).then_some(CastFault::OutOfRange)
+    }
+}
+
+// `CmpExact` from each signed `$S` to each unsigned `$U`: a negative `$S` is
+// less than any `$U`, and otherwise both fit in the unsigned `$W`.
+macro_rules! impl_cmp_signed_unsigned {
+    ($W:ty; $(($S:ty, $U:ty))*) => {
+        $(
+            impl CmpExact<$U> for $S {
+                #[inline(always)]
+                fn cmp_exact(self, rhs: $U) -> Option<Ordering> {
+                    if self < 0 {
+                        Some(Ordering::Less)
+                    } else {
+                        (self as $W).partial_cmp(&(rhs as $W))
+                    }
+                }
+            }
+        )*
+    }
+}
+
+// `CmpExact` from each floating-point `$F` to each integer `$I`. Values beyond
+// the range of `$I` are ordered by that alone (the bounds are exact; see
+// `impl_float_cast`). Within it, the truncated value converts exactly to
+// `$I`, and if that's equal to `rhs` the fraction breaks the tie.
+macro_rules! impl_cmp_float_int {
+    ($($F:ty => $($I:ty)*;)*) => {
+        $($(
+            impl CmpExact<$I> for $F {
+                #[inline(always)]
+                fn cmp_exact(self, rhs: $I) -> Option<Ordering> {
+                    if self.is_nan() {
+                        None
+                    } else if self < <$I>::MIN as $F {
+                        Some(Ordering::Less)
+                    } else if self >= <$I>::MAX as $F + 1.0 {
+                        Some(Ordering::Greater)
+                    } else {
+                        let t = self.trunc();
+                        match (t as $I).cmp(&rhs) {
+                            Ordering::Equal => self.partial_cmp(&t),
+                            o => Some(o),
+                        }
+                    }
+                }
+            }
+        )*)*
+    }
+}
+
+impl_unsigned!(u8 u16 u32 u64 u128);
+impl_signed!(i8 i16 i32 i64 i128);
+impl_float!(f32 f64);
+#[cfg(feature = "bitwise")]
+impl_bitwise!((u8, i8, u8) (u16, i16, u16) (u32, i32, u32) (u64, i64, u64) (u128, i128, u128)
+              (i8, i8, u8) (i16, i16, u16) (i32, i32, u32) (i64, i64, u64) (i128, i128, u128));
+impl_int_cast!(u8 => u16 u32 u64 u128 i8 i16 i32 i64 i128;
+               u16 => u8 u32 u64 u128 i8 i16 i32 i64 i128;
+               u32 => u8 u16 u64 u128 i8 i16 i32 i64 i128;
+               u64 => u8 u16 u32 u128 i8 i16 i32 i64 i128;
+               u128 => u8 u16 u32 u64 i8 i16 i32 i64 i128;
+               i8 => u8 u16 u32 u64 u128 i16 i32 i64 i128;
+               i16 => u8 u16 u32 u64 u128 i8 i32 i64 i128;
+               i32 => u8 u16 u32 u64 u128 i8 i16 i64 i128;
+               i64 => u8 u16 u32 u64 u128 i8 i16 i32 i128;
+               i128 => u8 u16 u32 u64 u128 i8 i16 i32 i64;);
+impl_float_cast!(f32 => u8 u16 u32 u64 u128 i8 i16 i32 i64 i128;
+                 f64 => u8 u16 u32 u64 u128 i8 i16 i32 i64 i128;);
+impl_cmp_signed_unsigned!(u64;
+                          (i8, u8) (i8, u16) (i8, u32) (i8, u64)
+                          (i16, u8) (i16, u16) (i16, u32) (i16, u64)
+                          (i32, u8) (i32, u16) (i32, u32) (i32, u64)
+                          (i64, u8) (i64, u16) (i64, u32) (i64, u64));
+impl_cmp_signed_unsigned!(u128;
+                          (i8, u128) (i16, u128) (i32, u128) (i64, u128)
+                          (i128, u8) (i128, u16) (i128, u32) (i128, u64) (i128, u128));
+impl_cmp_float_int!(f32 => u8 u16 u32 u64 u128 i8 i16 i32 i64 i128;
+                    f64 => u8 u16 u32 u64 u128 i8 i16 i32 i64 i128;);
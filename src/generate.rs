@@ -0,0 +1,273 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Generators: operations that fill `out` with a sequence made from a few
+// scalars or a short slice, rather than transforming an operand elementwise.
+//
+// Every generator writes each element of its result independently of the
+// others, so `out` is split into chunks that are filled in parallel, each
+// knowing the index of its first element. Arithmetic sequences compute each
+// element from its index, as `start + i * step`, rather than by repeated
+// addition, so floating-point ones don't drift and every chunk gets the same
+// values it would have serially.
+
+use crate::eval::*;
+use crate::operands::*;
+use crate::ops::ValBinOpCode;
+use crate::plan::val_binop_supported;
+use crate::scalarty::ScalarTy;
+use crate::sched::Sched;
+use crate::traits::ScalarT;
+
+// The element types of `iota`. Each element is computed from its index
+// alone, so chunks can be filled independently and in any order; integer
+// sequences that run past the end of their type wrap around.
+trait Seq: ScalarT + ScalarOf + Copy + Send + Sync {
+    // Returns element `i` of the sequence from `start` by `step`.
+    fn nth(start: Self, step: Self, i: usize) -> Self;
+}
+
+// Floats, which also have evenly spaced sequences between two ends.
+trait Lin: Seq {
+    // Returns the step between `n` elements from `start` to `stop`.
+    fn step_between(start: Self, stop: Self, n: usize) -> Self;
+}
+
+macro_rules! impl_seq_int {
+    ($($T:ty)*) => {
+        $(impl Seq for $T {
+            #[inline(always)]
+            fn nth(start: Self, step: Self, i: usize) -> Self {
+                start.wrapping_add((i as $T).wrapping_mul(step))
+            }
+        })*
+    }
+}
+
+macro_rules! impl_seq_float {
+    ($($T:ty)*) => {
+        $(impl Seq for $T {
+            #[inline(always)]
+            fn nth(start: Self, step: Self, i: usize) -> Self {
+                start + i as $T * step
+            }
+        }
+        impl Lin for $T {
+            fn step_between(start: Self, stop: Self, n: usize) -> Self {
+                (stop - start) / (n - 1) as $T
+            }
+        })*
+    }
+}
+
+impl_seq_int!(u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_seq_float!(f32 f64);
+
+// Returns `Ok` if there are arithmetic sequences of type `ty`: if the
+// arithmetic they're made with is compiled in for it.
+fn check_seq_ty(ty: ScalarTy) -> Result<(), EvalError> {
+    use ValBinOpCode::*;
+    if ty == ScalarTy::TBool || !val_binop_supported(Add, ty) || !val_binop_supported(Mul, ty) {
+        return Err(EvalError::UnsupportedOp);
+    }
+    Ok(())
+}
+
+// Returns the first `n` elements of `out` as a `T` slice, or an error if
+// there aren't that many.
+pub(crate) fn out_typed<T: ScalarT>(out: &mut [u8], n: usize) -> Result<&mut [T], EvalError> {
+    let dst: &mut [T] = transmute_buf_mut(out)?;
+    if dst.len() < n {
+        return Err(EvalError::BadBuffer);
+    }
+    Ok(&mut dst[..n])
+}
+
+// Fills `dst` with the sequence from `start` by `step`.
+fn fill_seq<T: Seq>(sched: &Sched, start: T, step: T, dst: &mut [T]) {
+    sched.for_each_row_chunk(1, dst, &|i0, chunk| {
+        for (i, x) in (i0..).zip(chunk.iter_mut()) {
+            *x = T::nth(start, step, i);
+        }
+    });
+}
+
+fn iota_typed<'a, T: Seq + 'a>(sched: &Sched, start: &Const, step: &Const, out: &'a mut [u8],
+                               n: usize) -> Result<Slice<'a>, EvalError>
+where &'a [T]: Into<Slice<'a>>
+{
+    let start = T::of_const(start).ok_or(EvalError::BadBuffer)?;
+    let step = T::of_const(step).ok_or(EvalError::BadBuffer)?;
+    let dst = out_typed::<T>(out, n)?;
+    fill_seq(sched, start, step, dst);
+    Ok((&*dst).into())
+}
+
+fn linspace_typed<'a, T: Lin + 'a>(sched: &Sched, start: &Const, stop: &Const, out: &'a mut [u8],
+                                   n: usize) -> Result<Slice<'a>, EvalError>
+where &'a [T]: Into<Slice<'a>>
+{
+    let start = T::of_const(start).ok_or(EvalError::BadBuffer)?;
+    let stop = T::of_const(stop).ok_or(EvalError::BadBuffer)?;
+    let dst = out_typed::<T>(out, n)?;
+    if n == 1 {
+        dst[0] = start;
+    } else if n > 1 {
+        fill_seq(sched, start, T::step_between(start, stop, n), dst);
+        // The last element is exactly `stop`, however the steps round.
+        dst[n - 1] = stop;
+    }
+    Ok((&*dst).into())
+}
+
+fn broadcast_typed<'a, T>(sched: &Sched, c: &Const, out: &'a mut [u8], n: usize)
+                          -> Result<Slice<'a>, EvalError>
+where T: ScalarT + ScalarOf + Copy + Send + Sync + 'a, &'a [T]: Into<Slice<'a>>
+{
+    let c = T::of_const(c).ok_or(EvalError::BadBuffer)?;
+    let dst = out_typed::<T>(out, n)?;
+    sched.for_each_row_chunk(1, dst, &|_, chunk| chunk.fill(c));
+    Ok((&*dst).into())
+}
+
+// Repeats each element of `x` `times` times if `each`, or else all of `x`
+// `times` times over.
+fn repeat_typed<'a, T>(sched: &Sched, x: &Slice, times: usize, each: bool, out: &'a mut [u8])
+                       -> Result<Slice<'a>, EvalError>
+where T: ScalarT + ScalarOf + Copy + Send + Sync + 'a, &'a [T]: Into<Slice<'a>>
+{
+    let x = T::of_slice(x).ok_or(EvalError::BadBuffer)?;
+    let n = x.len().checked_mul(times).ok_or(EvalError::BadBuffer)?;
+    let dst = out_typed::<T>(out, n)?;
+    if n > 0 {
+        if each {
+            sched.for_each_row_chunk(times, dst, &|i0, rows| {
+                for (&v, row) in x[i0..].iter().zip(rows.chunks_mut(times)) {
+                    row.fill(v);
+                }
+            });
+        } else {
+            sched.for_each_row_chunk(x.len(), dst, &|_, rows| {
+                for row in rows.chunks_mut(x.len()) {
+                    row.copy_from_slice(x);
+                }
+            });
+        }
+    }
+    Ok((&*dst).into())
+}
+
+/// The generators fill the first `n` elements of `out` with a sequence, and
+/// return them as a `Slice`; `tmp1` and `tmp2` aren't used. Scalars they're
+/// given are first converted to the type of the result. Arithmetic sequences
+/// aren't defined on `TBool`, or on types that aren't compiled in, which are
+/// `EvalError::UnsupportedOp`.
+impl<'eval> EvalCtx<'eval> {
+    /// APL's `⍳`, generalized: the `n` elements `start + i * step` of type
+    /// `ty`, for `i` from 0. Integers wrap, as `Add` and `Mul` do, so a
+    /// negative `step` counts down even for unsigned types.
+    pub fn iota(self, ty: ScalarTy, n: usize, start: &Const, step: &Const)
+                -> Result<Slice<'eval>, EvalError> {
+        use ScalarTy::*;
+        check_seq_ty(ty)?;
+        let (start, step) = (self.conv_scalar(start, ty)?, self.conv_scalar(step, ty)?);
+        let sched = self.sched();
+        let out = self.out;
+        match ty {
+            TBool => Err(EvalError::UnsupportedOp),
+            TU8 => iota_typed::<u8>(&sched, &start, &step, out, n),
+            TU16 => iota_typed::<u16>(&sched, &start, &step, out, n),
+            TU32 => iota_typed::<u32>(&sched, &start, &step, out, n),
+            TU64 => iota_typed::<u64>(&sched, &start, &step, out, n),
+            TU128 => iota_typed::<u128>(&sched, &start, &step, out, n),
+            TI8 => iota_typed::<i8>(&sched, &start, &step, out, n),
+            TI16 => iota_typed::<i16>(&sched, &start, &step, out, n),
+            TI32 => iota_typed::<i32>(&sched, &start, &step, out, n),
+            TI64 => iota_typed::<i64>(&sched, &start, &step, out, n),
+            TI128 => iota_typed::<i128>(&sched, &start, &step, out, n),
+            TF32 => iota_typed::<f32>(&sched, &start, &step, out, n),
+            TF64 => iota_typed::<f64>(&sched, &start, &step, out, n),
+        }
+    }
+
+    /// `n` evenly spaced elements of the float type `ty`, from `start` to
+    /// exactly `stop`. A single element is `start`. Integer types are
+    /// `EvalError::UnsupportedOp`.
+    pub fn linspace(self, ty: ScalarTy, n: usize, start: &Const, stop: &Const)
+                    -> Result<Slice<'eval>, EvalError> {
+        use ScalarTy::*;
+        check_seq_ty(ty)?;
+        let (start, stop) = (self.conv_scalar(start, ty)?, self.conv_scalar(stop, ty)?);
+        let sched = self.sched();
+        let out = self.out;
+        match ty {
+            TF32 => linspace_typed::<f32>(&sched, &start, &stop, out, n),
+            TF64 => linspace_typed::<f64>(&sched, &start, &stop, out, n),
+            _ => Err(EvalError::UnsupportedOp),
+        }
+    }
+
+    /// `n` copies of `c`.
+    pub fn broadcast(self, c: &Const, n: usize) -> Result<Slice<'eval>, EvalError> {
+        use ScalarTy::*;
+        let sched = self.sched();
+        let out = self.out;
+        match c.get_scalar_ty() {
+            TBool => broadcast_typed::<bool>(&sched, c, out, n),
+            TU8 => broadcast_typed::<u8>(&sched, c, out, n),
+            TU16 => broadcast_typed::<u16>(&sched, c, out, n),
+            TU32 => broadcast_typed::<u32>(&sched, c, out, n),
+            TU64 => broadcast_typed::<u64>(&sched, c, out, n),
+            TU128 => broadcast_typed::<u128>(&sched, c, out, n),
+            TI8 => broadcast_typed::<i8>(&sched, c, out, n),
+            TI16 => broadcast_typed::<i16>(&sched, c, out, n),
+            TI32 => broadcast_typed::<i32>(&sched, c, out, n),
+            TI64 => broadcast_typed::<i64>(&sched, c, out, n),
+            TI128 => broadcast_typed::<i128>(&sched, c, out, n),
+            TF32 => broadcast_typed::<f32>(&sched, c, out, n),
+            TF64 => broadcast_typed::<f64>(&sched, c, out, n),
+        }
+    }
+
+    /// Each element of `x` repeated `times` times in turn: `[a, a, b, b]`
+    /// for `[a, b]` twice.
+    pub fn repeat(self, x: &Slice, times: usize) -> Result<Slice<'eval>, EvalError> {
+        self.repeat_dynamic(x, times, true)
+    }
+
+    /// All of `x` repeated `times` times over: `[a, b, a, b]` for `[a, b]`
+    /// twice.
+    pub fn tile(self, x: &Slice, times: usize) -> Result<Slice<'eval>, EvalError> {
+        self.repeat_dynamic(x, times, false)
+    }
+
+    fn repeat_dynamic(self, x: &Slice, times: usize, each: bool)
+                      -> Result<Slice<'eval>, EvalError> {
+        use ScalarTy::*;
+        let sched = self.sched();
+        let out = self.out;
+        match x.get_scalar_ty() {
+            TBool => repeat_typed::<bool>(&sched, x, times, each, out),
+            TU8 => repeat_typed::<u8>(&sched, x, times, each, out),
+            TU16 => repeat_typed::<u16>(&sched, x, times, each, out),
+            TU32 => repeat_typed::<u32>(&sched, x, times, each, out),
+            TU64 => repeat_typed::<u64>(&sched, x, times, each, out),
+            TU128 => repeat_typed::<u128>(&sched, x, times, each, out),
+            TI8 => repeat_typed::<i8>(&sched, x, times, each, out),
+            TI16 => repeat_typed::<i16>(&sched, x, times, each, out),
+            TI32 => repeat_typed::<i32>(&sched, x, times, each, out),
+            TI64 => repeat_typed::<i64>(&sched, x, times, each, out),
+            TI128 => repeat_typed::<i128>(&sched, x, times, each, out),
+            TF32 => repeat_typed::<f32>(&sched, x, times, each, out),
+            TF64 => repeat_typed::<f64>(&sched, x, times, each, out),
+        }
+    }
+
+    // Converts `c` to type `ty`.
+    fn conv_scalar(&self, c: &Const, ty: ScalarTy) -> Result<Const, EvalError> {
+        match self.with_buffers(&mut [], &mut [], &mut []).conv(&Operand::OperandConst(c.clone()), ty)? {
+            Operand::OperandConst(c) => Ok(c),
+            Operand::OperandSlice(_) => Err(EvalError::UnsupportedOp),
+        }
+    }
+}
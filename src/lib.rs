@@ -37,19 +37,48 @@ mod macros;
 
 mod traits;
 mod zeroone;
+mod fallback;
 mod consts;
+mod simd;
+mod cpu;
+#[macro_use]
+mod steps;
+mod sched;
 mod operands;
+mod owned;
 mod scalarty;
+mod promotion;
 mod ops;
 mod eval;
+mod plan;
+mod caps;
+mod stream;
+mod shaped;
+mod structural;
+mod product;
+mod generate;
+mod random;
+mod codec;
+#[cfg(feature = "arrow")]
+mod arrow;
 mod tests;
 
 // These are the public API. Intentionally narrow and dynamically-typed.
 pub use consts::{CHUNKBYTES,VECBYTES};
 pub use scalarty::ScalarTy;
-pub use operands::{Const,Slice,Operand};
-pub use ops::{BoolBinOpCode,BoolUnOpCode,ValBinOpCode,ValUnOpCode};
+pub use promotion::{DefaultPromotion,PromotionPolicy,StrictPromotion};
+pub use operands::{Const,Slice,SliceMut,Operand};
+pub use owned::{OwnedSlice,OwnedOperand};
+pub use ops::{BoolBinOpCode,BoolUnOpCode,CastMode,ValBinOpCode,ValTernOpCode,ValUnOpCode};
 pub use eval::{EvalError,EvalCtx};
+pub use plan::{EvalPlan,OperandDesc,plan_bool_binop,plan_bool_unop,plan_cast,plan_conv,
+               plan_val_binop,plan_val_ternop,plan_val_unop};
+pub use caps::{Capability,OpCode,capabilities};
+pub use stream::StreamOperand;
+pub use shaped::{ShapedOperand,agree_shapes};
+pub use sched::{calibrate_chunk_bytes,Parallelism};
+pub use cpu::SimdLevel;
+pub use codec::{CodecError,CODEC_VERSION};
 
 // TODO:
 //   1. DONE: Switch from slices to discriminated union of constant-or-slice.
@@ -66,10 +95,10 @@ pub use eval::{EvalError,EvalCtx};
 //  11. DONE: Revisit buffer size calculations, expected and required sizes.
 //  12. DONE: Audit access control.
 //  13. DONE: Rename things to have less-silly names.
-//  14. LATER: Add non-SIMD fallback macros for ops not in packed_simd.
+//  14. DONE: Add non-SIMD fallback macros for ops not in packed_simd.
 //  15. LATER: Add decimal128.
 //  16. LATER: Add packed small-string types / ops.
-//  17. LATER: Add features to make a small or full-sized version.
+//  17. DONE: Add features to make a small or full-sized version.
 //  18. LATER: Figure out how best to trap ubiquitous faults like SIGFPE.
 //  19. DONE: Mop up egregious warnings / clippy-isms.
 //  20. DONE: At least a handful of tests.
@@ -6,7 +6,7 @@
 ////////////////////////////////////////////////////////////////////////////////
 
 macro_rules! impl_operand_from {
-    ($([$T:ty] $const_ctor:ident $slice_ctor:ident)*) => {
+    ($([$T:ty] $const_ctor:ident $slice_ctor:ident $slice_mut_ctor:ident)*) => {
         $(
             impl std::convert::From<$T> for Const {
                 fn from(s: $T) -> Self {
@@ -18,6 +18,16 @@ macro_rules! impl_operand_from {
                     Slice::$slice_ctor(s)
                 }
             }
+            impl<'a> std::convert::From<&'a mut [$T]> for SliceMut<'a> {
+                fn from(s: &'a mut [$T]) -> Self {
+                    SliceMut::$slice_mut_ctor(s)
+                }
+            }
+            impl<'a> std::convert::From<&'a mut Vec<$T>> for SliceMut<'a> {
+                fn from(s: &'a mut Vec<$T>) -> Self {
+                    SliceMut::$slice_mut_ctor(s.as_mut_slice())
+                }
+            }
             impl<'a> std::convert::From<$T> for Operand<'a> {
                 fn from(s: $T) -> Self {
                     Operand::OperandConst(Const::$const_ctor(s))
@@ -33,41 +43,100 @@ macro_rules! impl_operand_from {
                     Operand::OperandSlice(Slice::$slice_ctor(s.as_slice()))
                 }
             }
+            impl ScalarOf for $T {
+                fn of_const(c: &Const) -> Option<Self> {
+                    match *c {
+                        Const::$const_ctor(x) => Some(x),
+                        _ => None
+                    }
+                }
+                fn of_slice<'a>(s: &Slice<'a>) -> Option<&'a [Self]> {
+                    match *s {
+                        Slice::$slice_ctor(x) => Some(x),
+                        _ => None
+                    }
+                }
+            }
         )*
     }
 }
 
+macro_rules! impl_owned_operand_from {
+    ($([$T:ty] $const_ctor:ident $slice_ctor:ident)*) => {
+        $(
+            impl std::convert::From<std::sync::Arc<[$T]>> for OwnedSlice {
+                fn from(s: std::sync::Arc<[$T]>) -> Self {
+                    OwnedSlice::$slice_ctor(s)
+                }
+            }
+            impl std::convert::From<Vec<$T>> for OwnedSlice {
+                fn from(s: Vec<$T>) -> Self {
+                    OwnedSlice::$slice_ctor(s.into())
+                }
+            }
+            impl std::convert::From<$T> for OwnedOperand {
+                fn from(s: $T) -> Self {
+                    OwnedOperand::OperandConst(Const::$const_ctor(s))
+                }
+            }
+            impl std::convert::From<std::sync::Arc<[$T]>> for OwnedOperand {
+                fn from(s: std::sync::Arc<[$T]>) -> Self {
+                    OwnedOperand::OperandSlice(OwnedSlice::$slice_ctor(s))
+                }
+            }
+            impl std::convert::From<Vec<$T>> for OwnedOperand {
+                fn from(s: Vec<$T>) -> Self {
+                    OwnedOperand::OperandSlice(OwnedSlice::$slice_ctor(s.into()))
+                }
+            }
+        )*
+    }
+}
+
+
+////////////////////////////////////////////////////////////////////////////////
+// Operator structs
+////////////////////////////////////////////////////////////////////////////////
+
+// Declares the (empty, type-indexed) structs that skeletons and operators are
+// implemented on. They're declared separately from the macros that implement
+// them, since an operator's kernels are often instantiated in several pieces,
+// some compiled out by features (see `impl_gated`).
+macro_rules! decl_op_structs {
+    ($($struct_id:ident)*) => {
+        $(
+            pub struct $struct_id<SRC, DST> {
+                _x: std::marker::PhantomData<(SRC,DST)>,
+            }
+        )*
+    }
+}
 
 ////////////////////////////////////////////////////////////////////////////////
 // Operator skeletons
 ////////////////////////////////////////////////////////////////////////////////
 //
 // To reduce the amount of code amplification, we make a common outer
-// "skeletons" of rayon CHUNKSZ-walking code for each set of types (and for both
+// "skeletons" of rayon chunk-walking code for each set of types (and for both
 // unop and binop cases), and then dispatch once *dynamically* for each chunk to
-// an inner single-CHUNKSZ operator function.
+// an inner single-chunk operator function. The chunk length comes from the
+// `Sched` at runtime (see `Sched::chunk_len`). Slices needn't be a multiple of
+// it: the last chunk is just short, and the inner functions walk it with the
+// `steps` helpers, which take care of any final partial vector.
 
 macro_rules! impl_unop_skel {
     ($(($SRC:ty, $DST:ty))*) => {
-        pub struct UnOpSkel<SRC, DST> {
-            _x: std::marker::PhantomData<(SRC,DST)>,
-        }
         $(
             impl UnOpSkel<$SRC, $DST>
             {
                 #[inline(never)]
-                fn skel<'src, 'dst>(src: &'src [$SRC],
+                fn skel<'src, 'dst>(sched: &Sched,
+                                    src: &'src [$SRC],
                                     dst: &'dst mut[$DST],
                                     f: &(dyn Sync + Fn(&[$SRC], &mut [$DST])))
                 {
-                    const CHUNKSZ : usize = chunksz_min::<$SRC,$DST>();
-                    let len = src.len();
-                    assert_eq!(len, dst.len());
-                    assert_eq!((len & !(CHUNKSZ-1)), len);
-                    src.par_chunks(CHUNKSZ)
-                        .zip(dst.par_chunks_mut(CHUNKSZ))
-                        .for_each(|(srcchunk, dstchunk)|
-                                  f(srcchunk, dstchunk));
+                    let chunksz = sched.chunk_len::<$SRC,$DST>();
+                    sched.for_each_chunk1(chunksz, src, dst, f);
                 }
             }
         )*
@@ -76,29 +145,19 @@ macro_rules! impl_unop_skel {
 
 macro_rules! impl_binop_skel {
     ($(($SRC:ty , $DST:ty))*) => {
-        pub struct BinOpSkel<SRC,DST> {
-            _x: std::marker::PhantomData<(SRC,DST)>,
-        }
         $(
             impl BinOpSkel<$SRC,$DST>
             {
                 #[inline(never)]
-                fn skel<'src, 'dst>(lhs: &'src [$SRC],
+                fn skel<'src, 'dst>(sched: &Sched,
+                                    lhs: &'src [$SRC],
                                     rhs: &'src [$SRC],
                                     dst: &'dst mut[$DST],
                                     f: &(dyn Sync + Fn(&[$SRC], &[$SRC], &mut [$DST])))
                 where 'src: 'dst,
                 {
-                    const CHUNKSZ : usize = chunksz_min::<$SRC,$DST>();
-                    let len = rhs.len();
-                    assert_eq!(len, lhs.len());
-                    assert_eq!(len, dst.len());
-                    assert_eq!((len & !(CHUNKSZ-1)), len);
-                    lhs.par_chunks(CHUNKSZ)
-                        .zip(rhs.par_chunks(CHUNKSZ))
-                        .zip(dst.par_chunks_mut(CHUNKSZ))
-                        .for_each(|((lhschunk,rhschunk), dstchunk)|
-                                  f(lhschunk, rhschunk, dstchunk));
+                    let chunksz = sched.chunk_len::<$SRC,$DST>();
+                    sched.for_each_chunk2(chunksz, lhs, rhs, dst, f);
                 }
             }
         )*
@@ -111,10 +170,6 @@ macro_rules! impl_binop_skel {
 
 macro_rules! impl_unop {
     ($struct_id:ident, $op:ident, $($T:ty)*) => {
-        pub struct $struct_id<SRC, DST> {
-            _x: std::marker::PhantomData<(SRC,DST)>,
-        }
-
         $(
             impl UnOp<$T,$T> for $struct_id<$T,$T>
             {
@@ -125,23 +180,23 @@ macro_rules! impl_unop {
                 }
 
                 #[inline(never)]
-                fn apply_slice<'src, 'dst>(src: &'src [$T],
+                fn apply_slice<'src, 'dst>(sched: &Sched,
+                                           src: &'src [$T],
                                            dst: &'dst mut[$T])
                                            -> Result<&'dst [$T], OpError>
                 where 'src: 'dst
                 {
                     const STEPSZ : usize = VECBYTES / size_of::<$T>();
+                    let step = |src: &[$T], dst: &mut [$T]| {
+                        let sv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(src);
+                        let dv = sv.$op();
+                        dv.write_to_slice_unaligned(dst)
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
                     <UnOpSkel<$T,$T>>::skel(
-                        src, dst,
-                        &|srcchunk, dstchunk| {
-                            for (src, dst) in
-                                srcchunk.chunks_exact(STEPSZ)
-                                .zip(dstchunk.chunks_exact_mut(STEPSZ))
-                            {
-                                let sv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(src);
-                                let dv = sv.$op();
-                                dv.write_to_slice_unaligned(dst)
-                            }
+                        sched, src, dst,
+                        &|srcchunk, dstchunk| unsafe {
+                            steps(srcchunk, dstchunk, <$T>::ONE, &step)
                         });
                     Ok(dst)
                 }
@@ -154,12 +209,10 @@ macro_rules! impl_unop {
 // Unary T->bool ("predicate") operators
 ////////////////////////////////////////////////////////////////////////////////
 
+// The only unary predicates are floating-point classifications.
+#[cfg(feature = "float")]
 macro_rules! impl_unop_pred {
     ($struct_id:ident, $op:ident, $($T:ty)*) => {
-        pub struct $struct_id<SRC,DST> {
-            _x: std::marker::PhantomData<(SRC,DST)>,
-        }
-
         $(
             impl UnOp<$T,bool> for $struct_id<$T,bool>
             {
@@ -170,7 +223,8 @@ macro_rules! impl_unop_pred {
                 }
 
                 #[inline(never)]
-                fn apply_slice<'src, 'dst>(src: &'src [$T],
+                fn apply_slice<'src, 'dst>(sched: &Sched,
+                                           src: &'src [$T],
                                            dst: &'dst mut[bool])
                                            -> Result<&'dst [bool], OpError>
                 where 'src: 'dst
@@ -178,20 +232,19 @@ macro_rules! impl_unop_pred {
                     const STEPSZ : usize = stepsz_min::<$T,bool>();
                     const TRUES : Simd<[u8; STEPSZ]> = <Simd<[u8; STEPSZ]>>::splat(1);
                     const FALSES : Simd<[u8; STEPSZ]> = <Simd<[u8; STEPSZ]>>::splat(0);
+                    let step = |src: &[$T], dst: &mut [bool]| {
+                        let sv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(src);
+                        let mv = sv.$op();
+                        let bv = mv.select(TRUES, FALSES);
+                        unsafe {
+                            bv.write_to_slice_unaligned(::std::mem::transmute::<&mut[bool],&mut[u8]>(dst));
+                        }
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
                     <UnOpSkel<$T,bool>>::skel(
-                        src, dst,
-                        &|srcchunk, dstchunk| {
-                            for (src, dst) in
-                                srcchunk.chunks_exact(STEPSZ)
-                                .zip(dstchunk.chunks_exact_mut(STEPSZ))
-                            {
-                                let sv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(src);
-                                let mv = sv.$op();
-                                let bv = mv.select(TRUES, FALSES);
-                                unsafe {
-                                    bv.write_to_slice_unaligned(::std::mem::transmute::<&mut[bool],&mut[u8]>(dst));
-                                }
-                            }
+                        sched, src, dst,
+                        &|srcchunk, dstchunk| unsafe {
+                            steps(srcchunk, dstchunk, <$T>::ONE, &step)
                         });
                     Ok(dst)
                 }
@@ -206,14 +259,12 @@ macro_rules! impl_unop_pred {
 
 macro_rules! impl_binop {
     ($struct_id:ident, $op:ident, $($T:ty)*) => {
-        pub struct $struct_id<SRC, DST> {
-            _x: std::marker::PhantomData<(SRC,DST)>,
-        }
         $(
             impl BinOp<$T,$T> for $struct_id<$T,$T>
             {
                 #[inline(never)]
-                fn apply_slice_slice<'src, 'dst>(lhs: &'src [$T],
+                fn apply_slice_slice<'src, 'dst>(sched: &Sched,
+                                                 lhs: &'src [$T],
                                                  rhs: &'src [$T],
                                                  dst: &'dst mut[$T])
                                                  -> Result<&'dst [$T], OpError>
@@ -221,25 +272,24 @@ macro_rules! impl_binop {
                 'src: 'dst,
                 {
                     const STEPSZ : usize = VECBYTES / size_of::<$T>();
+                    let step = |lhs: &[$T], rhs: &[$T], dst: &mut [$T]| {
+                        let lv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(lhs);
+                        let rv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(rhs);
+                        let dv = lv.$op(rv);
+                        dv.write_to_slice_unaligned(dst);
+                    };
+                    let steps = select_steps2::<_, _, _, _, STEPSZ>(sched.simd_level());
                     <BinOpSkel<$T,$T>>::skel(
-                        lhs, rhs, dst,
-                        &|lhschunk, rhschunk, dstchunk| {
-                            for ((lhs, rhs), dst) in
-                                lhschunk.chunks_exact(STEPSZ)
-                                .zip(rhschunk.chunks_exact(STEPSZ))
-                                .zip(dstchunk.chunks_exact_mut(STEPSZ))
-                            {
-                                let lv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(lhs);
-                                let rv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(rhs);
-                                let dv = lv.$op(rv);
-                                dv.write_to_slice_unaligned(dst);
-                            }
+                        sched, lhs, rhs, dst,
+                        &|lhschunk, rhschunk, dstchunk| unsafe {
+                            steps(lhschunk, rhschunk, dstchunk, <$T>::ONE, <$T>::ONE, &step)
                         });
                     Ok(dst)
                 }
 
                 #[inline(never)]
-                fn apply_slice_const<'src, 'dst>(lhs: &'src [$T],
+                fn apply_slice_const<'src, 'dst>(sched: &Sched,
+                                                 lhs: &'src [$T],
                                                  rhs: $T,
                                                  dst: &'dst mut[$T])
                                                  -> Result<&'dst [$T], OpError>
@@ -248,23 +298,23 @@ macro_rules! impl_binop {
                 {
                     const STEPSZ : usize = VECBYTES / size_of::<$T>();
                     let rv = <Simd<[$T; STEPSZ]>>::splat(rhs);
+                    let step = |lhs: &[$T], dst: &mut [$T]| {
+                        let lv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(lhs);
+                        let dv = lv.$op(rv);
+                        dv.write_to_slice_unaligned(dst);
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
                     <UnOpSkel<$T,$T>>::skel(
-                        lhs, dst,
-                        &|lhschunk, dstchunk| {
-                            for (lhs, dst) in
-                                lhschunk.chunks_exact(STEPSZ)
-                                .zip(dstchunk.chunks_exact_mut(STEPSZ))
-                            {
-                                let lv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(lhs);
-                                let dv = lv.$op(rv);
-                                dv.write_to_slice_unaligned(dst);
-                            }
+                        sched, lhs, dst,
+                        &|lhschunk, dstchunk| unsafe {
+                            steps(lhschunk, dstchunk, <$T>::ONE, &step)
                         });
                     Ok(dst)
                 }
 
                 #[inline(never)]
-                fn apply_const_slice<'src, 'dst>(lhs: $T,
+                fn apply_const_slice<'src, 'dst>(sched: &Sched,
+                                                 lhs: $T,
                                                  rhs: &'src [$T],
                                                  dst: &'dst mut[$T])
                                                  -> Result<&'dst [$T], OpError>
@@ -273,17 +323,16 @@ macro_rules! impl_binop {
                 {
                     const STEPSZ : usize = VECBYTES / size_of::<$T>();
                     let lv = <Simd<[$T; STEPSZ]>>::splat(lhs);
+                    let step = |rhs: &[$T], dst: &mut [$T]| {
+                        let rv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(rhs);
+                        let dv = lv.$op(rv);
+                        dv.write_to_slice_unaligned(dst);
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
                     <UnOpSkel<$T,$T>>::skel(
-                        rhs, dst,
-                        &|rhschunk, dstchunk| {
-                            for (rhs, dst) in
-                                rhschunk.chunks_exact(STEPSZ)
-                                .zip(dstchunk.chunks_exact_mut(STEPSZ))
-                            {
-                                let rv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(rhs);
-                                let dv = lv.$op(rv);
-                                dv.write_to_slice_unaligned(dst);
-                            }
+                        sched, rhs, dst,
+                        &|rhschunk, dstchunk| unsafe {
+                            steps(rhschunk, dstchunk, <$T>::ONE, &step)
                         });
                     Ok(dst)
                 }
@@ -306,13 +355,16 @@ macro_rules! impl_unop_unsupported_full {
         $(
             impl UnOp<$T,$U> for $struct_id<$T,$U>
             {
+                const SUPPORTED: bool = false;
+
                 #[inline(never)]
                 fn apply_const(_src: $T) -> Result<$U, OpError>
                 {
                     Err(OpError::Unsupported)
                 }
                 #[inline(never)]
-                fn apply_slice<'src, 'dst>(_src: &'src [$T],
+                fn apply_slice<'src, 'dst>(_sched: &Sched,
+                                           _src: &'src [$T],
                                            _dst: &'dst mut[$U])
                                            -> Result<&'dst [$U], OpError>
                 where 'src: 'dst
@@ -347,8 +399,11 @@ macro_rules! impl_binop_unsupported_full {
                 for
                 $struct_id<$T,$U>
             {
+                const SUPPORTED: bool = false;
+
                 #[inline(never)]
-                fn apply_slice_slice<'src, 'dst>(_lhs: &'src [$T],
+                fn apply_slice_slice<'src, 'dst>(_sched: &Sched,
+                                                 _lhs: &'src [$T],
                                                  _rhs: &'src [$T],
                                                  _dst: &'dst mut[$U])
                                                  -> Result<&'dst [$U], OpError>
@@ -359,7 +414,8 @@ macro_rules! impl_binop_unsupported_full {
                 }
 
                 #[inline(never)]
-                fn apply_const_slice<'src, 'dst>(_lhs: $T,
+                fn apply_const_slice<'src, 'dst>(_sched: &Sched,
+                                                 _lhs: $T,
                                                  _rhs: &'src [$T],
                                                  _dst: &'dst mut[$U])
                                                  -> Result<&'dst [$U], OpError>
@@ -370,7 +426,8 @@ macro_rules! impl_binop_unsupported_full {
                 }
 
                 #[inline(never)]
-                fn apply_slice_const<'src, 'dst>(_lhs: &'src [$T],
+                fn apply_slice_const<'src, 'dst>(_sched: &Sched,
+                                                 _lhs: &'src [$T],
                                                  _rhs: $T,
                                                  _dst: &'dst mut[$U])
                                                  -> Result<&'dst [$U], OpError>
@@ -408,19 +465,14 @@ macro_rules! impl_binop_pred_unsupported {
 
 macro_rules! impl_binop_pred {
     ($struct_id:ident, $op:ident, $($T:ty)*) => {
-
-        pub struct $struct_id<SRC,DST>
-        {
-            _x: std::marker::PhantomData<(SRC,DST)>,
-        }
-
         $(
             impl BinOp<$T,bool>
                 for
                 $struct_id<$T,bool>
             {
                 #[inline(never)]
-                fn apply_slice_slice<'src, 'dst>(lhs: &'src [$T],
+                fn apply_slice_slice<'src, 'dst>(sched: &Sched,
+                                                 lhs: &'src [$T],
                                                  rhs: &'src [$T],
                                                  dst: &'dst mut[bool])
                                                  -> Result<&'dst [bool], OpError>
@@ -430,27 +482,27 @@ macro_rules! impl_binop_pred {
                     const STEPSZ : usize = stepsz_min::<$T,bool>();
                     const TRUES : Simd<[u8; STEPSZ]> = <Simd<[u8; STEPSZ]>>::splat(1);
                     const FALSES : Simd<[u8; STEPSZ]> = <Simd<[u8; STEPSZ]>>::splat(0);
+                    let step = |lhs: &[$T], rhs: &[$T], dst: &mut [bool]| {
+                        let lv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(lhs);
+                        let rv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(rhs);
+                        let mv = lv.$op(rv);
+                        let bv = mv.select(TRUES, FALSES);
+                        unsafe {
+                            bv.write_to_slice_unaligned(::std::mem::transmute::<&mut[bool],&mut[u8]>(dst));
+                        }
+                    };
+                    let steps = select_steps2::<_, _, _, _, STEPSZ>(sched.simd_level());
                     <BinOpSkel<$T,bool>>::skel(
-                        lhs, rhs, dst,
-                        &|lhschunk, rhschunk, dstchunk| {
-                            for ((lhs, rhs), dst) in
-                                lhschunk.chunks_exact(STEPSZ)
-                                .zip(rhschunk.chunks_exact(STEPSZ))
-                                .zip(dstchunk.chunks_exact_mut(STEPSZ)) {
-                                    let lv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(lhs);
-                                    let rv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(rhs);
-                                    let mv = lv.$op(rv);
-                                    let bv = mv.select(TRUES, FALSES);
-                                    unsafe {
-                                        bv.write_to_slice_unaligned(::std::mem::transmute::<&mut[bool],&mut[u8]>(dst));
-                                    }
-                                }
+                        sched, lhs, rhs, dst,
+                        &|lhschunk, rhschunk, dstchunk| unsafe {
+                            steps(lhschunk, rhschunk, dstchunk, <$T>::ONE, <$T>::ONE, &step)
                         });
                     Ok(dst)
                 }
 
                 #[inline(never)]
-                fn apply_const_slice<'src, 'dst>(lhs: $T,
+                fn apply_const_slice<'src, 'dst>(sched: &Sched,
+                                                 lhs: $T,
                                                  rhs: &'src [$T],
                                                  dst: &'dst mut[bool])
                                                  -> Result<&'dst [bool], OpError>
@@ -461,25 +513,26 @@ macro_rules! impl_binop_pred {
                     const TRUES : Simd<[u8; STEPSZ]> = <Simd<[u8; STEPSZ]>>::splat(1);
                     const FALSES : Simd<[u8; STEPSZ]> = <Simd<[u8; STEPSZ]>>::splat(0);
                     let lv = <Simd<[$T; STEPSZ]>>::splat(lhs);
+                    let step = |rhs: &[$T], dst: &mut [bool]| {
+                        let rv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(rhs);
+                        let mv = lv.$op(rv);
+                        let bv = mv.select(TRUES, FALSES);
+                        unsafe {
+                            bv.write_to_slice_unaligned(::std::mem::transmute::<&mut[bool],&mut[u8]>(dst));
+                        }
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
                     <UnOpSkel<$T,bool>>::skel(
-                        rhs, dst,
-                        &|rhschunk, dstchunk| {
-                            for (rhs, dst) in
-                                rhschunk.chunks_exact(STEPSZ)
-                                .zip(dstchunk.chunks_exact_mut(STEPSZ)) {
-                                    let rv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(rhs);
-                                    let mv = lv.$op(rv);
-                                    let bv = mv.select(TRUES, FALSES);
-                                    unsafe {
-                                        bv.write_to_slice_unaligned(::std::mem::transmute::<&mut[bool],&mut[u8]>(dst));
-                                    }
-                                }
+                        sched, rhs, dst,
+                        &|rhschunk, dstchunk| unsafe {
+                            steps(rhschunk, dstchunk, <$T>::ONE, &step)
                         });
                     Ok(dst)
                 }
 
                 #[inline(never)]
-                fn apply_slice_const<'src, 'dst>(lhs: &'src [$T],
+                fn apply_slice_const<'src, 'dst>(sched: &Sched,
+                                                 lhs: &'src [$T],
                                                  rhs: $T,
                                                  dst: &'dst mut[bool])
                                                  -> Result<&'dst [bool], OpError>
@@ -490,19 +543,19 @@ macro_rules! impl_binop_pred {
                     const TRUES : Simd<[u8; STEPSZ]> = <Simd<[u8; STEPSZ]>>::splat(1);
                     const FALSES : Simd<[u8; STEPSZ]> = <Simd<[u8; STEPSZ]>>::splat(0);
                     let rv = <Simd<[$T; STEPSZ]>>::splat(rhs);
+                    let step = |lhs: &[$T], dst: &mut [bool]| {
+                        let lv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(lhs);
+                        let mv = lv.$op(rv);
+                        let bv = mv.select(TRUES, FALSES);
+                        unsafe {
+                            bv.write_to_slice_unaligned(::std::mem::transmute::<&mut[bool],&mut[u8]>(dst));
+                        }
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
                     <UnOpSkel<$T,bool>>::skel(
-                        lhs, dst,
-                        &|lhschunk, dstchunk| {
-                            for (lhs, dst) in
-                                lhschunk.chunks_exact(STEPSZ)
-                                .zip(dstchunk.chunks_exact_mut(STEPSZ)) {
-                                    let lv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(lhs);
-                                    let mv = lv.$op(rv);
-                                    let bv = mv.select(TRUES, FALSES);
-                                    unsafe {
-                                        bv.write_to_slice_unaligned(::std::mem::transmute::<&mut[bool],&mut[u8]>(dst));
-                                    }
-                                }
+                        sched, lhs, dst,
+                        &|lhschunk, dstchunk| unsafe {
+                            steps(lhschunk, dstchunk, <$T>::ONE, &step)
                         });
                     Ok(dst)
                 }
@@ -517,6 +570,380 @@ macro_rules! impl_binop_pred {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// Scalar-loop (T)->T and (T,T)->T operators
+////////////////////////////////////////////////////////////////////////////////
+//
+// For operations with no vector form: each chunk is a plain loop calling the
+// scalar method `$op` on each element (or pair of elements). They run through
+// the same skeletons as the vector kernels, and LLVM auto-vectorizes the loops
+// where it can. They also go through `steps1` and `steps2`, a vector's worth of
+// elements per step, so that the loops are compiled for each `SimdLevel` and
+// can use its instructions (eg. AVX's rounding, or AVX2's per-lane shifts).
+
+macro_rules! impl_unop_scalar {
+    ($struct_id:ident, $op:ident, $($T:ty)*) => {
+        $(
+            impl UnOp<$T,$T> for $struct_id<$T,$T>
+            {
+                #[inline(never)]
+                fn apply_const(src: $T) -> Result<$T, OpError>
+                {
+                    Ok(src.$op())
+                }
+
+                #[inline(never)]
+                fn apply_slice<'src, 'dst>(sched: &Sched,
+                                           src: &'src [$T],
+                                           dst: &'dst mut[$T])
+                                           -> Result<&'dst [$T], OpError>
+                where 'src: 'dst
+                {
+                    const STEPSZ : usize = VECBYTES / size_of::<$T>();
+                    let step = |src: &[$T], dst: &mut [$T]| {
+                        for (d, s) in dst.iter_mut().zip(src) {
+                            *d = s.$op();
+                        }
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
+                    <UnOpSkel<$T,$T>>::skel(
+                        sched, src, dst,
+                        &|srcchunk, dstchunk| unsafe {
+                            steps(srcchunk, dstchunk, <$T>::ONE, &step)
+                        });
+                    Ok(dst)
+                }
+            }
+        )*
+    }
+}
+
+macro_rules! impl_binop_scalar {
+    ($struct_id:ident, $op:ident, $($T:ty)*) => {
+        $(
+            impl BinOp<$T,$T> for $struct_id<$T,$T>
+            {
+                #[inline(never)]
+                fn apply_slice_slice<'src, 'dst>(sched: &Sched,
+                                                 lhs: &'src [$T],
+                                                 rhs: &'src [$T],
+                                                 dst: &'dst mut[$T])
+                                                 -> Result<&'dst [$T], OpError>
+                where
+                'src: 'dst,
+                {
+                    const STEPSZ : usize = VECBYTES / size_of::<$T>();
+                    let step = |lhs: &[$T], rhs: &[$T], dst: &mut [$T]| {
+                        for ((d, l), r) in dst.iter_mut().zip(lhs).zip(rhs) {
+                            *d = l.$op(*r);
+                        }
+                    };
+                    let steps = select_steps2::<_, _, _, _, STEPSZ>(sched.simd_level());
+                    <BinOpSkel<$T,$T>>::skel(
+                        sched, lhs, rhs, dst,
+                        &|lhschunk, rhschunk, dstchunk| unsafe {
+                            steps(lhschunk, rhschunk, dstchunk, <$T>::ONE, <$T>::ONE, &step)
+                        });
+                    Ok(dst)
+                }
+
+                #[inline(never)]
+                fn apply_slice_const<'src, 'dst>(sched: &Sched,
+                                                 lhs: &'src [$T],
+                                                 rhs: $T,
+                                                 dst: &'dst mut[$T])
+                                                 -> Result<&'dst [$T], OpError>
+                where
+                'src: 'dst,
+                {
+                    const STEPSZ : usize = VECBYTES / size_of::<$T>();
+                    let step = |lhs: &[$T], dst: &mut [$T]| {
+                        for (d, l) in dst.iter_mut().zip(lhs) {
+                            *d = l.$op(rhs);
+                        }
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
+                    <UnOpSkel<$T,$T>>::skel(
+                        sched, lhs, dst,
+                        &|lhschunk, dstchunk| unsafe {
+                            steps(lhschunk, dstchunk, <$T>::ONE, &step)
+                        });
+                    Ok(dst)
+                }
+
+                #[inline(never)]
+                fn apply_const_slice<'src, 'dst>(sched: &Sched,
+                                                 lhs: $T,
+                                                 rhs: &'src [$T],
+                                                 dst: &'dst mut[$T])
+                                                 -> Result<&'dst [$T], OpError>
+                where
+                'src: 'dst,
+                {
+                    const STEPSZ : usize = VECBYTES / size_of::<$T>();
+                    let step = |rhs: &[$T], dst: &mut [$T]| {
+                        for (d, r) in dst.iter_mut().zip(rhs) {
+                            *d = lhs.$op(*r);
+                        }
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
+                    <UnOpSkel<$T,$T>>::skel(
+                        sched, rhs, dst,
+                        &|rhschunk, dstchunk| unsafe {
+                            steps(rhschunk, dstchunk, <$T>::ONE, &step)
+                        });
+                    Ok(dst)
+                }
+
+                #[inline(never)]
+                fn apply_const_const(lhs: $T, rhs: $T) -> Result<$T, OpError>
+                {
+                    Ok(lhs.$op(rhs))
+                }
+            }
+        )*
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Mixed-type (L,R)->bool comparisons
+////////////////////////////////////////////////////////////////////////////////
+//
+// Scalar loops over `CmpExact`, for each `(L, R)` pair, compiled for each SIMD
+// level like `impl_unop_scalar`. Only one order of each pair is implemented;
+// the evaluator swaps the operands (and the comparison) for the other.
+
+macro_rules! impl_mixed_cmp {
+    ($(($L:ty, $R:ty))*) => {
+        $(
+            impl MixedCmp<$L,$R> for MixedCmpOp<$L,$R>
+            {
+                #[inline(never)]
+                fn apply_slice_slice<'src, 'dst>(sched: &Sched,
+                                                 mask: u8,
+                                                 lhs: &'src [$L],
+                                                 rhs: &'src [$R],
+                                                 dst: &'dst mut[bool])
+                                                 -> Result<&'dst [bool], OpError>
+                where
+                'src: 'dst,
+                {
+                    const STEPSZ : usize = stepsz_min::<$L,$R>();
+                    let chunksz = sched.chunk_len::<$L,$R>();
+                    let step = |lhs: &[$L], rhs: &[$R], dst: &mut [bool]| {
+                        for ((d, l), r) in dst.iter_mut().zip(lhs).zip(rhs) {
+                            *d = mask & ordering_bit(l.cmp_exact(*r)) != 0;
+                        }
+                    };
+                    let steps = select_steps2::<_, _, _, _, STEPSZ>(sched.simd_level());
+                    sched.for_each_chunk2(
+                        chunksz, lhs, rhs, dst,
+                        &|lhschunk, rhschunk, dstchunk| unsafe {
+                            steps(lhschunk, rhschunk, dstchunk, <$L>::ONE, <$R>::ONE, &step)
+                        });
+                    Ok(dst)
+                }
+
+                #[inline(never)]
+                fn apply_slice_const<'src, 'dst>(sched: &Sched,
+                                                 mask: u8,
+                                                 lhs: &'src [$L],
+                                                 rhs: $R,
+                                                 dst: &'dst mut[bool])
+                                                 -> Result<&'dst [bool], OpError>
+                where
+                'src: 'dst,
+                {
+                    const STEPSZ : usize = stepsz_min::<$L,$R>();
+                    let chunksz = sched.chunk_len::<$L,$R>();
+                    let step = |lhs: &[$L], dst: &mut [bool]| {
+                        for (d, l) in dst.iter_mut().zip(lhs) {
+                            *d = mask & ordering_bit(l.cmp_exact(rhs)) != 0;
+                        }
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
+                    sched.for_each_chunk1(
+                        chunksz, lhs, dst,
+                        &|lhschunk, dstchunk| unsafe {
+                            steps(lhschunk, dstchunk, <$L>::ONE, &step)
+                        });
+                    Ok(dst)
+                }
+
+                #[inline(never)]
+                fn apply_const_slice<'src, 'dst>(sched: &Sched,
+                                                 mask: u8,
+                                                 lhs: $L,
+                                                 rhs: &'src [$R],
+                                                 dst: &'dst mut[bool])
+                                                 -> Result<&'dst [bool], OpError>
+                where
+                'src: 'dst,
+                {
+                    const STEPSZ : usize = stepsz_min::<$L,$R>();
+                    let chunksz = sched.chunk_len::<$L,$R>();
+                    let step = |rhs: &[$R], dst: &mut [bool]| {
+                        for (d, r) in dst.iter_mut().zip(rhs) {
+                            *d = mask & ordering_bit(lhs.cmp_exact(*r)) != 0;
+                        }
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
+                    sched.for_each_chunk1(
+                        chunksz, rhs, dst,
+                        &|rhschunk, dstchunk| unsafe {
+                            steps(rhschunk, dstchunk, <$R>::ONE, &step)
+                        });
+                    Ok(dst)
+                }
+
+                #[inline(never)]
+                fn apply_const_const(mask: u8, lhs: $L, rhs: $R) -> Result<bool, OpError>
+                {
+                    Ok(mask & ordering_bit(lhs.cmp_exact(rhs)) != 0)
+                }
+            }
+        )*
+    }
+}
+
+// Only needed when some of the types are compiled out.
+#[cfg(not(all(feature = "int128", feature = "float")))]
+macro_rules! impl_mixed_cmp_unsupported {
+    ($(($L:ty, $R:ty))*) => {
+        $(
+            impl MixedCmp<$L,$R> for MixedCmpOp<$L,$R>
+            {
+                const SUPPORTED: bool = false;
+
+                #[inline(never)]
+                fn apply_slice_slice<'src, 'dst>(_sched: &Sched,
+                                                 _mask: u8,
+                                                 _lhs: &'src [$L],
+                                                 _rhs: &'src [$R],
+                                                 _dst: &'dst mut[bool])
+                                                 -> Result<&'dst [bool], OpError>
+                where
+                    'src: 'dst,
+                {
+                    Err(OpError::Unsupported)
+                }
+
+                #[inline(never)]
+                fn apply_slice_const<'src, 'dst>(_sched: &Sched,
+                                                 _mask: u8,
+                                                 _lhs: &'src [$L],
+                                                 _rhs: $R,
+                                                 _dst: &'dst mut[bool])
+                                                 -> Result<&'dst [bool], OpError>
+                where
+                    'src: 'dst,
+                {
+                    Err(OpError::Unsupported)
+                }
+
+                #[inline(never)]
+                fn apply_const_slice<'src, 'dst>(_sched: &Sched,
+                                                 _mask: u8,
+                                                 _lhs: $L,
+                                                 _rhs: &'src [$R],
+                                                 _dst: &'dst mut[bool])
+                                                 -> Result<&'dst [bool], OpError>
+                where
+                    'src: 'dst,
+                {
+                    Err(OpError::Unsupported)
+                }
+
+                #[inline(never)]
+                fn apply_const_const(_mask: u8, _lhs: $L, _rhs: $R) -> Result<bool, OpError>
+                {
+                    Err(OpError::Unsupported)
+                }
+            }
+        )*
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Ternary (T,T,T)->T operators
+////////////////////////////////////////////////////////////////////////////////
+//
+// These accumulate in place, so go through `steps3`, which does any final
+// partial vector with the scalar `$op`; that's only right for ops whose scalar
+// and vector forms agree exactly, as with the (singly-rounded) fused
+// multiply-add. The only ternary operator is floating-point.
+
+#[cfg(feature = "float")]
+macro_rules! impl_ternop {
+    ($struct_id:ident, $op:ident, $($T:ty)*) => {
+        $(
+            impl TernOp<$T,$T> for $struct_id<$T,$T>
+            {
+                #[inline(never)]
+                fn apply_slices<'src, 'dst>(sched: &Sched,
+                                            a: &'src [$T],
+                                            b: &'src [$T],
+                                            acc: &'dst mut[$T])
+                                            -> Result<&'dst [$T], OpError>
+                where
+                'src: 'dst,
+                {
+                    const STEPSZ : usize = VECBYTES / size_of::<$T>();
+                    let step = |a: &[$T], b: &[$T], acc: &mut [$T]| {
+                        let av = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(a);
+                        let bv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(b);
+                        let cv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(acc);
+                        av.$op(bv, cv).write_to_slice_unaligned(acc)
+                    };
+                    let tail = |a: $T, b: $T, c: $T| a.$op(b, c);
+                    let steps = select_steps3::<_, _, _, STEPSZ>(sched.simd_level());
+                    <BinOpSkel<$T,$T>>::skel(
+                        sched, a, b, acc,
+                        &|achunk, bchunk, accchunk| unsafe {
+                            steps(achunk, bchunk, accchunk, &step, &tail)
+                        });
+                    Ok(acc)
+                }
+
+                #[inline(never)]
+                fn apply_const(a: $T, b: $T, c: $T) -> Result<$T, OpError>
+                {
+                    Ok(a.$op(b, c))
+                }
+            }
+        )*
+    }
+}
+
+macro_rules! impl_ternop_unsupported {
+    ($struct_id:ident, $($T:ty)*) => {
+        $(
+            impl TernOp<$T,$T> for $struct_id<$T,$T>
+            {
+                const SUPPORTED: bool = false;
+
+                #[inline(never)]
+                fn apply_slices<'src, 'dst>(_sched: &Sched,
+                                            _a: &'src [$T],
+                                            _b: &'src [$T],
+                                            _acc: &'dst mut[$T])
+                                            -> Result<&'dst [$T], OpError>
+                where
+                    'src: 'dst,
+                {
+                    Err(OpError::Unsupported)
+                }
+
+                #[inline(never)]
+                fn apply_const(_a: $T, _b: $T, _c: $T) -> Result<$T, OpError>
+                {
+                    Err(OpError::Unsupported)
+                }
+            }
+        )*
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Conversion operators
 ////////////////////////////////////////////////////////////////////////////////
@@ -532,7 +959,8 @@ macro_rules! impl_noop_convop {
                     Ok(src)
                 }
                 #[inline(never)]
-                fn apply_slice<'src, 'dst>(src: &'src [$T],
+                fn apply_slice<'src, 'dst>(_sched: &Sched,
+                                           src: &'src [$T],
                                            _dst: &'dst mut[$T])
                                            -> Result<&'dst [$T], OpError>
                 where
@@ -542,6 +970,7 @@ macro_rules! impl_noop_convop {
                 }
             }
         )*
+        impl_cast_as_conv!($(($T, $T))*);
     }
 }
 
@@ -560,34 +989,31 @@ macro_rules! impl_convop {
                 }
 
                 #[inline(never)]
-                fn apply_slice<'src, 'dst>(src: &'src [$SRC],
+                fn apply_slice<'src, 'dst>(sched: &Sched,
+                                           src: &'src [$SRC],
                                            dst: &'dst mut[$DST])
                                            -> Result<&'dst [$DST], OpError>
                 where
                     'src: 'dst
                 {
                     const STEPSZ : usize = stepsz_min::<$SRC,$DST>();
-                    const CHUNKSZ : usize = chunksz_min::<$SRC,$DST>();
-                    let len = src.len();
-                    assert_eq!(len, dst.len());
-                    assert_eq!((len & !(CHUNKSZ-1)), len);
-                    src.par_chunks(CHUNKSZ)
-                        .zip(dst.par_chunks_mut(CHUNKSZ))
-                        .for_each(|(srcchunk, dstchunk)|
-                                  {
-                                      for (src, dst) in
-                                          srcchunk.chunks_exact(STEPSZ)
-                                          .zip(dstchunk.chunks_exact_mut(STEPSZ))
-                                      {
-                                          let sv = <Simd<[$SRC; STEPSZ]>>::from_slice_unaligned(src);
-                                          let dv = <Simd<[$DST; STEPSZ]>>::from_cast(sv);
-                                          dv.write_to_slice_unaligned(dst);
-                                      }
-                                  });
+                    let chunksz = sched.chunk_len::<$SRC,$DST>();
+                    let step = |src: &[$SRC], dst: &mut [$DST]| {
+                        let sv = <Simd<[$SRC; STEPSZ]>>::from_slice_unaligned(src);
+                        let dv = <Simd<[$DST; STEPSZ]>>::from_cast(sv);
+                        dv.write_to_slice_unaligned(dst);
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
+                    sched.for_each_chunk1(
+                        chunksz, src, dst,
+                        &|srcchunk, dstchunk| unsafe {
+                            steps(srcchunk, dstchunk, <$SRC>::ONE, &step)
+                        });
                     Ok(dst)
                 }
             }
         )*
+        impl_cast_modes!($SRC, $($DST)*);
     }
 }
 
@@ -602,37 +1028,33 @@ macro_rules! impl_bool_convop {
                     Ok(src.ne(&<$T>::ZERO))
                 }
                 #[inline(never)]
-                fn apply_slice<'src, 'dst>(src: &'src [$T],
+                fn apply_slice<'src, 'dst>(sched: &Sched,
+                                           src: &'src [$T],
                                            dst: &'dst mut[bool])
                                            -> Result<&'dst [bool], OpError>
                 where
                     'src: 'dst
                 {
                     const STEPSZ : usize = stepsz_min::<$T,bool>();
-                    const CHUNKSZ : usize = chunksz_min::<$T,bool>();
+                    let chunksz = sched.chunk_len::<$T,bool>();
                     const TRUES : Simd<[u8; STEPSZ]> = <Simd<[u8; STEPSZ]>>::splat(1);
                     const FALSES : Simd<[u8; STEPSZ]> = <Simd<[u8; STEPSZ]>>::splat(0);
                     const ZEROES : Simd<[$T; STEPSZ]> = <Simd<[$T; STEPSZ]>>::splat(<$T>::ZERO);
 
-                    let len = src.len();
-                    assert_eq!(len, dst.len());
-                    assert_eq!((len & !(CHUNKSZ-1)), len);
-                    src.par_chunks(CHUNKSZ)
-                        .zip(dst.par_chunks_mut(CHUNKSZ))
-                        .for_each(|(srcchunk, dstchunk)|
-                                  {
-                                      for (src, dst) in
-                                          srcchunk.chunks_exact(STEPSZ)
-                                          .zip(dstchunk.chunks_exact_mut(STEPSZ))
-                                      {
-                                          let sv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(src);
-                                          let mv = sv.ne(ZEROES);
-                                          let bv = mv.select(TRUES, FALSES);
-                                          unsafe {
-                                              bv.write_to_slice_unaligned(::std::mem::transmute::<&mut[bool],&mut[u8]>(dst));
-                                          }
-                                      }
-                                  });
+                    let step = |src: &[$T], dst: &mut [bool]| {
+                        let sv = <Simd<[$T; STEPSZ]>>::from_slice_unaligned(src);
+                        let mv = sv.ne(ZEROES);
+                        let bv = mv.select(TRUES, FALSES);
+                        unsafe {
+                            bv.write_to_slice_unaligned(::std::mem::transmute::<&mut[bool],&mut[u8]>(dst));
+                        }
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
+                    sched.for_each_chunk1(
+                        chunksz, src, dst,
+                        &|srcchunk, dstchunk| unsafe {
+                            steps(srcchunk, dstchunk, <$T>::ONE, &step)
+                        });
                     Ok(dst)
                 }
             }
@@ -645,40 +1067,502 @@ macro_rules! impl_bool_convop {
                     Ok(if src { <$T>::ONE } else { <$T>::ZERO })
                 }
                 #[inline(never)]
-                fn apply_slice<'src, 'dst>(src: &'src [bool],
+                fn apply_slice<'src, 'dst>(sched: &Sched,
+                                           src: &'src [bool],
                                            dst: &'dst mut[$T])
                                            -> Result<&'dst [$T], OpError>
                 where
                     'src: 'dst
                 {
                     const STEPSZ : usize = stepsz_min::<$T,bool>();
-                    const CHUNKSZ : usize = chunksz_min::<$T,bool>();
+                    let chunksz = sched.chunk_len::<$T,bool>();
                     const ZEROES : Simd<[$T; STEPSZ]> = <Simd<[$T; STEPSZ]>>::splat(<$T>::ZERO);
                     const ONES : Simd<[$T; STEPSZ]> = <Simd<[$T; STEPSZ]>>::splat(<$T>::ONE);
 
-                    let len = src.len();
-                    assert_eq!(len, dst.len());
-                    assert_eq!((len & !(CHUNKSZ-1)), len);
-                    src.par_chunks(CHUNKSZ)
-                        .zip(dst.par_chunks_mut(CHUNKSZ))
-                        .for_each(|(srcchunk, dstchunk)|
-                                  {
-                                      for (src, dst) in
-                                          srcchunk.chunks_exact(STEPSZ)
-                                          .zip(dstchunk.chunks_exact_mut(STEPSZ))
-                                      {
-                                          let uv = unsafe {
-                                              <Simd<[u8; STEPSZ]>>::from_slice_unaligned(
-                                                  ::std::mem::transmute::<&[bool],&[u8]>(src))
-                                          };
-                                          let mv = <Simd<[packed_simd::m8; STEPSZ]>>::from_cast(uv);
-                                          let bv = mv.select(ONES, ZEROES);
-                                          bv.write_to_slice_unaligned(dst);
-                                      }
-                                  });
+                    let step = |src: &[bool], dst: &mut [$T]| {
+                        let uv = unsafe {
+                            <Simd<[u8; STEPSZ]>>::from_slice_unaligned(
+                                ::std::mem::transmute::<&[bool],&[u8]>(src))
+                        };
+                        let mv = <Simd<[M8; STEPSZ]>>::from_cast(uv);
+                        let bv = mv.select(ONES, ZEROES);
+                        bv.write_to_slice_unaligned(dst);
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
+                    sched.for_each_chunk1(
+                        chunksz, src, dst,
+                        &|srcchunk, dstchunk| unsafe {
+                            steps(srcchunk, dstchunk, false, &step)
+                        });
+                    Ok(dst)
+                }
+            }
+        )*
+        impl_cast_as_conv!($(($T, bool))* $((bool, $T))*);
+    }
+}
+
+// The saturating and checked conversions from `$SRC` to each `$DST`, as scalar
+// loops over the `Cast` conversions in fallback.rs, compiled for each SIMD
+// level like `impl_unop_scalar`. A checked conversion still converts whole
+// chunks in parallel, just noting whether there was a fault; only if so does it
+// go back and look for the first one in the slice, for the error it returns.
+macro_rules! impl_cast_modes {
+    ($SRC:ty, $($DST:ty)*) => {
+        $(
+            impl UnOp<$SRC, $DST> for SatConvOp<$SRC, $DST>
+            {
+                #[inline(never)]
+                fn apply_const(src: $SRC) -> Result<$DST, OpError>
+                {
+                    Ok(src.saturating_cast())
+                }
+
+                #[inline(never)]
+                fn apply_slice<'src, 'dst>(sched: &Sched,
+                                           src: &'src [$SRC],
+                                           dst: &'dst mut[$DST])
+                                           -> Result<&'dst [$DST], OpError>
+                where
+                    'src: 'dst
+                {
+                    const STEPSZ : usize = stepsz_min::<$SRC,$DST>();
+                    let chunksz = sched.chunk_len::<$SRC,$DST>();
+                    let step = |src: &[$SRC], dst: &mut [$DST]| {
+                        for (d, s) in dst.iter_mut().zip(src) {
+                            *d = s.saturating_cast();
+                        }
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
+                    sched.for_each_chunk1(
+                        chunksz, src, dst,
+                        &|srcchunk, dstchunk| unsafe {
+                            steps(srcchunk, dstchunk, <$SRC>::ONE, &step)
+                        });
                     Ok(dst)
                 }
             }
+
+            impl UnOp<$SRC, $DST> for CheckedConvOp<$SRC, $DST>
+            {
+                #[inline(never)]
+                fn apply_const(src: $SRC) -> Result<$DST, OpError>
+                {
+                    match <$SRC as Cast<$DST>>::cast_fault(src) {
+                        None => Ok(src.saturating_cast()),
+                        Some(fault) => Err(fault.at(0)),
+                    }
+                }
+
+                #[inline(never)]
+                fn apply_slice<'src, 'dst>(sched: &Sched,
+                                           src: &'src [$SRC],
+                                           dst: &'dst mut[$DST])
+                                           -> Result<&'dst [$DST], OpError>
+                where
+                    'src: 'dst
+                {
+                    use std::sync::atomic::{AtomicBool, Ordering};
+                    const STEPSZ : usize = stepsz_min::<$SRC,$DST>();
+                    let chunksz = sched.chunk_len::<$SRC,$DST>();
+                    let faulted = AtomicBool::new(false);
+                    let step = |src: &[$SRC], dst: &mut [$DST]| {
+                        let mut f = false;
+                        for (d, s) in dst.iter_mut().zip(src) {
+                            f |= <$SRC as Cast<$DST>>::cast_fault(*s).is_some();
+                            *d = s.saturating_cast();
+                        }
+                        if f {
+                            faulted.store(true, Ordering::Relaxed);
+                        }
+                    };
+                    let steps = select_steps1::<_, _, _, STEPSZ>(sched.simd_level());
+                    sched.for_each_chunk1(
+                        chunksz, src, dst,
+                        &|srcchunk, dstchunk| unsafe {
+                            steps(srcchunk, dstchunk, <$SRC>::ONE, &step)
+                        });
+                    if !faulted.into_inner() {
+                        return Ok(dst);
+                    }
+                    let first = src.iter().enumerate().find_map(|(i, s)| {
+                        <$SRC as Cast<$DST>>::cast_fault(*s).map(|fault| fault.at(i))
+                    });
+                    match first {
+                        None => Ok(dst),
+                        Some(err) => Err(err),
+                    }
+                }
+            }
+        )*
+    }
+}
+
+// Identity and boolean conversions can't go out of range, so are the same in
+// every mode.
+macro_rules! impl_cast_as_conv {
+    ($(($SRC:ty, $DST:ty))*) => {
+        $(
+            impl_cast_as_conv!(@op SatConvOp $SRC, $DST);
+            impl_cast_as_conv!(@op CheckedConvOp $SRC, $DST);
+        )*
+    };
+    (@op $struct_id:ident $SRC:ty, $DST:ty) => {
+        impl UnOp<$SRC, $DST> for $struct_id<$SRC, $DST>
+        {
+            #[inline(always)]
+            fn apply_const(src: $SRC) -> Result<$DST, OpError>
+            {
+                <ConvOp<$SRC, $DST>>::apply_const(src)
+            }
+            #[inline(always)]
+            fn apply_slice<'src, 'dst>(sched: &Sched,
+                                       src: &'src [$SRC],
+                                       dst: &'dst mut[$DST])
+                                       -> Result<&'dst [$DST], OpError>
+            where
+                'src: 'dst
+            {
+                <ConvOp<$SRC, $DST>>::apply_slice(sched, src, dst)
+            }
+        }
+    };
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Feature-gated kernels
+////////////////////////////////////////////////////////////////////////////////
+//
+// To keep build times and binary size down, families of types and operators can
+// be compiled out (see the features in Cargo.toml). Rather than compiling out
+// the dispatch to them as well, which would mean gating every match arm and
+// trait bound in eval.rs, each compiled-out kernel is replaced by an
+// unsupported one: dispatch is unchanged, and such combinations just return
+// `EvalError::UnsupportedOp` like any other unsupported ones.
+//
+// `impl_gated!(pred; kernel!(...); ...)` instantiates each kernel if `pred` (a
+// `cfg` predicate) holds, and the matching unsupported kernel if not.
+// Skeletons are only needed by real kernels, so they have no stand-in.
+
+macro_rules! impl_gated {
+    ($pred:meta; $($kernel:ident!($($args:tt)*);)*) => {
+        $(
+            #[cfg($pred)]
+            $kernel!($($args)*);
+            #[cfg(not($pred))]
+            impl_gated!(@unsupported $kernel ($($args)*));
+        )*
+    };
+    (@unsupported impl_unop ($struct_id:ident, $op:ident, $($T:ty)*)) => {
+        impl_unop_unsupported!($struct_id, $($T)*);
+    };
+    (@unsupported impl_unop_pred ($struct_id:ident, $op:ident, $($T:ty)*)) => {
+        impl_unop_pred_unsupported!($struct_id, $($T)*);
+    };
+    (@unsupported impl_binop ($struct_id:ident, $op:ident, $($T:ty)*)) => {
+        impl_binop_unsupported!($struct_id, $($T)*);
+    };
+    (@unsupported impl_unop_scalar ($struct_id:ident, $op:ident, $($T:ty)*)) => {
+        impl_unop_unsupported!($struct_id, $($T)*);
+    };
+    (@unsupported impl_binop_scalar ($struct_id:ident, $op:ident, $($T:ty)*)) => {
+        impl_binop_unsupported!($struct_id, $($T)*);
+    };
+    (@unsupported impl_binop_pred ($struct_id:ident, $op:ident, $($T:ty)*)) => {
+        impl_binop_pred_unsupported!($struct_id, $($T)*);
+    };
+    (@unsupported impl_mixed_cmp ($($pairs:tt)*)) => {
+        impl_mixed_cmp_unsupported!($($pairs)*);
+    };
+    (@unsupported impl_ternop ($struct_id:ident, $op:ident, $($T:ty)*)) => {
+        impl_ternop_unsupported!($struct_id, $($T)*);
+    };
+    (@unsupported impl_convop ($SRC:ty, $($DST:ty)*)) => {
+        impl_unop_unsupported_full!(ConvOp, $(($SRC, $DST))*);
+        impl_unop_unsupported_full!(SatConvOp, $(($SRC, $DST))*);
+        impl_unop_unsupported_full!(CheckedConvOp, $(($SRC, $DST))*);
+    };
+    (@unsupported impl_bool_convop ($($T:ty)*)) => {
+        impl_unop_unsupported_full!(ConvOp, $(($T, bool))* $((bool, $T))*);
+        impl_unop_unsupported_full!(SatConvOp, $(($T, bool))* $((bool, $T))*);
+        impl_unop_unsupported_full!(CheckedConvOp, $(($T, bool))* $((bool, $T))*);
+    };
+    (@unsupported impl_unop_skel ($($args:tt)*)) => {};
+    (@unsupported impl_binop_skel ($($args:tt)*)) => {};
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Apache Arrow interop
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "arrow")]
+macro_rules! impl_arrow_from {
+    ($([$A:ty] $slice_ctor:ident)*) => {
+        $(
+            impl<'a> std::convert::From<&'a PrimitiveArray<$A>> for Slice<'a> {
+                fn from(a: &'a PrimitiveArray<$A>) -> Self {
+                    Slice::$slice_ctor(a.values())
+                }
+            }
+            impl<'a> std::convert::From<&'a PrimitiveArray<$A>> for Operand<'a> {
+                fn from(a: &'a PrimitiveArray<$A>) -> Self {
+                    Operand::OperandSlice(Slice::$slice_ctor(a.values()))
+                }
+            }
+        )*
+
+        // Borrows the values of a dynamically-typed primitive array, if it's
+        // one of the types listed above.
+        fn primitive_slice_from_arrow(array: &dyn Array) -> Option<Slice<'_>> {
+            let any = array.as_any();
+            $(
+                if let Some(a) = any.downcast_ref::<PrimitiveArray<$A>>() {
+                    return Some(Slice::$slice_ctor(a.values()));
+                }
+            )*
+            None
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Binary encoding
+////////////////////////////////////////////////////////////////////////////////
+
+macro_rules! impl_wire {
+    ($($T:ty)*) => {
+        $(
+            impl Wire for $T {
+                fn write_le<W: Write>(s: &[$T], w: &mut W) -> std::io::Result<()> {
+                    if cfg!(target_endian = "little") {
+                        let bytes = unsafe {
+                            std::slice::from_raw_parts(s.as_ptr() as *const u8,
+                                                       size_of_val(s))
+                        };
+                        w.write_all(bytes)
+                    } else {
+                        for x in s {
+                            w.write_all(&x.to_le_bytes())?;
+                        }
+                        Ok(())
+                    }
+                }
+                fn fix_le(s: &mut [$T]) {
+                    if cfg!(target_endian = "big") {
+                        for x in s.iter_mut() {
+                            *x = <$T>::from_le_bytes(x.to_ne_bytes());
+                        }
+                    }
+                }
+                fn from_le(bytes: &[u8]) -> Result<$T, CodecError> {
+                    let mut b = [0u8; size_of::<$T>()];
+                    b.copy_from_slice(&bytes[0..size_of::<$T>()]);
+                    Ok(<$T>::from_le_bytes(b))
+                }
+                fn to_le16(self) -> [u8; 16] {
+                    let mut b = [0u8; 16];
+                    b[0..size_of::<$T>()].copy_from_slice(&self.to_le_bytes());
+                    b
+                }
+            }
+        )*
+    }
+}
+
+// Maps the variants of a fieldless enum to and from the fixed tag bytes they
+// are encoded as. The tags are part of the encoding, so must never change.
+macro_rules! impl_codec_tags {
+    ($E:ident { $($V:ident = $tag:expr),* $(,)* }) => {
+        impl $E {
+            pub(crate) fn codec_tag(&self) -> u8 {
+                match self {
+                    $($E::$V => $tag,)*
+                }
+            }
+            pub(crate) fn from_codec_tag(tag: u8) -> Option<$E> {
+                match tag {
+                    $($tag => Some($E::$V),)*
+                    _ => None
+                }
+            }
+        }
+    }
+}
+
+macro_rules! impl_opcode_codec {
+    ($($E:ident $kind:ident)*) => {
+        $(
+            impl $E {
+                /// Writes the opcode as a single record.
+                pub fn write_to<W: Write>(&self, w: &mut W) -> Result<(), CodecError> {
+                    write_header(w, $kind, self.codec_tag(), 0)
+                }
+
+                /// Reads an opcode record.
+                pub fn read_from<R: Read>(r: &mut R) -> Result<$E, CodecError> {
+                    match read_header(r)? {
+                        ($kind, tag, 0) => $E::from_codec_tag(tag).ok_or(CodecError::BadRecord),
+                        _ => Err(CodecError::BadRecord)
+                    }
+                }
+            }
+        )*
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Portable SIMD backend
+////////////////////////////////////////////////////////////////////////////////
+//
+// Lane-wise implementations of the vector operations kernels use, for the
+// portable `Simd` in simd.rs. Integer arithmetic wraps, as in packed_simd.
+// Inherent methods are only instantiated for the type families whose kernels
+// are compiled in, since they'd otherwise be unused.
+
+#[cfg(not(feature = "packed_simd"))]
+macro_rules! impl_portable_binop {
+    ($T:ty, $trait:ident, $method:ident, $f:expr) => {
+        impl<const N: usize> $trait for Simd<[$T; N]> {
+            type Output = Simd<[$T; N]>;
+            #[inline(always)]
+            fn $method(self, rhs: Simd<[$T; N]>) -> Simd<[$T; N]> {
+                self.zip(rhs, $f)
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "packed_simd"))]
+macro_rules! impl_portable_unop {
+    ($T:ty, $trait:ident, $method:ident, $f:expr) => {
+        impl<const N: usize> $trait for Simd<[$T; N]> {
+            type Output = Simd<[$T; N]>;
+            #[inline(always)]
+            fn $method(self) -> Simd<[$T; N]> {
+                self.map($f)
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "packed_simd"))]
+macro_rules! impl_portable_int {
+    ($($T:ty)*) => {
+        $(
+            impl_portable_binop!($T, Add, add, <$T>::wrapping_add);
+            impl_portable_binop!($T, Sub, sub, <$T>::wrapping_sub);
+            impl_portable_binop!($T, Mul, mul, <$T>::wrapping_mul);
+            impl_portable_binop!($T, Div, div, <$T>::wrapping_div);
+            impl_portable_binop!($T, Rem, rem, <$T>::wrapping_rem);
+            impl_portable_binop!($T, BitAnd, bitand, |x: $T, y: $T| x & y);
+            impl_portable_binop!($T, BitOr, bitor, |x: $T, y: $T| x | y);
+            impl_portable_binop!($T, BitXor, bitxor, |x: $T, y: $T| x ^ y);
+            impl_portable_unop!($T, Not, not, |x: $T| !x);
+
+            impl<const N: usize> Simd<[$T; N]> {
+                #[inline(always)]
+                pub fn min(self, rhs: Simd<[$T; N]>) -> Simd<[$T; N]> {
+                    self.zip(rhs, Ord::min)
+                }
+                #[inline(always)]
+                pub fn max(self, rhs: Simd<[$T; N]>) -> Simd<[$T; N]> {
+                    self.zip(rhs, Ord::max)
+                }
+            }
+        )*
+    }
+}
+
+#[cfg(not(feature = "packed_simd"))]
+macro_rules! impl_portable_neg {
+    ($($T:ty)*) => {
+        $(
+            impl_portable_unop!($T, Neg, neg, <$T>::wrapping_neg);
+        )*
+    }
+}
+
+#[cfg(all(not(feature = "packed_simd"), feature = "float"))]
+macro_rules! impl_portable_float {
+    ($($T:ty)*) => {
+        $(
+            impl_portable_binop!($T, Add, add, |x: $T, y: $T| x + y);
+            impl_portable_binop!($T, Sub, sub, |x: $T, y: $T| x - y);
+            impl_portable_binop!($T, Mul, mul, |x: $T, y: $T| x * y);
+            impl_portable_binop!($T, Div, div, |x: $T, y: $T| x / y);
+            impl_portable_binop!($T, Rem, rem, |x: $T, y: $T| x % y);
+            impl_portable_unop!($T, Neg, neg, |x: $T| -x);
+
+            impl<const N: usize> Simd<[$T; N]> {
+                #[inline(always)]
+                pub fn min(self, rhs: Simd<[$T; N]>) -> Simd<[$T; N]> { self.zip(rhs, <$T>::min) }
+                #[inline(always)]
+                pub fn max(self, rhs: Simd<[$T; N]>) -> Simd<[$T; N]> { self.zip(rhs, <$T>::max) }
+                #[inline(always)]
+                pub fn abs(self) -> Simd<[$T; N]> { self.map(<$T>::abs) }
+                #[inline(always)]
+                pub fn sqrt(self) -> Simd<[$T; N]> { self.map(<$T>::sqrt) }
+                #[inline(always)]
+                pub fn mul_add(self, b: Simd<[$T; N]>, c: Simd<[$T; N]>) -> Simd<[$T; N]> {
+                    let mut lanes = self.0;
+                    for (x, (y, z)) in lanes.iter_mut().zip(b.0.iter().zip(c.0.iter())) {
+                        *x = x.mul_add(*y, *z);
+                    }
+                    Simd(lanes)
+                }
+                #[inline(always)]
+                pub fn is_nan(self) -> Simd<[M8; N]> { self.test(<$T>::is_nan) }
+                #[inline(always)]
+                pub fn is_infinite(self) -> Simd<[M8; N]> { self.test(<$T>::is_infinite) }
+                #[inline(always)]
+                pub fn is_finite(self) -> Simd<[M8; N]> { self.test(<$T>::is_finite) }
+            }
+        )*
+    }
+}
+
+#[cfg(all(not(feature = "packed_simd"), feature = "transcendental"))]
+macro_rules! impl_portable_transcendental {
+    ($($T:ty)*) => {
+        $(
+            impl<const N: usize> Simd<[$T; N]> {
+                #[inline(always)]
+                pub fn sin(self) -> Simd<[$T; N]> { self.map(<$T>::sin) }
+                #[inline(always)]
+                pub fn cos(self) -> Simd<[$T; N]> { self.map(<$T>::cos) }
+                #[inline(always)]
+                pub fn ln(self) -> Simd<[$T; N]> { self.map(<$T>::ln) }
+                #[inline(always)]
+                pub fn exp(self) -> Simd<[$T; N]> { self.map(<$T>::exp) }
+                #[inline(always)]
+                pub fn tanh(self) -> Simd<[$T; N]> { self.map(<$T>::tanh) }
+                #[inline(always)]
+                pub fn powf(self, rhs: Simd<[$T; N]>) -> Simd<[$T; N]> { self.zip(rhs, <$T>::powf) }
+            }
+        )*
+    }
+}
+
+// Lane-wise `as` conversions from `$S` to each of the types, for each `$S`.
+#[cfg(not(feature = "packed_simd"))]
+macro_rules! impl_portable_cast {
+    ($($S:ty)*) => {
+        $(
+            impl_portable_cast!(@from $S; u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
+        )*
+    };
+    (@from $S:ty; $($D:ty)*) => {
+        $(
+            impl<const N: usize> FromCast<Simd<[$S; N]>> for Simd<[$D; N]> {
+                #[inline(always)]
+                fn from_cast(src: Simd<[$S; N]>) -> Simd<[$D; N]> {
+                    let mut lanes = [<$D>::default(); N];
+                    for (d, s) in lanes.iter_mut().zip(src.0.iter()) {
+                        *d = *s as $D;
+                    }
+                    Simd(lanes)
+                }
+            }
         )*
     }
 }
@@ -7,12 +7,14 @@ use crate::scalarty::*;
 /// returned from it after operations complete. They are either single-element
 /// constants or homogeneous slices.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Operand<'a> {
     OperandSlice(Slice<'a>),
     OperandConst(Const)
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Const {
     ConstBool(bool),
     ConstU8(u8),
@@ -30,6 +32,7 @@ pub enum Const {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Slice<'a> {
     SliceBool(&'a [bool]),
     SliceU8(&'a [u8]),
@@ -46,6 +49,24 @@ pub enum Slice<'a> {
     SliceF64(&'a [f64]),
 }
 
+/// A homogeneous mutable slice, updated in place by the `*_in_place`
+/// operations of `EvalCtx`.
+#[derive(Debug, PartialEq)]
+pub enum SliceMut<'a> {
+    SliceMutBool(&'a mut [bool]),
+    SliceMutU8(&'a mut [u8]),
+    SliceMutU16(&'a mut [u16]),
+    SliceMutU32(&'a mut [u32]),
+    SliceMutU64(&'a mut [u64]),
+    SliceMutU128(&'a mut [u128]),
+    SliceMutI8(&'a mut [i8]),
+    SliceMutI16(&'a mut [i16]),
+    SliceMutI32(&'a mut [i32]),
+    SliceMutI64(&'a mut [i64]),
+    SliceMutI128(&'a mut [i128]),
+    SliceMutF32(&'a mut [f32]),
+    SliceMutF64(&'a mut [f64]),
+}
 
 impl Const {
     pub fn get_scalar_ty(&self) -> ScalarTy {
@@ -89,6 +110,118 @@ impl<'a> Slice<'a> {
             SliceF64(_) => TF64,
         }
     }
+
+    /// Returns the number of elements in the slice.
+    pub fn len(&self) -> usize {
+        use Slice::*;
+        match self {
+            SliceBool(s) => s.len(),
+            SliceU8(s) => s.len(),
+            SliceU16(s) => s.len(),
+            SliceU32(s) => s.len(),
+            SliceU64(s) => s.len(),
+            SliceU128(s) => s.len(),
+            SliceI8(s) => s.len(),
+            SliceI16(s) => s.len(),
+            SliceI32(s) => s.len(),
+            SliceI64(s) => s.len(),
+            SliceI128(s) => s.len(),
+            SliceF32(s) => s.len(),
+            SliceF64(s) => s.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the element at index `i` as a `Const`, or `None` if it's out of
+    /// bounds.
+    pub fn get(&self, i: usize) -> Option<Const> {
+        use Slice::*;
+        match *self {
+            SliceBool(s) => s.get(i).map(|&x| x.into()),
+            SliceU8(s) => s.get(i).map(|&x| x.into()),
+            SliceU16(s) => s.get(i).map(|&x| x.into()),
+            SliceU32(s) => s.get(i).map(|&x| x.into()),
+            SliceU64(s) => s.get(i).map(|&x| x.into()),
+            SliceU128(s) => s.get(i).map(|&x| x.into()),
+            SliceI8(s) => s.get(i).map(|&x| x.into()),
+            SliceI16(s) => s.get(i).map(|&x| x.into()),
+            SliceI32(s) => s.get(i).map(|&x| x.into()),
+            SliceI64(s) => s.get(i).map(|&x| x.into()),
+            SliceI128(s) => s.get(i).map(|&x| x.into()),
+            SliceF32(s) => s.get(i).map(|&x| x.into()),
+            SliceF64(s) => s.get(i).map(|&x| x.into()),
+        }
+    }
+
+    /// Divides the slice in two at `mid`, as `<[T]>::split_at` does, panicking
+    /// if `mid > len`.
+    pub fn split_at(&self, mid: usize) -> (Slice<'a>, Slice<'a>) {
+        use Slice::*;
+        match *self {
+            SliceBool(s) => { let (a, b) = s.split_at(mid); (a.into(), b.into()) }
+            SliceU8(s) => { let (a, b) = s.split_at(mid); (a.into(), b.into()) }
+            SliceU16(s) => { let (a, b) = s.split_at(mid); (a.into(), b.into()) }
+            SliceU32(s) => { let (a, b) = s.split_at(mid); (a.into(), b.into()) }
+            SliceU64(s) => { let (a, b) = s.split_at(mid); (a.into(), b.into()) }
+            SliceU128(s) => { let (a, b) = s.split_at(mid); (a.into(), b.into()) }
+            SliceI8(s) => { let (a, b) = s.split_at(mid); (a.into(), b.into()) }
+            SliceI16(s) => { let (a, b) = s.split_at(mid); (a.into(), b.into()) }
+            SliceI32(s) => { let (a, b) = s.split_at(mid); (a.into(), b.into()) }
+            SliceI64(s) => { let (a, b) = s.split_at(mid); (a.into(), b.into()) }
+            SliceI128(s) => { let (a, b) = s.split_at(mid); (a.into(), b.into()) }
+            SliceF32(s) => { let (a, b) = s.split_at(mid); (a.into(), b.into()) }
+            SliceF64(s) => { let (a, b) = s.split_at(mid); (a.into(), b.into()) }
+        }
+    }
+}
+
+impl<'a> SliceMut<'a> {
+    pub fn get_scalar_ty(&self) -> ScalarTy {
+        use SliceMut::*;
+        use ScalarTy::*;
+        match self {
+            SliceMutBool(_) => TBool,
+            SliceMutU8(_) => TU8,
+            SliceMutU16(_) => TU16,
+            SliceMutU32(_) => TU32,
+            SliceMutU64(_) => TU64,
+            SliceMutU128(_) => TU128,
+            SliceMutI8(_) => TI8,
+            SliceMutI16(_) => TI16,
+            SliceMutI32(_) => TI32,
+            SliceMutI64(_) => TI64,
+            SliceMutI128(_) => TI128,
+            SliceMutF32(_) => TF32,
+            SliceMutF64(_) => TF64,
+        }
+    }
+
+    /// Returns the number of elements in the slice.
+    pub fn len(&self) -> usize {
+        use SliceMut::*;
+        match self {
+            SliceMutBool(s) => s.len(),
+            SliceMutU8(s) => s.len(),
+            SliceMutU16(s) => s.len(),
+            SliceMutU32(s) => s.len(),
+            SliceMutU64(s) => s.len(),
+            SliceMutU128(s) => s.len(),
+            SliceMutI8(s) => s.len(),
+            SliceMutI16(s) => s.len(),
+            SliceMutI32(s) => s.len(),
+            SliceMutI64(s) => s.len(),
+            SliceMutI128(s) => s.len(),
+            SliceMutF32(s) => s.len(),
+            SliceMutF64(s) => s.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 impl<'a> Operand<'a> {
@@ -101,18 +234,25 @@ impl<'a> Operand<'a> {
     }
 }
 
-impl_operand_from!([bool] ConstBool SliceBool
-                   [u8] ConstU8 SliceU8
-                   [u16] ConstU16 SliceU16
-                   [u32] ConstU32 SliceU32
-                   [u64] ConstU64 SliceU64
-                   [u128] ConstU128 SliceU128
-                   [i8] ConstI8 SliceI8
-                   [i16] ConstI16 SliceI16
-                   [i32] ConstI32 SliceI32
-                   [i64] ConstI64 SliceI64
-                   [i128] ConstI128 SliceI128
-                   [f32] ConstF32 SliceF32
-                   [f64] ConstF64 SliceF64
+// Access to the contents of a `Const` or `Slice` already known to be of type
+// `Self`, without conversion.
+pub(crate) trait ScalarOf: Sized {
+    fn of_const(c: &Const) -> Option<Self>;
+    fn of_slice<'a>(s: &Slice<'a>) -> Option<&'a [Self]>;
+}
+
+impl_operand_from!([bool] ConstBool SliceBool SliceMutBool
+                   [u8] ConstU8 SliceU8 SliceMutU8
+                   [u16] ConstU16 SliceU16 SliceMutU16
+                   [u32] ConstU32 SliceU32 SliceMutU32
+                   [u64] ConstU64 SliceU64 SliceMutU64
+                   [u128] ConstU128 SliceU128 SliceMutU128
+                   [i8] ConstI8 SliceI8 SliceMutI8
+                   [i16] ConstI16 SliceI16 SliceMutI16
+                   [i32] ConstI32 SliceI32 SliceMutI32
+                   [i64] ConstI64 SliceI64 SliceMutI64
+                   [i128] ConstI128 SliceI128 SliceMutI128
+                   [f32] ConstF32 SliceF32 SliceMutF32
+                   [f64] ConstF64 SliceF64 SliceMutF64
 );
 
@@ -1,16 +1,19 @@
 // Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
 // Licensed under the MIT and Apache-2.0 licenses.
 
-use packed_simd::{FromCast, Simd};
-use rayon::prelude::*;
 use std::ops::*;
 use std::mem::size_of;
 
+use crate::simd::{FromCast, Simd, M8};
 use crate::zeroone::*;
+use crate::fallback::*;
 use crate::traits::*;
 use crate::consts::*;
+use crate::steps::*;
+use crate::sched::*;
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BoolBinOpCode {
     Lt,
     Le,
@@ -21,6 +24,7 @@ pub enum BoolBinOpCode {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BoolUnOpCode {
     IsNaN,
     IsInf,
@@ -28,6 +32,7 @@ pub enum BoolUnOpCode {
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ValBinOpCode {
     Add,
     Sub,
@@ -40,9 +45,23 @@ pub enum ValBinOpCode {
     BitAnd,
     BitOr,
     BitXor,
+    // Shifts and rotates are computed in the type of their left operand, not
+    // the promoted type, and take the shift amount (which must be an integer)
+    // modulo its bit width. `LShr` shifts in zeroes and `AShr` copies of the
+    // sign bit, whatever the signedness of the type.
+    Shl,
+    LShr,
+    AShr,
+    RotL,
+    RotR,
+    // These call libm a pair of elements at a time, as it has no vector
+    // forms of them, so are several times slower than `Pow`.
+    Atan2,
+    Hypot,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ValUnOpCode {
     Neg,
     BitNot,
@@ -52,48 +71,390 @@ pub enum ValUnOpCode {
     Sqrt,
     Sin,
     Cos,
+    Signum,
+    // Bit counts are returned in the operand's own type.
+    PopCount,
+    LeadingZeros,
+    TrailingZeros,
+    BitReverse,
+    ByteSwap,
+    // `Tan` through `Cbrt`, other than `Tanh`, call libm an element at a
+    // time, as it has no vector forms of them, so are several times slower
+    // than `Sin` or `Exp`.
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Log2,
+    Log10,
+    Log1p,
+    Expm1,
+    Cbrt,
+    Floor,
+    Ceil,
+    Round,
+    Trunc,
 }
 
+impl BoolBinOpCode {
+    /// Every `BoolBinOpCode`.
+    pub const ALL: [BoolBinOpCode; 6] = {
+        use BoolBinOpCode::*;
+        [Lt, Le, Eq, Ne, Ge, Gt]
+    };
+
+    // The outcomes of a comparison for which this is true; see `MixedCmp`.
+    pub(crate) fn ordering_mask(&self) -> u8 {
+        use BoolBinOpCode::*;
+        match self {
+            Lt => ORD_LESS,
+            Le => ORD_LESS | ORD_EQUAL,
+            Eq => ORD_EQUAL,
+            Ne => ORD_LESS | ORD_GREATER | ORD_UNORDERED,
+            Ge => ORD_GREATER | ORD_EQUAL,
+            Gt => ORD_GREATER,
+        }
+    }
+}
+
+/// How `EvalCtx::cast` handles values that are out of range of the type
+/// they're converted to. In every mode, conversions from floating point to
+/// integers truncate towards zero, and conversions to `bool` test for nonzero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CastMode {
+    /// Integers wrap, keeping their low bits. Out-of-range floating-point
+    /// values convert to an unspecified (target-dependent) value. This is
+    /// what `EvalCtx::conv` and the implicit conversions of operands do.
+    #[default]
+    Wrapping,
+    /// Values are clamped to the range of the destination type, and NaNs
+    /// convert to 0 if it's an integer type.
+    Saturating,
+    /// Out-of-range values, and NaNs converted to integers, fail with
+    /// `EvalError::CastOutOfRange` or `EvalError::CastNaN`.
+    Checked,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ValTernOpCode {
+    // `a * b + c`, rounded once.
+    MulAdd,
+}
+
+impl BoolUnOpCode {
+    /// Every `BoolUnOpCode`.
+    pub const ALL: [BoolUnOpCode; 3] = {
+        use BoolUnOpCode::*;
+        [IsNaN, IsInf, IsFin]
+    };
+}
+
+impl ValBinOpCode {
+    /// Every `ValBinOpCode`.
+    pub const ALL: [ValBinOpCode; 18] = {
+        use ValBinOpCode::*;
+        [Add, Sub, Mul, Div, Rem, Min, Max, Pow, BitAnd, BitOr, BitXor,
+         Shl, LShr, AShr, RotL, RotR, Atan2, Hypot]
+    };
+
+    // Whether this is a shift or rotate, computed in its left operand's type.
+    pub(crate) fn is_shift(&self) -> bool {
+        use ValBinOpCode::*;
+        matches!(self, Shl | LShr | AShr | RotL | RotR)
+    }
+}
+
+impl ValUnOpCode {
+    /// Every `ValUnOpCode`.
+    pub const ALL: [ValUnOpCode; 30] = {
+        use ValUnOpCode::*;
+        [Neg, BitNot, Abs, Ln, Exp, Sqrt, Sin, Cos, Signum,
+         PopCount, LeadingZeros, TrailingZeros, BitReverse, ByteSwap,
+         Tan, Asin, Acos, Atan, Sinh, Cosh, Tanh, Log2, Log10, Log1p, Expm1, Cbrt,
+         Floor, Ceil, Round, Trunc]
+    };
+}
+
+impl ValTernOpCode {
+    /// Every `ValTernOpCode`.
+    pub const ALL: [ValTernOpCode; 1] = [ValTernOpCode::MulAdd];
+}
+
+decl_op_structs!(UnOpSkel BinOpSkel ConvOp SatConvOp CheckedConvOp
+                 NegOp NotOp AbsOp SqrtOp SinOp CosOp LnOp ExpOp SignumOp
+                 PopCountOp LeadingZerosOp TrailingZerosOp BitReverseOp ByteSwapOp
+                 TanOp AsinOp AcosOp AtanOp SinhOp CoshOp TanhOp
+                 Log2Op Log10Op Log1pOp Expm1Op CbrtOp FloorOp CeilOp RoundOp TruncOp
+                 IsNaNOp IsInfOp IsFinOp
+                 AddOp SubOp MulOp DivOp RemOp MinOp MaxOp PowOp
+                 BitAndOp BitOrOp BitXorOp ShlOp LShrOp AShrOp RotLOp RotROp
+                 Atan2Op HypotOp MulAddOp MixedCmpOp
+                 LtOp LeOp EqOp NeOp GeOp GtOp);
+
+// Each group of kernels below is either always built, or gated behind one or
+// more of the features `int128`, `float`, `transcendental` and `bitwise` (all
+// enabled by `full`, which is on by default). Gated kernels that are compiled
+// out are replaced by unsupported ones; see `impl_gated` in macros.rs.
+
+////////////////////////////////////////////////////////////////////////////////
+// 8 to 64-bit integers
+////////////////////////////////////////////////////////////////////////////////
+
 impl_unop_skel!((u8, u8) (u8, bool)
                 (u16, u16) (u16, bool)
                 (u32, u32) (u32, bool)
                 (u64, u64) (u64, bool)
-                (u128, u128) (u128, bool)
                 (i8, i8) (i8, bool)
                 (i16, i16) (i16, bool)
                 (i32, i32) (i32, bool)
-                (i64, i64) (i64, bool)
-                (i128, i128) (i128, bool)
-                (f32, f32) (f32, bool)
-                (f64, f64) (f64, bool));
+                (i64, i64) (i64, bool));
 
 impl_binop_skel!((u8, u8) (u8, bool)
                  (u16, u16) (u16, bool)
                  (u32, u32) (u32, bool)
                  (u64, u64) (u64, bool)
-                 (u128, u128) (u128, bool)
                  (i8, i8) (i8, bool)
                  (i16, i16) (i16, bool)
                  (i32, i32) (i32, bool)
-                 (i64, i64) (i64, bool)
-                 (i128, i128) (i128, bool)
-                 (f32, f32) (f32, bool)
-                 (f64, f64) (f64, bool));
+                 (i64, i64) (i64, bool));
 
 // Signed-only unops.
-impl_unop!(NegOp, neg, i8 i16 i32 i64 i128 f32 f64);
+impl_unop!(NegOp, neg, i8 i16 i32 i64);
+impl_unop_scalar!(AbsOp, wrapping_abs, i8 i16 i32 i64);
 
-// Unimplemented on bools and unsigned types.
-impl_unop_unsupported!(NegOp, bool u8 u16 u32 u64 u128);
+// Sign, as -1, 0 or 1; this has no vector form, so is a scalar loop.
+impl_unop_scalar!(SignumOp, signum_of, u8 u16 u32 u64 i8 i16 i32 i64);
+
+// General arithmetic binops.
+impl_binop!(AddOp, add, u8 u16 u32 u64 i8 i16 i32 i64);
+impl_binop!(SubOp, sub, u8 u16 u32 u64 i8 i16 i32 i64);
+impl_binop!(MulOp, mul, u8 u16 u32 u64 i8 i16 i32 i64);
+impl_binop!(DivOp, div, u8 u16 u32 u64 i8 i16 i32 i64);
+impl_binop!(RemOp, rem, u8 u16 u32 u64 i8 i16 i32 i64);
+impl_binop!(MinOp, min, u8 u16 u32 u64 i8 i16 i32 i64);
+impl_binop!(MaxOp, max, u8 u16 u32 u64 i8 i16 i32 i64);
 
+// Integer exponentiation, likewise a scalar loop. See `IntPow`.
+impl_binop_scalar!(PowOp, int_pow, u8 u16 u32 u64 i8 i16 i32 i64);
 
-// Floating-point-only unops
-impl_unop!(SinOp, sin, f32 f64);
-impl_unop!(CosOp, cos, f32 f64);
-impl_unop!(LnOp, ln, f32 f64);
-impl_unop!(ExpOp, exp, f32 f64);
-impl_unop!(SqrtOp, sqrt, f32 f64);
-impl_unop!(AbsOp, abs, f32 f64);
+// Binary predicates.
+impl_binop_pred!(LtOp, lt, u8 u16 u32 u64 i8 i16 i32 i64);
+impl_binop_pred!(LeOp, le, u8 u16 u32 u64 i8 i16 i32 i64);
+impl_binop_pred!(EqOp, eq, u8 u16 u32 u64 i8 i16 i32 i64);
+impl_binop_pred!(NeOp, ne, u8 u16 u32 u64 i8 i16 i32 i64);
+impl_binop_pred!(GeOp, ge, u8 u16 u32 u64 i8 i16 i32 i64);
+impl_binop_pred!(GtOp, gt, u8 u16 u32 u64 i8 i16 i32 i64);
+
+// Conversions.
+impl_convop!(u8, u16 u32 u64 i8 i16 i32 i64);
+impl_convop!(u16, u8 u32 u64 i8 i16 i32 i64);
+impl_convop!(u32, u8 u16 u64 i8 i16 i32 i64);
+impl_convop!(u64, u8 u16 u32 i8 i16 i32 i64);
+impl_convop!(i8, u8 u16 u32 u64 i16 i32 i64);
+impl_convop!(i16, u8 u16 u32 u64 i8 i32 i64);
+impl_convop!(i32, u8 u16 u32 u64 i8 i16 i64);
+impl_convop!(i64, u8 u16 u32 u64 i8 i16 i32);
+impl_bool_convop!(u8 u16 u32 u64 i8 i16 i32 i64);
+
+// Exact signed-vs-unsigned comparisons.
+impl_mixed_cmp!((i8, u8) (i8, u16) (i8, u32) (i8, u64)
+                (i16, u8) (i16, u16) (i16, u32) (i16, u64)
+                (i32, u8) (i32, u16) (i32, u32) (i32, u64)
+                (i64, u8) (i64, u16) (i64, u32) (i64, u64));
+
+// Integer-only bitwise ops.
+impl_gated!(feature = "bitwise";
+    impl_unop!(NotOp, not, u8 u16 u32 u64 i8 i16 i32 i64);
+    impl_binop!(BitAndOp, bitand, u8 u16 u32 u64 i8 i16 i32 i64);
+    impl_binop!(BitOrOp, bitor, u8 u16 u32 u64 i8 i16 i32 i64);
+    impl_binop!(BitXorOp, bitxor, u8 u16 u32 u64 i8 i16 i32 i64);
+
+    // Bit counts and byte order, as scalar loops compiled for each SIMD level
+    // (see `impl_unop_scalar`), which LLVM vectorizes where the level has
+    // instructions for them, eg. AVX2's byte shuffles. See `BitCount`.
+    impl_unop_scalar!(PopCountOp, pop_count, u8 u16 u32 u64 i8 i16 i32 i64);
+    impl_unop_scalar!(LeadingZerosOp, leading_zeros_of, u8 u16 u32 u64 i8 i16 i32 i64);
+    impl_unop_scalar!(TrailingZerosOp, trailing_zeros_of, u8 u16 u32 u64 i8 i16 i32 i64);
+    impl_unop_scalar!(BitReverseOp, reverse_bits, u8 u16 u32 u64 i8 i16 i32 i64);
+    impl_unop_scalar!(ByteSwapOp, swap_bytes, u8 u16 u32 u64 i8 i16 i32 i64);
+
+    // Shifts and rotates by per-element amounts, as scalar loops: packed_simd's
+    // vector shifts don't mask the amount. Like the bit counts they're compiled
+    // for each SIMD level, where LLVM can use AVX2's per-lane shifts. See
+    // `Shift`.
+    impl_binop_scalar!(ShlOp, shl_masked, u8 u16 u32 u64 i8 i16 i32 i64);
+    impl_binop_scalar!(LShrOp, lshr_masked, u8 u16 u32 u64 i8 i16 i32 i64);
+    impl_binop_scalar!(AShrOp, ashr_masked, u8 u16 u32 u64 i8 i16 i32 i64);
+    impl_binop_scalar!(RotLOp, rotl_masked, u8 u16 u32 u64 i8 i16 i32 i64);
+    impl_binop_scalar!(RotROp, rotr_masked, u8 u16 u32 u64 i8 i16 i32 i64);
+);
+
+////////////////////////////////////////////////////////////////////////////////
+// 128-bit integers
+////////////////////////////////////////////////////////////////////////////////
+
+impl_gated!(feature = "int128";
+    impl_unop_skel!((u128, u128) (u128, bool) (i128, i128) (i128, bool));
+    impl_binop_skel!((u128, u128) (u128, bool) (i128, i128) (i128, bool));
+
+    impl_unop!(NegOp, neg, i128);
+    impl_unop_scalar!(AbsOp, wrapping_abs, i128);
+    impl_unop_scalar!(SignumOp, signum_of, u128 i128);
+
+    impl_binop!(AddOp, add, u128 i128);
+    impl_binop!(SubOp, sub, u128 i128);
+    impl_binop!(MulOp, mul, u128 i128);
+    impl_binop!(DivOp, div, u128 i128);
+    impl_binop!(RemOp, rem, u128 i128);
+    impl_binop!(MinOp, min, u128 i128);
+    impl_binop!(MaxOp, max, u128 i128);
+    impl_binop_scalar!(PowOp, int_pow, u128 i128);
+
+    impl_binop_pred!(LtOp, lt, u128 i128);
+    impl_binop_pred!(LeOp, le, u128 i128);
+    impl_binop_pred!(EqOp, eq, u128 i128);
+    impl_binop_pred!(NeOp, ne, u128 i128);
+    impl_binop_pred!(GeOp, ge, u128 i128);
+    impl_binop_pred!(GtOp, gt, u128 i128);
+
+    impl_convop!(u8, u128 i128);
+    impl_convop!(u16, u128 i128);
+    impl_convop!(u32, u128 i128);
+    impl_convop!(u64, u128 i128);
+    impl_convop!(i8, u128 i128);
+    impl_convop!(i16, u128 i128);
+    impl_convop!(i32, u128 i128);
+    impl_convop!(i64, u128 i128);
+    impl_convop!(u128, u8 u16 u32 u64 i8 i16 i32 i64 i128);
+    impl_convop!(i128, u8 u16 u32 u64 i8 i16 i32 i64 u128);
+    impl_bool_convop!(u128 i128);
+
+    impl_mixed_cmp!((i8, u128) (i16, u128) (i32, u128) (i64, u128)
+                    (i128, u8) (i128, u16) (i128, u32) (i128, u64) (i128, u128));
+);
+
+impl_gated!(all(feature = "int128", feature = "bitwise");
+    impl_unop!(NotOp, not, u128 i128);
+    impl_binop!(BitAndOp, bitand, u128 i128);
+    impl_binop!(BitOrOp, bitor, u128 i128);
+    impl_binop!(BitXorOp, bitxor, u128 i128);
+    impl_unop_scalar!(PopCountOp, pop_count, u128 i128);
+    impl_unop_scalar!(LeadingZerosOp, leading_zeros_of, u128 i128);
+    impl_unop_scalar!(TrailingZerosOp, trailing_zeros_of, u128 i128);
+    impl_unop_scalar!(BitReverseOp, reverse_bits, u128 i128);
+    impl_unop_scalar!(ByteSwapOp, swap_bytes, u128 i128);
+    impl_binop_scalar!(ShlOp, shl_masked, u128 i128);
+    impl_binop_scalar!(LShrOp, lshr_masked, u128 i128);
+    impl_binop_scalar!(AShrOp, ashr_masked, u128 i128);
+    impl_binop_scalar!(RotLOp, rotl_masked, u128 i128);
+    impl_binop_scalar!(RotROp, rotr_masked, u128 i128);
+);
+
+////////////////////////////////////////////////////////////////////////////////
+// Floating point
+////////////////////////////////////////////////////////////////////////////////
+
+impl_gated!(feature = "float";
+    impl_unop_skel!((f32, f32) (f32, bool) (f64, f64) (f64, bool));
+    impl_binop_skel!((f32, f32) (f32, bool) (f64, f64) (f64, bool));
+
+    impl_unop!(NegOp, neg, f32 f64);
+    impl_unop!(SqrtOp, sqrt, f32 f64);
+    impl_unop!(AbsOp, abs, f32 f64);
+    impl_unop_scalar!(SignumOp, signum_of, f32 f64);
+
+    // Rounding, as scalar loops, which LLVM vectorizes at SIMD levels with
+    // rounding instructions (AVX2 and AVX-512, or SSE4.1 in the baseline).
+    impl_unop_scalar!(FloorOp, floor, f32 f64);
+    impl_unop_scalar!(CeilOp, ceil, f32 f64);
+    impl_unop_scalar!(RoundOp, round, f32 f64);
+    impl_unop_scalar!(TruncOp, trunc, f32 f64);
+
+    impl_ternop!(MulAddOp, mul_add, f32 f64);
+
+    impl_unop_pred!(IsNaNOp, is_nan, f32 f64);
+    impl_unop_pred!(IsInfOp, is_infinite, f32 f64);
+    impl_unop_pred!(IsFinOp, is_finite, f32 f64);
+
+    impl_binop!(AddOp, add, f32 f64);
+    impl_binop!(SubOp, sub, f32 f64);
+    impl_binop!(MulOp, mul, f32 f64);
+    impl_binop!(DivOp, div, f32 f64);
+    impl_binop!(RemOp, rem, f32 f64);
+    impl_binop!(MinOp, min, f32 f64);
+    impl_binop!(MaxOp, max, f32 f64);
+
+    impl_binop_pred!(LtOp, lt, f32 f64);
+    impl_binop_pred!(LeOp, le, f32 f64);
+    impl_binop_pred!(EqOp, eq, f32 f64);
+    impl_binop_pred!(NeOp, ne, f32 f64);
+    impl_binop_pred!(GeOp, ge, f32 f64);
+    impl_binop_pred!(GtOp, gt, f32 f64);
+
+    impl_convop!(u8, f32 f64);
+    impl_convop!(u16, f32 f64);
+    impl_convop!(u32, f32 f64);
+    impl_convop!(u64, f32 f64);
+    impl_convop!(i8, f32 f64);
+    impl_convop!(i16, f32 f64);
+    impl_convop!(i32, f32 f64);
+    impl_convop!(i64, f32 f64);
+    impl_convop!(f32, u8 u16 u32 u64 i8 i16 i32 i64 f64);
+    impl_convop!(f64, u8 u16 u32 u64 i8 i16 i32 i64 f32);
+    impl_bool_convop!(f32 f64);
+
+    // Exact float-vs-integer comparisons.
+    impl_mixed_cmp!((f32, u8) (f32, u16) (f32, u32) (f32, u64)
+                    (f32, i8) (f32, i16) (f32, i32) (f32, i64)
+                    (f64, u8) (f64, u16) (f64, u32) (f64, u64)
+                    (f64, i8) (f64, i16) (f64, i32) (f64, i64));
+);
+
+impl_gated!(all(feature = "int128", feature = "float");
+    impl_convop!(u128, f32 f64);
+    impl_convop!(i128, f32 f64);
+    impl_convop!(f32, u128 i128);
+    impl_convop!(f64, u128 i128);
+    impl_mixed_cmp!((f32, u128) (f32, i128) (f64, u128) (f64, i128));
+);
+
+// Transcendental functions, which pull in a fair amount of (vectorized libm)
+// code per type.
+impl_gated!(feature = "transcendental";
+    impl_unop!(SinOp, sin, f32 f64);
+    impl_unop!(CosOp, cos, f32 f64);
+    impl_unop!(LnOp, ln, f32 f64);
+    impl_unop!(ExpOp, exp, f32 f64);
+    impl_binop!(PowOp, powf, f32 f64);
+    impl_unop!(TanhOp, tanh, f32 f64);
+
+    // The rest of libm has no vector forms, so these are scalar loops.
+    impl_unop_scalar!(TanOp, tan, f32 f64);
+    impl_unop_scalar!(AsinOp, asin, f32 f64);
+    impl_unop_scalar!(AcosOp, acos, f32 f64);
+    impl_unop_scalar!(AtanOp, atan, f32 f64);
+    impl_unop_scalar!(SinhOp, sinh, f32 f64);
+    impl_unop_scalar!(CoshOp, cosh, f32 f64);
+    impl_unop_scalar!(Log2Op, log2, f32 f64);
+    impl_unop_scalar!(Log10Op, log10, f32 f64);
+    impl_unop_scalar!(Log1pOp, ln_1p, f32 f64);
+    impl_unop_scalar!(Expm1Op, exp_m1, f32 f64);
+    impl_unop_scalar!(CbrtOp, cbrt, f32 f64);
+    impl_binop_scalar!(Atan2Op, atan2, f32 f64);
+    impl_binop_scalar!(HypotOp, hypot, f32 f64);
+);
+
+////////////////////////////////////////////////////////////////////////////////
+// Unsupported combinations
+////////////////////////////////////////////////////////////////////////////////
+
+// Negation is unimplemented on bools and unsigned types.
+impl_unop_unsupported!(NegOp, bool u8 u16 u32 u64 u128);
 
 // The FP-only ops are unsupported for bools or integers.
 impl_unop_unsupported!(SinOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
@@ -101,36 +462,55 @@ impl_unop_unsupported!(CosOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
 impl_unop_unsupported!(LnOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
 impl_unop_unsupported!(ExpOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
 impl_unop_unsupported!(SqrtOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
-impl_unop_unsupported!(AbsOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
-
-// Unary predicates.
-impl_unop_pred!(IsNaNOp, is_nan, f32 f64);
-impl_unop_pred!(IsInfOp, is_infinite, f32 f64);
-impl_unop_pred!(IsFinOp, is_finite, f32 f64);
+impl_unop_unsupported!(TanOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(AsinOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(AcosOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(AtanOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(SinhOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(CoshOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(TanhOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(Log2Op, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(Log10Op, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(Log1pOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(Expm1Op, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(CbrtOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(FloorOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(CeilOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(RoundOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_unop_unsupported!(TruncOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_binop_unsupported!(Atan2Op, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_binop_unsupported!(HypotOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_ternop_unsupported!(MulAddOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+
+// Absolute value is unimplemented on bools and unsigned types.
+impl_unop_unsupported!(AbsOp, bool u8 u16 u32 u64 u128);
+
+// Signum and pow aren't supported by bools.
+impl_unop_unsupported!(SignumOp, bool);
+impl_binop_unsupported!(PowOp, bool);
 
 // The FP-only predicates are unsupported for integers or bools.
 impl_unop_pred_unsupported!(IsNaNOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
 impl_unop_pred_unsupported!(IsInfOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
 impl_unop_pred_unsupported!(IsFinOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
 
-// Integer-only unops
-impl_unop!(NotOp, not, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
-
-// Integer-only unops not supported for bool or FP.
+// Integer-only ops are not supported by bool or FP.
 impl_unop_unsupported!(NotOp, bool f32 f64);
+impl_unop_unsupported!(PopCountOp, bool f32 f64);
+impl_unop_unsupported!(LeadingZerosOp, bool f32 f64);
+impl_unop_unsupported!(TrailingZerosOp, bool f32 f64);
+impl_unop_unsupported!(BitReverseOp, bool f32 f64);
+impl_unop_unsupported!(ByteSwapOp, bool f32 f64);
+impl_binop_unsupported!(BitAndOp, bool f32 f64);
+impl_binop_unsupported!(BitOrOp, bool f32 f64);
+impl_binop_unsupported!(BitXorOp, bool f32 f64);
+impl_binop_unsupported!(ShlOp, bool f32 f64);
+impl_binop_unsupported!(LShrOp, bool f32 f64);
+impl_binop_unsupported!(AShrOp, bool f32 f64);
+impl_binop_unsupported!(RotLOp, bool f32 f64);
+impl_binop_unsupported!(RotROp, bool f32 f64);
 
-// General arithmetic binops.
-impl_binop!(AddOp, add, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-impl_binop!(SubOp, sub, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-
-impl_binop!(MulOp, mul, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-impl_binop!(DivOp, div, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-impl_binop!(RemOp, rem, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-
-impl_binop!(MinOp, min, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-impl_binop!(MaxOp, max, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-
-// General arithmetic binops aren't supported by bools.
+// General arithmetic binops and comparisons aren't supported by bools.
 impl_binop_unsupported!(AddOp, bool);
 impl_binop_unsupported!(SubOp, bool);
 impl_binop_unsupported!(MulOp, bool);
@@ -139,31 +519,6 @@ impl_binop_unsupported!(RemOp, bool);
 impl_binop_unsupported!(MinOp, bool);
 impl_binop_unsupported!(MaxOp, bool);
 
-
-// Floating-point-only binops.
-impl_binop!(PowOp, powf, f32 f64);
-
-// FP-only binop is not supported by bool or integer types.
-impl_binop_unsupported!(PowOp, bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
-
-// Integer-only binops.
-impl_binop!(BitAndOp, bitand, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
-impl_binop!(BitOrOp, bitor, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
-impl_binop!(BitXorOp, bitxor, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
-
-// Integer-only ops are not supported by bool or FP.
-impl_binop_unsupported!(BitAndOp, bool f32 f64);
-impl_binop_unsupported!(BitOrOp, bool f32 f64);
-impl_binop_unsupported!(BitXorOp, bool f32 f64);
-
-// Binary predicates.
-impl_binop_pred!(LtOp, lt, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-impl_binop_pred!(LeOp, le, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-impl_binop_pred!(EqOp, eq, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-impl_binop_pred!(NeOp, ne, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-impl_binop_pred!(GeOp, ge, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-impl_binop_pred!(GtOp, gt, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-
 impl_binop_pred_unsupported!(LtOp, bool);
 impl_binop_pred_unsupported!(LeOp, bool);
 impl_binop_pred_unsupported!(EqOp, bool);
@@ -171,26 +526,5 @@ impl_binop_pred_unsupported!(NeOp, bool);
 impl_binop_pred_unsupported!(GeOp, bool);
 impl_binop_pred_unsupported!(GtOp, bool);
 
-// The type-conversion operator.
-pub struct ConvOp<SRC, DST> {
-    _x: std::marker::PhantomData<(SRC, DST)>,
-}
-
-impl_convop!(u8, u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-impl_convop!(u16, u8 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-impl_convop!(u32, u8 u16 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-impl_convop!(u64, u8 u16 u32 u128 i8 i16 i32 i64 i128 f32 f64);
-impl_convop!(u128, u8 u16 u32 u64 i8 i16 i32 i64 i128 f32 f64);
-
-impl_convop!(i8, u8 u16 u32 u64 u128 i16 i32 i64 i128 f32 f64);
-impl_convop!(i16, u8 u16 u32 u64 u128 i8 i32 i64 i128 f32 f64);
-impl_convop!(i32, u8 u16 u32 u64 u128 i8 i16 i64 i128 f32 f64);
-impl_convop!(i64, u8 u16 u32 u64 u128 i8 i16 i32 i128 f32 f64);
-impl_convop!(i128, u8 u16 u32 u64 u128 i8 i16 i32 i64 f32 f64);
-
-impl_convop!(f32, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f64);
-impl_convop!(f64, u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32);
-
+// Every type converts to itself, for free.
 impl_noop_convop!(bool u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
-
-impl_bool_convop!(u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
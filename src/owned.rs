@@ -0,0 +1,136 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use std::sync::Arc;
+
+use crate::scalarty::*;
+use crate::operands::*;
+
+/// `OwnedOperand`s are the owned counterpart of `Operand`s: rather than
+/// borrowing their elements, slices are held in reference-counted `Arc<[T]>`
+/// columns. This lets results outlive the `EvalCtx` buffers they were written
+/// into, be cached, or be sent between threads. Cloning is cheap, and an
+/// `OwnedOperand` can be lent back to the evaluator as an `Operand` without
+/// copying.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OwnedOperand {
+    OperandSlice(OwnedSlice),
+    OperandConst(Const)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OwnedSlice {
+    SliceBool(Arc<[bool]>),
+    SliceU8(Arc<[u8]>),
+    SliceU16(Arc<[u16]>),
+    SliceU32(Arc<[u32]>),
+    SliceU64(Arc<[u64]>),
+    SliceU128(Arc<[u128]>),
+    SliceI8(Arc<[i8]>),
+    SliceI16(Arc<[i16]>),
+    SliceI32(Arc<[i32]>),
+    SliceI64(Arc<[i64]>),
+    SliceI128(Arc<[i128]>),
+    SliceF32(Arc<[f32]>),
+    SliceF64(Arc<[f64]>),
+}
+
+impl OwnedSlice {
+    pub fn get_scalar_ty(&self) -> ScalarTy {
+        self.as_slice().get_scalar_ty()
+    }
+
+    /// Borrows the owned column as a `Slice`, without copying.
+    pub fn as_slice(&self) -> Slice<'_> {
+        use OwnedSlice as O;
+        match self {
+            O::SliceBool(s) => Slice::SliceBool(s),
+            O::SliceU8(s) => Slice::SliceU8(s),
+            O::SliceU16(s) => Slice::SliceU16(s),
+            O::SliceU32(s) => Slice::SliceU32(s),
+            O::SliceU64(s) => Slice::SliceU64(s),
+            O::SliceU128(s) => Slice::SliceU128(s),
+            O::SliceI8(s) => Slice::SliceI8(s),
+            O::SliceI16(s) => Slice::SliceI16(s),
+            O::SliceI32(s) => Slice::SliceI32(s),
+            O::SliceI64(s) => Slice::SliceI64(s),
+            O::SliceI128(s) => Slice::SliceI128(s),
+            O::SliceF32(s) => Slice::SliceF32(s),
+            O::SliceF64(s) => Slice::SliceF64(s),
+        }
+    }
+}
+
+impl OwnedOperand {
+    pub fn get_scalar_ty(&self) -> ScalarTy {
+        use OwnedOperand::*;
+        match self {
+            OperandConst(c) => c.get_scalar_ty(),
+            OperandSlice(s) => s.get_scalar_ty()
+        }
+    }
+
+    /// Borrows the owned operand as an `Operand`, without copying, so that it
+    /// can be passed back into an `EvalCtx`.
+    pub fn as_operand(&self) -> Operand<'_> {
+        match self {
+            OwnedOperand::OperandConst(c) => Operand::OperandConst(c.clone()),
+            OwnedOperand::OperandSlice(s) => Operand::OperandSlice(s.as_slice())
+        }
+    }
+}
+
+// Copies a borrowed slice (typically one pointing into an `EvalCtx` output
+// buffer) into a freshly-allocated owned column.
+impl<'a> From<&Slice<'a>> for OwnedSlice {
+    fn from(s: &Slice<'a>) -> Self {
+        use Slice as S;
+        match *s {
+            S::SliceBool(s) => OwnedSlice::SliceBool(s.into()),
+            S::SliceU8(s) => OwnedSlice::SliceU8(s.into()),
+            S::SliceU16(s) => OwnedSlice::SliceU16(s.into()),
+            S::SliceU32(s) => OwnedSlice::SliceU32(s.into()),
+            S::SliceU64(s) => OwnedSlice::SliceU64(s.into()),
+            S::SliceU128(s) => OwnedSlice::SliceU128(s.into()),
+            S::SliceI8(s) => OwnedSlice::SliceI8(s.into()),
+            S::SliceI16(s) => OwnedSlice::SliceI16(s.into()),
+            S::SliceI32(s) => OwnedSlice::SliceI32(s.into()),
+            S::SliceI64(s) => OwnedSlice::SliceI64(s.into()),
+            S::SliceI128(s) => OwnedSlice::SliceI128(s.into()),
+            S::SliceF32(s) => OwnedSlice::SliceF32(s.into()),
+            S::SliceF64(s) => OwnedSlice::SliceF64(s.into()),
+        }
+    }
+}
+
+impl<'a> From<&Operand<'a>> for OwnedOperand {
+    fn from(o: &Operand<'a>) -> Self {
+        match o {
+            Operand::OperandConst(c) => OwnedOperand::OperandConst(c.clone()),
+            Operand::OperandSlice(s) => OwnedOperand::OperandSlice(s.into())
+        }
+    }
+}
+
+impl<'a> From<Operand<'a>> for OwnedOperand {
+    fn from(o: Operand<'a>) -> Self {
+        OwnedOperand::from(&o)
+    }
+}
+
+impl_owned_operand_from!([bool] ConstBool SliceBool
+                         [u8] ConstU8 SliceU8
+                         [u16] ConstU16 SliceU16
+                         [u32] ConstU32 SliceU32
+                         [u64] ConstU64 SliceU64
+                         [u128] ConstU128 SliceU128
+                         [i8] ConstI8 SliceI8
+                         [i16] ConstI16 SliceI16
+                         [i32] ConstI32 SliceI32
+                         [i64] ConstI64 SliceI64
+                         [i128] ConstI128 SliceI128
+                         [f32] ConstF32 SliceF32
+                         [f64] ConstF64 SliceF64
+);
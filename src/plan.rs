@@ -0,0 +1,693 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Planning: working out, from only the types and lengths of its operands, what
+// an `EvalCtx` operation will produce and how big its buffers need to be, so a
+// caller can allocate them before evaluating. The rules here mirror those in
+// eval.rs: slices are converted to the operator's type in `tmp1` (the first
+// operand) and `tmp2` (the second), unless they're of that type already, and
+// the result goes in `out`. Whether a combination is supported comes from the
+// `SUPPORTED` consts of the kernels eval.rs would dispatch to.
+
+use crate::ops::*;
+use crate::traits::*;
+use crate::scalarty::*;
+use crate::operands::*;
+use crate::promotion::*;
+use crate::eval::EvalError;
+
+/// What planning needs to know about an operand: its type, and whether it's a
+/// `Const` or a `Slice` of a given length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OperandDesc {
+    ConstDesc(ScalarTy),
+    SliceDesc(ScalarTy, usize),
+}
+
+impl OperandDesc {
+    pub fn get_scalar_ty(&self) -> ScalarTy {
+        match *self {
+            OperandDesc::ConstDesc(ty) => ty,
+            OperandDesc::SliceDesc(ty, _) => ty,
+        }
+    }
+}
+
+impl<'a> Operand<'a> {
+    /// Returns the `OperandDesc` of this operand.
+    pub fn desc(&self) -> OperandDesc {
+        match self {
+            Operand::OperandConst(c) => OperandDesc::ConstDesc(c.get_scalar_ty()),
+            Operand::OperandSlice(s) => OperandDesc::SliceDesc(s.get_scalar_ty(), s.len()),
+        }
+    }
+}
+
+/// The plan for an operation: the description of its result, and the least
+/// number of bytes each of the `EvalCtx`'s buffers must have for it. Buffers
+/// that aren't needed (0 bytes) can be empty; the rest must be aligned to
+/// `align`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EvalPlan {
+    pub result: OperandDesc,
+    pub tmp1: usize,
+    pub tmp2: usize,
+    pub out: usize,
+    pub align: usize,
+}
+
+impl EvalPlan {
+    // An operation on consts, which needs no buffers.
+    fn of_const(ty: ScalarTy) -> EvalPlan {
+        EvalPlan { result: OperandDesc::ConstDesc(ty), tmp1: 0, tmp2: 0, out: 0, align: 1 }
+    }
+
+    // An operation on `n`-element slices that writes a `res_ty` result to
+    // `out`, using `tmp1` and `tmp2` for `ty` values.
+    fn of_slices(ty: ScalarTy, res_ty: ScalarTy, n: usize, tmp1: usize, tmp2: usize) -> EvalPlan {
+        let align = if tmp1 > 0 || tmp2 > 0 {
+            ty.align_of().max(res_ty.align_of())
+        } else {
+            res_ty.align_of()
+        };
+        EvalPlan {
+            result: OperandDesc::SliceDesc(res_ty, n),
+            tmp1, tmp2,
+            out: n * res_ty.size_of(),
+            align,
+        }
+    }
+}
+
+// The bytes of `tmp` needed to convert `x` to `ty`: none for consts, which are
+// converted in registers, nor for slices already of type `ty`.
+fn conv_bytes(x: OperandDesc, ty: ScalarTy) -> usize {
+    match x {
+        OperandDesc::SliceDesc(xty, n) if xty != ty => n * ty.size_of(),
+        _ => 0,
+    }
+}
+
+// The bytes of `tmp` needed to make `x` an `n`-element slice of type `ty`:
+// consts are broadcast there.
+fn broadcast_bytes(x: OperandDesc, ty: ScalarTy, n: usize) -> usize {
+    match x {
+        OperandDesc::SliceDesc(xty, _) if xty == ty => 0,
+        _ => n * ty.size_of(),
+    }
+}
+
+// The length of the slices among `xs`, which must all be the same; `None` if
+// they're all consts.
+fn common_len(xs: &[OperandDesc]) -> Result<Option<usize>, EvalError> {
+    let mut len = None;
+    for x in xs {
+        if let OperandDesc::SliceDesc(_, n) = *x {
+            if len.is_some_and(|m| m != n) {
+                return Err(EvalError::BadBuffer);
+            }
+            len = Some(n);
+        }
+    }
+    Ok(len)
+}
+
+fn binop_plan(ty: ScalarTy, res_ty: ScalarTy,
+              lhs: OperandDesc, rhs: OperandDesc) -> Result<EvalPlan, EvalError> {
+    match common_len(&[lhs, rhs])? {
+        None => Ok(EvalPlan::of_const(res_ty)),
+        Some(n) => Ok(EvalPlan::of_slices(ty, res_ty, n,
+                                          conv_bytes(lhs, ty), conv_bytes(rhs, ty))),
+    }
+}
+
+fn unop_plan(ty: ScalarTy, res_ty: ScalarTy, x: OperandDesc) -> EvalPlan {
+    match x {
+        OperandDesc::ConstDesc(_) => EvalPlan::of_const(res_ty),
+        OperandDesc::SliceDesc(_, n) => EvalPlan::of_slices(ty, res_ty, n, 0, 0),
+    }
+}
+
+// Returns the type `op` is computed in on operands of types `lty` and `rty`:
+// `lty` for shifts and rotates, whose amounts are converted to it (which
+// keeps them modulo its bit width), and the promoted type for the rest.
+pub(crate) fn val_binop_ty(promotion: &dyn PromotionPolicy, op: &ValBinOpCode,
+                           lty: ScalarTy, rty: ScalarTy) -> Result<ScalarTy, EvalError> {
+    if !op.is_shift() {
+        return promotion.join(lty, rty);
+    }
+    match rty {
+        ScalarTy::TF32 | ScalarTy::TF64 => Err(EvalError::UnsupportedOp),
+        _ => Ok(lty),
+    }
+}
+
+/// Plans `EvalCtx::val_binop`, promoting with `promotion`.
+pub fn plan_val_binop(promotion: &dyn PromotionPolicy, op: ValBinOpCode,
+                      lhs: OperandDesc, rhs: OperandDesc) -> Result<EvalPlan, EvalError> {
+    let (lty, rty) = (lhs.get_scalar_ty(), rhs.get_scalar_ty());
+    let ty = val_binop_ty(promotion, &op, lty, rty)?;
+    if !(conv_supported(lty, ty) && conv_supported(rty, ty) && val_binop_supported(op, ty)) {
+        return Err(EvalError::UnsupportedOp);
+    }
+    binop_plan(ty, ty, lhs, rhs)
+}
+
+/// Plans `EvalCtx::bool_binop`, promoting with `promotion`.
+pub fn plan_bool_binop(promotion: &dyn PromotionPolicy, op: BoolBinOpCode,
+                       lhs: OperandDesc, rhs: OperandDesc) -> Result<EvalPlan, EvalError> {
+    let (lty, rty) = (lhs.get_scalar_ty(), rhs.get_scalar_ty());
+    if compares_exactly(op.clone(), lty, rty) {
+        // Compared exactly, without conversion, so only `out` is used.
+        if !mixed_cmp_supported(lty, rty) {
+            return Err(EvalError::UnsupportedOp);
+        }
+        return match common_len(&[lhs, rhs])? {
+            None => Ok(EvalPlan::of_const(ScalarTy::TBool)),
+            Some(n) => Ok(EvalPlan::of_slices(ScalarTy::TBool, ScalarTy::TBool, n, 0, 0)),
+        };
+    }
+    let ty = promotion.join(lty, rty)?;
+    if !(conv_supported(lty, ty) && conv_supported(rty, ty) && bool_binop_supported(op, ty)) {
+        return Err(EvalError::UnsupportedOp);
+    }
+    binop_plan(ty, ScalarTy::TBool, lhs, rhs)
+}
+
+/// Plans `EvalCtx::val_unop`.
+pub fn plan_val_unop(op: ValUnOpCode, x: OperandDesc) -> Result<EvalPlan, EvalError> {
+    let ty = x.get_scalar_ty();
+    if !val_unop_supported(op, ty) {
+        return Err(EvalError::UnsupportedOp);
+    }
+    Ok(unop_plan(ty, ty, x))
+}
+
+/// Plans `EvalCtx::bool_unop`.
+pub fn plan_bool_unop(op: BoolUnOpCode, x: OperandDesc) -> Result<EvalPlan, EvalError> {
+    let ty = x.get_scalar_ty();
+    if !bool_unop_supported(op, ty) {
+        return Err(EvalError::UnsupportedOp);
+    }
+    Ok(unop_plan(ty, ScalarTy::TBool, x))
+}
+
+/// Plans `EvalCtx::val_ternop`, promoting with `promotion`. The third operand
+/// is copied to `out` by way of `tmp1`, and the first two (broadcast, if
+/// they're consts) go in `tmp1` and `tmp2`.
+pub fn plan_val_ternop(promotion: &dyn PromotionPolicy, op: ValTernOpCode,
+                       a: OperandDesc, b: OperandDesc, c: OperandDesc)
+                       -> Result<EvalPlan, EvalError> {
+    let (aty, bty, cty) = (a.get_scalar_ty(), b.get_scalar_ty(), c.get_scalar_ty());
+    let ty = promotion.join(promotion.join(aty, bty)?, cty)?;
+    if !(conv_supported(aty, ty) && conv_supported(bty, ty) && conv_supported(cty, ty) &&
+         val_ternop_supported(op, ty)) {
+        return Err(EvalError::UnsupportedOp);
+    }
+    match common_len(&[a, b, c])? {
+        None => Ok(EvalPlan::of_const(ty)),
+        Some(n) => {
+            let tmp1 = broadcast_bytes(c, ty, n).max(broadcast_bytes(a, ty, n));
+            Ok(EvalPlan::of_slices(ty, ty, n, tmp1, broadcast_bytes(b, ty, n)))
+        }
+    }
+}
+
+/// Plans `EvalCtx::conv`. A slice already of type `ty` is returned as it is,
+/// so needs no buffers.
+pub fn plan_conv(x: OperandDesc, ty: ScalarTy) -> Result<EvalPlan, EvalError> {
+    plan_cast(x, ty, CastMode::Wrapping)
+}
+
+/// Plans `EvalCtx::cast`, which is like `conv`.
+pub fn plan_cast(x: OperandDesc, ty: ScalarTy, mode: CastMode) -> Result<EvalPlan, EvalError> {
+    if !cast_supported(x.get_scalar_ty(), ty, mode) {
+        return Err(EvalError::UnsupportedOp);
+    }
+    Ok(match x {
+        OperandDesc::ConstDesc(_) => EvalPlan::of_const(ty),
+        OperandDesc::SliceDesc(xty, _) if xty == ty => EvalPlan {
+            result: x, tmp1: 0, tmp2: 0, out: 0, align: 1
+        },
+        OperandDesc::SliceDesc(_, n) => EvalPlan::of_slices(ty, ty, n, 0, 0),
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Supported combinations
+////////////////////////////////////////////////////////////////////////////////
+//
+// These dispatch from `ScalarTy`s to the same kernels eval.rs does, and return
+// their `SUPPORTED` consts.
+
+pub(crate) fn val_binop_supported(op: ValBinOpCode, ty: ScalarTy) -> bool {
+    use ScalarTy::*;
+    match ty {
+        TBool => val_binop_supported_static::<bool>(op),
+        TU8 => val_binop_supported_static::<u8>(op),
+        TU16 => val_binop_supported_static::<u16>(op),
+        TU32 => val_binop_supported_static::<u32>(op),
+        TU64 => val_binop_supported_static::<u64>(op),
+        TU128 => val_binop_supported_static::<u128>(op),
+        TI8 => val_binop_supported_static::<i8>(op),
+        TI16 => val_binop_supported_static::<i16>(op),
+        TI32 => val_binop_supported_static::<i32>(op),
+        TI64 => val_binop_supported_static::<i64>(op),
+        TI128 => val_binop_supported_static::<i128>(op),
+        TF32 => val_binop_supported_static::<f32>(op),
+        TF64 => val_binop_supported_static::<f64>(op),
+    }
+}
+
+fn val_binop_supported_static<T>(op: ValBinOpCode) -> bool
+where
+    T: ScalarT,
+    AddOp<T, T>: BinOp<T, T>,
+    SubOp<T, T>: BinOp<T, T>,
+    MulOp<T, T>: BinOp<T, T>,
+    DivOp<T, T>: BinOp<T, T>,
+    RemOp<T, T>: BinOp<T, T>,
+    MinOp<T, T>: BinOp<T, T>,
+    MaxOp<T, T>: BinOp<T, T>,
+    PowOp<T, T>: BinOp<T, T>,
+    BitAndOp<T, T>: BinOp<T, T>,
+    BitOrOp<T, T>: BinOp<T, T>,
+    BitXorOp<T, T>: BinOp<T, T>,
+    ShlOp<T, T>: BinOp<T, T>,
+    LShrOp<T, T>: BinOp<T, T>,
+    AShrOp<T, T>: BinOp<T, T>,
+    RotLOp<T, T>: BinOp<T, T>,
+    RotROp<T, T>: BinOp<T, T>,
+    Atan2Op<T, T>: BinOp<T, T>,
+    HypotOp<T, T>: BinOp<T, T>,
+{
+    use ValBinOpCode::*;
+    match op {
+        Add => <AddOp<T, T>>::SUPPORTED,
+        Sub => <SubOp<T, T>>::SUPPORTED,
+        Mul => <MulOp<T, T>>::SUPPORTED,
+        Div => <DivOp<T, T>>::SUPPORTED,
+        Rem => <RemOp<T, T>>::SUPPORTED,
+        Min => <MinOp<T, T>>::SUPPORTED,
+        Max => <MaxOp<T, T>>::SUPPORTED,
+        Pow => <PowOp<T, T>>::SUPPORTED,
+        BitAnd => <BitAndOp<T, T>>::SUPPORTED,
+        BitOr => <BitOrOp<T, T>>::SUPPORTED,
+        BitXor => <BitXorOp<T, T>>::SUPPORTED,
+        Shl => <ShlOp<T, T>>::SUPPORTED,
+        LShr => <LShrOp<T, T>>::SUPPORTED,
+        AShr => <AShrOp<T, T>>::SUPPORTED,
+        RotL => <RotLOp<T, T>>::SUPPORTED,
+        RotR => <RotROp<T, T>>::SUPPORTED,
+        Atan2 => <Atan2Op<T, T>>::SUPPORTED,
+        Hypot => <HypotOp<T, T>>::SUPPORTED,
+    }
+}
+
+pub(crate) fn bool_binop_supported(op: BoolBinOpCode, ty: ScalarTy) -> bool {
+    use ScalarTy::*;
+    match ty {
+        TBool => bool_binop_supported_static::<bool>(op),
+        TU8 => bool_binop_supported_static::<u8>(op),
+        TU16 => bool_binop_supported_static::<u16>(op),
+        TU32 => bool_binop_supported_static::<u32>(op),
+        TU64 => bool_binop_supported_static::<u64>(op),
+        TU128 => bool_binop_supported_static::<u128>(op),
+        TI8 => bool_binop_supported_static::<i8>(op),
+        TI16 => bool_binop_supported_static::<i16>(op),
+        TI32 => bool_binop_supported_static::<i32>(op),
+        TI64 => bool_binop_supported_static::<i64>(op),
+        TI128 => bool_binop_supported_static::<i128>(op),
+        TF32 => bool_binop_supported_static::<f32>(op),
+        TF64 => bool_binop_supported_static::<f64>(op),
+    }
+}
+
+fn bool_binop_supported_static<T>(op: BoolBinOpCode) -> bool
+where
+    T: ScalarT,
+    LtOp<T, bool>: BinOp<T, bool>,
+    LeOp<T, bool>: BinOp<T, bool>,
+    EqOp<T, bool>: BinOp<T, bool>,
+    NeOp<T, bool>: BinOp<T, bool>,
+    GeOp<T, bool>: BinOp<T, bool>,
+    GtOp<T, bool>: BinOp<T, bool>,
+{
+    use BoolBinOpCode::*;
+    match op {
+        Lt => <LtOp<T, bool>>::SUPPORTED,
+        Le => <LeOp<T, bool>>::SUPPORTED,
+        Eq => <EqOp<T, bool>>::SUPPORTED,
+        Ne => <NeOp<T, bool>>::SUPPORTED,
+        Ge => <GeOp<T, bool>>::SUPPORTED,
+        Gt => <GtOp<T, bool>>::SUPPORTED,
+    }
+}
+
+// Returns whether `EvalCtx::bool_binop` compares operands of types `lty`
+// and `rty` exactly, as they are, rather than converted to their common type
+// `ty` (their `ScalarTy::join`): when converting would lose information, or
+// when comparing on `ty` isn't compiled in but comparing exactly is (eg. `u64`
+// and `i64` joined to `i128` without the `int128` feature). This depends only
+// on the types, not on the `PromotionPolicy`, which is only asked for a type
+// when the operands are converted.
+pub(crate) fn compares_exactly(op: BoolBinOpCode, lty: ScalarTy, rty: ScalarTy) -> bool {
+    let ty = lty.join(rty);
+    if !(ty.can_represent(lty) && ty.can_represent(rty)) {
+        return true;
+    }
+    let converted = conv_supported(lty, ty) && conv_supported(rty, ty) &&
+        bool_binop_supported(op, ty);
+    !converted && mixed_cmp_supported(lty, rty)
+}
+
+// The pairs, and their order, are those of `EvalCtx::mixed_cmp`.
+pub(crate) fn mixed_cmp_supported(lty: ScalarTy, rty: ScalarTy) -> bool {
+    use ScalarTy::*;
+    fn rank(ty: ScalarTy) -> u8 {
+        match ty {
+            TF32 | TF64 => 2,
+            TI8 | TI16 | TI32 | TI64 | TI128 => 1,
+            _ => 0,
+        }
+    }
+    if rank(lty) < rank(rty) {
+        return mixed_cmp_supported(rty, lty);
+    }
+    match (lty, rty) {
+        (TI8, TU8) => <MixedCmpOp<i8, u8>>::SUPPORTED,
+        (TI8, TU16) => <MixedCmpOp<i8, u16>>::SUPPORTED,
+        (TI8, TU32) => <MixedCmpOp<i8, u32>>::SUPPORTED,
+        (TI8, TU64) => <MixedCmpOp<i8, u64>>::SUPPORTED,
+        (TI8, TU128) => <MixedCmpOp<i8, u128>>::SUPPORTED,
+        (TI16, TU8) => <MixedCmpOp<i16, u8>>::SUPPORTED,
+        (TI16, TU16) => <MixedCmpOp<i16, u16>>::SUPPORTED,
+        (TI16, TU32) => <MixedCmpOp<i16, u32>>::SUPPORTED,
+        (TI16, TU64) => <MixedCmpOp<i16, u64>>::SUPPORTED,
+        (TI16, TU128) => <MixedCmpOp<i16, u128>>::SUPPORTED,
+        (TI32, TU8) => <MixedCmpOp<i32, u8>>::SUPPORTED,
+        (TI32, TU16) => <MixedCmpOp<i32, u16>>::SUPPORTED,
+        (TI32, TU32) => <MixedCmpOp<i32, u32>>::SUPPORTED,
+        (TI32, TU64) => <MixedCmpOp<i32, u64>>::SUPPORTED,
+        (TI32, TU128) => <MixedCmpOp<i32, u128>>::SUPPORTED,
+        (TI64, TU8) => <MixedCmpOp<i64, u8>>::SUPPORTED,
+        (TI64, TU16) => <MixedCmpOp<i64, u16>>::SUPPORTED,
+        (TI64, TU32) => <MixedCmpOp<i64, u32>>::SUPPORTED,
+        (TI64, TU64) => <MixedCmpOp<i64, u64>>::SUPPORTED,
+        (TI64, TU128) => <MixedCmpOp<i64, u128>>::SUPPORTED,
+        (TI128, TU8) => <MixedCmpOp<i128, u8>>::SUPPORTED,
+        (TI128, TU16) => <MixedCmpOp<i128, u16>>::SUPPORTED,
+        (TI128, TU32) => <MixedCmpOp<i128, u32>>::SUPPORTED,
+        (TI128, TU64) => <MixedCmpOp<i128, u64>>::SUPPORTED,
+        (TI128, TU128) => <MixedCmpOp<i128, u128>>::SUPPORTED,
+        (TF32, TU8) => <MixedCmpOp<f32, u8>>::SUPPORTED,
+        (TF32, TU16) => <MixedCmpOp<f32, u16>>::SUPPORTED,
+        (TF32, TU32) => <MixedCmpOp<f32, u32>>::SUPPORTED,
+        (TF32, TU64) => <MixedCmpOp<f32, u64>>::SUPPORTED,
+        (TF32, TU128) => <MixedCmpOp<f32, u128>>::SUPPORTED,
+        (TF32, TI8) => <MixedCmpOp<f32, i8>>::SUPPORTED,
+        (TF32, TI16) => <MixedCmpOp<f32, i16>>::SUPPORTED,
+        (TF32, TI32) => <MixedCmpOp<f32, i32>>::SUPPORTED,
+        (TF32, TI64) => <MixedCmpOp<f32, i64>>::SUPPORTED,
+        (TF32, TI128) => <MixedCmpOp<f32, i128>>::SUPPORTED,
+        (TF64, TU8) => <MixedCmpOp<f64, u8>>::SUPPORTED,
+        (TF64, TU16) => <MixedCmpOp<f64, u16>>::SUPPORTED,
+        (TF64, TU32) => <MixedCmpOp<f64, u32>>::SUPPORTED,
+        (TF64, TU64) => <MixedCmpOp<f64, u64>>::SUPPORTED,
+        (TF64, TU128) => <MixedCmpOp<f64, u128>>::SUPPORTED,
+        (TF64, TI8) => <MixedCmpOp<f64, i8>>::SUPPORTED,
+        (TF64, TI16) => <MixedCmpOp<f64, i16>>::SUPPORTED,
+        (TF64, TI32) => <MixedCmpOp<f64, i32>>::SUPPORTED,
+        (TF64, TI64) => <MixedCmpOp<f64, i64>>::SUPPORTED,
+        (TF64, TI128) => <MixedCmpOp<f64, i128>>::SUPPORTED,
+        _ => false,
+    }
+}
+
+pub(crate) fn val_unop_supported(op: ValUnOpCode, ty: ScalarTy) -> bool {
+    use ScalarTy::*;
+    match ty {
+        TBool => val_unop_supported_static::<bool>(op),
+        TU8 => val_unop_supported_static::<u8>(op),
+        TU16 => val_unop_supported_static::<u16>(op),
+        TU32 => val_unop_supported_static::<u32>(op),
+        TU64 => val_unop_supported_static::<u64>(op),
+        TU128 => val_unop_supported_static::<u128>(op),
+        TI8 => val_unop_supported_static::<i8>(op),
+        TI16 => val_unop_supported_static::<i16>(op),
+        TI32 => val_unop_supported_static::<i32>(op),
+        TI64 => val_unop_supported_static::<i64>(op),
+        TI128 => val_unop_supported_static::<i128>(op),
+        TF32 => val_unop_supported_static::<f32>(op),
+        TF64 => val_unop_supported_static::<f64>(op),
+    }
+}
+
+fn val_unop_supported_static<T>(op: ValUnOpCode) -> bool
+where
+    T: ScalarT,
+    NegOp<T, T>: UnOp<T, T>,
+    AbsOp<T, T>: UnOp<T, T>,
+    NotOp<T, T>: UnOp<T, T>,
+    LnOp<T, T>: UnOp<T, T>,
+    ExpOp<T, T>: UnOp<T, T>,
+    SqrtOp<T, T>: UnOp<T, T>,
+    SinOp<T, T>: UnOp<T, T>,
+    CosOp<T, T>: UnOp<T, T>,
+    SignumOp<T, T>: UnOp<T, T>,
+    PopCountOp<T, T>: UnOp<T, T>,
+    LeadingZerosOp<T, T>: UnOp<T, T>,
+    TrailingZerosOp<T, T>: UnOp<T, T>,
+    BitReverseOp<T, T>: UnOp<T, T>,
+    ByteSwapOp<T, T>: UnOp<T, T>,
+    TanOp<T, T>: UnOp<T, T>,
+    AsinOp<T, T>: UnOp<T, T>,
+    AcosOp<T, T>: UnOp<T, T>,
+    AtanOp<T, T>: UnOp<T, T>,
+    SinhOp<T, T>: UnOp<T, T>,
+    CoshOp<T, T>: UnOp<T, T>,
+    TanhOp<T, T>: UnOp<T, T>,
+    Log2Op<T, T>: UnOp<T, T>,
+    Log10Op<T, T>: UnOp<T, T>,
+    Log1pOp<T, T>: UnOp<T, T>,
+    Expm1Op<T, T>: UnOp<T, T>,
+    CbrtOp<T, T>: UnOp<T, T>,
+    FloorOp<T, T>: UnOp<T, T>,
+    CeilOp<T, T>: UnOp<T, T>,
+    RoundOp<T, T>: UnOp<T, T>,
+    TruncOp<T, T>: UnOp<T, T>,
+{
+    use ValUnOpCode::*;
+    match op {
+        Neg => <NegOp<T, T>>::SUPPORTED,
+        BitNot => <NotOp<T, T>>::SUPPORTED,
+        Abs => <AbsOp<T, T>>::SUPPORTED,
+        Ln => <LnOp<T, T>>::SUPPORTED,
+        Exp => <ExpOp<T, T>>::SUPPORTED,
+        Sqrt => <SqrtOp<T, T>>::SUPPORTED,
+        Sin => <SinOp<T, T>>::SUPPORTED,
+        Cos => <CosOp<T, T>>::SUPPORTED,
+        Signum => <SignumOp<T, T>>::SUPPORTED,
+        PopCount => <PopCountOp<T, T>>::SUPPORTED,
+        LeadingZeros => <LeadingZerosOp<T, T>>::SUPPORTED,
+        TrailingZeros => <TrailingZerosOp<T, T>>::SUPPORTED,
+        BitReverse => <BitReverseOp<T, T>>::SUPPORTED,
+        ByteSwap => <ByteSwapOp<T, T>>::SUPPORTED,
+        Tan => <TanOp<T, T>>::SUPPORTED,
+        Asin => <AsinOp<T, T>>::SUPPORTED,
+        Acos => <AcosOp<T, T>>::SUPPORTED,
+        Atan => <AtanOp<T, T>>::SUPPORTED,
+        Sinh => <SinhOp<T, T>>::SUPPORTED,
+        Cosh => <CoshOp<T, T>>::SUPPORTED,
+        Tanh => <TanhOp<T, T>>::SUPPORTED,
+        Log2 => <Log2Op<T, T>>::SUPPORTED,
+        Log10 => <Log10Op<T, T>>::SUPPORTED,
+        Log1p => <Log1pOp<T, T>>::SUPPORTED,
+        Expm1 => <Expm1Op<T, T>>::SUPPORTED,
+        Cbrt => <CbrtOp<T, T>>::SUPPORTED,
+        Floor => <FloorOp<T, T>>::SUPPORTED,
+        Ceil => <CeilOp<T, T>>::SUPPORTED,
+        Round => <RoundOp<T, T>>::SUPPORTED,
+        Trunc => <TruncOp<T, T>>::SUPPORTED,
+    }
+}
+
+pub(crate) fn bool_unop_supported(op: BoolUnOpCode, ty: ScalarTy) -> bool {
+    use ScalarTy::*;
+    match ty {
+        TBool => bool_unop_supported_static::<bool>(op),
+        TU8 => bool_unop_supported_static::<u8>(op),
+        TU16 => bool_unop_supported_static::<u16>(op),
+        TU32 => bool_unop_supported_static::<u32>(op),
+        TU64 => bool_unop_supported_static::<u64>(op),
+        TU128 => bool_unop_supported_static::<u128>(op),
+        TI8 => bool_unop_supported_static::<i8>(op),
+        TI16 => bool_unop_supported_static::<i16>(op),
+        TI32 => bool_unop_supported_static::<i32>(op),
+        TI64 => bool_unop_supported_static::<i64>(op),
+        TI128 => bool_unop_supported_static::<i128>(op),
+        TF32 => bool_unop_supported_static::<f32>(op),
+        TF64 => bool_unop_supported_static::<f64>(op),
+    }
+}
+
+fn bool_unop_supported_static<T>(op: BoolUnOpCode) -> bool
+where
+    T: ScalarT,
+    IsNaNOp<T, bool>: UnOp<T, bool>,
+    IsInfOp<T, bool>: UnOp<T, bool>,
+    IsFinOp<T, bool>: UnOp<T, bool>,
+{
+    use BoolUnOpCode::*;
+    match op {
+        IsNaN => <IsNaNOp<T, bool>>::SUPPORTED,
+        IsInf => <IsInfOp<T, bool>>::SUPPORTED,
+        IsFin => <IsFinOp<T, bool>>::SUPPORTED,
+    }
+}
+
+pub(crate) fn val_ternop_supported(op: ValTernOpCode, ty: ScalarTy) -> bool {
+    use ScalarTy::*;
+    match ty {
+        TBool => val_ternop_supported_static::<bool>(op),
+        TU8 => val_ternop_supported_static::<u8>(op),
+        TU16 => val_ternop_supported_static::<u16>(op),
+        TU32 => val_ternop_supported_static::<u32>(op),
+        TU64 => val_ternop_supported_static::<u64>(op),
+        TU128 => val_ternop_supported_static::<u128>(op),
+        TI8 => val_ternop_supported_static::<i8>(op),
+        TI16 => val_ternop_supported_static::<i16>(op),
+        TI32 => val_ternop_supported_static::<i32>(op),
+        TI64 => val_ternop_supported_static::<i64>(op),
+        TI128 => val_ternop_supported_static::<i128>(op),
+        TF32 => val_ternop_supported_static::<f32>(op),
+        TF64 => val_ternop_supported_static::<f64>(op),
+    }
+}
+
+fn val_ternop_supported_static<T>(op: ValTernOpCode) -> bool
+where
+    T: ScalarT,
+    MulAddOp<T, T>: TernOp<T, T>,
+{
+    use ValTernOpCode::*;
+    match op {
+        MulAdd => <MulAddOp<T, T>>::SUPPORTED,
+    }
+}
+
+pub(crate) fn conv_supported(src: ScalarTy, dst: ScalarTy) -> bool {
+    cast_supported(src, dst, CastMode::Wrapping)
+}
+
+pub(crate) fn cast_supported(src: ScalarTy, dst: ScalarTy, mode: CastMode) -> bool {
+    use ScalarTy::*;
+    match dst {
+        TBool => cast_supported_static::<bool>(src, mode),
+        TU8 => cast_supported_static::<u8>(src, mode),
+        TU16 => cast_supported_static::<u16>(src, mode),
+        TU32 => cast_supported_static::<u32>(src, mode),
+        TU64 => cast_supported_static::<u64>(src, mode),
+        TU128 => cast_supported_static::<u128>(src, mode),
+        TI8 => cast_supported_static::<i8>(src, mode),
+        TI16 => cast_supported_static::<i16>(src, mode),
+        TI32 => cast_supported_static::<i32>(src, mode),
+        TI64 => cast_supported_static::<i64>(src, mode),
+        TI128 => cast_supported_static::<i128>(src, mode),
+        TF32 => cast_supported_static::<f32>(src, mode),
+        TF64 => cast_supported_static::<f64>(src, mode),
+    }
+}
+
+fn cast_supported_static<DstT>(src: ScalarTy, mode: CastMode) -> bool
+where
+    DstT: ScalarT,
+    ConvOp<bool, DstT>: UnOp<bool, DstT>,
+    ConvOp<u8, DstT>: UnOp<u8, DstT>,
+    ConvOp<u16, DstT>: UnOp<u16, DstT>,
+    ConvOp<u32, DstT>: UnOp<u32, DstT>,
+    ConvOp<u64, DstT>: UnOp<u64, DstT>,
+    ConvOp<u128, DstT>: UnOp<u128, DstT>,
+    ConvOp<i8, DstT>: UnOp<i8, DstT>,
+    ConvOp<i16, DstT>: UnOp<i16, DstT>,
+    ConvOp<i32, DstT>: UnOp<i32, DstT>,
+    ConvOp<i64, DstT>: UnOp<i64, DstT>,
+    ConvOp<i128, DstT>: UnOp<i128, DstT>,
+    ConvOp<f32, DstT>: UnOp<f32, DstT>,
+    ConvOp<f64, DstT>: UnOp<f64, DstT>,
+    SatConvOp<bool, DstT>: UnOp<bool, DstT>,
+    SatConvOp<u8, DstT>: UnOp<u8, DstT>,
+    SatConvOp<u16, DstT>: UnOp<u16, DstT>,
+    SatConvOp<u32, DstT>: UnOp<u32, DstT>,
+    SatConvOp<u64, DstT>: UnOp<u64, DstT>,
+    SatConvOp<u128, DstT>: UnOp<u128, DstT>,
+    SatConvOp<i8, DstT>: UnOp<i8, DstT>,
+    SatConvOp<i16, DstT>: UnOp<i16, DstT>,
+    SatConvOp<i32, DstT>: UnOp<i32, DstT>,
+    SatConvOp<i64, DstT>: UnOp<i64, DstT>,
+    SatConvOp<i128, DstT>: UnOp<i128, DstT>,
+    SatConvOp<f32, DstT>: UnOp<f32, DstT>,
+    SatConvOp<f64, DstT>: UnOp<f64, DstT>,
+    CheckedConvOp<bool, DstT>: UnOp<bool, DstT>,
+    CheckedConvOp<u8, DstT>: UnOp<u8, DstT>,
+    CheckedConvOp<u16, DstT>: UnOp<u16, DstT>,
+    CheckedConvOp<u32, DstT>: UnOp<u32, DstT>,
+    CheckedConvOp<u64, DstT>: UnOp<u64, DstT>,
+    CheckedConvOp<u128, DstT>: UnOp<u128, DstT>,
+    CheckedConvOp<i8, DstT>: UnOp<i8, DstT>,
+    CheckedConvOp<i16, DstT>: UnOp<i16, DstT>,
+    CheckedConvOp<i32, DstT>: UnOp<i32, DstT>,
+    CheckedConvOp<i64, DstT>: UnOp<i64, DstT>,
+    CheckedConvOp<i128, DstT>: UnOp<i128, DstT>,
+    CheckedConvOp<f32, DstT>: UnOp<f32, DstT>,
+    CheckedConvOp<f64, DstT>: UnOp<f64, DstT>,
+{
+    use ScalarTy::*;
+    match mode {
+        CastMode::Wrapping => match src {
+            TBool => <ConvOp<bool, DstT>>::SUPPORTED,
+            TU8 => <ConvOp<u8, DstT>>::SUPPORTED,
+            TU16 => <ConvOp<u16, DstT>>::SUPPORTED,
+            TU32 => <ConvOp<u32, DstT>>::SUPPORTED,
+            TU64 => <ConvOp<u64, DstT>>::SUPPORTED,
+            TU128 => <ConvOp<u128, DstT>>::SUPPORTED,
+            TI8 => <ConvOp<i8, DstT>>::SUPPORTED,
+            TI16 => <ConvOp<i16, DstT>>::SUPPORTED,
+            TI32 => <ConvOp<i32, DstT>>::SUPPORTED,
+            TI64 => <ConvOp<i64, DstT>>::SUPPORTED,
+            TI128 => <ConvOp<i128, DstT>>::SUPPORTED,
+            TF32 => <ConvOp<f32, DstT>>::SUPPORTED,
+            TF64 => <ConvOp<f64, DstT>>::SUPPORTED,
+        },
+        CastMode::Saturating => match src {
+            TBool => <SatConvOp<bool, DstT>>::SUPPORTED,
+            TU8 => <SatConvOp<u8, DstT>>::SUPPORTED,
+            TU16 => <SatConvOp<u16, DstT>>::SUPPORTED,
+            TU32 => <SatConvOp<u32, DstT>>::SUPPORTED,
+            TU64 => <SatConvOp<u64, DstT>>::SUPPORTED,
+            TU128 => <SatConvOp<u128, DstT>>::SUPPORTED,
+            TI8 => <SatConvOp<i8, DstT>>::SUPPORTED,
+            TI16 => <SatConvOp<i16, DstT>>::SUPPORTED,
+            TI32 => <SatConvOp<i32, DstT>>::SUPPORTED,
+            TI64 => <SatConvOp<i64, DstT>>::SUPPORTED,
+            TI128 => <SatConvOp<i128, DstT>>::SUPPORTED,
+            TF32 => <SatConvOp<f32, DstT>>::SUPPORTED,
+            TF64 => <SatConvOp<f64, DstT>>::SUPPORTED,
+        },
+        CastMode::Checked => match src {
+            TBool => <CheckedConvOp<bool, DstT>>::SUPPORTED,
+            TU8 => <CheckedConvOp<u8, DstT>>::SUPPORTED,
+            TU16 => <CheckedConvOp<u16, DstT>>::SUPPORTED,
+            TU32 => <CheckedConvOp<u32, DstT>>::SUPPORTED,
+            TU64 => <CheckedConvOp<u64, DstT>>::SUPPORTED,
+            TU128 => <CheckedConvOp<u128, DstT>>::SUPPORTED,
+            TI8 => <CheckedConvOp<i8, DstT>>::SUPPORTED,
+            TI16 => <CheckedConvOp<i16, DstT>>::SUPPORTED,
+            TI32 => <CheckedConvOp<i32, DstT>>::SUPPORTED,
+            TI64 => <CheckedConvOp<i64, DstT>>::SUPPORTED,
+            TI128 => <CheckedConvOp<i128, DstT>>::SUPPORTED,
+            TF32 => <CheckedConvOp<f32, DstT>>::SUPPORTED,
+            TF64 => <CheckedConvOp<f64, DstT>>::SUPPORTED,
+        },
+    }
+}
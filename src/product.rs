@@ -0,0 +1,424 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Products: operations combining every element of one slice with every
+// element of another.
+//
+// The outer product of an `m`-element `lhs` and an `n`-element `rhs` is the
+// `m` by `n` matrix whose row `i` is `lhs[i] op rhs`: one const-slice
+// operation per row, each writing straight into its row of `out`. `rhs` is
+// converted to the operator's type once, up front, so the rows need no
+// buffers of their own and can run in parallel, grouped into chunks.
+//
+// The inner product of an `m` by `k` matrix and a `k` by `n` one is built the
+// same way, a row of the result at a time: row `i` is the reduction over `p`
+// of `lhs[i, p] combine rhs[p, ..]`, again const-slice operations on rows of
+// `rhs`. For `+.×` on numbers there's a dedicated kernel instead, which
+// accumulates each row of the result in blocks of `rhs` that stay in cache.
+// Both reduce from the last `p` to the first, as APL does, so the kernel's
+// floating-point results are exactly those of the general path.
+
+use std::sync::Mutex;
+
+use crate::consts::*;
+use crate::cpu::SimdLevel;
+use crate::eval::*;
+use crate::ops::*;
+use crate::operands::*;
+use crate::plan::*;
+use crate::promotion::PromotionPolicy;
+use crate::scalarty::ScalarTy;
+use crate::sched::{Parallelism, Sched};
+use crate::shaped::*;
+use crate::structural::dense_elements;
+use crate::traits::ScalarT;
+
+// The settings of an `EvalCtx`, for making serial ones over the buffers of
+// a row on whichever thread runs it: the rows are what's spread across
+// threads.
+#[derive(Clone, Copy)]
+struct RowSettings<'p> {
+    chunk_bytes: usize,
+    simd_level: SimdLevel,
+    promotion: &'p dyn PromotionPolicy,
+}
+
+impl<'p> RowSettings<'p> {
+    fn of(ctx: &EvalCtx<'p>) -> RowSettings<'p> {
+        RowSettings {
+            chunk_bytes: ctx.chunk_bytes,
+            simd_level: ctx.simd_level,
+            promotion: ctx.promotion,
+        }
+    }
+
+    fn ctx<'b>(&self, out: &'b mut [u8]) -> EvalCtx<'b>
+    where 'p: 'b
+    {
+        EvalCtx::new(&mut [], &mut [], out)
+            .with_chunk_bytes(self.chunk_bytes)
+            .with_parallelism(Parallelism::Serial)
+            .with_simd_level(self.simd_level)
+            .with_promotion(self.promotion)
+    }
+}
+
+// Returns `Ok` if `res` is an `n`-element slice, as the row operations
+// should produce.
+fn check_row(res: Operand, n: usize) -> Result<(), EvalError> {
+    match res {
+        Operand::OperandSlice(s) if s.len() == n => Ok(()),
+        _ => Err(EvalError::UnsupportedOp),
+    }
+}
+
+/// The outer products return an `m` by `n` dense row-major `ShapedOperand` in
+/// `out`, where `m` and `n` are the lengths of `lhs` and `rhs`. If `rhs` needs
+/// converting to the operator's type, that happens once, in `tmp2`; `tmp1`
+/// isn't used. The `plan_*` functions give the sizes for a const `lhs` and
+/// `rhs`, but `out` needs `m` times as much.
+impl<'eval> EvalCtx<'eval> {
+    /// APL's `∘.f` for a `ValBinOpCode`: `out[i*n + j] = lhs[i] op rhs[j]`.
+    pub fn outer_val_binop<'slice>(self, op: ValBinOpCode,
+                                   lhs: &Slice<'slice>,
+                                   rhs: &Slice<'slice>)
+                                   -> Result<ShapedOperand<'eval>, EvalError>
+    where 'slice: 'eval
+    {
+        let plan = plan_val_binop(self.promotion, op.clone(),
+                                  OperandDesc::ConstDesc(lhs.get_scalar_ty()),
+                                  OperandDesc::SliceDesc(rhs.get_scalar_ty(), rhs.len()))?;
+        let ty = val_binop_ty(self.promotion, &op, lhs.get_scalar_ty(), rhs.get_scalar_ty())?;
+        self.outer(plan, ty, lhs, rhs, |ctx, l, r| ctx.val_binop(op.clone(), l, r))
+    }
+
+    /// APL's `∘.f` for a `BoolBinOpCode`: `out[i*n + j] = lhs[i] op rhs[j]`.
+    pub fn outer_bool_binop<'slice>(self, op: BoolBinOpCode,
+                                    lhs: &Slice<'slice>,
+                                    rhs: &Slice<'slice>)
+                                    -> Result<ShapedOperand<'eval>, EvalError>
+    where 'slice: 'eval
+    {
+        let (lty, rty) = (lhs.get_scalar_ty(), rhs.get_scalar_ty());
+        let plan = plan_bool_binop(self.promotion, op.clone(),
+                                   OperandDesc::ConstDesc(lty),
+                                   OperandDesc::SliceDesc(rty, rhs.len()))?;
+        // Operands compared exactly aren't converted, or promoted at all.
+        let ty = if compares_exactly(op.clone(), lty, rty) {
+            rty
+        } else {
+            self.promotion.join(lty, rty)?
+        };
+        self.outer(plan, ty, lhs, rhs, |ctx, l, r| ctx.bool_binop(op.clone(), l, r))
+    }
+
+    // Runs `f` on each element of `lhs` and all of `rhs`, as planned by
+    // `plan` for a const `lhs`, where `ty` is the operator's type.
+    fn outer<'slice, F>(mut self, plan: EvalPlan, ty: ScalarTy,
+                        lhs: &Slice<'slice>, rhs: &Slice<'slice>, f: F)
+                        -> Result<ShapedOperand<'eval>, EvalError>
+    where
+        'slice: 'eval,
+        F: Sync + for<'r> Fn(EvalCtx<'r>, &Operand<'r>, &Operand<'r>)
+                             -> Result<Operand<'r>, EvalError>,
+    {
+        let (m, n) = (lhs.len(), rhs.len());
+        let res_ty = plan.result.get_scalar_ty();
+        let tmp2 = std::mem::take(&mut self.tmp2);
+        let out = std::mem::take(&mut self.out);
+        let rhs = if plan.tmp2 > 0 {
+            let rhs = Operand::OperandSlice(rhs.clone());
+            match self.with_buffers(&mut [], &mut [], tmp2).conv(&rhs, ty)? {
+                Operand::OperandSlice(s) => s,
+                Operand::OperandConst(_) => return Err(EvalError::UnsupportedOp),
+            }
+        } else {
+            rhs.clone()
+        };
+        let rowbytes = n * res_ty.size_of();
+        if out.len() < m * rowbytes {
+            return Err(EvalError::BadBuffer);
+        }
+        if m > 0 && n > 0 {
+            let settings = RowSettings::of(&self);
+            let failed = Mutex::new(None);
+            let (elemsz, dst) = (res_ty.size_of(), &mut out[..m * rowbytes]);
+            self.sched().for_each_byte_row_chunk(elemsz, rowbytes, dst, &|i0, rows| {
+                for (i, row) in (i0..).zip(rows.chunks_mut(rowbytes)) {
+                    let x = Operand::OperandConst(lhs.get(i).expect("row out of range"));
+                    let res = f(settings.ctx(row), &x, &Operand::OperandSlice(rhs.clone()));
+                    if let Err(e) = res.and_then(|res| check_row(res, n)) {
+                        failed.lock().unwrap().get_or_insert(e);
+                        return;
+                    }
+                }
+            });
+            if let Some(e) = failed.into_inner().unwrap() {
+                return Err(e);
+            }
+        }
+        ShapedOperand::new(slice_of_bytes(res_ty, out, m * n)?, vec![m, n])
+    }
+}
+
+// The element types `+.×` has a dedicated kernel for: integers, which wrap
+// as `Add` and `Mul` do, and floats.
+trait MulAcc: ScalarT + ScalarOf + Copy + Send + Sync {
+    // The starting value of a sum, which adding anything to leaves alone.
+    const ZERO: Self;
+    // The value of a sum of nothing.
+    const EMPTY_SUM: Self;
+    // Returns `a * b + acc`.
+    fn mul_acc(a: Self, b: Self, acc: Self) -> Self;
+}
+
+macro_rules! impl_mul_acc_int {
+    ($($T:ty)*) => {
+        $(impl MulAcc for $T {
+            const ZERO: Self = 0;
+            const EMPTY_SUM: Self = 0;
+            #[inline(always)]
+            fn mul_acc(a: Self, b: Self, acc: Self) -> Self {
+                a.wrapping_mul(b).wrapping_add(acc)
+            }
+        })*
+    }
+}
+
+// Adding -0.0 leaves even -0.0 alone, where 0.0 wouldn't; but with nothing
+// added, the sum is the usual 0.0. The multiply and add are separate, not
+// fused, as they are for `Mul` then `Add`.
+macro_rules! impl_mul_acc_float {
+    ($($T:ty)*) => {
+        $(impl MulAcc for $T {
+            const ZERO: Self = -0.0;
+            const EMPTY_SUM: Self = 0.0;
+            #[inline(always)]
+            fn mul_acc(a: Self, b: Self, acc: Self) -> Self {
+                a * b + acc
+            }
+        })*
+    }
+}
+
+impl_mul_acc_int!(u8 u16 u32 u64 u128 i8 i16 i32 i64 i128);
+impl_mul_acc_float!(f32 f64);
+
+// Sets `c`, some whole rows of an `m` by `n` matrix, to the rows of `a` (the
+// same rows of the `m` by `k` left-hand matrix) times the `k` by `n` matrix
+// `b`. The result is computed a block of `b` at a time, with the blocks down
+// the `k` axis taken last to first and each block's rows last to first, so
+// each element sums its products from `p = k - 1` down to 0. The innermost
+// loop runs along contiguous rows of `b` and `c`, which vectorizes.
+#[inline(always)]
+fn matmul_rows<T: MulAcc>(a: &[T], b: &[T], c: &mut [T], k: usize, n: usize) {
+    let zero = if k == 0 { T::EMPTY_SUM } else { T::ZERO };
+    c.iter_mut().for_each(|x| *x = zero);
+    let rows = c.len() / n;
+    for jb in (0..n).step_by(matmul_cols::<T>()) {
+        let je = n.min(jb + matmul_cols::<T>());
+        for kb in (0..k).step_by(MATMUL_DEPTH).rev() {
+            let ke = k.min(kb + MATMUL_DEPTH);
+            for i in 0..rows {
+                let crow = &mut c[i * n + jb..i * n + je];
+                for p in (kb..ke).rev() {
+                    let x = a[i * k + p];
+                    let brow = &b[p * n + jb..p * n + je];
+                    for (cj, &bj) in crow.iter_mut().zip(brow.iter()) {
+                        *cj = T::mul_acc(x, bj, *cj);
+                    }
+                }
+            }
+        }
+    }
+}
+
+type MatmulFn<T> = unsafe fn(&[T], &[T], &mut [T], usize, usize);
+
+simd_versions! {
+    fn select_matmul[T: MulAcc][T]
+        (a: &[T], b: &[T], c: &mut [T], k: usize, n: usize) -> MatmulFn<T>
+        where [] = matmul_rows::<T>;
+}
+
+fn matmul_typed<T: MulAcc>(sched: &Sched, a: &Slice, b: &Slice, out: &mut [u8],
+                           m: usize, k: usize, n: usize) -> Result<(), EvalError> {
+    let a = T::of_slice(a).ok_or(EvalError::BadBuffer)?;
+    let b = T::of_slice(b).ok_or(EvalError::BadBuffer)?;
+    let c: &mut [T] = transmute_buf_mut(out)?;
+    if c.len() < m * n {
+        return Err(EvalError::BadBuffer);
+    }
+    let kernel = select_matmul::<T>(sched.simd_level());
+    sched.for_each_row_chunk(n, &mut c[..m * n], &|i0, rows| {
+        unsafe { kernel(&a[i0 * k..], b, rows, k, n) }
+    });
+    Ok(())
+}
+
+// The `+.×` kernel over `a` and `b`, both already of type `ty`.
+#[allow(clippy::too_many_arguments)]
+fn matmul(sched: &Sched, ty: ScalarTy, a: &Slice, b: &Slice, out: &mut [u8],
+          m: usize, k: usize, n: usize) -> Result<(), EvalError> {
+    use ScalarTy::*;
+    match ty {
+        TBool => Err(EvalError::UnsupportedOp),
+        TU8 => matmul_typed::<u8>(sched, a, b, out, m, k, n),
+        TU16 => matmul_typed::<u16>(sched, a, b, out, m, k, n),
+        TU32 => matmul_typed::<u32>(sched, a, b, out, m, k, n),
+        TU64 => matmul_typed::<u64>(sched, a, b, out, m, k, n),
+        TU128 => matmul_typed::<u128>(sched, a, b, out, m, k, n),
+        TI8 => matmul_typed::<i8>(sched, a, b, out, m, k, n),
+        TI16 => matmul_typed::<i16>(sched, a, b, out, m, k, n),
+        TI32 => matmul_typed::<i32>(sched, a, b, out, m, k, n),
+        TI64 => matmul_typed::<i64>(sched, a, b, out, m, k, n),
+        TI128 => matmul_typed::<i128>(sched, a, b, out, m, k, n),
+        TF32 => matmul_typed::<f32>(sched, a, b, out, m, k, n),
+        TF64 => matmul_typed::<f64>(sched, a, b, out, m, k, n),
+    }
+}
+
+// A general inner product of an `m` by `k` matrix `a` and a `k` by `n`
+// matrix `b`, both of type `ty`, computed a row of the result at a time.
+struct InnerProduct<'a, 'p> {
+    settings: RowSettings<'p>,
+    ty: ScalarTy,
+    reduce: ValBinOpCode,
+    combine: ValBinOpCode,
+    a: Slice<'a>,
+    b: Slice<'a>,
+    k: usize,
+    n: usize,
+}
+
+impl<'a, 'p> InnerProduct<'a, 'p> {
+    // Computes row `i` of the result into `row`. The accumulated row moves
+    // back and forth between `row` and `ybuf`, and each row of products is
+    // formed in `xbuf`.
+    fn row(&self, i: usize, row: &mut [u8], xbuf: &mut RowBuf, ybuf: &mut RowBuf)
+           -> Result<(), EvalError> {
+        let (k, n) = (self.k, self.n);
+        let a_at = |p: usize| Operand::OperandConst(self.a.get(i * k + p).expect("index out of range"));
+        let b_row = |p: usize| Operand::OperandSlice(self.b.split_at(p * n).1.split_at(n).0);
+        let res = self.settings.ctx(row).val_binop(self.combine.clone(), &a_at(k - 1), &b_row(k - 1))?;
+        check_row(res, n)?;
+        let y = ybuf.bytes(row.len());
+        let mut in_row = true;
+        for p in (0..k - 1).rev() {
+            let x = self.settings.ctx(xbuf.bytes(row.len()))
+                .val_binop(self.combine.clone(), &a_at(p), &b_row(p))?;
+            let (src, dst) = if in_row { (&mut *row, &mut *y) } else { (&mut *y, &mut *row) };
+            let acc = Operand::OperandSlice(slice_of_bytes(self.ty, src, n)?);
+            check_row(self.settings.ctx(dst).val_binop(self.reduce.clone(), &x, &acc)?, n)?;
+            in_row = !in_row;
+        }
+        if !in_row {
+            row.copy_from_slice(y);
+        }
+        Ok(())
+    }
+
+    fn run(&self, sched: &Sched, out: &mut [u8]) -> Result<(), EvalError> {
+        let rowbytes = self.n * self.ty.size_of();
+        let failed = Mutex::new(None);
+        sched.for_each_byte_row_chunk(self.ty.size_of(), rowbytes, out, &|i0, rows| {
+            let (mut xbuf, mut ybuf) = (RowBuf::default(), RowBuf::default());
+            for (i, row) in (i0..).zip(rows.chunks_mut(rowbytes)) {
+                if let Err(e) = self.row(i, row, &mut xbuf, &mut ybuf) {
+                    failed.lock().unwrap().get_or_insert(e);
+                    return;
+                }
+            }
+        });
+        match failed.into_inner().unwrap() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The inner products take `lhs` of shape `[..a, k]` and `rhs` of shape
+/// `[k, ..b]`, and return a dense row-major `ShapedOperand` of shape
+/// `[..a, ..b]` in `out`. Other shapes are an `EvalError::ShapeMismatch`.
+/// The operands are promoted to a common type, converted in `tmp1` and
+/// `tmp2` if they need to be; strided ones are first copied to internal
+/// buffers, row-major. Rows of the result are spread across threads.
+impl<'eval> EvalCtx<'eval> {
+    /// APL's `reduce.combine`: element `[i.., j..]` of the result is the
+    /// reduction with `reduce` of `lhs[i.., p] combine rhs[p, j..]` over all
+    /// `p`, from right to left, as APL reduces. With no `p` to reduce over,
+    /// only `+.×` has a result (of zeroes); the rest are
+    /// `EvalError::UnsupportedOp`.
+    pub fn inner_product<'slice>(mut self, reduce: ValBinOpCode, combine: ValBinOpCode,
+                                 lhs: &ShapedOperand<'slice>,
+                                 rhs: &ShapedOperand<'slice>)
+                                 -> Result<ShapedOperand<'eval>, EvalError>
+    where 'slice: 'eval
+    {
+        use ValBinOpCode::*;
+        let (ls, rs) = (lhs.shape(), rhs.shape());
+        if ls.is_empty() || rs.is_empty() || ls[ls.len() - 1] != rs[0] {
+            return Err(EvalError::ShapeMismatch(ls.to_vec(), rs.to_vec()));
+        }
+        let (lead, k, trail) = (&ls[..ls.len() - 1], rs[0], &rs[1..]);
+        let (m, n) = (lead.iter().product::<usize>(), trail.iter().product::<usize>());
+        let shape: Vec<usize> = lead.iter().chain(trail.iter()).cloned().collect();
+        let ty = self.promotion.join(lhs.get_scalar_ty(), rhs.get_scalar_ty())?;
+        let fast = reduce == Add && combine == Mul && ty != ScalarTy::TBool
+            && val_binop_supported(Add, ty) && val_binop_supported(Mul, ty);
+        let supported = fast ||
+            (val_binop_supported(reduce.clone(), ty) && val_binop_supported(combine.clone(), ty));
+        if !supported || (!fast && k == 0 && m * n > 0) {
+            return Err(EvalError::UnsupportedOp);
+        }
+        let (sched, settings) = (self.sched(), RowSettings::of(&self));
+        let tmp1 = std::mem::take(&mut self.tmp1);
+        let tmp2 = std::mem::take(&mut self.tmp2);
+        let out = std::mem::take(&mut self.out);
+        let (mut lbuf, mut rbuf) = (RowBuf::default(), RowBuf::default());
+        let a = self.conv_dense(dense_elements(lhs, &mut lbuf)?, ty, tmp1)?;
+        let b = self.conv_dense(dense_elements(rhs, &mut rbuf)?, ty, tmp2)?;
+        let rowbytes = n * ty.size_of();
+        if out.len() < m * rowbytes {
+            return Err(EvalError::BadBuffer);
+        }
+        if m * n > 0 {
+            if fast {
+                matmul(&sched, ty, &a, &b, out, m, k, n)?;
+            } else {
+                let ip = InnerProduct { settings, ty, reduce, combine, a, b, k, n };
+                ip.run(&sched, &mut out[..m * rowbytes])?;
+            }
+        }
+        ShapedOperand::new(slice_of_bytes(ty, out, m * n)?, shape)
+    }
+
+    /// APL's `+.×`: the matrix product of `lhs` and `rhs`, or the
+    /// matrix-vector or vector-matrix product if one is a vector.
+    pub fn matmul<'slice>(self, lhs: &ShapedOperand<'slice>, rhs: &ShapedOperand<'slice>)
+                          -> Result<ShapedOperand<'eval>, EvalError>
+    where 'slice: 'eval
+    {
+        self.inner_product(ValBinOpCode::Add, ValBinOpCode::Mul, lhs, rhs)
+    }
+
+    /// The dot product of two slices of the same length: the sum of the
+    /// products of their elements, from the last to the first.
+    pub fn dot<'slice>(self, lhs: &Slice<'slice>, rhs: &Slice<'slice>) -> Result<Const, EvalError>
+    where 'slice: 'eval
+    {
+        let res = self.matmul(&lhs.clone().into(), &rhs.clone().into())?;
+        res.as_slice().and_then(|s| s.get(0)).ok_or(EvalError::BadBuffer)
+    }
+
+    // Converts `s` to type `ty` in `buf`, if it isn't of that type already.
+    fn conv_dense<'b>(&self, s: Slice<'b>, ty: ScalarTy, buf: &'b mut [u8])
+                      -> Result<Slice<'b>, EvalError>
+    where 'eval: 'b
+    {
+        match self.with_buffers(&mut [], &mut [], buf).conv(&Operand::OperandSlice(s), ty)? {
+            Operand::OperandSlice(s) => Ok(s),
+            Operand::OperandConst(_) => Err(EvalError::UnsupportedOp),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Type promotion for operators with several operands, which are all converted
+// to one common type before the operator runs. Which type that is, and which
+// mixtures of types are allowed at all, is a matter of the front-end's type
+// system, so it's a policy the `EvalCtx` is given.
+
+use crate::eval::EvalError;
+use crate::scalarty::ScalarTy;
+
+/// Decides the common type that the operands of a binary or ternary operator
+/// are promoted to. Ternary operators join their first two operand types, then
+/// that with the third.
+pub trait PromotionPolicy: Sync {
+    /// Returns the type to promote operands of types `a` and `b` to, or an
+    /// error if they may not be mixed.
+    fn join(&self, a: ScalarTy, b: ScalarTy) -> Result<ScalarTy, EvalError>;
+}
+
+/// The default policy, `ScalarTy::join`. It accepts every pair of types, at
+/// the cost of some joins being lossy: eg. `u128` with `i128`, or `f32` with
+/// `i32`, both of which can lose precision.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultPromotion;
+
+impl PromotionPolicy for DefaultPromotion {
+    fn join(&self, a: ScalarTy, b: ScalarTy) -> Result<ScalarTy, EvalError> {
+        Ok(a.join(b))
+    }
+}
+
+/// Like `DefaultPromotion`, but refuses joins that can't represent every
+/// value of both types exactly, with `EvalError::LossyPromotion`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StrictPromotion;
+
+impl PromotionPolicy for StrictPromotion {
+    fn join(&self, a: ScalarTy, b: ScalarTy) -> Result<ScalarTy, EvalError> {
+        let j = a.join(b);
+        if j.can_represent(a) && j.can_represent(b) {
+            Ok(j)
+        } else {
+            Err(EvalError::LossyPromotion(a, b))
+        }
+    }
+}
@@ -0,0 +1,242 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Pseudo-random number generation.
+//
+// The generator is counter-based: element `i` of a stream is a hash of the
+// stream's key and `i`, with no state carried from one element to the next.
+// So each chunk of `out` can be filled in parallel, knowing only the index of
+// its first element, and the result is the same however it's chunked or
+// scheduled; and a long stream can be generated in pieces, from any offset.
+//
+// The hash is SplitMix64's: its output for counter `i` and key `k` is the
+// finalizer `mix64` of `k + (i + 1) * GAMMA`, which is exactly the `i`th
+// output of a SplitMix64 generator started at `k`. A seed gives two keys,
+// hashed from it so that nearby seeds give unrelated streams. Every element
+// takes 64 bits from the first key's stream, and elements that need more
+// (128-bit integers, normal floats) take another 64 from the second's.
+
+use crate::cpu::SimdLevel;
+use crate::eval::*;
+use crate::generate::out_typed;
+use crate::operands::*;
+use crate::ops::{ValBinOpCode, ValUnOpCode};
+use crate::plan::{val_binop_supported, val_unop_supported};
+use crate::scalarty::ScalarTy;
+use crate::sched::Sched;
+use crate::traits::ScalarT;
+
+const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+#[inline(always)]
+fn mix64(z: u64) -> u64 {
+    let z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// The two keys of the streams a seed gives.
+#[derive(Clone, Copy)]
+struct Key(u64, u64);
+
+impl Key {
+    fn new(seed: u64) -> Key {
+        let k = mix64(seed);
+        Key(k, mix64(k ^ GAMMA))
+    }
+
+    // Returns the `i`th 64 bits of the first stream.
+    #[inline(always)]
+    fn lo(self, i: u64) -> u64 {
+        mix64(self.0.wrapping_add(i.wrapping_add(1).wrapping_mul(GAMMA)))
+    }
+
+    // Returns the `i`th 64 bits of the second stream.
+    #[inline(always)]
+    fn hi(self, i: u64) -> u64 {
+        mix64(self.1.wrapping_add(i.wrapping_add(1).wrapping_mul(GAMMA)))
+    }
+}
+
+// Returns the top 53 bits of `x` as a double in [0, 1).
+#[inline(always)]
+fn unit_f64(x: u64) -> f64 {
+    (x >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+}
+
+// Element types there are uniform random values of: integers and booleans
+// over all their values, and floats in [0, 1).
+trait Uniform: ScalarT + Copy + Send + Sync {
+    fn uniform(key: Key, i: u64) -> Self;
+}
+
+// Floats, which also have standard normal random values.
+trait Normal: Uniform {
+    fn normal(key: Key, i: u64) -> Self;
+}
+
+macro_rules! impl_uniform_int {
+    ($($T:ty)*) => {
+        $(impl Uniform for $T {
+            #[inline(always)]
+            fn uniform(key: Key, i: u64) -> Self {
+                key.lo(i) as $T
+            }
+        })*
+    }
+}
+
+macro_rules! impl_uniform_int128 {
+    ($($T:ty)*) => {
+        $(impl Uniform for $T {
+            #[inline(always)]
+            fn uniform(key: Key, i: u64) -> Self {
+                (((key.hi(i) as u128) << 64) | key.lo(i) as u128) as $T
+            }
+        })*
+    }
+}
+
+impl_uniform_int!(u8 u16 u32 u64 i8 i16 i32 i64);
+impl_uniform_int128!(u128 i128);
+
+impl Uniform for bool {
+    #[inline(always)]
+    fn uniform(key: Key, i: u64) -> Self {
+        key.lo(i) >> 63 == 1
+    }
+}
+
+impl Uniform for f32 {
+    #[inline(always)]
+    fn uniform(key: Key, i: u64) -> Self {
+        (key.lo(i) >> 40) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+}
+
+impl Uniform for f64 {
+    #[inline(always)]
+    fn uniform(key: Key, i: u64) -> Self {
+        unit_f64(key.lo(i))
+    }
+}
+
+// The Box-Muller transform of a uniform value in (0, 1] from the first
+// stream and one in [0, 1) from the second, using only one of the pair of
+// normal values it gives, so that each element is still made independently.
+#[inline(always)]
+fn normal_f64(key: Key, i: u64) -> f64 {
+    let (u, v) = (1.0 - unit_f64(key.lo(i)), unit_f64(key.hi(i)));
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}
+
+impl Normal for f32 {
+    fn normal(key: Key, i: u64) -> Self {
+        normal_f64(key, i) as f32
+    }
+}
+
+impl Normal for f64 {
+    fn normal(key: Key, i: u64) -> Self {
+        normal_f64(key, i)
+    }
+}
+
+// Sets `dst` to the uniform values of the elements of `key`'s streams from
+// `first`. The loop has no dependences between elements, and vectorizes.
+#[inline(always)]
+fn fill_uniform<T: Uniform>(key: Key, first: u64, dst: &mut [T]) {
+    for (j, x) in dst.iter_mut().enumerate() {
+        *x = T::uniform(key, first.wrapping_add(j as u64));
+    }
+}
+
+type UniformFn<T> = unsafe fn(Key, u64, &mut [T]);
+
+simd_versions! {
+    fn select_uniform[T: Uniform][T]
+        (key: Key, first: u64, dst: &mut [T]) -> UniformFn<T>
+        where [] = fill_uniform::<T>;
+}
+
+fn uniform_typed<'a, T: Uniform + 'a>(sched: &Sched, key: Key, offset: u64, out: &'a mut [u8],
+                                      n: usize) -> Result<Slice<'a>, EvalError>
+where &'a [T]: Into<Slice<'a>>
+{
+    let dst = out_typed::<T>(out, n)?;
+    let kernel = select_uniform::<T>(sched.simd_level());
+    sched.for_each_row_chunk(1, dst, &|i0, chunk| {
+        unsafe { kernel(key, offset.wrapping_add(i0 as u64), chunk) }
+    });
+    Ok((&*dst).into())
+}
+
+fn normal_typed<'a, T: Normal + 'a>(sched: &Sched, key: Key, offset: u64, out: &'a mut [u8],
+                                    n: usize) -> Result<Slice<'a>, EvalError>
+where &'a [T]: Into<Slice<'a>>
+{
+    let dst = out_typed::<T>(out, n)?;
+    sched.for_each_row_chunk(1, dst, &|i0, chunk| {
+        let first = offset.wrapping_add(i0 as u64);
+        for (j, x) in chunk.iter_mut().enumerate() {
+            *x = T::normal(key, first.wrapping_add(j as u64));
+        }
+    });
+    Ok((&*dst).into())
+}
+
+/// The random generators fill the first `n` elements of `out` with
+/// pseudo-random values of type `ty`, and return them as a `Slice`; `tmp1`
+/// and `tmp2` aren't used. Values come from the stream given by `seed`,
+/// starting at element `offset`: each depends only on the seed and its
+/// position in the stream, so the result is the same whatever the chunk size,
+/// parallelism or SIMD level, and filling `n` elements from `offset` gives
+/// the same values as elements `offset..offset + n` of a longer fill. The
+/// generator is fast and statistically sound, but not cryptographically
+/// secure. Types that aren't compiled in are `EvalError::UnsupportedOp`.
+impl<'eval> EvalCtx<'eval> {
+    /// Uniformly distributed values: over all values of integer and boolean
+    /// types, and in [0, 1) for floats.
+    pub fn random_uniform(self, ty: ScalarTy, n: usize, seed: u64, offset: u64)
+                          -> Result<Slice<'eval>, EvalError> {
+        use ScalarTy::*;
+        if ty != TBool && !val_binop_supported(ValBinOpCode::Add, ty) {
+            return Err(EvalError::UnsupportedOp);
+        }
+        let (sched, key) = (self.sched(), Key::new(seed));
+        let out = self.out;
+        match ty {
+            TBool => uniform_typed::<bool>(&sched, key, offset, out, n),
+            TU8 => uniform_typed::<u8>(&sched, key, offset, out, n),
+            TU16 => uniform_typed::<u16>(&sched, key, offset, out, n),
+            TU32 => uniform_typed::<u32>(&sched, key, offset, out, n),
+            TU64 => uniform_typed::<u64>(&sched, key, offset, out, n),
+            TU128 => uniform_typed::<u128>(&sched, key, offset, out, n),
+            TI8 => uniform_typed::<i8>(&sched, key, offset, out, n),
+            TI16 => uniform_typed::<i16>(&sched, key, offset, out, n),
+            TI32 => uniform_typed::<i32>(&sched, key, offset, out, n),
+            TI64 => uniform_typed::<i64>(&sched, key, offset, out, n),
+            TI128 => uniform_typed::<i128>(&sched, key, offset, out, n),
+            TF32 => uniform_typed::<f32>(&sched, key, offset, out, n),
+            TF64 => uniform_typed::<f64>(&sched, key, offset, out, n),
+        }
+    }
+
+    /// Standard normally distributed floats, with mean 0 and standard
+    /// deviation 1. They need the `transcendental` feature; other types are
+    /// `EvalError::UnsupportedOp`.
+    pub fn random_normal(self, ty: ScalarTy, n: usize, seed: u64, offset: u64)
+                         -> Result<Slice<'eval>, EvalError> {
+        use ScalarTy::*;
+        if !val_unop_supported(ValUnOpCode::Ln, ty) || !val_unop_supported(ValUnOpCode::Cos, ty) {
+            return Err(EvalError::UnsupportedOp);
+        }
+        let (sched, key) = (self.sched(), Key::new(seed));
+        let out = self.out;
+        match ty {
+            TF32 => normal_typed::<f32>(&sched, key, offset, out, n),
+            TF64 => normal_typed::<f64>(&sched, key, offset, out, n),
+            _ => Err(EvalError::UnsupportedOp),
+        }
+    }
+}
@@ -7,6 +7,7 @@
 // dynamically inspected in order to select the operation body and target type
 /// for any evaluation step.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ScalarTy {
     TBool,
     TU8,
@@ -25,9 +26,16 @@ pub enum ScalarTy {
 
 impl ScalarTy {
 
+    /// Every `ScalarTy`.
+    pub const ALL: [ScalarTy; 13] = {
+        use ScalarTy::*;
+        [TBool, TU8, TU16, TU32, TU64, TU128, TI8, TI16, TI32, TI64, TI128, TF32, TF64]
+    };
+
     /// Returns the ScalarTy that's the join of `self` and `other`: should be
     /// used to decide the type to promote `self` and `other` to when used as
-    /// the two types of a binary operator.
+    /// the two types of a binary operator. This is the `DefaultPromotion`
+    /// policy; see `PromotionPolicy` for others.
     pub fn join(self, other: Self) -> Self {
         use ScalarTy::*;
         match (self, other) {
@@ -71,6 +79,16 @@ impl ScalarTy {
             (TU64, TI64) => TI128,
             (TU64, TI128) => TI128,
             (TU128, TI128) => TF64,
+            (TU16, TI8) => TI32,
+            (TU32, TI8) => TI64,
+            (TU32, TI16) => TI64,
+            (TU64, TI8) => TI128,
+            (TU64, TI16) => TI128,
+            (TU64, TI32) => TI128,
+            (TU128, TI8) => TF64,
+            (TU128, TI16) => TF64,
+            (TU128, TI32) => TF64,
+            (TU128, TI64) => TF64,
 
             // float
             (TF32, TU8) => TF32,
@@ -86,8 +104,86 @@ impl ScalarTy {
 
             (TF64, _) => TF64,
 
+            // bool is 0 or 1, so fits in anything.
+            (TBool, x) => x,
+
             (a, b) if a == b => a,
             (x, y) => y.join(x),
         }
     }
+
+    /// Returns the size in bytes of a value of this type.
+    pub fn size_of(self) -> usize {
+        use std::mem::size_of;
+        use ScalarTy::*;
+        match self {
+            TBool => size_of::<bool>(),
+            TU8 => size_of::<u8>(),
+            TU16 => size_of::<u16>(),
+            TU32 => size_of::<u32>(),
+            TU64 => size_of::<u64>(),
+            TU128 => size_of::<u128>(),
+            TI8 => size_of::<i8>(),
+            TI16 => size_of::<i16>(),
+            TI32 => size_of::<i32>(),
+            TI64 => size_of::<i64>(),
+            TI128 => size_of::<i128>(),
+            TF32 => size_of::<f32>(),
+            TF64 => size_of::<f64>(),
+        }
+    }
+
+    /// Returns the alignment in bytes of a value of this type.
+    pub fn align_of(self) -> usize {
+        use std::mem::align_of;
+        use ScalarTy::*;
+        match self {
+            TBool => align_of::<bool>(),
+            TU8 => align_of::<u8>(),
+            TU16 => align_of::<u16>(),
+            TU32 => align_of::<u32>(),
+            TU64 => align_of::<u64>(),
+            TU128 => align_of::<u128>(),
+            TI8 => align_of::<i8>(),
+            TI16 => align_of::<i16>(),
+            TI32 => align_of::<i32>(),
+            TI64 => align_of::<i64>(),
+            TI128 => align_of::<i128>(),
+            TF32 => align_of::<f32>(),
+            TF64 => align_of::<f64>(),
+        }
+    }
+
+    /// Returns whether every value of type `other` converts exactly to `self`.
+    /// `bool` converts to 0 or 1, but only `bool` itself converts to `bool`.
+    pub fn can_represent(self, other: Self) -> bool {
+        use ScalarTy::*;
+        // The number of bits of magnitude, and whether there's a sign; for
+        // floating point, the bits of mantissa, including the implicit one.
+        fn bits(ty: ScalarTy) -> (u32, bool) {
+            match ty {
+                TBool => (1, false),
+                TU8 => (8, false),
+                TU16 => (16, false),
+                TU32 => (32, false),
+                TU64 => (64, false),
+                TU128 => (128, false),
+                TI8 => (7, true),
+                TI16 => (15, true),
+                TI32 => (31, true),
+                TI64 => (63, true),
+                TI128 => (127, true),
+                TF32 => (24, true),
+                TF64 => (53, true),
+            }
+        }
+        let ((sbits, ssigned), (obits, osigned)) = (bits(self), bits(other));
+        match (self, other) {
+            _ if self == other => true,
+            (TBool, _) => false,
+            (TF64, TF32) => true,
+            (_, TF32) | (_, TF64) => false,
+            _ => sbits >= obits && (ssigned || !osigned),
+        }
+    }
 }
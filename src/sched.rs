@@ -0,0 +1,343 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::consts::*;
+use crate::cpu::SimdLevel;
+use crate::ops::*;
+use crate::traits::*;
+
+/// How an operation's chunks are spread across threads.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Parallelism {
+    /// Run every chunk on the calling thread.
+    Serial,
+    /// Spread chunks across the thread pool.
+    #[default]
+    Parallel,
+    /// Spread chunks across the thread pool if the operation has more than the
+    /// given number of elements, otherwise run them on the calling thread.
+    /// Small operations finish faster than it takes to wake up other threads.
+    ParallelAbove(usize),
+}
+
+// The per-chunk functions that `Sched` runs over the chunks of an operation.
+pub type UnChunkFn<'f, S, D> = dyn Sync + Fn(&[S], &mut [D]) + 'f;
+pub type BinChunkFn<'f, L, R, D> = dyn Sync + Fn(&[L], &[R], &mut [D]) + 'f;
+pub type RowFn<'f, D> = dyn Sync + Fn(usize, &mut [D]) + 'f;
+
+// The runtime scheduling parameters an operation is run with, taken from the
+// `EvalCtx` and passed down through the operator traits to the skeletons.
+#[derive(Clone, Copy, Debug)]
+pub struct Sched<'pool> {
+    chunk_bytes: usize,
+    // Only consulted when there are threads to spread chunks across.
+    #[cfg_attr(not(feature = "rayon"), allow(dead_code))]
+    parallelism: Parallelism,
+    simd_level: SimdLevel,
+    #[cfg(feature = "rayon")]
+    pool: Option<&'pool rayon::ThreadPool>,
+    #[cfg(not(feature = "rayon"))]
+    _pool: std::marker::PhantomData<&'pool ()>,
+}
+
+impl<'pool> Sched<'pool> {
+    pub fn new(chunk_bytes: usize) -> Sched<'pool> {
+        Sched {
+            // Anything under a vector is meaningless; clamp rather than fail.
+            chunk_bytes: cmax(chunk_bytes, VECBYTES),
+            parallelism: Parallelism::Parallel,
+            simd_level: SimdLevel::detect(),
+            #[cfg(feature = "rayon")]
+            pool: None,
+            #[cfg(not(feature = "rayon"))]
+            _pool: std::marker::PhantomData,
+        }
+    }
+
+    pub fn with_parallelism(self, parallelism: Parallelism) -> Sched<'pool> {
+        Sched { parallelism, ..self }
+    }
+
+    // Limits kernels to `simd_level`; levels above what the CPU supports are
+    // clamped to what it does, since running those would be undefined.
+    pub fn with_simd_level(self, simd_level: SimdLevel) -> Sched<'pool> {
+        Sched { simd_level: simd_level.min(SimdLevel::detect()), ..self }
+    }
+
+    pub fn simd_level(&self) -> SimdLevel {
+        self.simd_level
+    }
+
+    #[cfg(feature = "rayon")]
+    pub fn with_pool(self, pool: Option<&'pool rayon::ThreadPool>) -> Sched<'pool> {
+        Sched { pool, ..self }
+    }
+
+    // Returns the number of elements in each chunk of an operation between `T`
+    // and `U` slices.
+    pub fn chunk_len<T, U>(&self) -> usize {
+        chunksz_min::<T, U>(self.chunk_bytes)
+    }
+
+    // Returns whether an operation over `len` elements should be spread
+    // across threads. Without the `rayon` feature, nothing is.
+    #[cfg(feature = "rayon")]
+    fn is_parallel(&self, len: usize) -> bool {
+        match self.parallelism {
+            Parallelism::Serial => false,
+            Parallelism::Parallel => true,
+            Parallelism::ParallelAbove(n) => len > n,
+        }
+    }
+
+    // Runs `f` over corresponding `chunksz`-element chunks of `src` and `dst`,
+    // which must be the same length.
+    #[inline(always)]
+    pub fn for_each_chunk1<S: Sync, D: Send>(&self, chunksz: usize,
+                                             src: &[S], dst: &mut [D],
+                                             f: &UnChunkFn<'_, S, D>)
+    {
+        assert_eq!(src.len(), dst.len());
+        #[cfg(feature = "rayon")]
+        {
+            if self.is_parallel(src.len()) {
+                let mut run = || {
+                    src.par_chunks(chunksz)
+                        .zip(dst.par_chunks_mut(chunksz))
+                        .for_each(|(srcchunk, dstchunk)| f(srcchunk, dstchunk))
+                };
+                match self.pool {
+                    Some(pool) => pool.install(run),
+                    None => run()
+                }
+                return;
+            }
+        }
+        src.chunks(chunksz)
+            .zip(dst.chunks_mut(chunksz))
+            .for_each(|(srcchunk, dstchunk)| f(srcchunk, dstchunk))
+    }
+
+    // Runs `f` over corresponding `chunksz`-element chunks of `lhs`, `rhs` and
+    // `dst`, which must all be the same length. `lhs` and `rhs` are usually of
+    // the same type, but needn't be.
+    #[inline(always)]
+    pub fn for_each_chunk2<L: Sync, R: Sync, D: Send>(&self, chunksz: usize,
+                                                      lhs: &[L], rhs: &[R], dst: &mut [D],
+                                                      f: &BinChunkFn<'_, L, R, D>)
+    {
+        let len = rhs.len();
+        assert_eq!(len, lhs.len());
+        assert_eq!(len, dst.len());
+        #[cfg(feature = "rayon")]
+        {
+            if self.is_parallel(len) {
+                let mut run = || {
+                    lhs.par_chunks(chunksz)
+                        .zip(rhs.par_chunks(chunksz))
+                        .zip(dst.par_chunks_mut(chunksz))
+                        .for_each(|((lhschunk, rhschunk), dstchunk)|
+                                  f(lhschunk, rhschunk, dstchunk))
+                };
+                match self.pool {
+                    Some(pool) => pool.install(run),
+                    None => run()
+                }
+                return;
+            }
+        }
+        lhs.chunks(chunksz)
+            .zip(rhs.chunks(chunksz))
+            .zip(dst.chunks_mut(chunksz))
+            .for_each(|((lhschunk, rhschunk), dstchunk)|
+                      f(lhschunk, rhschunk, dstchunk))
+    }
+
+    // Runs `f` over each `chunksz`-element chunk of `acc`, passing it a copy
+    // of the chunk as its input and the chunk itself as its output, so it can
+    // update `acc` in place. Each thread copies into one chunk-sized buffer,
+    // which it reuses for every chunk it runs.
+    #[inline(always)]
+    pub fn for_each_chunk_in_place<T: Copy + Send + Sync>(&self, chunksz: usize,
+                                                          acc: &mut [T],
+                                                          f: &UnChunkFn<'_, T, T>)
+    {
+        let run_chunk = |buf: &mut Vec<T>, accchunk: &mut [T]| {
+            buf.clear();
+            buf.extend_from_slice(accchunk);
+            f(buf, accchunk)
+        };
+        #[cfg(feature = "rayon")]
+        {
+            if self.is_parallel(acc.len()) {
+                let mut run = || {
+                    acc.par_chunks_mut(chunksz)
+                        .for_each_init(|| Vec::with_capacity(chunksz), run_chunk)
+                };
+                match self.pool {
+                    Some(pool) => pool.install(run),
+                    None => run()
+                }
+                return;
+            }
+        }
+        let mut buf = Vec::with_capacity(chunksz);
+        acc.chunks_mut(chunksz)
+            .for_each(|accchunk| run_chunk(&mut buf, accchunk))
+    }
+
+    // As `for_each_chunk_in_place`, but with a second input `rhs`, which must
+    // be the same length as `acc`.
+    #[inline(always)]
+    pub fn for_each_chunk2_in_place<T: Copy + Send + Sync, R: Sync>(&self, chunksz: usize,
+                                                                    acc: &mut [T], rhs: &[R],
+                                                                    f: &BinChunkFn<'_, T, R, T>)
+    {
+        assert_eq!(acc.len(), rhs.len());
+        let run_chunk = |buf: &mut Vec<T>, (accchunk, rhschunk): (&mut [T], &[R])| {
+            buf.clear();
+            buf.extend_from_slice(accchunk);
+            f(buf, rhschunk, accchunk)
+        };
+        #[cfg(feature = "rayon")]
+        {
+            if self.is_parallel(acc.len()) {
+                let mut run = || {
+                    acc.par_chunks_mut(chunksz)
+                        .zip(rhs.par_chunks(chunksz))
+                        .for_each_init(|| Vec::with_capacity(chunksz), run_chunk)
+                };
+                match self.pool {
+                    Some(pool) => pool.install(run),
+                    None => run()
+                }
+                return;
+            }
+        }
+        let mut buf = Vec::with_capacity(chunksz);
+        acc.chunks_mut(chunksz)
+            .zip(rhs.chunks(chunksz))
+            .for_each(|chunks| run_chunk(&mut buf, chunks))
+    }
+
+    // Runs `f` over chunks of whole `rowlen`-element rows of `dst`, with the
+    // index of each chunk's first row. Chunks are about the chunk size, or one
+    // row if rows are longer than that, and are spread across threads.
+    #[inline(always)]
+    pub fn for_each_row_chunk<D: Send>(&self, rowlen: usize, dst: &mut [D], f: &RowFn<'_, D>)
+    {
+        self.for_each_row_chunk_of(dst.len(), rowlen, dst, f)
+    }
+
+    // As `for_each_row_chunk`, over the bytes of a buffer of `elemsz`-byte
+    // elements, in rows of `rowbytes` bytes. It's the number of elements
+    // that's compared to a `Parallelism::ParallelAbove` threshold.
+    #[inline(always)]
+    pub fn for_each_byte_row_chunk(&self, elemsz: usize, rowbytes: usize, dst: &mut [u8],
+                                   f: &RowFn<'_, u8>)
+    {
+        self.for_each_row_chunk_of(dst.len() / elemsz.max(1), rowbytes, dst, f)
+    }
+
+    #[inline(always)]
+    fn for_each_row_chunk_of<D: Send>(&self, elems: usize, rowlen: usize, dst: &mut [D],
+                                      f: &RowFn<'_, D>)
+    {
+        assert!(dst.len().checked_rem(rowlen) == Some(0));
+        let rows = cmax(self.chunk_bytes / (rowlen * std::mem::size_of::<D>()).max(1), 1);
+        let run_chunk = |(g, chunk): (usize, &mut [D])| f(g * rows, chunk);
+        #[cfg(feature = "rayon")]
+        {
+            if self.is_parallel(elems) {
+                let mut run = || {
+                    dst.par_chunks_mut(rows * rowlen)
+                        .enumerate()
+                        .for_each(run_chunk)
+                };
+                match self.pool {
+                    Some(pool) => pool.install(run),
+                    None => run()
+                }
+                return;
+            }
+        }
+        #[cfg(not(feature = "rayon"))]
+        let _ = elems;
+        dst.chunks_mut(rows * rowlen)
+            .enumerate()
+            .for_each(run_chunk)
+    }
+}
+
+impl<'pool> Default for Sched<'pool> {
+    fn default() -> Sched<'pool> {
+        Sched::new(CHUNKBYTES)
+    }
+}
+
+// The chunk sizes calibration chooses between: 4k to 1M.
+const CALIBRATION_CANDIDATES: [usize; 9] = [
+    0x1000, 0x2000, 0x4000, 0x8000, 0x10000, 0x20000, 0x40000, 0x80000, 0x100000
+];
+
+/// Times a few representative kernels (an arithmetic binop, a comparison and
+/// a widening conversion) over a range of chunk sizes, and returns the one
+/// that ran fastest on this machine. It takes a noticeable fraction of a second
+/// and allocates a few tens of megabytes while running, so is meant to be
+/// called once at startup, with the result stored in each `EvalCtx`'s
+/// `chunk_bytes`. The result is never smaller than 4k or larger than 1M.
+pub fn calibrate_chunk_bytes() -> usize {
+    calibrate(0x400000, &CALIBRATION_CANDIDATES)
+}
+
+// Returns whichever of `candidates` runs the calibration kernels fastest over
+// buffers of `nbytes` bytes. The kernels are all on 8 to 64-bit integers, which
+// are compiled in whatever the features.
+pub(crate) fn calibrate(nbytes: usize, candidates: &[usize]) -> usize {
+    const REPS: usize = 3;
+    let n = nbytes / 8;
+    let ua: Vec<u32> = (0..n).map(|i| i as u32).collect();
+    let ub: Vec<u32> = (0..n).map(|i| (n - i) as u32).collect();
+    let ia: Vec<i32> = (0..n).map(|i| i as i32).collect();
+    let ib: Vec<i32> = (0..n).map(|i| (n - i) as i32).collect();
+    let mut udst = vec![0u32; n];
+    let mut bdst = vec![false; n];
+    let mut wdst = vec![0i64; n];
+
+    let mut best = (Duration::MAX, CHUNKBYTES);
+    for &chunk_bytes in candidates {
+        let sched = Sched::new(chunk_bytes);
+        // Take the fastest of a few runs, to filter out noise from the
+        // machine being briefly busy with something else.
+        let mut total = Duration::ZERO;
+        for rep in 0..REPS {
+            let mut times = [Duration::ZERO; 3];
+            let t = Instant::now();
+            let _ = <AddOp<u32, u32>>::apply_slice_slice(&sched, &ua, &ub, &mut udst);
+            times[0] = t.elapsed();
+            let t = Instant::now();
+            let _ = <LtOp<i32, bool>>::apply_slice_slice(&sched, &ia, &ib, &mut bdst);
+            times[1] = t.elapsed();
+            let t = Instant::now();
+            let _ = <ConvOp<i32, i64>>::apply_slice(&sched, &ia, &mut wdst);
+            times[2] = t.elapsed();
+            if rep == 0 {
+                // The first run also warms up the thread pool and caches.
+                continue;
+            }
+            let rep_total = times.iter().sum();
+            if rep == 1 || rep_total < total {
+                total = rep_total;
+            }
+        }
+        if total < best.0 {
+            best = (total, chunk_bytes);
+        }
+    }
+    best.1
+}
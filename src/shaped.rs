@@ -0,0 +1,453 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Rank-N arrays: a shape and strides over a base operand, and elementwise
+// operations on them.
+//
+// An operation first finds the shape of its result by the agreement rules
+// below, then gives each operand strides over that shape, with a stride of
+// zero along any axis it's extended across. Adjacent axes every operand walks
+// contiguously are then merged, so that whatever's dense is one long row, and
+// the operation runs a row at a time through the ordinary `EvalCtx` kernels,
+// writing the result row-major into `out`. In each row an operand is either
+// a contiguous run of its base slice, a const (when its stride along the row
+// is zero), or, when it's strided, gathered into a row-sized buffer first.
+
+use std::mem::size_of;
+
+use crate::eval::*;
+use crate::ops::*;
+use crate::operands::*;
+use crate::plan::val_binop_ty;
+use crate::scalarty::ScalarTy;
+use crate::traits::ScalarT;
+
+/// A rank-N array: a view of the elements of a base `Operand` with a given
+/// shape, where the element at index `[i0, i1, ...]` is at
+/// `offset + i0 * strides[0] + i1 * strides[1] + ...` in the base. A const
+/// base is that value at every index, whatever the shape. Rank 0 is a scalar.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShapedOperand<'a> {
+    base: Operand<'a>,
+    offset: usize,
+    shape: Vec<usize>,
+    strides: Vec<isize>,
+}
+
+// Returns the row-major strides of a dense array of the given shape.
+pub(crate) fn dense_strides(shape: &[usize]) -> Vec<isize> {
+    let mut strides = vec![0; shape.len()];
+    let mut stride = 1;
+    for (s, &n) in strides.iter_mut().zip(shape.iter()).rev() {
+        *s = stride as isize;
+        stride *= n;
+    }
+    strides
+}
+
+impl<'a> ShapedOperand<'a> {
+    /// A dense row-major array of the given shape over all of `base`, whose
+    /// length must be the product of the shape.
+    pub fn new(base: Slice<'a>, shape: Vec<usize>) -> Result<ShapedOperand<'a>, EvalError> {
+        if shape.iter().product::<usize>() != base.len() {
+            return Err(EvalError::BadBuffer);
+        }
+        let strides = dense_strides(&shape);
+        Ok(ShapedOperand { base: Operand::OperandSlice(base), offset: 0, shape, strides })
+    }
+
+    /// An array of the given shape and strides over `base`, starting at
+    /// `offset`. Every index within the shape must land within `base`.
+    pub fn with_strides(base: Slice<'a>, offset: usize, shape: Vec<usize>, strides: Vec<isize>)
+                        -> Result<ShapedOperand<'a>, EvalError> {
+        if shape.len() != strides.len() {
+            return Err(EvalError::BadBuffer);
+        }
+        if !shape.contains(&0) {
+            let (mut lo, mut hi) = (offset as isize, offset as isize);
+            for (&n, &s) in shape.iter().zip(strides.iter()) {
+                let reach = (n as isize - 1).checked_mul(s).ok_or(EvalError::BadBuffer)?;
+                if reach < 0 { lo += reach } else { hi += reach }
+            }
+            if lo < 0 || hi >= base.len() as isize {
+                return Err(EvalError::BadBuffer);
+            }
+        }
+        Ok(ShapedOperand { base: Operand::OperandSlice(base), offset, shape, strides })
+    }
+
+    /// The array of the given shape with `c` at every index.
+    pub fn splat(c: Const, shape: Vec<usize>) -> ShapedOperand<'a> {
+        let strides = vec![0; shape.len()];
+        ShapedOperand { base: Operand::OperandConst(c), offset: 0, shape, strides }
+    }
+
+    /// The scalar (rank 0 array) `c`.
+    pub fn scalar(c: Const) -> ShapedOperand<'a> {
+        ShapedOperand::splat(c, Vec::new())
+    }
+
+    pub fn base(&self) -> &Operand<'a> {
+        &self.base
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    pub fn strides(&self) -> &[isize] {
+        &self.strides
+    }
+
+    pub fn rank(&self) -> usize {
+        self.shape.len()
+    }
+
+    /// Returns the number of elements: the product of the shape.
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get_scalar_ty(&self) -> ScalarTy {
+        self.base.get_scalar_ty()
+    }
+
+    /// Returns the elements as one contiguous slice in row-major order, if
+    /// they're laid out that way in a slice base.
+    pub fn as_slice(&self) -> Option<Slice<'a>> {
+        match &self.base {
+            Operand::OperandSlice(s) => {
+                let dense = self.shape.iter().zip(self.strides.iter())
+                    .zip(dense_strides(&self.shape).iter())
+                    .all(|((&n, &s), &d)| n <= 1 || s == d);
+                if !dense {
+                    return None;
+                }
+                let (_, rest) = s.split_at(self.offset.min(s.len()));
+                Some(rest.split_at(self.len()).0)
+            }
+            Operand::OperandConst(_) => None,
+        }
+    }
+}
+
+impl<'a> From<Slice<'a>> for ShapedOperand<'a> {
+    fn from(s: Slice<'a>) -> Self {
+        let n = s.len();
+        ShapedOperand { base: Operand::OperandSlice(s), offset: 0, shape: vec![n], strides: vec![1] }
+    }
+}
+
+impl<'a> From<Const> for ShapedOperand<'a> {
+    fn from(c: Const) -> Self {
+        ShapedOperand::scalar(c)
+    }
+}
+
+/// Returns the shape of the result of an elementwise operation on arrays of
+/// shapes `a` and `b`, by APL's rules: equal shapes agree, and an array of
+/// one element (such as a scalar) extends to the other's shape. Beyond that,
+/// as with J's and APL's leading-axis agreement, a shape that's a prefix of
+/// the other extends along the other's trailing axes, each of its elements
+/// repeated across the corresponding cell of the other. Anything else is an
+/// `EvalError::ShapeMismatch`.
+pub fn agree_shapes(a: &[usize], b: &[usize]) -> Result<Vec<usize>, EvalError> {
+    let (an, bn) = (a.iter().product::<usize>(), b.iter().product::<usize>());
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if a == b || (an == 1 && bn == 1) {
+        Ok(long.to_vec())
+    } else if an == 1 {
+        Ok(b.to_vec())
+    } else if bn == 1 {
+        Ok(a.to_vec())
+    } else if long.starts_with(short) {
+        Ok(long.to_vec())
+    } else {
+        Err(EvalError::ShapeMismatch(a.to_vec(), b.to_vec()))
+    }
+}
+
+// Returns the strides `x` is walked with over an agreeing result `shape`.
+fn extended_strides(x: &ShapedOperand, shape: &[usize]) -> Vec<isize> {
+    if x.len() == 1 || matches!(x.base, Operand::OperandConst(_)) {
+        return vec![0; shape.len()];
+    }
+    let mut strides = x.strides.clone();
+    strides.resize(shape.len(), 0);
+    strides
+}
+
+// Merges adjacent axes of `shape` that every one of `strides` walks
+// contiguously, and drops axes of length 1. There's always at least one
+// axis left, of length 0 if `shape` is empty of elements.
+pub(crate) fn collapse_axes(shape: &[usize], strides: &[Vec<isize>]) -> (Vec<usize>, Vec<Vec<isize>>) {
+    let mut cshape: Vec<usize> = Vec::new();
+    let mut cstrides: Vec<Vec<isize>> = vec![Vec::new(); strides.len()];
+    if shape.contains(&0) {
+        return (vec![0], vec![vec![0]; strides.len()]);
+    }
+    for (axis, &n) in shape.iter().enumerate() {
+        if n == 1 {
+            continue;
+        }
+        let mergeable = !cshape.is_empty() && strides.iter().zip(cstrides.iter())
+            .all(|(s, c)| *c.last().unwrap() == s[axis] * n as isize);
+        if mergeable {
+            *cshape.last_mut().unwrap() *= n;
+            for (s, c) in strides.iter().zip(cstrides.iter_mut()) {
+                *c.last_mut().unwrap() = s[axis];
+            }
+        } else {
+            cshape.push(n);
+            for (s, c) in strides.iter().zip(cstrides.iter_mut()) {
+                c.push(s[axis]);
+            }
+        }
+    }
+    if cshape.is_empty() {
+        cshape.push(1);
+        for c in cstrides.iter_mut() {
+            c.push(0);
+        }
+    }
+    (cshape, cstrides)
+}
+
+// A buffer for gathering the strided elements of a row into, aligned for any
+// scalar type.
+#[derive(Default)]
+pub(crate) struct RowBuf(Vec<u128>);
+
+impl RowBuf {
+    pub(crate) fn bytes(&mut self, n: usize) -> &mut [u8] {
+        self.0.resize(n.div_ceil(size_of::<u128>()), 0);
+        let p = self.0.as_mut_ptr() as *mut u8;
+        unsafe { core::slice::from_raw_parts_mut(p, n) }
+    }
+}
+
+fn gather_typed<'b, T>(src: &[T], start: isize, stride: isize, n: usize, buf: &'b mut RowBuf)
+                       -> Result<&'b [T], EvalError>
+where T: ScalarT + Copy
+{
+    let dst: &mut [T] = transmute_buf_mut(buf.bytes(n * size_of::<T>()))?;
+    for (i, d) in dst.iter_mut().enumerate() {
+        *d = src[(start + i as isize * stride) as usize];
+    }
+    Ok(dst)
+}
+
+// Copies the `n` elements of `s` from `start`, `stride` apart, into `buf`.
+fn gather<'b>(s: &Slice, start: isize, stride: isize, n: usize, buf: &'b mut RowBuf)
+              -> Result<Slice<'b>, EvalError> {
+    use Slice::*;
+    let g = match *s {
+        SliceBool(x) => SliceBool(gather_typed(x, start, stride, n, buf)?),
+        SliceU8(x) => SliceU8(gather_typed(x, start, stride, n, buf)?),
+        SliceU16(x) => SliceU16(gather_typed(x, start, stride, n, buf)?),
+        SliceU32(x) => SliceU32(gather_typed(x, start, stride, n, buf)?),
+        SliceU64(x) => SliceU64(gather_typed(x, start, stride, n, buf)?),
+        SliceU128(x) => SliceU128(gather_typed(x, start, stride, n, buf)?),
+        SliceI8(x) => SliceI8(gather_typed(x, start, stride, n, buf)?),
+        SliceI16(x) => SliceI16(gather_typed(x, start, stride, n, buf)?),
+        SliceI32(x) => SliceI32(gather_typed(x, start, stride, n, buf)?),
+        SliceI64(x) => SliceI64(gather_typed(x, start, stride, n, buf)?),
+        SliceI128(x) => SliceI128(gather_typed(x, start, stride, n, buf)?),
+        SliceF32(x) => SliceF32(gather_typed(x, start, stride, n, buf)?),
+        SliceF64(x) => SliceF64(gather_typed(x, start, stride, n, buf)?),
+    };
+    Ok(g)
+}
+
+fn view_typed<T: ScalarT>(bytes: &mut [u8], n: usize) -> Result<&[T], EvalError> {
+    let s: &mut [T] = transmute_buf_mut(bytes)?;
+    if s.len() < n {
+        return Err(EvalError::BadBuffer);
+    }
+    Ok(&s[..n])
+}
+
+// Returns the first `n` elements of type `ty` in `bytes`.
+pub(crate) fn slice_of_bytes(ty: ScalarTy, bytes: &mut [u8], n: usize)
+                             -> Result<Slice<'_>, EvalError> {
+    use Slice::*;
+    use ScalarTy::*;
+    let s = match ty {
+        TBool => SliceBool(view_typed(bytes, n)?),
+        TU8 => SliceU8(view_typed(bytes, n)?),
+        TU16 => SliceU16(view_typed(bytes, n)?),
+        TU32 => SliceU32(view_typed(bytes, n)?),
+        TU64 => SliceU64(view_typed(bytes, n)?),
+        TU128 => SliceU128(view_typed(bytes, n)?),
+        TI8 => SliceI8(view_typed(bytes, n)?),
+        TI16 => SliceI16(view_typed(bytes, n)?),
+        TI32 => SliceI32(view_typed(bytes, n)?),
+        TI64 => SliceI64(view_typed(bytes, n)?),
+        TI128 => SliceI128(view_typed(bytes, n)?),
+        TF32 => SliceF32(view_typed(bytes, n)?),
+        TF64 => SliceF64(view_typed(bytes, n)?),
+    };
+    Ok(s)
+}
+
+// How an operand is fed to each row of an operation.
+enum Row<'a> {
+    Const(Const),
+    // A slice base and the element strides it's walked with.
+    Strided(Slice<'a>, usize, Vec<isize>),
+}
+
+/// The shaped operations run the corresponding elementwise operation over
+/// `ShapedOperand`s whose shapes agree (see `agree_shapes`), and return the
+/// result as a dense row-major array in `out`, or a splatted const if every
+/// operand has a const base. Each operand is converted a row at a time, so
+/// `tmp1` and `tmp2` need only hold the longest row, but `out` needs the
+/// whole result; the `plan_*` functions give these sizes for operands of the
+/// row and result lengths. Strided operands are gathered a row at a time into
+/// an internal buffer.
+impl<'eval> EvalCtx<'eval> {
+    /// Perform a given `ValUnOpCode` on a `ShapedOperand`.
+    pub fn shaped_val_unop<'slice>(self, op: ValUnOpCode, operand: &ShapedOperand<'slice>)
+                                   -> Result<ShapedOperand<'eval>, EvalError>
+    where 'slice: 'eval
+    {
+        let ty = operand.get_scalar_ty();
+        self.shaped_elementwise(ty, &[operand], |ctx, xs| ctx.val_unop(op.clone(), &xs[0]))
+    }
+
+    /// Perform a given `BoolUnOpCode` on a `ShapedOperand`.
+    pub fn shaped_bool_unop<'slice>(self, op: BoolUnOpCode, operand: &ShapedOperand<'slice>)
+                                    -> Result<ShapedOperand<'eval>, EvalError>
+    where 'slice: 'eval
+    {
+        self.shaped_elementwise(ScalarTy::TBool, &[operand],
+                                |ctx, xs| ctx.bool_unop(op.clone(), &xs[0]))
+    }
+
+    /// Perform a given `ValBinOpCode` on a pair of `ShapedOperand`s.
+    pub fn shaped_val_binop<'slice>(self, op: ValBinOpCode,
+                                    lhs: &ShapedOperand<'slice>,
+                                    rhs: &ShapedOperand<'slice>)
+                                    -> Result<ShapedOperand<'eval>, EvalError>
+    where 'slice: 'eval
+    {
+        let ty = val_binop_ty(self.promotion, &op, lhs.get_scalar_ty(), rhs.get_scalar_ty())?;
+        self.shaped_elementwise(ty, &[lhs, rhs],
+                                |ctx, xs| ctx.val_binop(op.clone(), &xs[0], &xs[1]))
+    }
+
+    /// Perform a given `BoolBinOpCode` on a pair of `ShapedOperand`s.
+    pub fn shaped_bool_binop<'slice>(self, op: BoolBinOpCode,
+                                     lhs: &ShapedOperand<'slice>,
+                                     rhs: &ShapedOperand<'slice>)
+                                     -> Result<ShapedOperand<'eval>, EvalError>
+    where 'slice: 'eval
+    {
+        self.shaped_elementwise(ScalarTy::TBool, &[lhs, rhs],
+                                |ctx, xs| ctx.bool_binop(op.clone(), &xs[0], &xs[1]))
+    }
+
+    // Runs `f` over the rows of `args`, as described above, where `ty` is the
+    // type of its result.
+    fn shaped_elementwise<'slice, F>(mut self, ty: ScalarTy, args: &[&ShapedOperand<'slice>], f: F)
+                                     -> Result<ShapedOperand<'eval>, EvalError>
+    where
+        'slice: 'eval,
+        F: for<'r> Fn(EvalCtx<'r>, &[Operand<'r>]) -> Result<Operand<'r>, EvalError>,
+    {
+        let mut shape: Vec<usize> = Vec::new();
+        for (i, x) in args.iter().enumerate() {
+            shape = if i == 0 { x.shape.clone() } else { agree_shapes(&shape, &x.shape)? };
+        }
+        let total: usize = shape.iter().product();
+        let tmp1 = std::mem::take(&mut self.tmp1);
+        let tmp2 = std::mem::take(&mut self.tmp2);
+        let out = std::mem::take(&mut self.out);
+
+        // With only consts, there's one element to compute.
+        if args.iter().all(|x| matches!(x.base, Operand::OperandConst(_))) {
+            let xs: Vec<Operand> = args.iter().map(|x| x.base.clone()).collect();
+            return match f(self.with_buffers(tmp1, tmp2, out), &xs)? {
+                Operand::OperandConst(c) => Ok(ShapedOperand::splat(c, shape)),
+                Operand::OperandSlice(_) => Err(EvalError::UnsupportedOp),
+            };
+        }
+
+        let strides: Vec<Vec<isize>> = args.iter().map(|x| extended_strides(x, &shape)).collect();
+        let (cshape, cstrides) = collapse_axes(&shape, &strides);
+        let rank = cshape.len();
+        let rowlen = cshape[rank - 1];
+        let nrows = if total == 0 { 1 } else { total / rowlen };
+        let rows: Vec<Row> = args.iter().zip(cstrides).map(|(x, s)| {
+            match &x.base {
+                Operand::OperandConst(c) => Row::Const(c.clone()),
+                Operand::OperandSlice(b) => Row::Strided(b.clone(), x.offset, s),
+            }
+        }).collect();
+        // Some operand has to be a slice for the kernels to write a row of
+        // results; if every one is walked with stride 0 along the rows, the
+        // first with a slice base gets gathered (repeating its element).
+        let all_const = rows.iter().all(|r| match r {
+            Row::Const(_) => true,
+            Row::Strided(_, _, s) => s[rank - 1] == 0,
+        });
+        let elt_bytes = ty.size_of();
+        if out.len() < total * elt_bytes {
+            return Err(EvalError::BadBuffer);
+        }
+        let mut bufs: Vec<RowBuf> = rows.iter().map(|_| RowBuf::default()).collect();
+        let mut index = vec![0usize; rank - 1];
+        for row in 0..nrows {
+            let mut gathered_one = false;
+            let mut xs: Vec<Operand> = Vec::with_capacity(rows.len());
+            for (r, buf) in rows.iter().zip(bufs.iter_mut()) {
+                match r {
+                    Row::Const(c) => xs.push(Operand::OperandConst(c.clone())),
+                    Row::Strided(base, offset, s) => {
+                        let start = *offset as isize + index.iter().zip(s.iter())
+                            .map(|(&i, &st)| i as isize * st).sum::<isize>();
+                        let stride = s[rank - 1];
+                        let x = if total == 0 {
+                            Operand::OperandSlice(base.split_at(0).0)
+                        } else if stride == 1 {
+                            let (_, rest) = base.split_at(start as usize);
+                            Operand::OperandSlice(rest.split_at(rowlen).0)
+                        } else if stride == 0 && (gathered_one || !all_const) {
+                            Operand::OperandConst(base.get(start as usize).ok_or(EvalError::BadBuffer)?)
+                        } else {
+                            gathered_one = true;
+                            Operand::OperandSlice(gather(base, start, stride, rowlen, buf)?)
+                        };
+                        xs.push(x);
+                    }
+                }
+            }
+            let span = row * rowlen * elt_bytes..(row + 1) * rowlen * elt_bytes;
+            // With a slice among its operands, each kernel writes its row of
+            // results to the start of its `out`, the row's place in ours.
+            let res = f(self.with_buffers(&mut *tmp1, &mut *tmp2, &mut out[span]), &xs)?;
+            if !matches!(res, Operand::OperandSlice(s) if s.len() == rowlen) {
+                return Err(EvalError::UnsupportedOp);
+            }
+            // Step the row index, odometer-style.
+            for (i, &n) in index.iter_mut().zip(cshape.iter()).rev() {
+                *i += 1;
+                if *i < n {
+                    break;
+                }
+                *i = 0;
+            }
+        }
+        let res = slice_of_bytes(ty, out, total)?;
+        let strides = dense_strides(&shape);
+        Ok(ShapedOperand { base: Operand::OperandSlice(res), offset: 0, shape, strides })
+    }
+}
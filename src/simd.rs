@@ -0,0 +1,142 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// The vector types the kernels in macros.rs are written against: `Simd<[T; N]>`
+// vectors of `N` lanes of `T`, `M8` mask lanes (as returned by comparisons and
+// consumed by `select`), and the lane-wise `FromCast` conversion.
+//
+// With the `packed_simd` feature (on by default) these are packed_simd's own,
+// which lower to explicit SIMD instructions but need a nightly compiler. Without
+// it they're the portable versions below, which build on stable: plain arrays,
+// operated on a lane at a time in fixed-length loops that LLVM reliably
+// auto-vectorizes. The portable backend only provides the operations kernels
+// actually use, with the same semantics as packed_simd's: in particular,
+// integer arithmetic wraps. A `core::simd` backend can slot in here the same
+// way once that's stable.
+
+#[cfg(feature = "packed_simd")]
+pub use packed_simd::{m8 as M8, FromCast, Simd};
+
+#[cfg(not(feature = "packed_simd"))]
+pub use self::portable::{FromCast, Simd, M8};
+
+#[cfg(not(feature = "packed_simd"))]
+mod portable {
+    use std::ops::*;
+
+    #[derive(Clone, Copy, Debug)]
+    pub struct Simd<A>(A);
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct M8(bool);
+
+    pub trait FromCast<T> {
+        fn from_cast(src: T) -> Self;
+    }
+
+    impl<T: Copy, const N: usize> Simd<[T; N]> {
+        pub const fn splat(x: T) -> Simd<[T; N]> {
+            Simd([x; N])
+        }
+
+        #[inline(always)]
+        pub fn from_slice_unaligned(src: &[T]) -> Simd<[T; N]> {
+            let mut lanes = [src[0]; N];
+            lanes.copy_from_slice(&src[..N]);
+            Simd(lanes)
+        }
+
+        #[inline(always)]
+        pub fn write_to_slice_unaligned(self, dst: &mut [T]) {
+            dst[..N].copy_from_slice(&self.0)
+        }
+
+        #[inline(always)]
+        pub fn extract(self, i: usize) -> T {
+            self.0[i]
+        }
+
+        #[inline(always)]
+        pub(crate) fn map(self, f: impl Fn(T) -> T) -> Simd<[T; N]> {
+            let mut lanes = self.0;
+            for x in lanes.iter_mut() {
+                *x = f(*x);
+            }
+            Simd(lanes)
+        }
+
+        #[inline(always)]
+        pub(crate) fn zip(self, rhs: Simd<[T; N]>, f: impl Fn(T, T) -> T) -> Simd<[T; N]> {
+            let mut lanes = self.0;
+            for (x, y) in lanes.iter_mut().zip(rhs.0.iter()) {
+                *x = f(*x, *y);
+            }
+            Simd(lanes)
+        }
+
+        #[inline(always)]
+        pub(crate) fn test(self, f: impl Fn(T) -> bool) -> Simd<[M8; N]> {
+            let mut mask = [M8(false); N];
+            for (m, x) in mask.iter_mut().zip(self.0.iter()) {
+                *m = M8(f(*x));
+            }
+            Simd(mask)
+        }
+
+        #[inline(always)]
+        pub(crate) fn test2(self, rhs: Simd<[T; N]>, f: impl Fn(T, T) -> bool) -> Simd<[M8; N]> {
+            let mut mask = [M8(false); N];
+            for (m, (x, y)) in mask.iter_mut().zip(self.0.iter().zip(rhs.0.iter())) {
+                *m = M8(f(*x, *y));
+            }
+            Simd(mask)
+        }
+    }
+
+    impl<T: Copy + PartialOrd, const N: usize> Simd<[T; N]> {
+        #[inline(always)]
+        pub fn lt(self, rhs: Simd<[T; N]>) -> Simd<[M8; N]> { self.test2(rhs, |x, y| x < y) }
+        #[inline(always)]
+        pub fn le(self, rhs: Simd<[T; N]>) -> Simd<[M8; N]> { self.test2(rhs, |x, y| x <= y) }
+        #[inline(always)]
+        pub fn eq(self, rhs: Simd<[T; N]>) -> Simd<[M8; N]> { self.test2(rhs, |x, y| x == y) }
+        #[inline(always)]
+        pub fn ne(self, rhs: Simd<[T; N]>) -> Simd<[M8; N]> { self.test2(rhs, |x, y| x != y) }
+        #[inline(always)]
+        pub fn ge(self, rhs: Simd<[T; N]>) -> Simd<[M8; N]> { self.test2(rhs, |x, y| x >= y) }
+        #[inline(always)]
+        pub fn gt(self, rhs: Simd<[T; N]>) -> Simd<[M8; N]> { self.test2(rhs, |x, y| x > y) }
+    }
+
+    impl<const N: usize> Simd<[M8; N]> {
+        #[inline(always)]
+        pub fn select<T: Copy>(self, t: Simd<[T; N]>, f: Simd<[T; N]>) -> Simd<[T; N]> {
+            let mut lanes = f.0;
+            for ((x, m), y) in lanes.iter_mut().zip(self.0.iter()).zip(t.0.iter()) {
+                if m.0 {
+                    *x = *y;
+                }
+            }
+            Simd(lanes)
+        }
+    }
+
+    impl<const N: usize> FromCast<Simd<[u8; N]>> for Simd<[M8; N]> {
+        #[inline(always)]
+        fn from_cast(src: Simd<[u8; N]>) -> Simd<[M8; N]> {
+            src.test(|x| x != 0)
+        }
+    }
+
+    impl_portable_int!(u8 u16 u32 u64 i8 i16 i32 i64);
+    impl_portable_neg!(i8 i16 i32 i64);
+    #[cfg(feature = "int128")]
+    impl_portable_int!(u128 i128);
+    #[cfg(feature = "int128")]
+    impl_portable_neg!(i128);
+    #[cfg(feature = "float")]
+    impl_portable_float!(f32 f64);
+    #[cfg(feature = "transcendental")]
+    impl_portable_transcendental!(f32 f64);
+    impl_portable_cast!(u8 u16 u32 u64 u128 i8 i16 i32 i64 i128 f32 f64);
+}
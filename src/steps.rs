@@ -0,0 +1,186 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Helpers for walking the input and output slices of a single (rayon) chunk in
+// SIMD-vector-sized steps of `N` elements, calling a per-step function `f`.
+//
+// Slices need not be an exact multiple of `N` long: if there's a final partial
+// step, its inputs are copied into `N`-element stack buffers (padded out with
+// the caller-supplied harmless value `pad`, eg. `ONE` so that padding lanes
+// never divide by zero), run through the very same `f`, and the live part of
+// the result copied back out. This keeps every element going through the same
+// vector code, so results never depend on where a tail happens to fall.
+
+use crate::cpu::SimdLevel;
+
+#[inline(always)]
+pub fn steps1<S, D, F, const N: usize>(src: &[S], dst: &mut [D], pad: S, f: F)
+where
+    S: Copy,
+    D: Copy + Default,
+    F: Fn(&[S], &mut [D]),
+{
+    debug_assert_eq!(src.len(), dst.len());
+    let mut srcs = src.chunks_exact(N);
+    let mut dsts = dst.chunks_exact_mut(N);
+    for (s, d) in (&mut srcs).zip(&mut dsts) {
+        f(s, d)
+    }
+    let stail = srcs.remainder();
+    let dtail = dsts.into_remainder();
+    if !stail.is_empty() {
+        let mut sbuf = [pad; N];
+        let mut dbuf = [D::default(); N];
+        sbuf[..stail.len()].copy_from_slice(stail);
+        f(&sbuf, &mut dbuf);
+        dtail.copy_from_slice(&dbuf[..dtail.len()]);
+    }
+}
+
+// As `steps1`, for two inputs, which may be of different types (as in exact
+// mixed-type comparisons), each padded with its own value.
+#[inline(always)]
+pub fn steps2<L, R, D, F, const N: usize>(lhs: &[L], rhs: &[R], dst: &mut [D],
+                                          lpad: L, rpad: R, f: F)
+where
+    L: Copy,
+    R: Copy,
+    D: Copy + Default,
+    F: Fn(&[L], &[R], &mut [D]),
+{
+    debug_assert_eq!(lhs.len(), dst.len());
+    debug_assert_eq!(rhs.len(), dst.len());
+    let mut lhss = lhs.chunks_exact(N);
+    let mut rhss = rhs.chunks_exact(N);
+    let mut dsts = dst.chunks_exact_mut(N);
+    for ((l, r), d) in (&mut lhss).zip(&mut rhss).zip(&mut dsts) {
+        f(l, r, d)
+    }
+    let ltail = lhss.remainder();
+    let rtail = rhss.remainder();
+    let dtail = dsts.into_remainder();
+    if !ltail.is_empty() {
+        let mut lbuf = [lpad; N];
+        let mut rbuf = [rpad; N];
+        let mut dbuf = [D::default(); N];
+        lbuf[..ltail.len()].copy_from_slice(ltail);
+        rbuf[..rtail.len()].copy_from_slice(rtail);
+        f(&lbuf, &rbuf, &mut dbuf);
+        dtail.copy_from_slice(&dbuf[..dtail.len()]);
+    }
+}
+
+// As `steps2`, but for ternary operators, which update `acc` in place: it's
+// the third input as well as the output. A final partial step can't go
+// through `f` padded, since the padding would have to carry `acc`'s contents
+// back out, so it's done an element at a time with `g` instead; that's only
+// right when `g` agrees exactly with the lanes of `f`. Only float ops are
+// ternary.
+#[cfg(feature = "float")]
+#[inline(always)]
+pub fn steps3<T, F, G, const N: usize>(a: &[T], b: &[T], acc: &mut [T], f: F, g: G)
+where
+    T: Copy,
+    F: Fn(&[T], &[T], &mut [T]),
+    G: Fn(T, T, T) -> T,
+{
+    debug_assert_eq!(a.len(), acc.len());
+    debug_assert_eq!(b.len(), acc.len());
+    let mut as_ = a.chunks_exact(N);
+    let mut bs = b.chunks_exact(N);
+    let mut accs = acc.chunks_exact_mut(N);
+    for ((a, b), acc) in (&mut as_).zip(&mut bs).zip(&mut accs) {
+        f(a, b, acc)
+    }
+    let tails = as_.remainder().iter().zip(bs.remainder());
+    for ((&a, &b), acc) in tails.zip(accs.into_remainder()) {
+        *acc = g(a, b, *acc);
+    }
+}
+
+// Defines `$select`, which returns a version of `$inner` compiled for the
+// given `SimdLevel`: for the target's baseline features, or, on x86 and
+// x86-64, with AVX2 or AVX-512 (each with FMA) enabled. `$inner` should be
+// `#[inline(always)]`, as should whatever it calls per element, so that the
+// whole loop is compiled for the level. The generic parameters of the
+// versions are given in brackets, followed by the arguments to instantiate
+// them with, since not all of them need appear in their signature.
+//
+// Calling the versions is only safe on CPUs that support the instruction
+// set, which `$select` guarantees by only returning versions for levels no
+// higher than the `SimdLevel` it's passed, which in turn must come from a
+// `Sched`, which clamps it to the detected level.
+macro_rules! simd_versions {
+    ($(#[$attr:meta])* $vis:vis fn $select:ident [$($gen:tt)*] [$($inst:tt)*]
+     ($($arg:ident: $argty:ty),*) -> $fnty:ty
+     where [$($bound:tt)*] = $inner:expr;) => {
+        $(#[$attr])*
+        $vis fn $select<$($gen)*>(level: SimdLevel) -> $fnty
+        where $($bound)*
+        {
+            unsafe fn baseline<$($gen)*>($($arg: $argty),*)
+            where $($bound)*
+            {
+                $inner($($arg),*)
+            }
+
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            #[target_feature(enable = "avx2,fma")]
+            unsafe fn avx2<$($gen)*>($($arg: $argty),*)
+            where $($bound)*
+            {
+                $inner($($arg),*)
+            }
+
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            #[target_feature(enable = "avx512f,avx512bw,fma")]
+            unsafe fn avx512<$($gen)*>($($arg: $argty),*)
+            where $($bound)*
+            {
+                $inner($($arg),*)
+            }
+
+            match level {
+                #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                SimdLevel::Avx512 => avx512::<$($inst)*>,
+                #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+                SimdLevel::Avx2 => avx2::<$($inst)*>,
+                _ => baseline::<$($inst)*>,
+            }
+        }
+    }
+}
+
+// The above, compiled for each `SimdLevel`. Kernels pick a version once per
+// operation and call it for each chunk.
+
+pub type Steps1Fn<S, D, F> = unsafe fn(&[S], &mut [D], S, F);
+pub type Steps2Fn<L, R, D, F> = unsafe fn(&[L], &[R], &mut [D], L, R, F);
+#[cfg(feature = "float")]
+pub type Steps3Fn<T, F, G> = unsafe fn(&[T], &[T], &mut [T], F, G);
+
+simd_versions! {
+    #[inline(always)]
+    pub fn select_steps1[S, D, F, const N: usize][S, D, F, N]
+        (src: &[S], dst: &mut [D], pad: S, f: F) -> Steps1Fn<S, D, F>
+        where [S: Copy, D: Copy + Default, F: Fn(&[S], &mut [D])]
+        = steps1::<S, D, F, N>;
+}
+
+simd_versions! {
+    #[inline(always)]
+    pub fn select_steps2[L, R, D, F, const N: usize][L, R, D, F, N]
+        (lhs: &[L], rhs: &[R], dst: &mut [D], lpad: L, rpad: R, f: F)
+        -> Steps2Fn<L, R, D, F>
+        where [L: Copy, R: Copy, D: Copy + Default, F: Fn(&[L], &[R], &mut [D])]
+        = steps2::<L, R, D, F, N>;
+}
+
+#[cfg(feature = "float")]
+simd_versions! {
+    #[inline(always)]
+    pub fn select_steps3[T, F, G, const N: usize][T, F, G, N]
+        (a: &[T], b: &[T], acc: &mut [T], f: F, g: G) -> Steps3Fn<T, F, G>
+        where [T: Copy, F: Fn(&[T], &[T], &mut [T]), G: Fn(T, T, T) -> T]
+        = steps3::<T, F, G, N>;
+}
@@ -0,0 +1,376 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Streaming evaluation: running an operation over columns that arrive as
+// sequences of chunks rather than as one contiguous slice, feeding each chunk
+// of the result to a sink as it's produced.
+//
+// The inputs' chunk boundaries needn't line up. The state carried from one
+// step to the next is the unconsumed rest of each input's current chunk: each
+// step takes the longest run every input has left in its current chunk, so
+// nothing is ever copied to join chunks together, and every step is one
+// ordinary `EvalCtx` operation on slices (or consts). A step is never longer
+// than the shortest input chunk it spans, so buffers sized by the `plan_*`
+// functions for the longest input chunk are always big enough.
+//
+// The elementwise operations carry no other state. A reduction also carries
+// its accumulator, as a `Const`, from each step to the next, folding each
+// step's elements into it in order. There are no streamed scans.
+
+use crate::eval::*;
+use crate::ops::*;
+use crate::operands::*;
+use crate::plan::val_binop_ty;
+use crate::scalarty::ScalarTy;
+use crate::traits::*;
+
+/// One input of a streamed binary or ternary operation: either a column
+/// arriving as a sequence of chunks, or a const broadcast across all of them.
+pub enum StreamOperand<'a> {
+    StreamChunks(Box<dyn Iterator<Item = Slice<'a>> + 'a>),
+    StreamConst(Const),
+}
+
+impl<'a> StreamOperand<'a> {
+    /// A column arriving as the given sequence of chunks.
+    pub fn chunks<I>(chunks: I) -> StreamOperand<'a>
+    where I: IntoIterator<Item = Slice<'a>>, I::IntoIter: 'a
+    {
+        StreamOperand::StreamChunks(Box::new(chunks.into_iter()))
+    }
+}
+
+impl<'a> From<Const> for StreamOperand<'a> {
+    fn from(c: Const) -> Self {
+        StreamOperand::StreamConst(c)
+    }
+}
+
+// The position in a chunked input: the rest of its current chunk, if there's
+// any left, and the chunks after it.
+struct Cursor<'a> {
+    chunks: Box<dyn Iterator<Item = Slice<'a>> + 'a>,
+    rest: Option<Slice<'a>>,
+}
+
+impl<'a> Cursor<'a> {
+    // Returns how much is left of the current chunk, moving on to the next
+    // non-empty one if it's used up, or `None` at the end of the input.
+    fn remaining(&mut self) -> Option<usize> {
+        while self.rest.is_none() {
+            match self.chunks.next() {
+                Some(s) if !s.is_empty() => self.rest = Some(s),
+                Some(_) => (),
+                None => return None,
+            }
+        }
+        self.rest.as_ref().map(|s| s.len())
+    }
+
+    // Takes the next `n` elements of the current chunk, which must have at
+    // least that many left.
+    fn take(&mut self, n: usize) -> Slice<'a> {
+        let (head, tail) = self.rest.take().expect("no current chunk").split_at(n);
+        if !tail.is_empty() {
+            self.rest = Some(tail);
+        }
+        head
+    }
+}
+
+enum Input<'a> {
+    Chunks(Cursor<'a>),
+    Const(Const),
+}
+
+impl<'a> Input<'a> {
+    fn chunks<I>(chunks: I) -> Input<'a>
+    where I: IntoIterator<Item = Slice<'a>>, I::IntoIter: 'a
+    {
+        Input::of(StreamOperand::chunks(chunks))
+    }
+
+    fn of(operand: StreamOperand<'a>) -> Input<'a> {
+        match operand {
+            StreamOperand::StreamChunks(chunks) => Input::Chunks(Cursor { chunks, rest: None }),
+            StreamOperand::StreamConst(c) => Input::Const(c),
+        }
+    }
+}
+
+// Splits a set of inputs into steps of equal-length operands.
+struct Aligner<'a> {
+    inputs: Vec<Input<'a>>,
+    finished: bool,
+}
+
+impl<'a> Aligner<'a> {
+    fn new(inputs: Vec<Input<'a>>) -> Aligner<'a> {
+        Aligner { inputs, finished: false }
+    }
+
+    // Returns the operands of the next step, or `None` once every chunked
+    // input has ended. If some end before others, their lengths differ, and
+    // that's an `EvalError::BadBuffer`, as it is for slices of different
+    // lengths. If every input is a const, there's just the one step.
+    fn next_step(&mut self) -> Result<Option<Vec<Operand<'a>>>, EvalError> {
+        if self.finished {
+            return Ok(None);
+        }
+        let (mut step, mut chunked, mut ended) = (usize::MAX, 0, 0);
+        for input in self.inputs.iter_mut() {
+            if let Input::Chunks(cursor) = input {
+                chunked += 1;
+                match cursor.remaining() {
+                    Some(n) => step = step.min(n),
+                    None => ended += 1,
+                }
+            }
+        }
+        if chunked == 0 {
+            self.finished = true;
+        } else if ended == chunked {
+            self.finished = true;
+            return Ok(None);
+        } else if ended != 0 {
+            self.finished = true;
+            return Err(EvalError::BadBuffer);
+        }
+        let operands = self.inputs.iter_mut().map(|input| match input {
+            Input::Chunks(cursor) => Operand::OperandSlice(cursor.take(step)),
+            Input::Const(c) => Operand::OperandConst(c.clone()),
+        });
+        Ok(Some(operands.collect()))
+    }
+}
+
+/// The streaming operations run the corresponding operation over successive
+/// pieces of their inputs, reusing the `EvalCtx`'s buffers for each, and pass
+/// each piece of the result to `sink` in order. They stop at the first error,
+/// from the operation or the sink. Pieces are never longer than the input
+/// chunks they come from, so buffers sized by the `plan_*` functions for the
+/// longest input chunk are big enough.
+impl<'eval> EvalCtx<'eval> {
+    /// Stream a `ValUnOpCode` over a chunked column.
+    pub fn stream_val_unop<'a, I, F>(mut self, op: ValUnOpCode, input: I, mut sink: F)
+                                     -> Result<(), EvalError>
+    where
+        I: IntoIterator<Item = Slice<'a>>,
+        I::IntoIter: 'a,
+        F: FnMut(Operand<'_>) -> Result<(), EvalError>,
+    {
+        let mut steps = Aligner::new(vec![Input::chunks(input)]);
+        while let Some(step) = steps.next_step()? {
+            sink(self.reborrow().val_unop(op.clone(), &step[0])?)?;
+        }
+        Ok(())
+    }
+
+    /// Stream a `BoolUnOpCode` over a chunked column.
+    pub fn stream_bool_unop<'a, I, F>(mut self, op: BoolUnOpCode, input: I, mut sink: F)
+                                      -> Result<(), EvalError>
+    where
+        I: IntoIterator<Item = Slice<'a>>,
+        I::IntoIter: 'a,
+        F: FnMut(Operand<'_>) -> Result<(), EvalError>,
+    {
+        let mut steps = Aligner::new(vec![Input::chunks(input)]);
+        while let Some(step) = steps.next_step()? {
+            sink(self.reborrow().bool_unop(op.clone(), &step[0])?)?;
+        }
+        Ok(())
+    }
+
+    /// Stream a conversion to `ty` over a chunked column.
+    pub fn stream_conv<'a, I, F>(mut self, input: I, ty: ScalarTy, mut sink: F)
+                                 -> Result<(), EvalError>
+    where
+        I: IntoIterator<Item = Slice<'a>>,
+        I::IntoIter: 'a,
+        F: FnMut(Operand<'_>) -> Result<(), EvalError>,
+    {
+        let mut steps = Aligner::new(vec![Input::chunks(input)]);
+        while let Some(step) = steps.next_step()? {
+            sink(self.reborrow().conv(&step[0], ty)?)?;
+        }
+        Ok(())
+    }
+
+    /// Stream a cast to `ty` over a chunked column. The index in a
+    /// `CastMode::Checked` error counts from the start of the column.
+    pub fn stream_cast<'a, I, F>(mut self, input: I, ty: ScalarTy, mode: CastMode, mut sink: F)
+                                 -> Result<(), EvalError>
+    where
+        I: IntoIterator<Item = Slice<'a>>,
+        I::IntoIter: 'a,
+        F: FnMut(Operand<'_>) -> Result<(), EvalError>,
+    {
+        let mut steps = Aligner::new(vec![Input::chunks(input)]);
+        let mut pos = 0;
+        while let Some(step) = steps.next_step()? {
+            let res = self.reborrow().cast(&step[0], ty, mode).map_err(|e| match e {
+                EvalError::CastOutOfRange(i) => EvalError::CastOutOfRange(pos + i),
+                EvalError::CastNaN(i) => EvalError::CastNaN(pos + i),
+                e => e,
+            })?;
+            sink(res)?;
+            if let Operand::OperandSlice(s) = &step[0] {
+                pos += s.len();
+            }
+        }
+        Ok(())
+    }
+
+    /// Stream a `ValBinOpCode` over a pair of chunked columns, or a chunked
+    /// column and a const.
+    pub fn stream_val_binop<'a, F>(mut self, op: ValBinOpCode,
+                                   lhs: StreamOperand<'a>,
+                                   rhs: StreamOperand<'a>,
+                                   mut sink: F)
+                                   -> Result<(), EvalError>
+    where
+        F: FnMut(Operand<'_>) -> Result<(), EvalError>,
+    {
+        let mut steps = Aligner::new(vec![Input::of(lhs), Input::of(rhs)]);
+        while let Some(step) = steps.next_step()? {
+            sink(self.reborrow().val_binop(op.clone(), &step[0], &step[1])?)?;
+        }
+        Ok(())
+    }
+
+    /// Stream a `BoolBinOpCode` over a pair of chunked columns, or a chunked
+    /// column and a const.
+    pub fn stream_bool_binop<'a, F>(mut self, op: BoolBinOpCode,
+                                    lhs: StreamOperand<'a>,
+                                    rhs: StreamOperand<'a>,
+                                    mut sink: F)
+                                    -> Result<(), EvalError>
+    where
+        F: FnMut(Operand<'_>) -> Result<(), EvalError>,
+    {
+        let mut steps = Aligner::new(vec![Input::of(lhs), Input::of(rhs)]);
+        while let Some(step) = steps.next_step()? {
+            sink(self.reborrow().bool_binop(op.clone(), &step[0], &step[1])?)?;
+        }
+        Ok(())
+    }
+
+    /// Stream a `ValTernOpCode` over three inputs, each a chunked column or a
+    /// const.
+    pub fn stream_val_ternop<'a, F>(mut self, op: ValTernOpCode,
+                                    a: StreamOperand<'a>,
+                                    b: StreamOperand<'a>,
+                                    c: StreamOperand<'a>,
+                                    mut sink: F)
+                                    -> Result<(), EvalError>
+    where
+        F: FnMut(Operand<'_>) -> Result<(), EvalError>,
+    {
+        let mut steps = Aligner::new(vec![Input::of(a), Input::of(b), Input::of(c)]);
+        while let Some(step) = steps.next_step()? {
+            sink(self.reborrow().val_ternop(op.clone(), &step[0], &step[1], &step[2])?)?;
+        }
+        Ok(())
+    }
+
+    /// Stream a reduction of a chunked column with a `ValBinOpCode`: the fold
+    /// `((init op x0) op x1) op ...` over its elements, carrying the
+    /// accumulator from each chunk to the next. The accumulator and each chunk
+    /// are promoted to a common type as for `val_binop`, converting the chunk
+    /// in `out` if it needs it. Unlike APL's `op/`, which reduces from the
+    /// right, this folds from the left, so needn't wait for the end of the
+    /// column; the two agree for associative ops, such as `Min`, `Max`, and
+    /// `Add` and `Mul` on integers. An empty column reduces to `init`.
+    pub fn stream_reduce<'a, I>(mut self, op: ValBinOpCode, init: Const, input: I)
+                                -> Result<Const, EvalError>
+    where
+        I: IntoIterator<Item = Slice<'a>>,
+        I::IntoIter: 'a,
+    {
+        use ScalarTy::*;
+        let mut acc = init;
+        let mut steps = Aligner::new(vec![Input::chunks(input)]);
+        while let Some(step) = steps.next_step()? {
+            let ty = val_binop_ty(self.promotion, &op, acc.get_scalar_ty(),
+                                  step[0].get_scalar_ty())?;
+            let acc_ty = match self.reborrow().conv(&Operand::OperandConst(acc), ty)? {
+                Operand::OperandConst(c) => c,
+                Operand::OperandSlice(_) => return Err(EvalError::UnsupportedOp),
+            };
+            let xs = match self.reborrow().conv(&step[0], ty)? {
+                Operand::OperandSlice(s) => s,
+                Operand::OperandConst(_) => return Err(EvalError::UnsupportedOp),
+            };
+            acc = match ty {
+                TBool => fold_static::<bool>(op.clone(), &acc_ty, &xs),
+                TU8 => fold_static::<u8>(op.clone(), &acc_ty, &xs),
+                TU16 => fold_static::<u16>(op.clone(), &acc_ty, &xs),
+                TU32 => fold_static::<u32>(op.clone(), &acc_ty, &xs),
+                TU64 => fold_static::<u64>(op.clone(), &acc_ty, &xs),
+                TU128 => fold_static::<u128>(op.clone(), &acc_ty, &xs),
+                TI8 => fold_static::<i8>(op.clone(), &acc_ty, &xs),
+                TI16 => fold_static::<i16>(op.clone(), &acc_ty, &xs),
+                TI32 => fold_static::<i32>(op.clone(), &acc_ty, &xs),
+                TI64 => fold_static::<i64>(op.clone(), &acc_ty, &xs),
+                TI128 => fold_static::<i128>(op.clone(), &acc_ty, &xs),
+                TF32 => fold_static::<f32>(op.clone(), &acc_ty, &xs),
+                TF64 => fold_static::<f64>(op.clone(), &acc_ty, &xs),
+            }?;
+        }
+        Ok(acc)
+    }
+}
+
+// Folds the elements of `xs` into `acc` with `op`, from the left, where both
+// are of type `T`.
+fn fold_static<T>(op: ValBinOpCode, acc: &Const, xs: &Slice<'_>) -> Result<Const, EvalError>
+where
+    T: ScalarT + ScalarOf + Copy,
+    Const: From<T>,
+
+    AddOp<T, T>: BinOp<T, T>,
+    SubOp<T, T>: BinOp<T, T>,
+    MulOp<T, T>: BinOp<T, T>,
+    DivOp<T, T>: BinOp<T, T>,
+    RemOp<T, T>: BinOp<T, T>,
+    MinOp<T, T>: BinOp<T, T>,
+    MaxOp<T, T>: BinOp<T, T>,
+    PowOp<T, T>: BinOp<T, T>,
+    BitAndOp<T, T>: BinOp<T, T>,
+    BitOrOp<T, T>: BinOp<T, T>,
+    BitXorOp<T, T>: BinOp<T, T>,
+    ShlOp<T, T>: BinOp<T, T>,
+    LShrOp<T, T>: BinOp<T, T>,
+    AShrOp<T, T>: BinOp<T, T>,
+    RotLOp<T, T>: BinOp<T, T>,
+    RotROp<T, T>: BinOp<T, T>,
+    Atan2Op<T, T>: BinOp<T, T>,
+    HypotOp<T, T>: BinOp<T, T>,
+{
+    use ValBinOpCode::*;
+    let f: fn(T, T) -> Result<T, OpError> = match op {
+        Add => <AddOp<T, T>>::apply_const_const,
+        Sub => <SubOp<T, T>>::apply_const_const,
+        Mul => <MulOp<T, T>>::apply_const_const,
+        Div => <DivOp<T, T>>::apply_const_const,
+        Rem => <RemOp<T, T>>::apply_const_const,
+        Min => <MinOp<T, T>>::apply_const_const,
+        Max => <MaxOp<T, T>>::apply_const_const,
+        Pow => <PowOp<T, T>>::apply_const_const,
+        BitAnd => <BitAndOp<T, T>>::apply_const_const,
+        BitOr => <BitOrOp<T, T>>::apply_const_const,
+        BitXor => <BitXorOp<T, T>>::apply_const_const,
+        Shl => <ShlOp<T, T>>::apply_const_const,
+        LShr => <LShrOp<T, T>>::apply_const_const,
+        AShr => <AShrOp<T, T>>::apply_const_const,
+        RotL => <RotLOp<T, T>>::apply_const_const,
+        RotR => <RotROp<T, T>>::apply_const_const,
+        Atan2 => <Atan2Op<T, T>>::apply_const_const,
+        Hypot => <HypotOp<T, T>>::apply_const_const,
+    };
+    let mut acc = T::of_const(acc).ok_or(EvalError::UnsupportedOp)?;
+    for x in T::of_slice(xs).ok_or(EvalError::UnsupportedOp)? {
+        acc = f(acc, *x).map_err(|_| EvalError::UnsupportedOp)?;
+    }
+    Ok(acc.into())
+}
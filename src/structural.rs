@@ -0,0 +1,432 @@
+// Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
+// Licensed under the MIT and Apache-2.0 licenses.
+
+// Structural operations: APL's reshape, transpose, reverse, rotate, take and
+// drop, which move elements around without looking at their values.
+//
+// Each operation works out the shape of its result and a list of boxes to
+// copy from a strided view of its operand into the dense result (plus, for
+// take and reshape, a fill value and a cycle). The copies are type-generic:
+// one kernel per element type moves the boxes, collapsing the axes they walk
+// contiguously into long rows first. When a box reads across the rows it
+// writes, as a transpose does, it's copied in square tiles sized to keep both
+// the rows read and the rows written in cache.
+
+use crate::consts::tile_len;
+use crate::eval::*;
+use crate::operands::*;
+use crate::scalarty::ScalarTy;
+use crate::shaped::*;
+use crate::traits::ScalarT;
+
+// A box of elements copied from the operand to the result: `shape` elements
+// from `soff` along `sstrides` in the operand's base, to `doff` along
+// `dstrides` in the result.
+struct Piece {
+    soff: isize,
+    sstrides: Vec<isize>,
+    doff: isize,
+    dstrides: Vec<isize>,
+    shape: Vec<usize>,
+}
+
+// How to build the result of a structural operation: `out` is first filled
+// with `fill`, if there is one, then each piece is copied, then, if `cycle`
+// is `Some(n)`, the first `n` elements are repeated through the rest.
+struct Layout {
+    shape: Vec<usize>,
+    fill: Option<Const>,
+    pieces: Vec<Piece>,
+    cycle: Option<usize>,
+}
+
+impl Layout {
+    fn new(shape: Vec<usize>) -> Layout {
+        Layout { shape, fill: None, pieces: Vec::new(), cycle: None }
+    }
+
+    // Adds the box of `extent` elements from index `sstart` of the view of
+    // `x` given by `soff` and `sstrides`, to index `dstart` of the result.
+    fn add(&mut self, soff: isize, sstrides: &[isize], sstart: &[usize],
+           dstart: &[usize], extent: Vec<usize>) {
+        let dstrides = dense_strides(&self.shape);
+        self.pieces.push(Piece {
+            soff: soff + dot(sstart, sstrides),
+            sstrides: sstrides.to_vec(),
+            doff: dot(dstart, &dstrides),
+            dstrides,
+            shape: extent,
+        });
+    }
+}
+
+fn dot(index: &[usize], strides: &[isize]) -> isize {
+    index.iter().zip(strides.iter()).map(|(&i, &s)| i as isize * s).sum()
+}
+
+// Steps `index` to the next row-major position within `shape`.
+fn step_index(index: &mut [usize], shape: &[usize]) {
+    for (i, &n) in index.iter_mut().zip(shape.iter()).rev() {
+        *i += 1;
+        if *i < n {
+            break;
+        }
+        *i = 0;
+    }
+}
+
+fn check_axis(x: &ShapedOperand, axis: usize) -> Result<(), EvalError> {
+    if axis >= x.rank() {
+        return Err(EvalError::BadAxis);
+    }
+    Ok(())
+}
+
+// Copies `n` elements `sstride` apart from `src` to `dstride` apart in `dst`.
+fn copy_row<T: Copy>(src: &[T], s0: isize, sstride: isize,
+                     dst: &mut [T], d0: isize, dstride: isize, n: usize) {
+    if sstride == 1 && dstride == 1 {
+        let (s0, d0) = (s0 as usize, d0 as usize);
+        dst[d0..d0 + n].copy_from_slice(&src[s0..s0 + n]);
+    } else {
+        for i in 0..n as isize {
+            dst[(d0 + i * dstride) as usize] = src[(s0 + i * sstride) as usize];
+        }
+    }
+}
+
+// Copies an `m` by `n` block whose rows are contiguous in `dst`, `drow`
+// apart, and whose columns are contiguous in `src`, `scol` apart, a tile at
+// a time.
+#[allow(clippy::too_many_arguments)]
+fn copy_tiled<T: Copy>(src: &[T], s0: isize, scol: isize,
+                       dst: &mut [T], d0: isize, drow: isize, m: usize, n: usize) {
+    let tile = tile_len::<T>();
+    for ib in (0..m).step_by(tile) {
+        for jb in (0..n).step_by(tile) {
+            for i in ib..m.min(ib + tile) {
+                let (s, d) = (s0 + i as isize, (d0 + i as isize * drow) as usize);
+                for j in jb..n.min(jb + tile) {
+                    dst[d + j] = src[(s + j as isize * scol) as usize];
+                }
+            }
+        }
+    }
+}
+
+fn copy_piece<T: Copy>(src: &[T], dst: &mut [T], p: &Piece) {
+    let (shape, strides) = collapse_axes(&p.shape, &[p.sstrides.clone(), p.dstrides.clone()]);
+    if shape[0] == 0 {
+        return;
+    }
+    let (ss, ds) = (&strides[0], &strides[1]);
+    let rank = shape.len();
+    let tiled = rank >= 2 && ds[rank - 1] == 1 && ss[rank - 2] == 1 && ss[rank - 1] != 1;
+    let outer = if tiled { rank - 2 } else { rank - 1 };
+    let mut index = vec![0; outer];
+    for _ in 0..shape[..outer].iter().product::<usize>() {
+        let s0 = p.soff + dot(&index, ss);
+        let d0 = p.doff + dot(&index, ds);
+        if tiled {
+            copy_tiled(src, s0, ss[rank - 1], dst, d0, ds[rank - 2],
+                       shape[rank - 2], shape[rank - 1]);
+        } else {
+            copy_row(src, s0, ss[rank - 1], dst, d0, ds[rank - 1], shape[rank - 1]);
+        }
+        step_index(&mut index, &shape[..outer]);
+    }
+}
+
+fn build_typed<T>(base: &Operand, fill: Option<&Const>, layout: &Layout, out: &mut [u8])
+                  -> Result<(), EvalError>
+where T: ScalarT + ScalarOf + Copy
+{
+    let total: usize = layout.shape.iter().product();
+    let dst: &mut [T] = transmute_buf_mut(out)?;
+    if dst.len() < total {
+        return Err(EvalError::BadBuffer);
+    }
+    let dst = &mut dst[..total];
+    let one: [T; 1];
+    let src: &[T] = match base {
+        Operand::OperandSlice(s) => T::of_slice(s).ok_or(EvalError::BadBuffer)?,
+        Operand::OperandConst(c) => {
+            one = [T::of_const(c).ok_or(EvalError::BadBuffer)?];
+            &one
+        }
+    };
+    if let Some(f) = fill {
+        let v = T::of_const(f).ok_or(EvalError::BadBuffer)?;
+        dst.iter_mut().for_each(|d| *d = v);
+    }
+    for p in layout.pieces.iter() {
+        copy_piece(src, dst, p);
+    }
+    if let Some(n) = layout.cycle {
+        let mut filled = n.min(total);
+        if filled > 0 {
+            while filled < total {
+                let k = filled.min(total - filled);
+                dst.copy_within(0..k, filled);
+                filled += k;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The structural operations copy the elements of a `ShapedOperand` into a
+/// new arrangement, returned as a dense row-major array in `out`, which needs
+/// room for the whole result; `tmp1` and `tmp2` aren't used. An operand with
+/// a const base gives a splatted const, unless the result has fill elements.
+/// An axis out of range of the operand's rank is an `EvalError::BadAxis`.
+impl<'eval> EvalCtx<'eval> {
+    /// APL's `⍴`: the elements of `x` in row-major order, repeated as often
+    /// as needed (or cut short) to fill `shape`. If `x` is empty, the result
+    /// is all zeroes.
+    pub fn reshape(self, x: &ShapedOperand, shape: Vec<usize>)
+                   -> Result<ShapedOperand<'eval>, EvalError> {
+        let n = x.len();
+        let total: usize = shape.iter().product();
+        let mut layout = Layout::new(shape);
+        if n == 0 {
+            layout.fill = Some(Const::ConstU8(0));
+            return self.build(x, layout);
+        }
+        // The first `total` elements of `x`, if there are more than that, are
+        // at most one box per axis: the whole rows they cover along it.
+        let xshape = x.shape().to_vec();
+        let dstrides = dense_strides(&xshape);
+        let mut start = vec![0; xshape.len()];
+        let mut left = total.min(n);
+        for axis in 0..xshape.len() {
+            let block: usize = xshape[axis + 1..].iter().product();
+            let m = left / block;
+            if m > 0 {
+                let mut extent = vec![1; xshape.len()];
+                extent[axis] = m;
+                extent[axis + 1..].copy_from_slice(&xshape[axis + 1..]);
+                let soff = x.offset() as isize + dot(&start, x.strides());
+                layout.pieces.push(Piece {
+                    soff,
+                    sstrides: x.strides().to_vec(),
+                    doff: dot(&start, &dstrides),
+                    dstrides: dstrides.clone(),
+                    shape: extent,
+                });
+                start[axis] += m;
+                left -= m * block;
+            }
+        }
+        if xshape.is_empty() && total > 0 {
+            layout.pieces.push(Piece {
+                soff: x.offset() as isize,
+                sstrides: Vec::new(),
+                doff: 0,
+                dstrides: Vec::new(),
+                shape: Vec::new(),
+            });
+        }
+        if total > n {
+            layout.cycle = Some(n);
+        }
+        self.build(x, layout)
+    }
+
+    /// APL's monadic `⍉`: `x` with its axes in reverse order.
+    pub fn transpose(self, x: &ShapedOperand) -> Result<ShapedOperand<'eval>, EvalError> {
+        let axes: Vec<usize> = (0..x.rank()).rev().collect();
+        self.transpose_axes(x, &axes)
+    }
+
+    /// APL's dyadic `⍉`: `x` with its axis `i` moved to axis `axes[i]` of the
+    /// result. `axes` must be a permutation of `x`'s axes, or the result is an
+    /// `EvalError::BadAxis`.
+    pub fn transpose_axes(self, x: &ShapedOperand, axes: &[usize])
+                          -> Result<ShapedOperand<'eval>, EvalError> {
+        let rank = x.rank();
+        if axes.len() != rank {
+            return Err(EvalError::BadAxis);
+        }
+        let mut shape = vec![usize::MAX; rank];
+        let mut strides = vec![0; rank];
+        for (i, &a) in axes.iter().enumerate() {
+            if a >= rank || shape[a] != usize::MAX {
+                return Err(EvalError::BadAxis);
+            }
+            shape[a] = x.shape()[i];
+            strides[a] = x.strides()[i];
+        }
+        let mut layout = Layout::new(shape.clone());
+        layout.add(x.offset() as isize, &strides, &[], &[], shape);
+        self.build(x, layout)
+    }
+
+    /// APL's `⌽` (on the last axis) and `⊖` (on the first): `x` reversed
+    /// along `axis`.
+    pub fn reverse(self, x: &ShapedOperand, axis: usize) -> Result<ShapedOperand<'eval>, EvalError> {
+        check_axis(x, axis)?;
+        let shape = x.shape().to_vec();
+        let mut strides = x.strides().to_vec();
+        let mut soff = x.offset() as isize;
+        if shape[axis] > 0 {
+            soff += (shape[axis] as isize - 1) * strides[axis];
+        }
+        strides[axis] = -strides[axis];
+        let mut layout = Layout::new(shape.clone());
+        layout.add(soff, &strides, &[], &[], shape);
+        self.build(x, layout)
+    }
+
+    /// APL's dyadic `⌽` and `⊖`: `x` rotated `n` places along `axis`, so
+    /// that index `i` of the result is index `(i + n) mod len` of `x`. A
+    /// negative `n` rotates the other way.
+    pub fn rotate(self, x: &ShapedOperand, axis: usize, n: isize)
+                  -> Result<ShapedOperand<'eval>, EvalError> {
+        check_axis(x, axis)?;
+        let shape = x.shape().to_vec();
+        let len = shape[axis];
+        let k = if len == 0 { 0 } else { n.rem_euclid(len as isize) as usize };
+        let mut layout = Layout::new(shape.clone());
+        let (soff, strides) = (x.offset() as isize, x.strides().to_vec());
+        let (mut sstart, mut dstart) = (vec![0; shape.len()], vec![0; shape.len()]);
+        let mut extent = shape.clone();
+        // The last `len - k` along the axis go first, then the first `k`.
+        sstart[axis] = k;
+        extent[axis] = len - k;
+        layout.add(soff, &strides, &sstart, &dstart, extent.clone());
+        if k > 0 {
+            sstart[axis] = 0;
+            dstart[axis] = len - k;
+            extent[axis] = k;
+            layout.add(soff, &strides, &sstart, &dstart, extent);
+        }
+        self.build(x, layout)
+    }
+
+    /// APL's `↑`: the first `counts[i]` elements of `x` along each axis `i`,
+    /// or the last `-counts[i]` if it's negative, padded with `fill` where
+    /// that's more than there are. Axes past the end of `counts` are taken
+    /// whole; a scalar is taken as having as many axes of length 1 as there
+    /// are counts. `fill` is converted to the type of `x`.
+    pub fn take(self, x: &ShapedOperand, counts: &[isize], fill: Const)
+                -> Result<ShapedOperand<'eval>, EvalError> {
+        let (xshape, xstrides) = counted_axes(x, counts)?;
+        let mut shape = xshape.clone();
+        let (mut sstart, mut dstart) = (vec![0; shape.len()], vec![0; shape.len()]);
+        let mut extent = xshape.clone();
+        let mut overtaken = false;
+        for (axis, &c) in counts.iter().enumerate() {
+            let len = xshape[axis];
+            let m = c.unsigned_abs();
+            shape[axis] = m;
+            extent[axis] = m.min(len);
+            overtaken |= m > len;
+            if c < 0 {
+                sstart[axis] = len.saturating_sub(m);
+                dstart[axis] = m.saturating_sub(len);
+            }
+        }
+        let mut layout = Layout::new(shape);
+        layout.add(x.offset() as isize, &xstrides, &sstart, &dstart, extent);
+        if overtaken {
+            layout.fill = Some(fill);
+        }
+        self.build(x, layout)
+    }
+
+    /// APL's `↓`: `x` without its first `counts[i]` elements along each axis
+    /// `i`, or its last `-counts[i]` if it's negative. Axes past the end of
+    /// `counts` are kept whole; a scalar is dropped from as having as many
+    /// axes of length 1 as there are counts.
+    pub fn drop(self, x: &ShapedOperand, counts: &[isize])
+                -> Result<ShapedOperand<'eval>, EvalError> {
+        let (xshape, xstrides) = counted_axes(x, counts)?;
+        let mut shape = xshape.clone();
+        let mut sstart = vec![0; shape.len()];
+        for (axis, &c) in counts.iter().enumerate() {
+            let m = c.unsigned_abs().min(xshape[axis]);
+            shape[axis] = xshape[axis] - m;
+            if c > 0 {
+                sstart[axis] = m;
+            }
+        }
+        let mut layout = Layout::new(shape.clone());
+        layout.add(x.offset() as isize, &xstrides, &sstart, &[], shape);
+        self.build(x, layout)
+    }
+
+    // Builds the result `layout` describes from the elements of `x`.
+    fn build(self, x: &ShapedOperand, mut layout: Layout)
+             -> Result<ShapedOperand<'eval>, EvalError> {
+        let ty = x.get_scalar_ty();
+        if let Operand::OperandConst(c) = x.base() {
+            if layout.fill.is_none() {
+                return Ok(ShapedOperand::splat(c.clone(), layout.shape));
+            }
+        }
+        if let Some(f) = layout.fill.take() {
+            let ctx = self.with_buffers(&mut [], &mut [], &mut []);
+            match ctx.conv(&Operand::OperandConst(f), ty)? {
+                Operand::OperandConst(c) => layout.fill = Some(c),
+                Operand::OperandSlice(_) => return Err(EvalError::UnsupportedOp),
+            }
+        }
+        let out = self.out;
+        build_into(x, &layout, out)?;
+        let total = layout.shape.iter().product();
+        ShapedOperand::new(slice_of_bytes(ty, out, total)?, layout.shape)
+    }
+}
+
+// Builds the result `layout` describes from the elements of `x`, in `out`.
+fn build_into(x: &ShapedOperand, layout: &Layout, out: &mut [u8]) -> Result<(), EvalError> {
+    use ScalarTy::*;
+    let fill = layout.fill.as_ref();
+    let base = x.base();
+    match x.get_scalar_ty() {
+        TBool => build_typed::<bool>(base, fill, layout, out),
+        TU8 => build_typed::<u8>(base, fill, layout, out),
+        TU16 => build_typed::<u16>(base, fill, layout, out),
+        TU32 => build_typed::<u32>(base, fill, layout, out),
+        TU64 => build_typed::<u64>(base, fill, layout, out),
+        TU128 => build_typed::<u128>(base, fill, layout, out),
+        TI8 => build_typed::<i8>(base, fill, layout, out),
+        TI16 => build_typed::<i16>(base, fill, layout, out),
+        TI32 => build_typed::<i32>(base, fill, layout, out),
+        TI64 => build_typed::<i64>(base, fill, layout, out),
+        TI128 => build_typed::<i128>(base, fill, layout, out),
+        TF32 => build_typed::<f32>(base, fill, layout, out),
+        TF64 => build_typed::<f64>(base, fill, layout, out),
+    }
+}
+
+// Returns the elements of `x` as a dense row-major slice: its base, if it's
+// already laid out that way, or else a copy of them in `buf`. A const base is
+// copied as often as the shape needs.
+pub(crate) fn dense_elements<'a: 'b, 'b>(x: &ShapedOperand<'a>, buf: &'b mut RowBuf)
+                                         -> Result<Slice<'b>, EvalError> {
+    if let Some(s) = x.as_slice() {
+        return Ok(s);
+    }
+    let shape = x.shape().to_vec();
+    let total = x.len();
+    let mut layout = Layout::new(shape.clone());
+    layout.add(x.offset() as isize, x.strides(), &[], &[], shape);
+    let ty = x.get_scalar_ty();
+    let out = buf.bytes(total * ty.size_of());
+    build_into(x, &layout, out)?;
+    slice_of_bytes(ty, out, total)
+}
+
+// Returns the shape and strides of `x` as seen by take or drop with
+// `counts`: as it is, or as axes of length 1 if it's a scalar.
+fn counted_axes(x: &ShapedOperand, counts: &[isize]) -> Result<(Vec<usize>, Vec<isize>), EvalError> {
+    if x.rank() == 0 {
+        return Ok((vec![1; counts.len()], vec![0; counts.len()]));
+    }
+    if counts.len() > x.rank() {
+        return Err(EvalError::BadAxis);
+    }
+    Ok((x.shape().to_vec(), x.strides().to_vec()))
+}
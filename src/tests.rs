@@ -4,6 +4,7 @@
 #[cfg(test)]
 mod test_helpers {
     use crate::*;
+    use crate::operands::ScalarOf;
 
     // We set this to a multiple of 16x CHUNKBYTES so that the worst case
     // (16-byte input type -> 1-byte output type) still passes an output vector
@@ -28,11 +29,39 @@ mod test_helpers {
             NBYTES / std::mem::size_of::<T>()
         }
         pub fn get_eval_ctx<'a>(&'a mut self) -> EvalCtx<'a> {
-            EvalCtx {
-                tmp1: &mut self.tmp1[..],
-                tmp2: &mut self.tmp2[..],
-                out:  &mut self.out[..]
-            }
+            EvalCtx::new(&mut self.tmp1[..],
+                         &mut self.tmp2[..],
+                         &mut self.out[..])
+        }
+    }
+
+    // Returns whether kernels over `ty` are compiled in with the enabled
+    // features. Operations on compiled-out types should be unsupported.
+    pub fn ty_compiled_in(ty: ScalarTy) -> bool {
+        use ScalarTy::*;
+        (cfg!(feature = "int128") || !matches!(ty, TU128 | TI128)) &&
+            (cfg!(feature = "float") || !matches!(ty, TF32 | TF64))
+    }
+
+    pub fn val_binop_compiled_in(op: &ValBinOpCode, ty: ScalarTy) -> bool {
+        use ScalarTy::*;
+        use ValBinOpCode::*;
+        (cfg!(feature = "bitwise") ||
+         !matches!(op, BitAnd | BitOr | BitXor | Shl | LShr | AShr | RotL | RotR)) &&
+            (cfg!(feature = "transcendental") ||
+             !(matches!(op, Pow) && matches!(ty, TF32 | TF64)))
+    }
+
+    // Returns the elements of `s`.
+    pub fn slice_elements<T: ScalarOf + Copy>(s: &Slice) -> Vec<T> {
+        T::of_slice(s).unwrap().to_vec()
+    }
+
+    // Returns the elements of a dense `ShapedOperand`, in row-major order.
+    pub fn elements<T: ScalarOf + Copy>(x: &ShapedOperand) -> Vec<T> {
+        match x.base() {
+            Operand::OperandSlice(_) => slice_elements(&x.as_slice().unwrap()),
+            Operand::OperandConst(c) => vec![T::of_const(c).unwrap(); x.len()],
         }
     }
 }
@@ -65,6 +94,9 @@ mod test_cmpops {
                         let res = tcx.get_eval_ctx().bool_binop(BoolBinOpCode::$opcode,
                                                                 &Operand::from(&a),
                                                                 &Operand::from(&b));
+                        if !ty_compiled_in(Operand::from(&a).get_scalar_ty()) {
+                            return TestResult::from_bool(matches!(res, Err(EvalError::UnsupportedOp)));
+                        }
                         match res {
                             Ok(r) => TestResult::from_bool(Operand::from(&exp) == r),
                             _ => TestResult::failed()
@@ -176,6 +208,35 @@ mod test_cmpops {
                           test_f64_gt Gt gt);
 }
 
+#[cfg(test)]
+mod test_scalarty {
+    use super::super::*;
+
+    // Every pair of types has a join, the same either way round, rather than
+    // recursing forever, and bool joins with anything as that type.
+    #[test]
+    fn test_join_total() {
+        use ScalarTy::*;
+        assert_eq!(TU16.join(TI8), TI32);
+        assert_eq!(TI16.join(TU32), TI64);
+        assert_eq!(TU64.join(TI32), TI128);
+        assert_eq!(TI8.join(TU128), TF64);
+        assert_eq!(TF32.join(TBool), TF32);
+        let all = [
+            TBool, TU8, TU16, TU32, TU64, TU128, TI8, TI16, TI32, TI64, TI128,
+            TF32, TF64,
+        ];
+        for a in all.iter() {
+            for b in all.iter() {
+                assert_eq!(a.join(*b), b.join(*a));
+                if *a == TBool {
+                    assert_eq!(a.join(*b), *b);
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test_valops {
     use super::super::*;
@@ -217,6 +278,11 @@ mod test_valops {
                         let res = tcx.get_eval_ctx().val_binop(ValBinOpCode::$opcode,
                                                                &Operand::from(&a),
                                                                &Operand::from(&b));
+                        if !ty_compiled_in(Operand::from(&a).get_scalar_ty()) ||
+                            !val_binop_compiled_in(&ValBinOpCode::$opcode,
+                                                   Operand::from(&a).get_scalar_ty()) {
+                            return TestResult::from_bool(matches!(res, Err(EvalError::UnsupportedOp)));
+                        }
                         match res {
                             Ok(r) => TestResult::from_bool(Operand::from(&exp) == r),
                             _ => TestResult::failed()
@@ -351,3 +417,2100 @@ mod test_valops {
                          test_i128_bitor BitOr bitor,
                          test_i128_bitxor BitXor bitxor);
 }
+
+#[cfg(test)]
+mod test_fallback {
+    use super::super::*;
+    use super::test_helpers::*;
+
+    #[test]
+    fn test_int_pow() {
+        let mut tcx = TestCtx::new();
+        let base: Vec<i32> = (0..1000).map(|x| x % 7 - 3).collect();
+        let exp: Vec<i32> = (0..1000).map(|x| x % 11 - 2).collect();
+        let pow: Vec<i32> = base.iter().zip(&exp).map(|(b, e)| match (b, e) {
+            (_, e) if *e >= 0 => b.wrapping_pow(*e as u32),
+            (1, _) => 1,
+            (-1, e) => if e % 2 == 0 { 1 } else { -1 },
+            _ => 0,
+        }).collect();
+        let res = tcx.get_eval_ctx()
+            .val_binop(ValBinOpCode::Pow, &Operand::from(&base), &Operand::from(&exp));
+        assert_eq!(res.unwrap(), Operand::from(&pow));
+
+        // Exponents don't have to fit in a u32, and overflow wraps.
+        let base: Vec<u64> = (0..100).collect();
+        let pow: Vec<u64> = base.iter().map(|b| b.wrapping_pow(1 << 31).wrapping_mul(*b)).collect();
+        let res = tcx.get_eval_ctx()
+            .val_binop(ValBinOpCode::Pow, &Operand::from(&base), &Operand::from((1u64 << 31) + 1));
+        assert_eq!(res.unwrap(), Operand::from(&pow));
+        let res = tcx.get_eval_ctx()
+            .val_binop(ValBinOpCode::Pow, &Operand::from(3u8), &Operand::from(5u8));
+        assert_eq!(res.unwrap(), Operand::from(243u8));
+    }
+
+    #[test]
+    fn test_abs_signum() {
+        let mut tcx = TestCtx::new();
+        let a: Vec<i8> = (-128..=127).collect();
+        let abs: Vec<i8> = a.iter().map(|x| x.wrapping_abs()).collect();
+        let res = tcx.get_eval_ctx().val_unop(ValUnOpCode::Abs, &Operand::from(&a));
+        assert_eq!(res.unwrap(), Operand::from(&abs));
+        let signum: Vec<i8> = a.iter().map(|x| x.signum()).collect();
+        let res = tcx.get_eval_ctx().val_unop(ValUnOpCode::Signum, &Operand::from(&a));
+        assert_eq!(res.unwrap(), Operand::from(&signum));
+
+        let u: Vec<u16> = (0..300).collect();
+        let signum: Vec<u16> = u.iter().map(|x| (*x != 0) as u16).collect();
+        let res = tcx.get_eval_ctx().val_unop(ValUnOpCode::Signum, &Operand::from(&u));
+        assert_eq!(res.unwrap(), Operand::from(&signum));
+        let res = tcx.get_eval_ctx().val_unop(ValUnOpCode::Abs, &Operand::from(&u));
+        assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+
+        let f = vec![-2.5f64, -0.0, 0.0, 3.0];
+        let res = tcx.get_eval_ctx().val_unop(ValUnOpCode::Signum, &Operand::from(&f));
+        if cfg!(feature = "float") {
+            assert_eq!(res.unwrap(), Operand::from(&vec![-1.0f64, -0.0, 0.0, 1.0]));
+        } else {
+            assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+        }
+    }
+    // Shift amounts are taken modulo the bit width, whether they come from a
+    // slice or a constant, and logical and arithmetic right shifts differ
+    // only in what they shift in.
+    #[test]
+    fn test_shifts() {
+        let mut tcx = TestCtx::new();
+        let a: Vec<i16> = (0..1000).map(|x| (x * 37 - 18000) as i16).collect();
+        let n: Vec<i16> = (0..1000).map(|x| (x % 41 - 20) as i16).collect();
+        type Shift = fn(i16, i16) -> i16;
+        let cases: [(ValBinOpCode, Shift); 5] = [
+            (ValBinOpCode::Shl, |x, n| x.wrapping_shl(n as u32)),
+            (ValBinOpCode::LShr, |x, n| ((x as u16) >> (n & 15)) as i16),
+            (ValBinOpCode::AShr, |x, n| x >> (n & 15)),
+            (ValBinOpCode::RotL, |x, n| x.rotate_left((n & 15) as u32)),
+            (ValBinOpCode::RotR, |x, n| x.rotate_right((n & 15) as u32)),
+        ];
+        for (op, f) in cases.iter() {
+            let exp: Vec<i16> = a.iter().zip(&n).map(|(x, n)| f(*x, *n)).collect();
+            let res = tcx.get_eval_ctx()
+                .val_binop(op.clone(), &Operand::from(&a), &Operand::from(&n));
+            if !cfg!(feature = "bitwise") {
+                assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+                continue;
+            }
+            assert_eq!(res.unwrap(), Operand::from(&exp));
+        }
+
+        if cfg!(feature = "bitwise") {
+            let u: Vec<u32> = (0..1000u32).map(|x| x.wrapping_mul(0x01010101)).collect();
+            let lshr: Vec<u32> = u.iter().map(|x| x >> 3).collect();
+            let ashr: Vec<u32> = u.iter().map(|x| ((*x as i32) >> 3) as u32).collect();
+            let res = tcx.get_eval_ctx()
+                .val_binop(ValBinOpCode::LShr, &Operand::from(&u), &Operand::from(35u32));
+            assert_eq!(res.unwrap(), Operand::from(&lshr));
+            let res = tcx.get_eval_ctx()
+                .val_binop(ValBinOpCode::AShr, &Operand::from(&u), &Operand::from(35u32));
+            assert_eq!(res.unwrap(), Operand::from(&ashr));
+        }
+        let res = tcx.get_eval_ctx()
+            .val_binop(ValBinOpCode::Shl, &Operand::from(1.0f32), &Operand::from(2.0f32));
+        assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+    }
+
+    // Shifts and rotates keep the type of the value shifted, whatever the
+    // type of the amount, rather than promoting both.
+    #[test]
+    fn test_shift_amount_types() {
+        if !cfg!(feature = "bitwise") {
+            return;
+        }
+        let mut tcx = TestCtx::new();
+        let a: Vec<u32> = vec![0x8000_0000, 1, 0xffff_ffff];
+        let res = tcx.get_eval_ctx()
+            .val_binop(ValBinOpCode::Shl, &Operand::from(&a), &Operand::from(1i32));
+        assert_eq!(res.unwrap(), Operand::from(&vec![0u32, 2, 0xffff_fffe]));
+        let res = tcx.get_eval_ctx()
+            .val_binop(ValBinOpCode::Shl, &Operand::from(0x8000_0000u32), &Operand::from(1i64));
+        assert_eq!(res.unwrap(), Operand::from(0u32));
+
+        // Amounts are taken modulo the width of the value shifted, even when
+        // they're wider or negative.
+        let b: Vec<u8> = vec![0x81, 0x0f, 0xf0];
+        let n: Vec<u32> = vec![1, 33, 4];
+        let res = tcx.get_eval_ctx()
+            .val_binop(ValBinOpCode::RotL, &Operand::from(&b), &Operand::from(&n));
+        assert_eq!(res.unwrap(), Operand::from(&vec![0x03u8, 0x1e, 0x0f]));
+        let res = tcx.get_eval_ctx()
+            .val_binop(ValBinOpCode::RotR, &Operand::from(&b), &Operand::from(-1i16));
+        assert_eq!(res.unwrap(), Operand::from(&vec![0x03u8, 0x1e, 0xe1]));
+        let c: Vec<i8> = vec![-128, 64, -1];
+        let res = tcx.get_eval_ctx()
+            .val_binop(ValBinOpCode::AShr, &Operand::from(&c), &Operand::from(&n));
+        assert_eq!(res.unwrap(), Operand::from(&vec![-64i8, 32, -1]));
+
+        // Planning agrees: only the amount is converted.
+        let plan = plan_val_binop(&DefaultPromotion, ValBinOpCode::RotL,
+                                  OperandDesc::SliceDesc(ScalarTy::TU8, 100),
+                                  OperandDesc::SliceDesc(ScalarTy::TU32, 100)).unwrap();
+        assert_eq!(plan.result, OperandDesc::SliceDesc(ScalarTy::TU8, 100));
+        assert_eq!((plan.tmp1, plan.tmp2, plan.out), (0, 100, 100));
+        let plan = plan_val_binop(&StrictPromotion, ValBinOpCode::Shl,
+                                  OperandDesc::SliceDesc(ScalarTy::TU32, 100),
+                                  OperandDesc::ConstDesc(ScalarTy::TI32)).unwrap();
+        assert_eq!(plan.result, OperandDesc::SliceDesc(ScalarTy::TU32, 100));
+        assert!(matches!(plan_val_binop(&DefaultPromotion, ValBinOpCode::Shl,
+                                        OperandDesc::ConstDesc(ScalarTy::TU32),
+                                        OperandDesc::ConstDesc(ScalarTy::TF32)),
+                         Err(EvalError::UnsupportedOp)));
+
+        // In place, and through outer and shaped operations.
+        let mut acc = a.clone();
+        tcx.get_eval_ctx().val_binop_in_place(ValBinOpCode::Shl, &mut (&mut acc).into(),
+                                              &Operand::from(1i8)).unwrap();
+        assert_eq!(acc, vec![0u32, 2, 0xffff_fffe]);
+        let r = tcx.get_eval_ctx()
+            .outer_val_binop(ValBinOpCode::Shl, &(&b[..2]).into(), &(&n[..]).into()).unwrap();
+        assert_eq!(elements::<u8>(&r), vec![0x02, 0x02, 0x10, 0x1e, 0x1e, 0xf0]);
+        let bs = ShapedOperand::from(Slice::from(&b[..]));
+        let r = tcx.get_eval_ctx()
+            .shaped_val_binop(ValBinOpCode::RotL, &bs, &ShapedOperand::from(Slice::from(&n[..])))
+            .unwrap();
+        assert_eq!(elements::<u8>(&r), vec![0x03, 0x1e, 0x0f]);
+    }
+
+    #[test]
+    fn test_bit_counts() {
+        let mut tcx = TestCtx::new();
+        let a: Vec<u32> = (0..1000).map(|x: u32| x.wrapping_mul(0x9e3779b9) >> (x % 32)).collect();
+        type Count = fn(u32) -> u32;
+        let cases: [(ValUnOpCode, Count); 5] = [
+            (ValUnOpCode::PopCount, |x| x.count_ones()),
+            (ValUnOpCode::LeadingZeros, |x| x.leading_zeros()),
+            (ValUnOpCode::TrailingZeros, |x| x.trailing_zeros()),
+            (ValUnOpCode::BitReverse, |x| x.reverse_bits()),
+            (ValUnOpCode::ByteSwap, |x| x.swap_bytes()),
+        ];
+        for (op, f) in cases.iter() {
+            let exp: Vec<u32> = a.iter().map(|x| f(*x)).collect();
+            let res = tcx.get_eval_ctx().val_unop(op.clone(), &Operand::from(&a));
+            if cfg!(feature = "bitwise") {
+                assert_eq!(res.unwrap(), Operand::from(&exp));
+            } else {
+                assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+            }
+        }
+        let res = tcx.get_eval_ctx().val_unop(ValUnOpCode::LeadingZeros, &Operand::from(0i8));
+        if cfg!(feature = "bitwise") {
+            assert_eq!(res.unwrap(), Operand::from(8i8));
+        }
+        let res = tcx.get_eval_ctx().val_unop(ValUnOpCode::PopCount, &Operand::from(1.0f64));
+        assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+    }
+}
+
+#[cfg(test)]
+mod test_floatmath {
+    use super::super::*;
+    use super::test_helpers::*;
+
+    #[test]
+    fn test_float_unops() {
+        let mut tcx = TestCtx::new();
+        let x: Vec<f64> = (0..1000).map(|i| (i as f64 - 500.0) / 600.0).collect();
+        type Fun = fn(f64) -> f64;
+        let cases: [(ValUnOpCode, Fun, bool); 16] = [
+            (ValUnOpCode::Tan, f64::tan, true),
+            (ValUnOpCode::Asin, f64::asin, true),
+            (ValUnOpCode::Acos, f64::acos, true),
+            (ValUnOpCode::Atan, f64::atan, true),
+            (ValUnOpCode::Sinh, f64::sinh, true),
+            (ValUnOpCode::Cosh, f64::cosh, true),
+            (ValUnOpCode::Tanh, f64::tanh, true),
+            (ValUnOpCode::Log2, f64::log2, true),
+            (ValUnOpCode::Log10, f64::log10, true),
+            (ValUnOpCode::Log1p, f64::ln_1p, true),
+            (ValUnOpCode::Expm1, f64::exp_m1, true),
+            (ValUnOpCode::Cbrt, f64::cbrt, true),
+            (ValUnOpCode::Floor, f64::floor, false),
+            (ValUnOpCode::Ceil, f64::ceil, false),
+            (ValUnOpCode::Round, f64::round, false),
+            (ValUnOpCode::Trunc, f64::trunc, false),
+        ];
+        for (op, f, transcendental) in cases.iter() {
+            let res = tcx.get_eval_ctx().val_unop(op.clone(), &Operand::from(&x));
+            let enabled = cfg!(feature = "float") &&
+                (!transcendental || cfg!(feature = "transcendental"));
+            if !enabled {
+                assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+                continue;
+            }
+            let res = OwnedOperand::from(res.unwrap());
+            let got: &[f64] = match &res {
+                OwnedOperand::OperandSlice(OwnedSlice::SliceF64(v)) => &v[..],
+                _ => panic!("wrong result type for {:?}", op),
+            };
+            // Vector kernels (eg. tanh) needn't be bit-identical to libm.
+            for (g, e) in got.iter().zip(x.iter().map(|x| f(*x))) {
+                assert!(g == &e || (g.is_nan() && e.is_nan()) ||
+                        (g - e).abs() <= 1e-12 * e.abs().max(1.0),
+                        "{:?}: {} != {}", op, g, e);
+            }
+        }
+        let res = tcx.get_eval_ctx().val_unop(ValUnOpCode::Floor, &Operand::from(3i32));
+        assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+    }
+
+    #[test]
+    fn test_float_binops() {
+        let mut tcx = TestCtx::new();
+        let y: Vec<f32> = (0..777).map(|i| i as f32 - 300.0).collect();
+        let x: Vec<f32> = (0..777).map(|i| (i % 13) as f32 - 6.0).collect();
+        let atan2: Vec<f32> = y.iter().zip(&x).map(|(y, x)| y.atan2(*x)).collect();
+        let hypot: Vec<f32> = y.iter().map(|y| y.hypot(4.0)).collect();
+        let res = tcx.get_eval_ctx()
+            .val_binop(ValBinOpCode::Atan2, &Operand::from(&y), &Operand::from(&x));
+        if cfg!(feature = "transcendental") {
+            assert_eq!(res.unwrap(), Operand::from(&atan2));
+        } else {
+            assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+        }
+        let res = tcx.get_eval_ctx()
+            .val_binop(ValBinOpCode::Hypot, &Operand::from(&y), &Operand::from(4.0f32));
+        if cfg!(feature = "transcendental") {
+            assert_eq!(res.unwrap(), Operand::from(&hypot));
+        } else {
+            assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+        }
+    }
+
+    // Fused multiply-add is rounded once, whether a lane falls in a vector or
+    // in a chunk's tail, and mixes slices and broadcast consts freely.
+    #[test]
+    fn test_mul_add() {
+        let mut tcx = TestCtx::new();
+        let n = tcx.len::<f64>() - 3;
+        let a: Vec<f64> = (0..n).map(|i| 1.0 + (i as f64) * f64::EPSILON).collect();
+        let b: Vec<f64> = (0..n).map(|i| 1.0 - (i as f64) * f64::EPSILON).collect();
+        let c: Vec<f32> = (0..n).map(|i| -(i as f32)).collect();
+        let exp: Vec<f64> = (0..n).map(|i| a[i].mul_add(b[i], c[i] as f64)).collect();
+        let res = tcx.get_eval_ctx().val_ternop(ValTernOpCode::MulAdd,
+                                                &Operand::from(&a),
+                                                &Operand::from(&b),
+                                                &Operand::from(&c));
+        if !cfg!(feature = "float") {
+            assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+            return;
+        }
+        assert_eq!(res.unwrap(), Operand::from(&exp));
+
+        let exp: Vec<f64> = a.iter().map(|a| a.mul_add(2.0, -1.0)).collect();
+        let res = tcx.get_eval_ctx().val_ternop(ValTernOpCode::MulAdd,
+                                                &Operand::from(&a),
+                                                &Operand::from(2.0f64),
+                                                &Operand::from(-1.0f64));
+        assert_eq!(res.unwrap(), Operand::from(&exp));
+
+        let res = tcx.get_eval_ctx().val_ternop(ValTernOpCode::MulAdd,
+                                                &Operand::from(2.0f32),
+                                                &Operand::from(3.0f32),
+                                                &Operand::from(1i8));
+        assert_eq!(res.unwrap(), Operand::from(7.0f32));
+
+        let res = tcx.get_eval_ctx().val_ternop(ValTernOpCode::MulAdd,
+                                                &Operand::from(&a),
+                                                &Operand::from(&b[1..]),
+                                                &Operand::from(0.0f64));
+        assert!(matches!(res, Err(EvalError::BadBuffer)));
+        let ints: Vec<i32> = (0..10).collect();
+        let res = tcx.get_eval_ctx().val_ternop(ValTernOpCode::MulAdd,
+                                                &Operand::from(&ints),
+                                                &Operand::from(&ints),
+                                                &Operand::from(&ints));
+        assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+    }
+}
+
+#[cfg(test)]
+mod test_cast {
+    use super::super::*;
+    use super::test_helpers::*;
+
+    #[test]
+    fn test_cast_int() {
+        let mut tcx = TestCtx::new();
+        let a = vec![-300i32, -1, 0, 100, 300];
+        let src = Operand::from(&a);
+        let res = tcx.get_eval_ctx().cast(&src, ScalarTy::TU8, CastMode::Wrapping);
+        assert_eq!(res.unwrap(), Operand::from(&vec![212u8, 255, 0, 100, 44]));
+        let res = tcx.get_eval_ctx().cast(&src, ScalarTy::TU8, CastMode::Saturating);
+        assert_eq!(res.unwrap(), Operand::from(&vec![0u8, 0, 0, 100, 255]));
+        let res = tcx.get_eval_ctx().cast(&src, ScalarTy::TU8, CastMode::Checked);
+        assert!(matches!(res, Err(EvalError::CastOutOfRange(0))));
+        let res = tcx.get_eval_ctx().cast(&Operand::from(&a[2..4]), ScalarTy::TU8,
+                                          CastMode::Checked);
+        assert_eq!(res.unwrap(), Operand::from(&vec![0u8, 100]));
+
+        let res = tcx.get_eval_ctx().cast(&Operand::from(-5i64), ScalarTy::TU32,
+                                          CastMode::Saturating);
+        assert_eq!(res.unwrap(), Operand::from(0u32));
+        let res = tcx.get_eval_ctx().cast(&Operand::from(300u16), ScalarTy::TI8,
+                                          CastMode::Checked);
+        assert!(matches!(res, Err(EvalError::CastOutOfRange(0))));
+        // Conversions to bool are truth tests in every mode.
+        let res = tcx.get_eval_ctx().cast(&src, ScalarTy::TBool, CastMode::Checked);
+        assert_eq!(res.unwrap(), Operand::from(&vec![true, true, false, true, true]));
+    }
+
+    // Faults are found in parallel, but the first one is reported.
+    #[test]
+    fn test_cast_first_fault() {
+        let mut tcx = TestCtx::new();
+        let n = tcx.len::<i64>();
+        let mut a: Vec<i64> = (0..n as i64).map(|x| x % 1000).collect();
+        a[n - 3] = -1;
+        a[n / 2] = 1 << 40;
+        let res = tcx.get_eval_ctx()
+            .with_chunk_bytes(4096)
+            .cast(&Operand::from(&a), ScalarTy::TU32, CastMode::Checked);
+        assert!(matches!(res, Err(EvalError::CastOutOfRange(i)) if i == n / 2));
+    }
+
+    #[test]
+    fn test_cast_float() {
+        if !cfg!(feature = "float") {
+            return;
+        }
+        let mut tcx = TestCtx::new();
+        let f = vec![1.5f64, -2.7, 1e20, -1e20, f64::NAN, 255.9, 256.0];
+        let src = Operand::from(&f);
+        let res = tcx.get_eval_ctx().cast(&src, ScalarTy::TI32, CastMode::Saturating);
+        assert_eq!(res.unwrap(), Operand::from(&vec![1i32, -2, i32::MAX, i32::MIN, 0, 255, 256]));
+        let res = tcx.get_eval_ctx().cast(&src, ScalarTy::TI32, CastMode::Checked);
+        assert!(matches!(res, Err(EvalError::CastOutOfRange(2))));
+        let res = tcx.get_eval_ctx().cast(&Operand::from(&f[4..]), ScalarTy::TI32,
+                                          CastMode::Checked);
+        assert!(matches!(res, Err(EvalError::CastNaN(0))));
+        let res = tcx.get_eval_ctx().cast(&Operand::from(&f[5..]), ScalarTy::TU8,
+                                          CastMode::Checked);
+        assert!(matches!(res, Err(EvalError::CastOutOfRange(1))));
+        let res = tcx.get_eval_ctx().cast(&Operand::from(&f[..2]), ScalarTy::TU8,
+                                          CastMode::Checked);
+        assert!(matches!(res, Err(EvalError::CastOutOfRange(1))));
+
+        // Bounds that aren't exactly representable in the source type.
+        let g = vec![-2147483648.0f32, 2147483648.0];
+        let res = tcx.get_eval_ctx().cast(&Operand::from(&g), ScalarTy::TI32, CastMode::Checked);
+        assert!(matches!(res, Err(EvalError::CastOutOfRange(1))));
+        let res = tcx.get_eval_ctx().cast(&Operand::from(&g[..1]), ScalarTy::TI32,
+                                          CastMode::Checked);
+        assert_eq!(res.unwrap(), Operand::from(&vec![i32::MIN]));
+
+        let res = tcx.get_eval_ctx().cast(&Operand::from(-1e300f64), ScalarTy::TF32,
+                                          CastMode::Saturating);
+        assert_eq!(res.unwrap(), Operand::from(f32::MIN));
+        let res = tcx.get_eval_ctx().cast(&Operand::from(f64::INFINITY), ScalarTy::TF32,
+                                          CastMode::Checked);
+        assert_eq!(res.unwrap(), Operand::from(f32::INFINITY));
+        let res = tcx.get_eval_ctx().cast(&Operand::from(1e300f64), ScalarTy::TF32,
+                                          CastMode::Checked);
+        assert!(matches!(res, Err(EvalError::CastOutOfRange(0))));
+        if cfg!(feature = "int128") {
+            let res = tcx.get_eval_ctx().cast(&Operand::from(u128::MAX), ScalarTy::TF32,
+                                              CastMode::Saturating);
+            assert_eq!(res.unwrap(), Operand::from(f32::MAX));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_promotion {
+    use super::super::*;
+    use super::test_helpers::*;
+
+    #[test]
+    fn test_can_represent() {
+        use ScalarTy::*;
+        assert!(TI16.can_represent(TU8));
+        assert!(!TI16.can_represent(TU16));
+        assert!(!TU64.can_represent(TI8));
+        assert!(TF32.can_represent(TI16));
+        assert!(!TF32.can_represent(TU32));
+        assert!(TF64.can_represent(TI32));
+        assert!(!TF64.can_represent(TI64));
+        assert!(TF64.can_represent(TF32));
+        assert!(!TF32.can_represent(TF64));
+        assert!(TU8.can_represent(TBool));
+        assert!(!TBool.can_represent(TU8));
+        // Every pair of types has a join, which can represent bool.
+        assert_eq!(TBool.join(TI32), TI32);
+        assert_eq!(TF32.join(TBool), TF32);
+        assert_eq!(TI8.join(TU32), TI64);
+        assert_eq!(TU128.join(TI16), TF64);
+        let all = [TBool, TU8, TU16, TU32, TU64, TU128, TI8, TI16, TI32, TI64, TI128, TF32, TF64];
+        for a in all.iter() {
+            for b in all.iter() {
+                assert_eq!(a.join(*b), b.join(*a));
+            }
+        }
+    }
+
+    #[test]
+    fn test_strict_promotion() {
+        let mut tcx = TestCtx::new();
+        let a: Vec<i16> = (0..100).map(|x| x - 50).collect();
+        let b: Vec<u8> = (0..100).collect();
+        let exp: Vec<i16> = a.iter().zip(&b).map(|(x, y)| x + *y as i16).collect();
+        let res = tcx.get_eval_ctx()
+            .with_promotion(&StrictPromotion)
+            .val_binop(ValBinOpCode::Add, &Operand::from(&a), &Operand::from(&b));
+        assert_eq!(res.unwrap(), Operand::from(&exp));
+
+        let c: Vec<i32> = (0..100).collect();
+        let res = tcx.get_eval_ctx()
+            .with_promotion(&StrictPromotion)
+            .val_binop(ValBinOpCode::Mul, &Operand::from(1.5f32), &Operand::from(&c));
+        assert!(matches!(res, Err(EvalError::LossyPromotion(ScalarTy::TF32, ScalarTy::TI32))));
+        let res = tcx.get_eval_ctx()
+            .with_promotion(&StrictPromotion)
+            .bool_binop(BoolBinOpCode::Lt, &Operand::from(&c), &Operand::from(u64::MAX));
+        assert_eq!(res.unwrap(), Operand::from(&vec![true; 100]));
+        let res = tcx.get_eval_ctx()
+            .with_promotion(&StrictPromotion)
+            .val_ternop(ValTernOpCode::MulAdd, &Operand::from(&c), &Operand::from(2.0f64),
+                        &Operand::from(u64::MAX));
+        assert!(matches!(res, Err(EvalError::LossyPromotion(ScalarTy::TF64, ScalarTy::TU64))));
+
+        // The default policy allows it, and bools join with anything.
+        let res = tcx.get_eval_ctx()
+            .val_binop(ValBinOpCode::Mul, &Operand::from(2.0f32), &Operand::from(&c));
+        if cfg!(feature = "float") {
+            let exp: Vec<f32> = c.iter().map(|x| *x as f32 * 2.0).collect();
+            assert_eq!(res.unwrap(), Operand::from(&exp));
+        }
+        let t = vec![true; 100];
+        let exp: Vec<u8> = b.iter().map(|x| x + 1).collect();
+        let res = tcx.get_eval_ctx()
+            .with_promotion(&StrictPromotion)
+            .val_binop(ValBinOpCode::Add, &Operand::from(&b), &Operand::from(&t));
+        assert_eq!(res.unwrap(), Operand::from(&exp));
+    }
+}
+
+#[cfg(test)]
+mod test_mixed_cmp {
+    use super::super::*;
+    use super::test_helpers::*;
+
+    fn cmp(op: BoolBinOpCode, lhs: Operand<'_>, rhs: Operand<'_>) -> Result<Const, EvalError> {
+        let mut tcx = TestCtx::new();
+        match tcx.get_eval_ctx().bool_binop(op, &lhs, &rhs)? {
+            Operand::OperandConst(c) => Ok(c),
+            _ => panic!("expected a const"),
+        }
+    }
+
+    #[test]
+    fn test_signed_unsigned() {
+        use BoolBinOpCode::*;
+        // Joins to i128, which is exact where compiled in; without it, they're
+        // compared exactly, unconverted.
+        assert_eq!(cmp(Lt, (-1i64).into(), u64::MAX.into()).unwrap(), Const::from(true));
+        assert_eq!(cmp(Eq, 5u64.into(), 5i64.into()).unwrap(), Const::from(true));
+        assert_eq!(cmp(Gt, u64::MAX.into(), (-1i8).into()).unwrap(), Const::from(true));
+        let mut tcx = TestCtx::new();
+        let a: Vec<u64> = vec![u64::MAX, 0, 1];
+        let b: Vec<i64> = vec![-1, 1, 1];
+        let res = tcx.get_eval_ctx().bool_binop(Lt, &Operand::from(&a), &Operand::from(&b));
+        assert_eq!(res.unwrap(), Operand::from(&vec![false, true, false]));
+        let c: Vec<i32> = vec![-1, 1, 2];
+        let res = tcx.get_eval_ctx().bool_binop(Ne, &Operand::from(&c), &Operand::from(&a));
+        assert_eq!(res.unwrap(), Operand::from(&vec![true, true, true]));
+
+        if ty_compiled_in(ScalarTy::TI128) {
+            // Joins to f64, which can't tell these apart.
+            let big = u128::MAX - 1;
+            assert_eq!(cmp(Lt, big.into(), u128::MAX.into()).unwrap(), Const::from(true));
+            assert_eq!(cmp(Gt, u128::MAX.into(), (-1i128).into()).unwrap(), Const::from(true));
+            assert_eq!(cmp(Eq, (i128::MAX as u128).into(), i128::MAX.into()).unwrap(),
+                       Const::from(true));
+            assert_eq!(cmp(Ne, ((1u128 << 100) + 1).into(), (1i128 << 100).into()).unwrap(),
+                       Const::from(true));
+            assert_eq!(cmp(Ge, (-1i8).into(), 0u128.into()).unwrap(), Const::from(false));
+        }
+
+        // Unsigned on the left gets swapped. Without the `int128` feature,
+        // 128-bit operands aren't supported at all.
+        let mut tcx = TestCtx::new();
+        let a: Vec<i128> = vec![-1, 0, 1, i128::MAX];
+        let b: Vec<u128> = vec![u128::MAX, 0, 0, i128::MAX as u128 + 1];
+        let res = tcx.get_eval_ctx().bool_binop(Le, &Operand::from(&b), &Operand::from(&a));
+        if ty_compiled_in(ScalarTy::TI128) {
+            assert_eq!(res.unwrap(), Operand::from(&vec![false, true, true, false]));
+        } else {
+            assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+        }
+    }
+
+    #[test]
+    fn test_float_int() {
+        use BoolBinOpCode::*;
+        if !ty_compiled_in(ScalarTy::TF64) {
+            assert!(matches!(cmp(Lt, 1.0f32.into(), 2i64.into()),
+                             Err(EvalError::UnsupportedOp)));
+            return;
+        }
+        // 2^53 + 1 isn't an f64, so converting it would make these equal.
+        let n = (1i64 << 53) + 1;
+        assert_eq!(cmp(Lt, ((1u64 << 53) as f64).into(), n.into()).unwrap(), Const::from(true));
+        assert_eq!(cmp(Eq, (n as f32).into(), n.into()).unwrap(), Const::from(false));
+        // 2^63 is just past i64::MAX, and u64 has one more bit.
+        assert_eq!(cmp(Gt, 9.223372e18f32.into(), i64::MAX.into()).unwrap(), Const::from(true));
+        assert_eq!(cmp(Lt, (-9.223372e18f32).into(), i64::MIN.into()).unwrap(),
+                   Const::from(false));
+        assert_eq!(cmp(Eq, 9.223372e18f32.into(), (1u64 << 63).into()).unwrap(),
+                   Const::from(true));
+        // Fractions break ties.
+        assert_eq!(cmp(Gt, (-2.5f64).into(), (-3i64).into()).unwrap(), Const::from(true));
+        assert_eq!(cmp(Lt, (-2.5f64).into(), (-2i64).into()).unwrap(), Const::from(true));
+        assert_eq!(cmp(Le, i64::MAX.into(), 9.3e18f64.into()).unwrap(), Const::from(true));
+        // NaN is unordered with everything: only Ne holds.
+        for op in &[Lt, Le, Eq, Ge, Gt] {
+            assert_eq!(cmp(op.clone(), f64::NAN.into(), i64::MAX.into()).unwrap(), Const::from(false));
+            assert_eq!(cmp(op.clone(), u64::MAX.into(), f32::NAN.into()).unwrap(), Const::from(false));
+        }
+        assert_eq!(cmp(Ne, f64::NAN.into(), 0u64.into()).unwrap(), Const::from(true));
+
+        let mut tcx = TestCtx::new();
+        let a: Vec<i64> = vec![i64::MIN, -1, n, i64::MAX];
+        let res = tcx.get_eval_ctx().bool_binop(Lt, &Operand::from(&a),
+                                                &Operand::from((1u64 << 53) as f64));
+        assert_eq!(res.unwrap(), Operand::from(&vec![true, true, false, false]));
+        let b: Vec<f32> = vec![f32::NAN, -0.5, 0.0, 1.0e30];
+        let c: Vec<i64> = vec![0, -1, 0, i64::MAX];
+        let res = tcx.get_eval_ctx().bool_binop(Ge, &Operand::from(&b), &Operand::from(&c));
+        assert_eq!(res.unwrap(), Operand::from(&vec![false, true, true, true]));
+        let d: Vec<i64> = vec![0, 1, 2];
+        let res = tcx.get_eval_ctx().bool_binop(Ge, &Operand::from(&b), &Operand::from(&d));
+        assert!(matches!(res, Err(EvalError::BadBuffer)));
+    }
+
+    // Exact comparisons never promote, so a strict policy that would refuse
+    // to join the types doesn't get in their way.
+    #[test]
+    fn test_strict_exact() {
+        use BoolBinOpCode::*;
+        let strict = |op: BoolBinOpCode, lhs: Operand, rhs: Operand| {
+            let plan = plan_bool_binop(&StrictPromotion, op.clone(), lhs.desc(), rhs.desc());
+            let mut tcx = TestCtx::new();
+            let res = tcx.get_eval_ctx().with_promotion(&StrictPromotion)
+                .bool_binop(op, &lhs, &rhs);
+            assert_eq!(plan.is_ok(), res.is_ok());
+            res.map(OwnedOperand::from)
+        };
+        let a: Vec<u64> = vec![u64::MAX, 0, 1];
+        let b: Vec<i64> = vec![-1, 1, 1];
+        assert_eq!(strict(Lt, Operand::from(&a), Operand::from(&b)).unwrap(),
+                   OwnedOperand::from(vec![false, true, false]));
+        assert_eq!(strict(Gt, Operand::from(u64::MAX), Operand::from(-1i64)).unwrap(),
+                   OwnedOperand::from(true));
+        if ty_compiled_in(ScalarTy::TF32) {
+            let f: Vec<f32> = vec![9.223372e18, -0.5, f32::NAN];
+            let c: Vec<i64> = vec![i64::MAX, -1, 0];
+            assert_eq!(strict(Gt, Operand::from(&f), Operand::from(&c)).unwrap(),
+                       OwnedOperand::from(vec![true, true, false]));
+            assert_eq!(strict(Le, Operand::from(i64::MIN), Operand::from(-9.3e18f32)).unwrap(),
+                       OwnedOperand::from(false));
+            // A lossless join goes through the policy as usual.
+            assert_eq!(strict(Lt, Operand::from(1.5f32), Operand::from(2i16)).unwrap(),
+                       OwnedOperand::from(true));
+        }
+        if ty_compiled_in(ScalarTy::TI128) {
+            let u: Vec<u128> = vec![u128::MAX, 0, i128::MAX as u128 + 1];
+            let i: Vec<i128> = vec![-1, 0, i128::MAX];
+            assert_eq!(strict(Ge, Operand::from(&u), Operand::from(&i)).unwrap(),
+                       OwnedOperand::from(vec![true, true, true]));
+            assert_eq!(strict(Eq, Operand::from(-1i128), Operand::from(u128::MAX)).unwrap(),
+                       OwnedOperand::from(false));
+        }
+
+        // Outer products compare the same way.
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().with_promotion(&StrictPromotion)
+            .outer_bool_binop(Lt, &(&b[..]).into(), &(&a[..]).into()).unwrap();
+        assert_eq!(elements::<bool>(&r),
+                   vec![true, true, true, true, false, false, true, false, false]);
+    }
+}
+
+#[cfg(test)]
+mod test_plan {
+    use super::super::*;
+    use super::test_helpers::*;
+    use OperandDesc::*;
+
+    // Buffers of exactly the sizes a plan asks for, or one byte short of them.
+    struct PlannedBufs {
+        tmp1: Vec<u8>,
+        tmp2: Vec<u8>,
+        out: Vec<u8>,
+    }
+
+    impl PlannedBufs {
+        fn new(plan: &EvalPlan, short: usize) -> PlannedBufs {
+            PlannedBufs {
+                tmp1: vec![0; plan.tmp1],
+                tmp2: vec![0; plan.tmp2],
+                out: vec![0; plan.out - short],
+            }
+        }
+        fn ctx(&mut self) -> EvalCtx<'_> {
+            EvalCtx::new(&mut self.tmp1[..], &mut self.tmp2[..], &mut self.out[..])
+        }
+    }
+
+    #[test]
+    fn test_plan_binops() {
+        use ScalarTy::*;
+        let a: Vec<i32> = (0..100).collect();
+        let b: Vec<i64> = (0..100).map(|x| x * 3).collect();
+        let (la, lb) = (Operand::from(&a), Operand::from(&b));
+        let plan = plan_val_binop(&DefaultPromotion, ValBinOpCode::Add, la.desc(), lb.desc());
+        let plan = plan.unwrap();
+        assert_eq!(plan, EvalPlan { result: SliceDesc(TI64, 100),
+                                    tmp1: 800, tmp2: 0, out: 800, align: 8 });
+        let mut bufs = PlannedBufs::new(&plan, 0);
+        let res = bufs.ctx().val_binop(ValBinOpCode::Add, &la, &lb);
+        assert_eq!(res.unwrap().desc(), plan.result);
+        let mut bufs = PlannedBufs::new(&plan, 1);
+        let res = bufs.ctx().val_binop(ValBinOpCode::Add, &la, &lb);
+        assert!(matches!(res, Err(EvalError::BadBuffer)));
+
+        // Comparing against a const of the same type needs only `out`.
+        let c = Operand::from(7i64);
+        let plan = plan_bool_binop(&DefaultPromotion, BoolBinOpCode::Lt, lb.desc(), c.desc());
+        let plan = plan.unwrap();
+        assert_eq!(plan, EvalPlan { result: SliceDesc(TBool, 100),
+                                    tmp1: 0, tmp2: 0, out: 100, align: 1 });
+        let mut bufs = PlannedBufs::new(&plan, 0);
+        let res = bufs.ctx().bool_binop(BoolBinOpCode::Lt, &lb, &c);
+        assert_eq!(res.unwrap().desc(), plan.result);
+
+        // Mixed signed and unsigned comparisons convert to i128 where it's
+        // compiled in, and otherwise are exact, needing only `out`.
+        let d: Vec<u64> = (0..100).collect();
+        let ld = Operand::from(&d);
+        let plan = plan_bool_binop(&DefaultPromotion, BoolBinOpCode::Eq, ld.desc(), lb.desc());
+        let plan = plan.unwrap();
+        if ty_compiled_in(TI128) {
+            assert_eq!((plan.tmp1, plan.tmp2, plan.out), (1600, 1600, 100));
+        } else {
+            assert_eq!((plan.tmp1, plan.tmp2, plan.out), (0, 0, 100));
+        }
+        let mut bufs = PlannedBufs::new(&plan, 0);
+        let res = bufs.ctx().bool_binop(BoolBinOpCode::Eq, &ld, &lb);
+        assert_eq!(res.unwrap().desc(), plan.result);
+        if ty_compiled_in(TF32) {
+            let e = Operand::from(1.5f32);
+            let plan = plan_bool_binop(&DefaultPromotion, BoolBinOpCode::Gt, e.desc(), lb.desc());
+            let plan = plan.unwrap();
+            assert_eq!(plan, EvalPlan { result: SliceDesc(TBool, 100),
+                                        tmp1: 0, tmp2: 0, out: 100, align: 1 });
+            let mut bufs = PlannedBufs::new(&plan, 0);
+            let res = bufs.ctx().bool_binop(BoolBinOpCode::Gt, &e, &lb);
+            assert_eq!(res.unwrap().desc(), plan.result);
+        }
+
+        // Consts need no buffers.
+        let plan = plan_val_binop(&DefaultPromotion, ValBinOpCode::Mul,
+                                  ConstDesc(TU8), ConstDesc(TI16));
+        assert_eq!(plan.unwrap(), EvalPlan { result: ConstDesc(TI16),
+                                             tmp1: 0, tmp2: 0, out: 0, align: 1 });
+    }
+
+    #[test]
+    fn test_plan_errors() {
+        use ScalarTy::*;
+        assert!(matches!(plan_val_binop(&DefaultPromotion, ValBinOpCode::Add,
+                                        SliceDesc(TBool, 4), SliceDesc(TBool, 4)),
+                         Err(EvalError::UnsupportedOp)));
+        assert!(matches!(plan_val_unop(ValUnOpCode::Neg, SliceDesc(TU8, 4)),
+                         Err(EvalError::UnsupportedOp)));
+        assert!(matches!(plan_bool_unop(BoolUnOpCode::IsNaN, ConstDesc(TI32)),
+                         Err(EvalError::UnsupportedOp)));
+        assert!(matches!(plan_val_binop(&DefaultPromotion, ValBinOpCode::Sub,
+                                        SliceDesc(TU8, 4), SliceDesc(TU8, 5)),
+                         Err(EvalError::BadBuffer)));
+        assert!(matches!(plan_val_binop(&StrictPromotion, ValBinOpCode::Sub,
+                                        SliceDesc(TF32, 4), ConstDesc(TI32)),
+                         Err(EvalError::LossyPromotion(TF32, TI32))));
+        let plan = plan_val_unop(ValUnOpCode::Sqrt, SliceDesc(TF64, 4));
+        assert_eq!(plan.is_ok(), ty_compiled_in(TF64));
+        let plan = plan_val_binop(&DefaultPromotion, ValBinOpCode::BitXor,
+                                  SliceDesc(TU32, 4), ConstDesc(TU32));
+        assert_eq!(plan.is_ok(), cfg!(feature = "bitwise"));
+    }
+
+    #[test]
+    fn test_plan_unops_and_casts() {
+        use ScalarTy::*;
+        let a: Vec<i16> = (0..50).map(|x| x - 25).collect();
+        let la = Operand::from(&a);
+        let plan = plan_val_unop(ValUnOpCode::Abs, la.desc()).unwrap();
+        assert_eq!(plan, EvalPlan { result: SliceDesc(TI16, 50),
+                                    tmp1: 0, tmp2: 0, out: 100, align: 2 });
+        let mut bufs = PlannedBufs::new(&plan, 0);
+        let res = bufs.ctx().val_unop(ValUnOpCode::Abs, &la);
+        assert_eq!(res.unwrap().desc(), plan.result);
+
+        // Converting to the same type is free.
+        let plan = plan_conv(la.desc(), TI16).unwrap();
+        assert_eq!(plan, EvalPlan { result: SliceDesc(TI16, 50),
+                                    tmp1: 0, tmp2: 0, out: 0, align: 1 });
+        let mut bufs = PlannedBufs::new(&plan, 0);
+        let res = bufs.ctx().conv(&la, TI16);
+        assert_eq!(res.unwrap(), la);
+        let plan = plan_cast(la.desc(), TU8, CastMode::Saturating).unwrap();
+        assert_eq!(plan, EvalPlan { result: SliceDesc(TU8, 50),
+                                    tmp1: 0, tmp2: 0, out: 50, align: 1 });
+        let mut bufs = PlannedBufs::new(&plan, 0);
+        let res = bufs.ctx().cast(&la, TU8, CastMode::Saturating);
+        assert_eq!(res.unwrap().desc(), plan.result);
+    }
+
+    #[test]
+    fn test_plan_ternop() {
+        use ScalarTy::*;
+        if !ty_compiled_in(TF64) {
+            return;
+        }
+        let a: Vec<f64> = (0..30).map(|x| x as f64).collect();
+        let c: Vec<f32> = (0..30).map(|x| x as f32).collect();
+        let (la, lb, lc) = (Operand::from(&a), Operand::from(2i32), Operand::from(&c));
+        let plan = plan_val_ternop(&DefaultPromotion, ValTernOpCode::MulAdd,
+                                   la.desc(), lb.desc(), lc.desc()).unwrap();
+        assert_eq!(plan, EvalPlan { result: SliceDesc(TF64, 30),
+                                    tmp1: 240, tmp2: 240, out: 240, align: 8 });
+        let mut bufs = PlannedBufs::new(&plan, 0);
+        let res = bufs.ctx()
+            .val_ternop(ValTernOpCode::MulAdd, &la, &lb, &lc);
+        let exp: Vec<f64> = (0..30).map(|x| x as f64 * 3.0).collect();
+        assert_eq!(res.unwrap(), Operand::from(&exp));
+    }
+}
+
+#[cfg(test)]
+mod test_caps {
+    use super::super::*;
+    use super::test_helpers::*;
+
+    #[test]
+    fn test_all_lists() {
+        // The codec tags are dense, so each `ALL` should list them in order.
+        macro_rules! check_all {
+            ($($E:ident)*) => {
+                $(
+                    for (i, x) in $E::ALL.iter().enumerate() {
+                        assert_eq!(x.codec_tag() as usize, i);
+                    }
+                    assert!($E::from_codec_tag($E::ALL.len() as u8).is_none());
+                )*
+            }
+        }
+        check_all!(ScalarTy ValBinOpCode ValUnOpCode ValTernOpCode BoolBinOpCode BoolUnOpCode);
+        assert_eq!(capabilities().count(), (18 + 30 + 1 + 6 + 3) * 13);
+    }
+
+    #[test]
+    fn test_caps_match_eval() {
+        let mut tcx = TestCtx::new();
+        for cap in capabilities() {
+            let one = match tcx.get_eval_ctx().conv(&Operand::from(1u8), cap.ty) {
+                Ok(x) => x,
+                Err(_) => {
+                    // Compiled-out types support nothing.
+                    assert!(!ty_compiled_in(cap.ty));
+                    assert!(!cap.supported, "{:?}", cap);
+                    continue;
+                }
+            };
+            let mut tcx = TestCtx::new();
+            let ctx = tcx.get_eval_ctx();
+            let res = match &cap.op {
+                OpCode::ValBin(op) => ctx.val_binop(op.clone(), &one, &one),
+                OpCode::ValUn(op) => ctx.val_unop(op.clone(), &one),
+                OpCode::ValTern(op) => ctx.val_ternop(op.clone(), &one, &one, &one),
+                OpCode::BoolBin(op) => ctx.bool_binop(op.clone(), &one, &one),
+                OpCode::BoolUn(op) => ctx.bool_unop(op.clone(), &one),
+            };
+            assert_eq!(res.is_ok(), cap.supported, "{:?}", cap);
+            if let Ok(r) = res {
+                assert_eq!(r.get_scalar_ty(), cap.result_ty, "{:?}", cap);
+            }
+        }
+
+        let add = OpCode::ValBin(ValBinOpCode::Add);
+        assert!(add.supports(ScalarTy::TU8));
+        assert!(!add.supports(ScalarTy::TBool));
+        let lt = OpCode::BoolBin(BoolBinOpCode::Lt).capability(ScalarTy::TI32);
+        assert!(lt.supported);
+        assert_eq!(lt.result_ty, ScalarTy::TBool);
+        assert_eq!(OpCode::ValUn(ValUnOpCode::Sin).supports(ScalarTy::TF64),
+                   cfg!(feature = "transcendental"));
+    }
+}
+
+#[cfg(test)]
+mod test_in_place {
+    use super::super::*;
+    use super::test_helpers::*;
+
+    // In-place operations use none of the buffers.
+    fn empty_ctx() -> EvalCtx<'static> {
+        EvalCtx::new(&mut [], &mut [], &mut [])
+    }
+
+    #[test]
+    fn test_in_place_matches_out_of_place() {
+        let n = 10_000;
+        let lhs: Vec<i32> = (0..n).map(|i| i * 7 - 20_000).collect();
+        let rhs: Vec<i32> = (0..n).map(|i| 13 - i).collect();
+        let modes = [Parallelism::Serial, Parallelism::Parallel];
+        for &mode in modes.iter() {
+            let mut tcx = TestCtx::new();
+            let want = tcx.get_eval_ctx().val_unop(ValUnOpCode::Neg, &(&lhs).into()).unwrap();
+            let mut acc = lhs.clone();
+            empty_ctx().with_chunk_bytes(4096).with_parallelism(mode)
+                .val_unop_in_place(ValUnOpCode::Neg, &mut (&mut acc).into()).unwrap();
+            assert_eq!(Operand::from(&acc), want);
+
+            let mut tcx = TestCtx::new();
+            let want = tcx.get_eval_ctx()
+                .val_binop(ValBinOpCode::Sub, &(&lhs).into(), &(&rhs).into()).unwrap();
+            let mut acc = lhs.clone();
+            empty_ctx().with_chunk_bytes(4096).with_parallelism(mode)
+                .val_binop_in_place(ValBinOpCode::Sub, &mut (&mut acc).into(),
+                                    &(&rhs).into()).unwrap();
+            assert_eq!(Operand::from(&acc), want);
+
+            let mut tcx = TestCtx::new();
+            let want = tcx.get_eval_ctx()
+                .val_binop(ValBinOpCode::Max, &(&lhs).into(), &0i32.into()).unwrap();
+            let mut acc = lhs.clone();
+            // A narrower const promotes to the slice's type.
+            empty_ctx().with_chunk_bytes(4096).with_parallelism(mode)
+                .val_binop_in_place(ValBinOpCode::Max, &mut (&mut acc).into(),
+                                    &0u8.into()).unwrap();
+            assert_eq!(Operand::from(&acc), want);
+        }
+    }
+
+    #[test]
+    fn test_in_place_errors() {
+        let mut acc = vec![1u8, 2, 3];
+
+        // Anything that would need converting is refused.
+        let wide = vec![1u16, 2, 3];
+        let res = empty_ctx().val_binop_in_place(
+            ValBinOpCode::Add, &mut (&mut acc).into(), &(&wide).into());
+        assert!(matches!(res, Err(EvalError::InPlaceConversion(ScalarTy::TU8, ScalarTy::TU16))));
+        let res = empty_ctx().val_binop_in_place(
+            ValBinOpCode::Add, &mut (&mut acc).into(), &1i32.into());
+        assert!(matches!(res, Err(EvalError::InPlaceConversion(ScalarTy::TU8, ScalarTy::TI32))));
+        let narrow = vec![1i8, 2, 3];
+        let res = empty_ctx().val_binop_in_place(
+            ValBinOpCode::Add, &mut (&mut acc).into(), &(&narrow).into());
+        assert!(matches!(res, Err(EvalError::InPlaceConversion(ScalarTy::TU8, ScalarTy::TI8))));
+
+        let short = vec![1u8, 2];
+        let res = empty_ctx().val_binop_in_place(
+            ValBinOpCode::Add, &mut (&mut acc).into(), &(&short).into());
+        assert!(matches!(res, Err(EvalError::BadBuffer)));
+
+        // Unsupported opcodes leave the slice as it was.
+        let res = empty_ctx().val_unop_in_place(ValUnOpCode::Neg, &mut (&mut acc).into());
+        assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+        let mut bools = vec![true, false];
+        let res = empty_ctx().val_binop_in_place(
+            ValBinOpCode::Div, &mut (&mut bools).into(), &true.into());
+        assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+        assert_eq!(acc, vec![1, 2, 3]);
+        assert_eq!(bools, vec![true, false]);
+
+        empty_ctx().val_binop_in_place(
+            ValBinOpCode::Mul, &mut (&mut acc).into(), &3u8.into()).unwrap();
+        assert_eq!(acc, vec![3, 6, 9]);
+    }
+}
+
+#[cfg(test)]
+mod test_stream {
+    use super::super::*;
+    use super::test_helpers::*;
+    use super::super::operands::ScalarOf;
+
+    // Splits `xs` into chunks of the given lengths, cycling through them.
+    fn chunked<'a, T>(mut xs: &'a [T], lens: &[usize]) -> Vec<Slice<'a>>
+    where Slice<'a>: From<&'a [T]>
+    {
+        let mut chunks = Vec::new();
+        for &n in lens.iter().cycle() {
+            if xs.is_empty() {
+                break;
+            }
+            let (head, tail) = xs.split_at(n.min(xs.len()));
+            chunks.push(head.into());
+            xs = tail;
+        }
+        chunks
+    }
+
+    // A sink that appends each result chunk of type `T` to `out`.
+    fn collect<T: ScalarOf + Copy>(out: &mut Vec<T>) -> impl FnMut(Operand<'_>) -> Result<(), EvalError> + '_ {
+        move |res| {
+            match res {
+                Operand::OperandSlice(s) => out.extend_from_slice(T::of_slice(&s).unwrap()),
+                Operand::OperandConst(c) => out.push(T::of_const(&c).unwrap()),
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_stream_misaligned_chunks() {
+        let n = 1000;
+        let lhs: Vec<i32> = (0..n).map(|i| i * 3 - 700).collect();
+        let rhs: Vec<i16> = (0..n).map(|i| (i % 37) as i16 - 18).collect();
+        let want: Vec<i32> = lhs.iter().zip(rhs.iter()).map(|(l, r)| l * (*r as i32)).collect();
+
+        // Chunk boundaries that never line up, including empty chunks.
+        let mut tcx = TestCtx::new();
+        let mut got: Vec<i32> = Vec::new();
+        tcx.get_eval_ctx().stream_val_binop(
+            ValBinOpCode::Mul,
+            StreamOperand::chunks(chunked(&lhs, &[7, 0, 64, 3])),
+            StreamOperand::chunks(chunked(&rhs, &[50, 1])),
+            collect(&mut got)).unwrap();
+        assert_eq!(got, want);
+
+        let mut tcx = TestCtx::new();
+        let mut got: Vec<bool> = Vec::new();
+        tcx.get_eval_ctx().stream_bool_binop(
+            BoolBinOpCode::Lt,
+            StreamOperand::chunks(chunked(&lhs, &[100])),
+            Const::from(0i32).into(),
+            collect(&mut got)).unwrap();
+        assert_eq!(got, lhs.iter().map(|&x| x < 0).collect::<Vec<_>>());
+
+        let mut tcx = TestCtx::new();
+        let mut got: Vec<i32> = Vec::new();
+        tcx.get_eval_ctx().stream_val_unop(
+            ValUnOpCode::Abs, chunked(&lhs, &[9, 90]), collect(&mut got)).unwrap();
+        assert_eq!(got, lhs.iter().map(|x| x.abs()).collect::<Vec<_>>());
+
+        let mut tcx = TestCtx::new();
+        let mut got: Vec<i64> = Vec::new();
+        tcx.get_eval_ctx().stream_conv(
+            chunked(&rhs, &[11]), ScalarTy::TI64, collect(&mut got)).unwrap();
+        assert_eq!(got, rhs.iter().map(|&x| x as i64).collect::<Vec<_>>());
+
+        let mut tcx = TestCtx::new();
+        let mut got: Vec<u8> = Vec::new();
+        tcx.get_eval_ctx().stream_cast(
+            chunked(&lhs, &[13]), ScalarTy::TU8, CastMode::Saturating,
+            collect(&mut got)).unwrap();
+        assert_eq!(got, lhs.iter().map(|&x| x.clamp(0, 255) as u8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_stream_ends() {
+        let xs: Vec<u32> = (0..100).collect();
+
+        // Inputs of different total lengths are an error, after the pieces
+        // they have in common.
+        let mut tcx = TestCtx::new();
+        let mut got: Vec<u32> = Vec::new();
+        let res = tcx.get_eval_ctx().stream_val_binop(
+            ValBinOpCode::Add,
+            StreamOperand::chunks(chunked(&xs, &[30])),
+            StreamOperand::chunks(chunked(&xs[..90], &[30])),
+            collect(&mut got));
+        assert!(matches!(res, Err(EvalError::BadBuffer)));
+        assert_eq!(got.len(), 90);
+
+        // No chunks at all gives no results; only consts gives one.
+        let mut tcx = TestCtx::new();
+        let mut got: Vec<u32> = Vec::new();
+        tcx.get_eval_ctx().stream_conv(
+            Vec::new(), ScalarTy::TU32, collect(&mut got)).unwrap();
+        assert!(got.is_empty());
+        let mut tcx = TestCtx::new();
+        tcx.get_eval_ctx().stream_val_binop(
+            ValBinOpCode::Add, Const::from(2u32).into(),
+            Const::from(3u32).into(),
+            collect(&mut got)).unwrap();
+        assert_eq!(got, vec![5]);
+
+        // Errors from the sink stop the stream.
+        let mut tcx = TestCtx::new();
+        let mut calls = 0;
+        let res = tcx.get_eval_ctx().stream_conv(
+            chunked(&xs, &[10]), ScalarTy::TU64,
+            |_| { calls += 1; if calls == 3 { Err(EvalError::BadBuffer) } else { Ok(()) } });
+        assert!(matches!(res, Err(EvalError::BadBuffer)));
+        assert_eq!(calls, 3);
+
+        // Checked cast errors are indexed from the start of the column.
+        let mut tcx = TestCtx::new();
+        let mut got: Vec<u8> = Vec::new();
+        let big: Vec<u32> = (200..300).collect();
+        let res = tcx.get_eval_ctx().stream_cast(
+            chunked(&big, &[30]), ScalarTy::TU8, CastMode::Checked, collect(&mut got));
+        assert!(matches!(res, Err(EvalError::CastOutOfRange(56))));
+    }
+
+    #[test]
+    fn test_stream_ternop() {
+        if !ty_compiled_in(ScalarTy::TF64) {
+            return;
+        }
+        let a: Vec<f64> = (0..200).map(|i| i as f64).collect();
+        let b: Vec<f64> = (0..200).map(|i| 0.5 * i as f64).collect();
+        let mut tcx = TestCtx::new();
+        let mut got: Vec<f64> = Vec::new();
+        tcx.get_eval_ctx().stream_val_ternop(
+            ValTernOpCode::MulAdd,
+            StreamOperand::chunks(chunked(&a, &[17])),
+            StreamOperand::chunks(chunked(&b, &[40, 3])),
+            Const::from(1.0f64).into(),
+            collect(&mut got)).unwrap();
+        let want: Vec<f64> = a.iter().zip(b.iter()).map(|(x, y)| x.mul_add(*y, 1.0)).collect();
+        assert_eq!(got, want);
+    }
+
+    // Reductions carry their accumulator across chunks, folding from the left.
+    #[test]
+    fn test_stream_reduce() {
+        use ValBinOpCode::*;
+        let a: Vec<i32> = (0..1000).map(|i| i * 7 - 3000).collect();
+        let mut tcx = TestCtx::new();
+        let res = tcx.get_eval_ctx()
+            .stream_reduce(Add, Const::from(5i64), chunked(&a, &[13, 200, 1]));
+        assert_eq!(res.unwrap(), Const::from(5 + a.iter().map(|x| *x as i64).sum::<i64>()));
+        let res = tcx.get_eval_ctx()
+            .stream_reduce(Sub, Const::from(0i32), chunked(&a, &[64]));
+        assert_eq!(res.unwrap(), Const::from(a.iter().fold(0i32, |acc, x| acc - x)));
+        let res = tcx.get_eval_ctx()
+            .stream_reduce(Max, Const::from(i32::MIN), chunked(&a, &[3, 500]));
+        assert_eq!(res.unwrap(), Const::from(3993i32));
+        let res = tcx.get_eval_ctx()
+            .stream_reduce(Mul, Const::from(2u8), Vec::<Slice>::new());
+        assert_eq!(res.unwrap(), Const::from(2u8));
+        if ty_compiled_in(ScalarTy::TF64) {
+            let f: Vec<f64> = (1..300).map(|i| 1.0 / i as f64).collect();
+            let res = tcx.get_eval_ctx()
+                .stream_reduce(Add, Const::from(0.0f64), chunked(&f, &[7, 31]));
+            assert_eq!(res.unwrap(), Const::from(f.iter().fold(0.0, |acc, x| acc + x)));
+        } else {
+            let res = tcx.get_eval_ctx()
+                .stream_reduce(Atan2, Const::from(0i32), chunked(&a, &[64]));
+            assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_shaped {
+    use super::super::*;
+    use super::test_helpers::*;
+
+    #[test]
+    fn test_agree_shapes() {
+        assert_eq!(agree_shapes(&[2, 3], &[2, 3]).unwrap(), vec![2, 3]);
+        assert_eq!(agree_shapes(&[], &[2, 3]).unwrap(), vec![2, 3]);
+        assert_eq!(agree_shapes(&[2, 3], &[1, 1, 1]).unwrap(), vec![2, 3]);
+        assert_eq!(agree_shapes(&[1], &[]).unwrap(), vec![1]);
+        assert_eq!(agree_shapes(&[2], &[2, 3]).unwrap(), vec![2, 3]);
+        assert_eq!(agree_shapes(&[2, 3, 4], &[2, 3]).unwrap(), vec![2, 3, 4]);
+        assert!(matches!(agree_shapes(&[3], &[2, 3]), Err(EvalError::ShapeMismatch(..))));
+        assert!(matches!(agree_shapes(&[2, 3], &[3, 2]), Err(EvalError::ShapeMismatch(..))));
+    }
+
+    #[test]
+    fn test_shaped_views() {
+        let xs: Vec<i32> = (0..6).collect();
+        assert!(ShapedOperand::new((&xs[..]).into(), vec![4, 2]).is_err());
+        assert!(ShapedOperand::with_strides((&xs[..]).into(), 0, vec![3, 2], vec![1]).is_err());
+        assert!(ShapedOperand::with_strides((&xs[..]).into(), 1, vec![3, 2], vec![2, 1]).is_err());
+        assert!(ShapedOperand::with_strides((&xs[..]).into(), 0, vec![2], vec![-1]).is_err());
+        let rev = ShapedOperand::with_strides((&xs[..]).into(), 5, vec![6], vec![-1]).unwrap();
+        assert_eq!(rev.as_slice(), None);
+        let m = ShapedOperand::new((&xs[..]).into(), vec![2, 3]).unwrap();
+        assert_eq!(m.rank(), 2);
+        assert_eq!(m.strides(), &[3, 1]);
+        assert_eq!(m.as_slice(), Some((&xs[..]).into()));
+        let row = ShapedOperand::with_strides((&xs[..]).into(), 3, vec![1, 3], vec![99, 1]).unwrap();
+        assert_eq!(row.as_slice(), Some((&xs[3..]).into()));
+    }
+
+    #[test]
+    fn test_shaped_binops() {
+        let xs: Vec<i32> = (0..6).collect();
+        let ys: Vec<i32> = (0..6).map(|i| 10 * i).collect();
+        let m = ShapedOperand::new((&xs[..]).into(), vec![2, 3]).unwrap();
+        let n = ShapedOperand::new((&ys[..]).into(), vec![2, 3]).unwrap();
+
+        // Dense operands of the same shape.
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().shaped_val_binop(ValBinOpCode::Add, &m, &n).unwrap();
+        assert_eq!(r.shape(), &[2, 3]);
+        assert_eq!(elements::<i32>(&r), vec![0, 11, 22, 33, 44, 55]);
+
+        // Scalar and singleton extension.
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx()
+            .shaped_val_binop(ValBinOpCode::Mul, &m, &Const::from(2i32).into()).unwrap();
+        assert_eq!(elements::<i32>(&r), vec![0, 2, 4, 6, 8, 10]);
+        let one = ShapedOperand::new((&ys[1..2]).into(), vec![1, 1, 1]).unwrap();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().shaped_val_binop(ValBinOpCode::Sub, &one, &m).unwrap();
+        assert_eq!(r.shape(), &[2, 3]);
+        assert_eq!(elements::<i32>(&r), vec![10, 9, 8, 7, 6, 5]);
+
+        // Leading-axis agreement: each element of a vector is extended across
+        // a row of the matrix.
+        let v: Vec<i32> = vec![100, 200];
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx()
+            .shaped_val_binop(ValBinOpCode::Add, &Slice::from(&v[..]).into(), &m).unwrap();
+        assert_eq!(r.shape(), &[2, 3]);
+        assert_eq!(elements::<i32>(&r), vec![100, 101, 102, 203, 204, 205]);
+
+        // A transposed view is walked by its strides.
+        let t = ShapedOperand::with_strides((&ys[..]).into(), 0, vec![3, 2], vec![1, 3]).unwrap();
+        let m32 = ShapedOperand::new((&xs[..]).into(), vec![3, 2]).unwrap();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().shaped_val_binop(ValBinOpCode::Add, &m32, &t).unwrap();
+        assert_eq!(elements::<i32>(&r), vec![0, 31, 12, 43, 24, 55]);
+
+        // Comparisons give bools, and a mixed-type comparison is exact.
+        if ty_compiled_in(ScalarTy::TI128) {
+            let big: Vec<u64> = vec![u64::MAX; 6];
+            let b = ShapedOperand::new((&big[..]).into(), vec![2, 3]).unwrap();
+            let mut tcx = TestCtx::new();
+            let r = tcx.get_eval_ctx().shaped_bool_binop(BoolBinOpCode::Lt, &m, &b).unwrap();
+            assert_eq!(elements::<bool>(&r), vec![true; 6]);
+        }
+
+        let mut tcx = TestCtx::new();
+        let res = tcx.get_eval_ctx().shaped_val_binop(ValBinOpCode::Add, &m, &m32);
+        assert!(matches!(res, Err(EvalError::ShapeMismatch(..))));
+    }
+
+    #[test]
+    fn test_shaped_edge_cases() {
+        let xs: Vec<i32> = (0..6).collect();
+
+        // Only consts gives a splatted const.
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().shaped_val_binop(
+            ValBinOpCode::Add, &ShapedOperand::splat(1i32.into(), vec![2, 2]),
+            &Const::from(2i32).into()).unwrap();
+        assert_eq!(r.shape(), &[2, 2]);
+        assert_eq!(r.base(), &Operand::OperandConst(3i32.into()));
+
+        // Rows where every operand is a repeated element still get computed.
+        let cols = ShapedOperand::with_strides((&xs[..]).into(), 0, vec![3, 4], vec![2, 0]).unwrap();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().shaped_val_binop(
+            ValBinOpCode::Add, &cols, &Const::from(1i32).into()).unwrap();
+        assert_eq!(elements::<i32>(&r), vec![1, 1, 1, 1, 3, 3, 3, 3, 5, 5, 5, 5]);
+
+        // A reversed view, through a unop.
+        let rev = ShapedOperand::with_strides((&xs[..]).into(), 5, vec![6], vec![-1]).unwrap();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().shaped_val_unop(ValUnOpCode::Neg, &rev).unwrap();
+        assert_eq!(elements::<i32>(&r), vec![-5, -4, -3, -2, -1, 0]);
+
+        // Empty arrays give empty results, but are still type-checked.
+        let empty = ShapedOperand::new((&xs[..0]).into(), vec![2, 0]).unwrap();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().shaped_val_unop(ValUnOpCode::Abs, &empty).unwrap();
+        assert_eq!(r.shape(), &[2, 0]);
+        assert!(r.is_empty());
+        let mut tcx = TestCtx::new();
+        let res = tcx.get_eval_ctx().shaped_bool_unop(BoolUnOpCode::IsNaN, &empty);
+        assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+
+        // The result has to fit in `out`.
+        let mut small = [0u8; 8];
+        let m = ShapedOperand::new((&xs[..]).into(), vec![2, 3]).unwrap();
+        let res = EvalCtx::new(&mut [], &mut [], &mut small[..])
+            .shaped_val_unop(ValUnOpCode::Neg, &m);
+        assert!(matches!(res, Err(EvalError::BadBuffer)));
+    }
+}
+
+#[cfg(test)]
+mod test_structural {
+    use super::super::*;
+    use super::test_helpers::*;
+
+    #[test]
+    fn test_reshape() {
+        let xs: Vec<i16> = (0..5).collect();
+        let x: ShapedOperand = Slice::from(&xs[..]).into();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().reshape(&x, vec![3, 4]).unwrap();
+        assert_eq!(r.shape(), &[3, 4]);
+        assert_eq!(elements::<i16>(&r), vec![0, 1, 2, 3, 4, 0, 1, 2, 3, 4, 0, 1]);
+
+        // Cut short, from a strided view.
+        let m = ShapedOperand::with_strides((&xs[..]).into(), 4, vec![2, 2], vec![-2, -1]).unwrap();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().reshape(&m, vec![3]).unwrap();
+        assert_eq!(elements::<i16>(&r), vec![4, 3, 2]);
+
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().reshape(&Const::from(7u8).into(), vec![2, 2]).unwrap();
+        assert_eq!(elements::<u8>(&r), vec![7; 4]);
+
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().reshape(&Slice::from(&xs[..0]).into(), vec![3]).unwrap();
+        assert_eq!(elements::<i16>(&r), vec![0; 3]);
+    }
+
+    #[test]
+    fn test_transpose() {
+        // Big enough for several tiles, and not a multiple of them.
+        let (m, n) = (37, 53);
+        let xs: Vec<u64> = (0..m * n).map(|i| i as u64).collect();
+        let x = ShapedOperand::new((&xs[..]).into(), vec![m, n]).unwrap();
+        let mut tcx = TestCtx::new();
+        let t = tcx.get_eval_ctx().transpose(&x).unwrap();
+        assert_eq!(t.shape(), &[n, m]);
+        let ts = elements::<u64>(&t);
+        for i in 0..m {
+            for j in 0..n {
+                assert_eq!(ts[j * m + i], xs[i * n + j]);
+            }
+        }
+
+        let bs: Vec<bool> = (0..24).map(|i| i % 3 == 0).collect();
+        let b = ShapedOperand::new((&bs[..]).into(), vec![2, 3, 4]).unwrap();
+        let mut tcx = TestCtx::new();
+        let t = tcx.get_eval_ctx().transpose_axes(&b, &[1, 2, 0]).unwrap();
+        assert_eq!(t.shape(), &[4, 2, 3]);
+        let ts = elements::<bool>(&t);
+        for i in 0..2 {
+            for j in 0..3 {
+                for k in 0..4 {
+                    assert_eq!(ts[k * 6 + i * 3 + j], bs[i * 12 + j * 4 + k]);
+                }
+            }
+        }
+        let mut tcx = TestCtx::new();
+        assert!(matches!(tcx.get_eval_ctx().transpose_axes(&b, &[0, 0, 1]),
+                         Err(EvalError::BadAxis)));
+    }
+
+    #[test]
+    fn test_reverse_rotate() {
+        let xs: Vec<i32> = (0..6).collect();
+        let x = ShapedOperand::new((&xs[..]).into(), vec![2, 3]).unwrap();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().reverse(&x, 1).unwrap();
+        assert_eq!(elements::<i32>(&r), vec![2, 1, 0, 5, 4, 3]);
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().reverse(&x, 0).unwrap();
+        assert_eq!(elements::<i32>(&r), vec![3, 4, 5, 0, 1, 2]);
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().rotate(&x, 1, 1).unwrap();
+        assert_eq!(elements::<i32>(&r), vec![1, 2, 0, 4, 5, 3]);
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().rotate(&x, 1, -4).unwrap();
+        assert_eq!(elements::<i32>(&r), vec![2, 0, 1, 5, 3, 4]);
+        let mut tcx = TestCtx::new();
+        assert!(matches!(tcx.get_eval_ctx().rotate(&x, 2, 1), Err(EvalError::BadAxis)));
+    }
+
+    #[test]
+    fn test_take_drop() {
+        let xs: Vec<u8> = (1..7).collect();
+        let x = ShapedOperand::new((&xs[..]).into(), vec![2, 3]).unwrap();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().take(&x, &[3, -2], Const::from(0i64)).unwrap();
+        assert_eq!(r.shape(), &[3, 2]);
+        assert_eq!(elements::<u8>(&r), vec![2, 3, 5, 6, 0, 0]);
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().take(&x, &[-3], Const::from(9u8)).unwrap();
+        assert_eq!(elements::<u8>(&r), vec![9, 9, 9, 1, 2, 3, 4, 5, 6]);
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().take(&Const::from(4u8).into(), &[3], Const::from(0u8)).unwrap();
+        assert_eq!(elements::<u8>(&r), vec![4, 0, 0]);
+
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().drop(&x, &[1, -1]).unwrap();
+        assert_eq!(r.shape(), &[1, 2]);
+        assert_eq!(elements::<u8>(&r), vec![4, 5]);
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().drop(&x, &[0, 5]).unwrap();
+        assert_eq!(r.shape(), &[2, 0]);
+        let mut tcx = TestCtx::new();
+        assert!(matches!(tcx.get_eval_ctx().drop(&x, &[0, 0, 0]), Err(EvalError::BadAxis)));
+    }
+}
+
+#[cfg(test)]
+mod test_product {
+    use super::super::*;
+    use super::test_helpers::*;
+
+    #[test]
+    fn test_outer_val_binop() {
+        let xs: Vec<i32> = vec![1, -2, 3];
+        let ys: Vec<u8> = vec![10, 20];
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx()
+            .outer_val_binop(ValBinOpCode::Mul, &(&xs[..]).into(), &(&ys[..]).into()).unwrap();
+        assert_eq!(r.shape(), &[3, 2]);
+        assert_eq!(elements::<i32>(&r), vec![10, 20, -20, -40, 30, 60]);
+
+        // Enough rows to be split into several chunks.
+        let (m, n) = (200, 5000);
+        let xs: Vec<u32> = (0..m).collect();
+        let ys: Vec<u32> = (0..n).collect();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().with_chunk_bytes(0x8000)
+            .outer_val_binop(ValBinOpCode::Add, &(&xs[..]).into(), &(&ys[..]).into()).unwrap();
+        let rs = elements::<u32>(&r);
+        for i in 0..m {
+            for j in 0..n {
+                assert_eq!(rs[(i * n + j) as usize], i + j);
+            }
+        }
+
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx()
+            .outer_val_binop(ValBinOpCode::Add, &(&xs[..0]).into(), &(&ys[..]).into()).unwrap();
+        assert_eq!(r.shape(), &[0, n as usize]);
+    }
+
+    #[test]
+    fn test_matmul() {
+        let xs: Vec<i32> = vec![1, 2, 3, 4, 5, 6];
+        let ys: Vec<i32> = vec![7, 8, 9, 10, 11, 12];
+        let a = ShapedOperand::new((&xs[..]).into(), vec![2, 3]).unwrap();
+        let b = ShapedOperand::new((&ys[..]).into(), vec![3, 2]).unwrap();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().matmul(&a, &b).unwrap();
+        assert_eq!(r.shape(), &[2, 2]);
+        assert_eq!(elements::<i32>(&r), vec![58, 64, 139, 154]);
+
+        // A strided (transposed) left operand.
+        let zs: Vec<i32> = vec![7, 9, 11, 8, 10, 12];
+        let bt = ShapedOperand::with_strides((&zs[..]).into(), 0, vec![3, 2], vec![1, 3]).unwrap();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().matmul(&bt, &a).unwrap();
+        assert_eq!(r.shape(), &[3, 3]);
+        assert_eq!(elements::<i32>(&r), vec![39, 54, 69, 49, 68, 87, 59, 82, 105]);
+
+        let mut tcx = TestCtx::new();
+        assert!(matches!(tcx.get_eval_ctx().matmul(&a, &a), Err(EvalError::ShapeMismatch(..))));
+
+        let us: Vec<u8> = vec![1, 2, 3];
+        let is: Vec<i16> = vec![-1, 5, 300];
+        let mut tcx = TestCtx::new();
+        let d = tcx.get_eval_ctx().dot(&(&us[..]).into(), &(&is[..]).into()).unwrap();
+        assert_eq!(d, Const::ConstI16(909));
+        let mut tcx = TestCtx::new();
+        let d = tcx.get_eval_ctx().dot(&(&us[..0]).into(), &(&is[..0]).into()).unwrap();
+        assert_eq!(d, Const::ConstI16(0));
+    }
+
+    #[test]
+    fn test_matmul_blocked() {
+        if !ty_compiled_in(ScalarTy::TF64) {
+            return;
+        }
+        // Big enough to span several blocks along every axis.
+        let (m, k, n) = (70, 150, 600);
+        let xs: Vec<f64> = (0..m * k).map(|i| ((i * 37) % 101) as f64 / 7.0 - 5.0).collect();
+        let ys: Vec<f64> = (0..k * n).map(|i| ((i * 53) % 97) as f64 / 3.0 - 11.0).collect();
+        let a = ShapedOperand::new((&xs[..]).into(), vec![m, k]).unwrap();
+        let b = ShapedOperand::new((&ys[..]).into(), vec![k, n]).unwrap();
+        for level in [SimdLevel::Baseline, SimdLevel::detect()].iter() {
+            let mut tcx = TestCtx::new();
+            let r = tcx.get_eval_ctx().with_simd_level(*level).with_chunk_bytes(0x4000)
+                .matmul(&a, &b).unwrap();
+            let rs = elements::<f64>(&r);
+            for i in 0..m {
+                for j in 0..n {
+                    // Summed right to left, as APL reduces.
+                    let mut acc = xs[i * k + k - 1] * ys[(k - 1) * n + j];
+                    for p in (0..k - 1).rev() {
+                        acc += xs[i * k + p] * ys[p * n + j];
+                    }
+                    assert_eq!(rs[i * n + j], acc);
+                }
+            }
+        }
+
+        // Empty sums are 0.0, not -0.0.
+        let mut tcx = TestCtx::new();
+        let d = tcx.get_eval_ctx().dot(&(&xs[..0]).into(), &(&ys[..0]).into()).unwrap();
+        assert!(matches!(d, Const::ConstF64(x) if x == 0.0 && x.is_sign_positive()));
+        let a = ShapedOperand::new((&xs[..0]).into(), vec![m, 0]).unwrap();
+        let b = ShapedOperand::new((&ys[..0]).into(), vec![0, n]).unwrap();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().matmul(&a, &b).unwrap();
+        assert!(elements::<f64>(&r).iter().all(|x| *x == 0.0 && x.is_sign_positive()));
+    }
+
+    #[test]
+    fn test_inner_product() {
+        let xs: Vec<i64> = vec![1, 2, 3];
+        let ys: Vec<i64> = vec![4, 5, 6];
+        let x: ShapedOperand = Slice::from(&xs[..]).into();
+        let y: ShapedOperand = Slice::from(&ys[..]).into();
+        // Reduced right to left: 4 - (10 - 18).
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx()
+            .inner_product(ValBinOpCode::Sub, ValBinOpCode::Mul, &x, &y).unwrap();
+        assert_eq!(r.rank(), 0);
+        assert_eq!(elements::<i64>(&r), vec![12]);
+
+        // Max.Min over the rows of a matrix, and an operand with a const base.
+        let ms: Vec<u8> = vec![3, 9, 1, 7, 2, 8];
+        let m = ShapedOperand::new((&ms[..]).into(), vec![2, 3]).unwrap();
+        let c = ShapedOperand::splat(Const::from(5u8), vec![3, 2]);
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx()
+            .inner_product(ValBinOpCode::Max, ValBinOpCode::Min, &m, &c).unwrap();
+        assert_eq!(r.shape(), &[2, 2]);
+        assert_eq!(elements::<u8>(&r), vec![5, 5, 5, 5]);
+        let v: ShapedOperand = Slice::from(&ms[3..]).into();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx()
+            .inner_product(ValBinOpCode::Min, ValBinOpCode::Max, &m, &v).unwrap();
+        assert_eq!(elements::<u8>(&r), vec![7, 2]);
+
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx()
+            .inner_product(ValBinOpCode::Max, ValBinOpCode::Min, &x.clone(), &y.clone());
+        assert_eq!(elements::<i64>(&r.unwrap()), vec![3]);
+        let e: ShapedOperand = Slice::from(&xs[..0]).into();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().inner_product(ValBinOpCode::Max, ValBinOpCode::Min, &e, &e);
+        assert!(matches!(r, Err(EvalError::UnsupportedOp)));
+    }
+
+    #[test]
+    fn test_outer_bool_binop() {
+        let xs: Vec<u16> = vec![1, 2, 3];
+        let ys: Vec<u16> = vec![2, 1];
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx()
+            .outer_bool_binop(BoolBinOpCode::Lt, &(&xs[..]).into(), &(&ys[..]).into()).unwrap();
+        assert_eq!(r.shape(), &[3, 2]);
+        assert_eq!(elements::<bool>(&r), vec![true, false, false, false, false, false]);
+
+        if ty_compiled_in(ScalarTy::TI128) {
+            let ys: Vec<u64> = vec![0, u64::MAX];
+            let xs: Vec<i32> = vec![-1, 1];
+            let mut tcx = TestCtx::new();
+            let r = tcx.get_eval_ctx()
+                .outer_bool_binop(BoolBinOpCode::Lt, &(&xs[..]).into(), &(&ys[..]).into())
+                .unwrap();
+            assert_eq!(elements::<bool>(&r), vec![true, true, false, true]);
+        }
+
+        let mut out = [0u8; 5];
+        let res = EvalCtx::new(&mut [], &mut [], &mut out[..])
+            .outer_bool_binop(BoolBinOpCode::Lt, &(&xs[..]).into(), &(&ys[..]).into());
+        assert!(matches!(res, Err(EvalError::BadBuffer)));
+    }
+}
+
+#[cfg(test)]
+mod test_generate {
+    use super::super::*;
+    use super::test_helpers::*;
+
+    #[test]
+    fn test_iota() {
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().iota(ScalarTy::TI16, 5, &Const::from(3i32), &Const::from(-2i32));
+        assert_eq!(slice_elements::<i16>(&r.unwrap()), vec![3, 1, -1, -3, -5]);
+
+        // Unsigned types wrap, and count down with a negative step.
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().iota(ScalarTy::TU8, 4, &Const::from(2u8), &Const::from(-1i8));
+        assert_eq!(slice_elements::<u8>(&r.unwrap()), vec![2, 1, 0, 255]);
+
+        // Enough elements to be split into several chunks.
+        let n = 100000;
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().with_chunk_bytes(0x1000)
+            .iota(ScalarTy::TU64, n, &Const::from(7u64), &Const::from(3u64)).unwrap();
+        let rs = slice_elements::<u64>(&r);
+        assert!((0..n).all(|i| rs[i] == 7 + 3 * i as u64));
+
+        if ty_compiled_in(ScalarTy::TF64) {
+            let mut tcx = TestCtx::new();
+            let r = tcx.get_eval_ctx().with_chunk_bytes(0x1000)
+                .iota(ScalarTy::TF64, n, &Const::from(0.5f64), &Const::from(0.1f64)).unwrap();
+            let rs = slice_elements::<f64>(&r);
+            assert!((0..n).all(|i| rs[i] == 0.5 + i as f64 * 0.1));
+        }
+
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().iota(ScalarTy::TBool, 2, &Const::from(0u8), &Const::from(1u8));
+        assert!(matches!(r, Err(EvalError::UnsupportedOp)));
+        let mut out = [0u8; 2];
+        let r = EvalCtx::new(&mut [], &mut [], &mut out[..])
+            .iota(ScalarTy::TU8, 3, &Const::from(0u8), &Const::from(1u8));
+        assert!(matches!(r, Err(EvalError::BadBuffer)));
+    }
+
+    #[test]
+    fn test_linspace() {
+        if !ty_compiled_in(ScalarTy::TF32) {
+            return;
+        }
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().linspace(ScalarTy::TF32, 5, &Const::from(1i8), &Const::from(3i8));
+        assert_eq!(slice_elements::<f32>(&r.unwrap()), vec![1.0, 1.5, 2.0, 2.5, 3.0]);
+
+        // The last element is exactly `stop`, however the steps round.
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx()
+            .linspace(ScalarTy::TF64, 7, &Const::from(0.1f64), &Const::from(0.7f64)).unwrap();
+        assert_eq!(slice_elements::<f64>(&r)[6], 0.7);
+
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().linspace(ScalarTy::TF64, 1, &Const::from(2.0f64), &Const::from(9.0f64));
+        assert_eq!(slice_elements::<f64>(&r.unwrap()), vec![2.0]);
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().linspace(ScalarTy::TI32, 3, &Const::from(0i32), &Const::from(4i32));
+        assert!(matches!(r, Err(EvalError::UnsupportedOp)));
+    }
+
+    #[test]
+    fn test_repeat_tile_broadcast() {
+        let xs: Vec<i8> = vec![1, -2, 3];
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().repeat(&(&xs[..]).into(), 2).unwrap();
+        assert_eq!(slice_elements::<i8>(&r), vec![1, 1, -2, -2, 3, 3]);
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().tile(&(&xs[..]).into(), 2).unwrap();
+        assert_eq!(slice_elements::<i8>(&r), vec![1, -2, 3, 1, -2, 3]);
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().tile(&(&xs[..]).into(), 0).unwrap();
+        assert!(r.is_empty());
+
+        // Enough rows to be split into several chunks.
+        let ys: Vec<u32> = (0..1000).collect();
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().with_chunk_bytes(0x1000).repeat(&(&ys[..]).into(), 30).unwrap();
+        let rs = slice_elements::<u32>(&r);
+        assert!((0..30000).all(|i| rs[i] == i as u32 / 30));
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().with_chunk_bytes(0x1000).tile(&(&ys[..]).into(), 30).unwrap();
+        let rs = slice_elements::<u32>(&r);
+        assert!((0..30000).all(|i| rs[i] == i as u32 % 1000));
+
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().broadcast(&Const::from(true), 3).unwrap();
+        assert_eq!(slice_elements::<bool>(&r), vec![true, true, true]);
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().with_chunk_bytes(0x1000).broadcast(&Const::from(-9i64), 5000);
+        assert!(slice_elements::<i64>(&r.unwrap()).iter().all(|&x| x == -9));
+    }
+}
+
+#[cfg(test)]
+mod test_random {
+    use super::super::*;
+    use super::test_helpers::*;
+    use super::super::operands::ScalarOf;
+
+    fn random<T: ScalarOf + Copy>(ctx: EvalCtx, ty: ScalarTy, n: usize, seed: u64,
+                                  offset: u64) -> Vec<T> {
+        slice_elements(&ctx.random_uniform(ty, n, seed, offset).unwrap())
+    }
+
+    #[test]
+    fn test_random_reproducible() {
+        let n = 50000;
+        let mut tcx = TestCtx::new();
+        let base = random::<u32>(tcx.get_eval_ctx(), ScalarTy::TU32, n, 42, 0);
+        for level in [SimdLevel::Baseline, SimdLevel::detect()].iter() {
+            for parallelism in [Parallelism::Serial, Parallelism::Parallel].iter() {
+                let mut tcx = TestCtx::new();
+                let ctx = tcx.get_eval_ctx().with_chunk_bytes(0x1000)
+                    .with_simd_level(*level).with_parallelism(*parallelism);
+                assert_eq!(random::<u32>(ctx, ScalarTy::TU32, n, 42, 0), base);
+            }
+        }
+        // A fill from an offset continues the stream.
+        let mut tcx = TestCtx::new();
+        assert_eq!(random::<u32>(tcx.get_eval_ctx(), ScalarTy::TU32, 100, 42, 777),
+                   &base[777..877]);
+        let mut tcx = TestCtx::new();
+        let other = random::<u32>(tcx.get_eval_ctx(), ScalarTy::TU32, n, 43, 0);
+        assert!(base.iter().zip(other.iter()).filter(|(a, b)| a == b).count() < 10);
+    }
+
+    #[test]
+    fn test_random_uniform() {
+        let n = 100000;
+        let mut tcx = TestCtx::new();
+        let bs = random::<bool>(tcx.get_eval_ctx(), ScalarTy::TBool, n, 1, 0);
+        let trues = bs.iter().filter(|&&b| b).count();
+        assert!((trues as f64 / n as f64 - 0.5).abs() < 0.01);
+
+        let mut tcx = TestCtx::new();
+        let xs = random::<i8>(tcx.get_eval_ctx(), ScalarTy::TI8, n, 1, 0);
+        assert!(xs.contains(&i8::MIN) && xs.contains(&i8::MAX));
+
+        if ty_compiled_in(ScalarTy::TF64) {
+            let mut tcx = TestCtx::new();
+            let fs = random::<f64>(tcx.get_eval_ctx(), ScalarTy::TF64, n, 2, 0);
+            assert!(fs.iter().all(|&f| (0.0..1.0).contains(&f)));
+            let mean = fs.iter().sum::<f64>() / n as f64;
+            assert!((mean - 0.5).abs() < 0.01);
+            let mut tcx = TestCtx::new();
+            let fs = random::<f32>(tcx.get_eval_ctx(), ScalarTy::TF32, n, 2, 0);
+            assert!(fs.iter().all(|&f| (0.0..1.0).contains(&f)));
+        }
+        if ty_compiled_in(ScalarTy::TU128) {
+            let mut tcx = TestCtx::new();
+            let ws = random::<u128>(tcx.get_eval_ctx(), ScalarTy::TU128, 1000, 3, 0);
+            assert!(ws.iter().any(|&w| w >> 127 == 1) && ws.iter().any(|&w| w >> 127 == 0));
+        }
+        let mut out = [0u8; 2];
+        let r = EvalCtx::new(&mut [], &mut [], &mut out[..]).random_uniform(ScalarTy::TU8, 3, 0, 0);
+        assert!(matches!(r, Err(EvalError::BadBuffer)));
+    }
+
+    #[test]
+    fn test_random_normal() {
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().random_normal(ScalarTy::TI32, 10, 0, 0);
+        assert!(matches!(r, Err(EvalError::UnsupportedOp)));
+        if !cfg!(feature = "transcendental") {
+            return;
+        }
+        let n = 100000;
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().with_chunk_bytes(0x1000)
+            .random_normal(ScalarTy::TF64, n, 5, 0).unwrap();
+        let xs = slice_elements::<f64>(&r);
+        assert!(xs.iter().all(|x| x.is_finite()));
+        let mean = xs.iter().sum::<f64>() / n as f64;
+        let var = xs.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / n as f64;
+        assert!(mean.abs() < 0.02 && (var - 1.0).abs() < 0.02);
+
+        let mut tcx = TestCtx::new();
+        let r = tcx.get_eval_ctx().with_parallelism(Parallelism::Serial)
+            .random_normal(ScalarTy::TF64, 100, 5, 1000).unwrap();
+        assert_eq!(slice_elements::<f64>(&r), &xs[1000..1100]);
+    }
+}
+
+#[cfg(test)]
+mod test_owned {
+    use super::super::*;
+    use super::test_helpers::*;
+
+    #[test]
+    fn test_owned_outlives_ctx() {
+        let mut tcx = TestCtx::new();
+        let n = tcx.len::<u32>();
+        let a: Vec<u32> = (0..n as u32).collect();
+        let owned = {
+            let res = tcx.get_eval_ctx().val_binop(ValBinOpCode::Add,
+                                                   &Operand::from(&a),
+                                                   &Operand::from(1u32));
+            OwnedOperand::from(res.unwrap())
+        };
+        let exp: Vec<u32> = a.iter().map(|x| x + 1).collect();
+        assert_eq!(owned.as_operand(), Operand::from(&exp));
+
+        // Owned operands can be sent to other threads and fed back in.
+        let shared = owned.clone();
+        let back = std::thread::spawn(move || shared.get_scalar_ty()).join().unwrap();
+        assert_eq!(back, ScalarTy::TU32);
+        let res = tcx.get_eval_ctx().val_binop(ValBinOpCode::Sub,
+                                               &owned.as_operand(),
+                                               &Operand::from(1u32));
+        assert_eq!(res.unwrap(), Operand::from(&a));
+    }
+
+    #[test]
+    fn test_owned_const() {
+        let c = OwnedOperand::from(7i64);
+        assert_eq!(c.as_operand(), Operand::from(7i64));
+        assert_eq!(OwnedOperand::from(&Operand::from(7i64)), c);
+    }
+}
+
+#[cfg(test)]
+mod test_ragged {
+    use super::super::*;
+    use super::test_helpers::*;
+
+    // Operands needn't be padded to any particular multiple of elements; the
+    // final partial vector (and chunk) is handled like any other.
+    #[test]
+    fn test_ragged_lengths() {
+        for &n in &[1usize, 3, 15, 17, 63, 65, 1000, CHUNKBYTES + 7] {
+            let mut tcx = TestCtx::new();
+            let a: Vec<i32> = (0..n).map(|i| (i as i32) - 500).collect();
+            let b: Vec<u8> = (0..n).map(|i| (i * 7) as u8).collect();
+
+            let exp: Vec<i32> = a.iter().zip(b.iter()).map(|(x, y)| x + (*y as i32)).collect();
+            let res = tcx.get_eval_ctx().val_binop(ValBinOpCode::Add,
+                                                   &Operand::from(&a),
+                                                   &Operand::from(&b));
+            assert_eq!(res.unwrap(), Operand::from(&exp));
+
+            let exp: Vec<bool> = a.iter().map(|x| (*x as f32) < 10.5).collect();
+            let res = tcx.get_eval_ctx().bool_binop(BoolBinOpCode::Lt,
+                                                    &Operand::from(&a),
+                                                    &Operand::from(10.5f32));
+            if ty_compiled_in(ScalarTy::TF32) {
+                assert_eq!(res.unwrap(), Operand::from(&exp));
+            } else {
+                assert!(matches!(res, Err(EvalError::UnsupportedOp)));
+            }
+
+            let exp: Vec<i32> = a.iter().map(|x| -x).collect();
+            let res = tcx.get_eval_ctx().val_unop(ValUnOpCode::Neg,
+                                                  &Operand::from(&a));
+            assert_eq!(res.unwrap(), Operand::from(&exp));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_sched {
+    use super::super::*;
+    use super::test_helpers::*;
+
+    // Results don't depend on the chunk size, including ones that aren't a
+    // multiple of any vector size.
+    #[test]
+    fn test_chunk_bytes() {
+        let mut tcx = TestCtx::new();
+        let n = 10007;
+        let a: Vec<i32> = (0..n).map(|x| x * 7 - 5000).collect();
+        let b: Vec<u8> = (0..n).map(|x| (x % 3) as u8).collect();
+        let sum: Vec<i64> = a.iter().zip(&b).map(|(x, y)| (x + *y as i32) as i64).collect();
+        let lt: Vec<bool> = a.iter().zip(&b).map(|(x, y)| *x < *y as i32).collect();
+        for &chunk_bytes in &[0, 100, 4096, CHUNKBYTES, CHUNKBYTES * 64] {
+            let res = tcx.get_eval_ctx().with_chunk_bytes(chunk_bytes)
+                .val_binop(ValBinOpCode::Add, &Operand::from(&a), &Operand::from(&b));
+            let res = OwnedOperand::from(res.unwrap());
+            let res = tcx.get_eval_ctx().with_chunk_bytes(chunk_bytes)
+                .conv(&res.as_operand(), ScalarTy::TI64);
+            assert_eq!(res.unwrap(), Operand::from(&sum));
+            let res = tcx.get_eval_ctx().with_chunk_bytes(chunk_bytes)
+                .bool_binop(BoolBinOpCode::Lt, &Operand::from(&a), &Operand::from(&b));
+            assert_eq!(res.unwrap(), Operand::from(&lt));
+        }
+    }
+
+    #[test]
+    fn test_parallelism() {
+        let mut tcx = TestCtx::new();
+        let n = 50000;
+        let a: Vec<u32> = (0..n as u32).collect();
+        let exp: Vec<u32> = a.iter().map(|x| x * 2).collect();
+        let modes = [Parallelism::Serial, Parallelism::Parallel,
+                     Parallelism::ParallelAbove(100), Parallelism::ParallelAbove(n)];
+        for &mode in &modes {
+            let res = tcx.get_eval_ctx().with_chunk_bytes(4096).with_parallelism(mode)
+                .val_binop(ValBinOpCode::Mul, &Operand::from(&a), &Operand::from(2u32));
+            assert_eq!(res.unwrap(), Operand::from(&exp));
+        }
+    }
+
+    // Every SIMD level gives the same results, including levels the CPU
+    // doesn't support, which fall back to the highest one it does.
+    #[test]
+    fn test_simd_level() {
+        let mut tcx = TestCtx::new();
+        let n = 1001;
+        let a: Vec<i64> = (0..n).map(|x| x * 5 - 2000).collect();
+        let b: Vec<i16> = (0..n).map(|x| (x % 300) as i16).collect();
+        let sum: Vec<i64> = a.iter().zip(&b).map(|(x, y)| x + *y as i64).collect();
+        let lt: Vec<bool> = b.iter().map(|x| 100 < *x).collect();
+        let neg: Vec<i64> = a.iter().map(|x| -x).collect();
+        let x: Vec<f64> = (0..n).map(|i| 1.0 + (i as f64) * f64::EPSILON).collect();
+        let fma: Vec<f64> = x.iter().map(|x| x.mul_add(*x, -1.0)).collect();
+        let y: Vec<f32> = (0..n).map(|i| (i as f32 - 500.0) / 7.0).collect();
+        let floor: Vec<f32> = y.iter().map(|y| y.floor()).collect();
+        let pop: Vec<i64> = a.iter().map(|x| x.count_ones() as i64).collect();
+        let conv: Vec<i16> = a.iter().map(|x| *x as i16).collect();
+        let sat: Vec<u8> = a.iter().map(|x| (*x).clamp(0, 255) as u8).collect();
+        let u: Vec<u64> = (0..n).map(|x| (x as u64) * 7).collect();
+        let cmp: Vec<bool> = a.iter().zip(&u).map(|(x, y)| (*x as i128) < (*y as i128)).collect();
+        let sh: Vec<i64> = (0..n).map(|i| i % 70 - 3).collect();
+        let shl: Vec<i64> = a.iter().zip(&sh).map(|(x, s)| x.wrapping_shl(*s as u32)).collect();
+        let ashr: Vec<i64> = a.iter().zip(&sh).map(|(x, s)| x.wrapping_shr(*s as u32)).collect();
+        for &level in &[SimdLevel::Baseline, SimdLevel::Avx2, SimdLevel::Avx512] {
+            let res = tcx.get_eval_ctx().with_simd_level(level)
+                .val_binop(ValBinOpCode::Add, &Operand::from(&a), &Operand::from(&b));
+            assert_eq!(res.unwrap(), Operand::from(&sum));
+            let res = tcx.get_eval_ctx().with_simd_level(level)
+                .bool_binop(BoolBinOpCode::Lt, &Operand::from(100i16), &Operand::from(&b));
+            assert_eq!(res.unwrap(), Operand::from(&lt));
+            let res = tcx.get_eval_ctx().with_simd_level(level)
+                .val_unop(ValUnOpCode::Neg, &Operand::from(&a));
+            assert_eq!(res.unwrap(), Operand::from(&neg));
+            let res = tcx.get_eval_ctx().with_simd_level(level)
+                .conv(&Operand::from(&a), ScalarTy::TI16);
+            assert_eq!(res.unwrap(), Operand::from(&conv));
+            let res = tcx.get_eval_ctx().with_simd_level(level)
+                .cast(&Operand::from(&a), ScalarTy::TU8, CastMode::Saturating);
+            assert_eq!(res.unwrap(), Operand::from(&sat));
+            let res = tcx.get_eval_ctx().with_simd_level(level)
+                .cast(&Operand::from(&a), ScalarTy::TU8, CastMode::Checked);
+            assert!(matches!(res, Err(EvalError::CastOutOfRange(0))));
+            let res = tcx.get_eval_ctx().with_simd_level(level)
+                .bool_binop(BoolBinOpCode::Lt, &Operand::from(&a), &Operand::from(&u));
+            assert_eq!(res.unwrap(), Operand::from(&cmp));
+            if cfg!(feature = "float") {
+                let res = tcx.get_eval_ctx().with_simd_level(level)
+                    .val_ternop(ValTernOpCode::MulAdd, &Operand::from(&x), &Operand::from(&x),
+                                &Operand::from(-1.0f64));
+                assert_eq!(res.unwrap(), Operand::from(&fma));
+                let res = tcx.get_eval_ctx().with_simd_level(level)
+                    .val_unop(ValUnOpCode::Floor, &Operand::from(&y));
+                assert_eq!(res.unwrap(), Operand::from(&floor));
+            }
+            if cfg!(feature = "bitwise") {
+                let res = tcx.get_eval_ctx().with_simd_level(level)
+                    .val_unop(ValUnOpCode::PopCount, &Operand::from(&a));
+                assert_eq!(res.unwrap(), Operand::from(&pop));
+                let res = tcx.get_eval_ctx().with_simd_level(level)
+                    .val_binop(ValBinOpCode::Shl, &Operand::from(&a), &Operand::from(&sh));
+                assert_eq!(res.unwrap(), Operand::from(&shl));
+                let res = tcx.get_eval_ctx().with_simd_level(level)
+                    .val_binop(ValBinOpCode::AShr, &Operand::from(&a), &Operand::from(&sh));
+                assert_eq!(res.unwrap(), Operand::from(&ashr));
+            }
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_thread_pool() {
+        let mut tcx = TestCtx::new();
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+        let a: Vec<u16> = (0..40000).collect();
+        let exp: Vec<bool> = a.iter().map(|x| x % 2 == 1).collect();
+        let res = tcx.get_eval_ctx().with_chunk_bytes(4096).with_thread_pool(&pool)
+            .val_binop(ValBinOpCode::BitAnd, &Operand::from(&a), &Operand::from(1u16));
+        let res = OwnedOperand::from(res.unwrap());
+        let res = tcx.get_eval_ctx().with_thread_pool(&pool)
+            .conv(&res.as_operand(), ScalarTy::TBool);
+        assert_eq!(res.unwrap(), Operand::from(&exp));
+    }
+
+    #[test]
+    fn test_calibrate() {
+        let candidates = [0x1000, 0x4000];
+        let best = crate::sched::calibrate(0x10000, &candidates);
+        assert!(candidates.contains(&best));
+    }
+}
+
+#[cfg(test)]
+mod test_codec {
+    use super::super::*;
+    use super::test_helpers::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_codec_roundtrip() {
+        let mut tcx = TestCtx::new();
+        let a: Vec<i16> = (0..1001).map(|x| x * 3 - 500).collect();
+        let b = vec![true, false, true];
+        let mut w = Vec::new();
+        Const::ConstF64(2.5).write_to(&mut w).unwrap();
+        Const::ConstI128(-1).write_to(&mut w).unwrap();
+        Operand::from(&a).write_to(&mut w).unwrap();
+        Slice::from(&b[..]).write_to(&mut w).unwrap();
+        ValBinOpCode::BitXor.write_to(&mut w).unwrap();
+        BoolUnOpCode::IsFin.write_to(&mut w).unwrap();
+        ValTernOpCode::MulAdd.write_to(&mut w).unwrap();
+        // Every record is a multiple of 16 bytes.
+        assert_eq!(w.len() % 16, 0);
+
+        let mut r = Cursor::new(&w[..]);
+        assert_eq!(Const::read_from(&mut r).unwrap(), Const::ConstF64(2.5));
+        assert_eq!(Const::read_from(&mut r).unwrap(), Const::ConstI128(-1));
+        assert_eq!(Operand::read_from(&mut r, &mut tcx.out[..]).unwrap(), Operand::from(&a));
+        assert_eq!(OwnedOperand::read_from(&mut r).unwrap(), OwnedOperand::from(b));
+        assert_eq!(ValBinOpCode::read_from(&mut r).unwrap(), ValBinOpCode::BitXor);
+        assert_eq!(BoolUnOpCode::read_from(&mut r).unwrap(), BoolUnOpCode::IsFin);
+        assert_eq!(ValTernOpCode::read_from(&mut r).unwrap(), ValTernOpCode::MulAdd);
+        assert_eq!(r.position() as usize, w.len());
+
+        // Slices read into a buffer can be evaluated directly.
+        let mut r = Cursor::new(&w[..]);
+        Const::read_from(&mut r).unwrap();
+        Const::read_from(&mut r).unwrap();
+        let mut buf = vec![0u8; 4096];
+        let s = Operand::read_from(&mut r, &mut buf[..]).unwrap();
+        let res = tcx.get_eval_ctx().val_binop(ValBinOpCode::Add, &s, &Operand::from(1i16));
+        let exp: Vec<i16> = a.iter().map(|x| x + 1).collect();
+        assert_eq!(res.unwrap(), Operand::from(&exp));
+    }
+
+    #[test]
+    fn test_codec_errors() {
+        let mut tcx = TestCtx::new();
+        let mut w = Vec::new();
+        Const::ConstBool(true).write_to(&mut w).unwrap();
+
+        let mut bad = w.clone();
+        bad[0] = b'X';
+        assert!(matches!(Const::read_from(&mut &bad[..]), Err(CodecError::BadMagic)));
+        let mut bad = w.clone();
+        bad[3] = CODEC_VERSION + 1;
+        assert!(matches!(Const::read_from(&mut &bad[..]), Err(CodecError::BadVersion(_))));
+        let mut bad = w.clone();
+        bad[16] = 2;
+        assert!(matches!(Const::read_from(&mut &bad[..]), Err(CodecError::BadRecord)));
+        assert!(matches!(ValUnOpCode::read_from(&mut &w[..]), Err(CodecError::BadRecord)));
+        assert!(matches!(Const::read_from(&mut &w[0..20]), Err(CodecError::Io(_))));
+
+        let a: Vec<u64> = (0..100).collect();
+        let mut w = Vec::new();
+        Slice::from(&a[..]).write_to(&mut w).unwrap();
+        assert!(matches!(Slice::read_from(&mut &w[..], &mut tcx.out[0..799]),
+                         Err(CodecError::BadBuffer)));
+        assert!(matches!(Slice::read_from(&mut &w[..], &mut tcx.out[1..]),
+                         Err(CodecError::BadBuffer)));
+        assert!(matches!(OwnedOperand::read_from(&mut &w[0..100]), Err(CodecError::Io(_))));
+    }
+
+    // Reserved, unused and padding bytes must be zero, so that a corrupt
+    // record, or one using them in a later format, isn't misread.
+    #[test]
+    fn test_codec_nonzero_reserved() {
+        let mut tcx = TestCtx::new();
+        let mut w = Vec::new();
+        Const::ConstU32(7).write_to(&mut w).unwrap();
+        for i in [6, 7, 8, 15, 20, 31].iter() {
+            let mut bad = w.clone();
+            bad[*i] = 1;
+            assert!(matches!(Const::read_from(&mut &bad[..]), Err(CodecError::BadRecord)));
+            assert!(matches!(OwnedOperand::read_from(&mut &bad[..]), Err(CodecError::BadRecord)));
+        }
+
+        let mut w = Vec::new();
+        ValBinOpCode::Shl.write_to(&mut w).unwrap();
+        let mut bad = w.clone();
+        bad[6] = 1;
+        assert!(matches!(ValBinOpCode::read_from(&mut &bad[..]), Err(CodecError::BadRecord)));
+        let mut bad = w.clone();
+        bad[8] = 1;
+        assert!(matches!(ValBinOpCode::read_from(&mut &bad[..]), Err(CodecError::BadRecord)));
+
+        // Three u16s, padded with 10 bytes.
+        let a: Vec<u16> = vec![1, 2, 3];
+        let mut w = Vec::new();
+        Slice::from(&a[..]).write_to(&mut w).unwrap();
+        assert_eq!(w.len(), 32);
+        assert_eq!(Slice::read_from(&mut &w[..], &mut tcx.out[..]).unwrap(), Slice::from(&a[..]));
+        let mut bad = w.clone();
+        bad[31] = 1;
+        assert!(matches!(Slice::read_from(&mut &bad[..], &mut tcx.out[..]),
+                         Err(CodecError::BadRecord)));
+        assert!(matches!(OwnedOperand::read_from(&mut &bad[..]), Err(CodecError::BadRecord)));
+    }
+}
+
+#[cfg(all(test, feature = "arrow"))]
+mod test_arrow {
+    use super::super::*;
+    use super::test_helpers::*;
+    use arrow_array::{Array, BooleanArray, Float64Array, Int32Array, Int64Array};
+
+    #[test]
+    fn test_arrow_primitive_roundtrip() {
+        let mut tcx = TestCtx::new();
+        let a = Int32Array::from((0..1001).collect::<Vec<i32>>());
+        let b = Float64Array::from(vec![0.5; 1001]);
+        // Primitive arrays are borrowed in place.
+        match Slice::from(&a) {
+            Slice::SliceI32(s) => assert_eq!(s.as_ptr(), a.values().as_ptr()),
+            _ => panic!("wrong slice type")
+        }
+        let res = tcx.get_eval_ctx().val_binop(ValBinOpCode::Mul,
+                                               &Operand::from(&a),
+                                               &Operand::from(&b)).unwrap();
+        let arr = match res {
+            Operand::OperandSlice(s) => s.to_arrow(None).unwrap(),
+            _ => panic!("expected a slice")
+        };
+        let arr = arr.as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(arr.len(), 1001);
+        assert_eq!(arr.value(1000), 500.0);
+    }
+
+    #[test]
+    fn test_arrow_bools_and_validity() {
+        let vals = BooleanArray::from(vec![Some(true), None, Some(false), Some(true)]);
+        let mut buf = vec![0u8; 64];
+        let mut vbuf = vec![0u8; 64];
+        let s = Slice::from_arrow(&vals, &mut buf).unwrap();
+        assert_eq!(s, Slice::SliceBool(&[true, false, false, true]));
+        let v = Slice::from_arrow_validity(&vals, &mut vbuf).unwrap().unwrap();
+        let v = match v {
+            Slice::SliceBool(v) => v,
+            _ => panic!("validity should be bools")
+        };
+        assert_eq!(v, &[true, false, true, true]);
+        let back = s.to_arrow(Some(v)).unwrap();
+        assert_eq!(back.as_any().downcast_ref::<BooleanArray>().unwrap(), &vals);
+
+        let none = Int32Array::from(vec![1, 2, 3]);
+        assert!(Slice::from_arrow_validity(&none, &mut vbuf).unwrap().is_none());
+        assert!(Slice::SliceU128(&[1]).to_arrow(None).is_err());
+    }
+
+    #[test]
+    fn test_arrow_owned_export() {
+        let mut tcx = TestCtx::new();
+        let xs: Vec<i64> = (0..1001).collect();
+        let res = tcx.get_eval_ctx().val_unop(ValUnOpCode::Neg, &Operand::from(&xs)).unwrap();
+        let owned = match OwnedOperand::from(res) {
+            OwnedOperand::OperandSlice(s) => s,
+            _ => panic!("expected a slice")
+        };
+        // The array shares the owned column, and keeps it alive.
+        let arr = owned.to_arrow(Some(&[true; 1001])).unwrap();
+        let ptr = match owned.as_slice() {
+            Slice::SliceI64(s) => s.as_ptr(),
+            _ => panic!("wrong slice type")
+        };
+        drop(owned);
+        let arr = arr.as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(arr.values().as_ptr(), ptr);
+        assert_eq!(arr.value(1000), -1000);
+        assert_eq!(arr.null_count(), 0);
+
+        let bools = OwnedSlice::from(&Slice::SliceBool(&[true, false]));
+        let arr = bools.to_arrow(Some(&[false, true])).unwrap();
+        let expected = BooleanArray::from(vec![None, Some(false)]);
+        assert_eq!(arr.as_any().downcast_ref::<BooleanArray>().unwrap(), &expected);
+        assert!(bools.to_arrow(Some(&[true])).is_err());
+        assert!(OwnedSlice::from(&Slice::SliceI128(&[1])).to_arrow(None).is_err());
+    }
+}
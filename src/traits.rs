@@ -1,6 +1,8 @@
 // Copyright 2019-2020 Graydon Hoare <graydon@pobox.com>
 // Licensed under the MIT and Apache-2.0 licenses.
 
+use crate::sched::Sched;
+
 pub trait ScalarT: Sized {}
 impl ScalarT for bool {}
 impl ScalarT for u8 {}
@@ -18,10 +20,19 @@ impl ScalarT for f64 {}
 
 pub enum OpError {
     Unsupported,
+    // A checked conversion failed at the given element.
+    CastOutOfRange(usize),
+    CastNaN(usize),
 }
 
+// Every operator trait has a `SUPPORTED` const, which is false for the
+// stand-ins that only return `OpError::Unsupported`, so planning can tell
+// which combinations work without running anything.
 pub trait UnOp<SRC: ScalarT, DST: ScalarT> {
-    fn apply_slice<'src, 'dst>(src: &'src [SRC],
+    const SUPPORTED: bool = true;
+
+    fn apply_slice<'src, 'dst>(sched: &Sched,
+                               src: &'src [SRC],
                                dst: &'dst mut [DST])
                                -> Result<&'dst [DST], OpError>
     where
@@ -31,21 +42,26 @@ pub trait UnOp<SRC: ScalarT, DST: ScalarT> {
 }
 
 pub trait BinOp<SRC: ScalarT, DST: ScalarT> {
-    fn apply_slice_slice<'src, 'dst>(lhs: &'src [SRC],
+    const SUPPORTED: bool = true;
+
+    fn apply_slice_slice<'src, 'dst>(sched: &Sched,
+                                     lhs: &'src [SRC],
                                      rhs: &'src [SRC],
                                      dst: &'dst mut [DST])
                                      -> Result<&'dst [DST], OpError>
     where
         'src: 'dst;
 
-    fn apply_slice_const<'src, 'dst>(lhs: &'src [SRC],
+    fn apply_slice_const<'src, 'dst>(sched: &Sched,
+                                     lhs: &'src [SRC],
                                      rhs: SRC,
                                      dst: &'dst mut [DST])
                                      -> Result<&'dst [DST], OpError>
     where
         'src: 'dst;
 
-    fn apply_const_slice<'src, 'dst>(lhs: SRC,
+    fn apply_const_slice<'src, 'dst>(sched: &Sched,
+                                     lhs: SRC,
                                      rhs: &'src [SRC],
                                      dst: &'dst mut [DST])
                                      -> Result<&'dst [DST], OpError>
@@ -54,3 +70,56 @@ pub trait BinOp<SRC: ScalarT, DST: ScalarT> {
 
     fn apply_const_const(lhs: SRC, rhs: SRC) -> Result<DST, OpError>;
 }
+
+// Comparisons between operands of different types, which are done exactly
+// rather than by converting to a common type. Which comparison is given by
+// `mask`, a set of the outcomes for which it's true (see `ordering_bit` in
+// fallback.rs).
+pub trait MixedCmp<L: ScalarT, R: ScalarT> {
+    const SUPPORTED: bool = true;
+
+    fn apply_slice_slice<'src, 'dst>(sched: &Sched,
+                                     mask: u8,
+                                     lhs: &'src [L],
+                                     rhs: &'src [R],
+                                     dst: &'dst mut [bool])
+                                     -> Result<&'dst [bool], OpError>
+    where
+        'src: 'dst;
+
+    fn apply_slice_const<'src, 'dst>(sched: &Sched,
+                                     mask: u8,
+                                     lhs: &'src [L],
+                                     rhs: R,
+                                     dst: &'dst mut [bool])
+                                     -> Result<&'dst [bool], OpError>
+    where
+        'src: 'dst;
+
+    fn apply_const_slice<'src, 'dst>(sched: &Sched,
+                                     mask: u8,
+                                     lhs: L,
+                                     rhs: &'src [R],
+                                     dst: &'dst mut [bool])
+                                     -> Result<&'dst [bool], OpError>
+    where
+        'src: 'dst;
+
+    fn apply_const_const(mask: u8, lhs: L, rhs: R) -> Result<bool, OpError>;
+}
+
+// Ternary operators work in place: `acc` holds the third operand on entry,
+// and the result on return.
+pub trait TernOp<SRC: ScalarT, DST: ScalarT> {
+    const SUPPORTED: bool = true;
+
+    fn apply_slices<'src, 'dst>(sched: &Sched,
+                                a: &'src [SRC],
+                                b: &'src [SRC],
+                                acc: &'dst mut [DST])
+                                -> Result<&'dst [DST], OpError>
+    where
+        'src: 'dst;
+
+    fn apply_const(a: SRC, b: SRC, c: SRC) -> Result<DST, OpError>;
+}